@@ -7,6 +7,9 @@ uniform mat4 persp;
 uniform mat4 view;
 uniform vec3 chunkpos;
 uniform float skybrightness;
+//Biome tints for grass and leaves, the index of the tint plus 1 is stored in
+//the bits of data.x above the face id
+uniform vec3 biometints[63];
 
 out vec3 fragpos;
 out vec3 chunkfragpos;
@@ -45,4 +48,8 @@ void main() {
 	tint.r = max(sky, r);
 	tint.g = max(sky, g);
 	tint.b = max(sky, b);
+
+	uint tintindex = data.x >> 2;
+	if(tintindex > 0u)
+		tint *= biometints[tintindex - 1u];
 }
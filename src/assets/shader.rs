@@ -210,6 +210,13 @@ impl ShaderProgram {
         })
     }
 
+    //Send an array of 3 dimensional vectors to the shader
+    pub fn uniform_vec3f_array(&self, uniform_name: &str, values: &[[f32; 3]]) {
+        self.uniform(uniform_name, |location| unsafe {
+            gl::Uniform3fv(location, values.len() as i32, values.as_ptr() as *const f32);
+        })
+    }
+
     //Send a 4 x 4 matrix to the shader
     pub fn uniform_matrix4f(&self, uniform_name: &str, mat: &Matrix4<f32>) {
         self.uniform(uniform_name, |location| unsafe {
@@ -28,12 +28,50 @@ pub fn load_image_pixels(path: &str) -> Result<(Vec<u32>, png::OutputInfo), Stri
     Ok((pixels, info))
 }
 
+//Returns the pixels of an image as values from 0.0 to 1.0 along with the
+//width and height of the image, color images use the average of the red,
+//green and blue channels and the alpha channel is ignored
+pub fn load_grayscale_pixels(path: &str) -> Result<(Vec<f32>, u32, u32), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    //Convert palettes and images with less than 8 bits per channel
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+
+    let (bytes, max) = match info.bit_depth {
+        png::BitDepth::Sixteen => (2, u16::MAX as f32),
+        _ => (1, u8::MAX as f32),
+    };
+    let samples = info.color_type.samples();
+    let color_samples = if samples >= 3 { 3 } else { 1 };
+    let pixels = buf[..info.buffer_size()]
+        .chunks_exact(samples * bytes)
+        .map(|pixel| {
+            let sum: f32 = pixel
+                .chunks_exact(bytes)
+                .take(color_samples)
+                .map(|sample| match sample {
+                    [hi, lo] => u16::from_be_bytes([*hi, *lo]) as f32,
+                    _ => sample[0] as f32,
+                })
+                .sum();
+            sum / (color_samples as f32 * max)
+        })
+        .collect();
+
+    Ok((pixels, info.width, info.height))
+}
+
 pub struct Texture {
     id: u32,
 }
 
 impl Texture {
     //Creates a blank texture with id 0
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self { id: 0 }
     }
@@ -0,0 +1,271 @@
+/*
+ * Command line tool for inspecting voxelworld saves without opening a window,
+ * this reuses the same loading code as the game so that it can be used by
+ * scripts to check the contents of a world.
+ * */
+
+use std::collections::HashMap;
+use voxelworld::{
+    bin_data::DataType,
+    game::{
+        entities::{region::EntityRegion, ENTITIES_PATH},
+        inventory::item_to_string,
+        save::CHUNK_PATH,
+    },
+    impfile,
+    voxel::{
+        block_registry::{get_block_registry, init_block_registry},
+        region::{chunkpos_to_regionpos, save::parse_region_file_name, Region},
+        tile_data::TileData,
+        Block, Chunk, CHUNK_SIZE,
+    },
+};
+
+const BLOCKS_PATH: &str = "assets/blocks.impfile";
+
+const USAGE: &str = "usage: voxelworld-tool <world directory> <command> [args]
+
+commands:
+  info                  print world metadata
+  regions               list saved regions, how many chunks they contain
+                        and whether they are corrupted
+  chunk <x> <y> <z>     dump the blocks and tile data of a chunk
+                        (add --blocks to list every non-empty block)
+  entities <x> <y> <z>  list entities saved in a region";
+
+//Loads the block registry (needed for the block flags used when loading
+//chunks) and maps block ids to the names defined in blocks.impfile
+fn load_block_names(path: &str) -> HashMap<u16, String> {
+    if !std::path::Path::new(path).exists() {
+        return HashMap::new();
+    }
+
+    if let Err(msg) = init_block_registry(path) {
+        eprintln!("E: failed to load blocks: {msg}");
+    }
+
+    get_block_registry()
+        .map(|registry| {
+            registry
+                .iter()
+                .map(|def| (def.id, def.name.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn block_to_string(block: Block, names: &HashMap<u16, String>) -> String {
+    let name = if block.id == 0 {
+        "air"
+    } else {
+        names.get(&block.id).map(|s| s.as_str()).unwrap_or("?")
+    };
+    format!("{}:{} ({name})", block.id, block.geometry)
+}
+
+fn data_type_to_string(val: &DataType) -> String {
+    match val {
+        DataType::Int(i) => i.to_string(),
+        DataType::Float(f) => f.to_string(),
+        DataType::Vec3(v) => format!("{} {} {}", v.x, v.y, v.z),
+        DataType::Str(s) => format!("\"{s}\""),
+    }
+}
+
+fn parse_coords(args: &[String]) -> Result<(i32, i32, i32), String> {
+    if args.len() < 3 {
+        return Err("expected 3 coordinates".to_string());
+    }
+    let mut coords = [0i32; 3];
+    for (i, c) in coords.iter_mut().enumerate() {
+        *c = args[i]
+            .parse::<i32>()
+            .map_err(|_| format!("invalid coordinate: {}", args[i]))?;
+    }
+    Ok((coords[0], coords[1], coords[2]))
+}
+
+fn print_info(world_path: &str) -> Result<(), String> {
+    let path = world_path.to_string() + "world.impfile";
+    let entries = impfile::parse_file(&path);
+    if entries.is_empty() {
+        return Err(format!("failed to read {path}"));
+    }
+
+    for entry in entries {
+        println!("{}:", entry.get_name());
+        let mut vars = entry.get_all_vars();
+        vars.sort();
+        for (name, val) in vars {
+            println!("  {name} = {val}");
+        }
+    }
+    Ok(())
+}
+
+fn list_regions(world_path: &str) -> Result<(), String> {
+    let chunk_dir = world_path.to_string() + CHUNK_PATH;
+    let dir = std::fs::read_dir(&chunk_dir).map_err(|e| format!("{chunk_dir}: {e}"))?;
+
+    let mut regions = vec![];
+    for entry in dir.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(pos) = parse_region_file_name(&name) {
+            let sz = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            regions.push((pos, sz));
+        }
+    }
+    regions.sort();
+
+    for ((x, y, z), sz) in &regions {
+        let chunk_count = match Region::load_region(world_path, *x, *y, *z) {
+            Some(region) => region.chunks.iter().flatten().count(),
+            None => 0,
+        };
+        let status = match Region::check_region_file(world_path, *x, *y, *z) {
+            Ok(version) => format!("version {version}"),
+            Err(msg) => format!("CORRUPTED: {msg}"),
+        };
+        println!("region {x} {y} {z}: {chunk_count} chunks, {sz} bytes, {status}");
+    }
+    println!("{} regions", regions.len());
+    Ok(())
+}
+
+fn print_tile_data(chunk: &Chunk) {
+    let mut tiles: Vec<((i32, i32, i32), TileData)> = chunk
+        .tiles_to_data_tables()
+        .iter()
+        .filter_map(TileData::from_data_table)
+        .collect();
+    tiles.sort_by_key(|(pos, _)| *pos);
+
+    println!("tile data: {}", tiles.len());
+    for ((x, y, z), tile_data) in tiles {
+        println!("  {x} {y} {z}:");
+        let items: Vec<String> = tile_data
+            .get_items()
+            .iter()
+            .filter(|item| !item.is_empty())
+            .map(|item| item_to_string(*item))
+            .collect();
+        println!("    items: [{}]", items.join(" "));
+        let mut values: Vec<(&String, &DataType)> = tile_data.values.iter().collect();
+        values.sort_by_key(|(name, _)| *name);
+        for (name, val) in values {
+            println!("    {name} = {}", data_type_to_string(val));
+        }
+    }
+}
+
+fn dump_chunk(world_path: &str, args: &[String]) -> Result<(), String> {
+    let (x, y, z) = parse_coords(args)?;
+    let list_blocks = args.iter().any(|arg| arg == "--blocks");
+    let names = load_block_names(BLOCKS_PATH);
+
+    let (rx, ry, rz) = chunkpos_to_regionpos(x, y, z);
+    let region = Region::load_region(world_path, rx, ry, rz)
+        .ok_or(format!("region {rx} {ry} {rz} does not exist"))?;
+    let chunk = region
+        .chunks
+        .iter()
+        .flatten()
+        .find(|chunk| {
+            let pos = chunk.get_chunk_pos();
+            (pos.x, pos.y, pos.z) == (x, y, z)
+        })
+        .ok_or(format!("chunk {x} {y} {z} has not been generated"))?;
+
+    println!("chunk {x} {y} {z} (region {rx} {ry} {rz})");
+    let rle = chunk.rle_encode();
+    println!("runs: {}", rle.len());
+    let mut index = 0;
+    for (count, block) in &rle {
+        let end = index + *count as usize - 1;
+        println!(
+            "  [{index}..{end}] {count} x {}",
+            block_to_string(*block, &names)
+        );
+        index += *count as usize;
+    }
+
+    if list_blocks {
+        println!("blocks:");
+        for iy in 0..CHUNK_SIZE {
+            for iz in 0..CHUNK_SIZE {
+                for ix in 0..CHUNK_SIZE {
+                    let block = chunk.get_block_relative(ix, iy, iz);
+                    if block.id == 0 {
+                        continue;
+                    }
+                    let bx = x * CHUNK_SIZE as i32 + ix as i32;
+                    let by = y * CHUNK_SIZE as i32 + iy as i32;
+                    let bz = z * CHUNK_SIZE as i32 + iz as i32;
+                    println!("  {bx} {by} {bz} {}", block_to_string(block, &names));
+                }
+            }
+        }
+    }
+
+    print_tile_data(chunk);
+    Ok(())
+}
+
+fn list_entities(world_path: &str, args: &[String]) -> Result<(), String> {
+    let (x, y, z) = parse_coords(args)?;
+    let region = EntityRegion::load_region(world_path, x, y, z).ok_or(format!(
+        "{world_path}{ENTITIES_PATH}: no entities saved for region {x} {y} {z}"
+    ))?;
+
+    let names = load_block_names(BLOCKS_PATH);
+    println!("dropped items: {}", region.dropped_items.len());
+    for dropped_item in &region.dropped_items {
+        let pos = dropped_item.pos();
+        println!(
+            "  {} {} {}: {}",
+            pos.x,
+            pos.y,
+            pos.z,
+            item_to_string(dropped_item.item)
+        );
+    }
+
+    println!("falling blocks: {}", region.falling_blocks.len());
+    for falling_block in &region.falling_blocks {
+        let pos = falling_block.pos();
+        println!(
+            "  {} {} {}: {}",
+            pos.x,
+            pos.y,
+            pos.z,
+            block_to_string(falling_block.block, &names)
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("{USAGE}");
+        std::process::exit(1);
+    }
+
+    let mut world_path = args[1].clone();
+    if !world_path.ends_with('/') {
+        world_path.push('/');
+    }
+
+    let res = match args[2].as_str() {
+        "info" => print_info(&world_path),
+        "regions" => list_regions(&world_path),
+        "chunk" => dump_chunk(&world_path, &args[3..]),
+        "entities" => list_entities(&world_path, &args[3..]),
+        cmd => Err(format!("unknown command: {cmd}\n{USAGE}")),
+    };
+
+    if let Err(msg) = res {
+        eprintln!("E: {msg}");
+        std::process::exit(1);
+    }
+}
@@ -67,6 +67,7 @@ pub struct DataTable {
 
 #[allow(dead_code)]
 impl DataTable {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             values: HashMap::new(),
@@ -322,3 +323,161 @@ pub fn get_table_list_bytes(name: &str, tables: &[DataTable]) -> Vec<u8> {
     }
     bytes
 }
+
+pub const HEADER_SIZE: usize = 6;
+
+//Creates a file header: 4 byte magic number followed by a u16 version
+pub fn header_bytes(magic: &[u8; 4], version: u16) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.extend(version.to_be_bytes());
+    bytes
+}
+
+//Returns (version, header size) of a file that may start with a header,
+//files that were saved before headers were added do not start with the
+//magic number and are treated as version 0 with no header
+pub fn read_header(bytes: &[u8], magic: &[u8; 4]) -> (u16, usize) {
+    if bytes.len() < HEADER_SIZE || bytes[0..4] != magic[..] {
+        return (0, 0);
+    }
+    let version = u16::from_be_bytes([bytes[4], bytes[5]]);
+    (version, HEADER_SIZE)
+}
+
+//CRC-32 (IEEE) lookup table
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in bytes {
+        crc = CRC32_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Compression {
+    None,
+    Deflate,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        if cfg!(feature = "compression") {
+            Compression::Deflate
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl Compression {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Deflate => 1,
+        }
+    }
+
+    pub fn from_u8(val: u8) -> Result<Self, String> {
+        match val {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Deflate),
+            _ => Err(format!("unknown compression type {val}")),
+        }
+    }
+}
+
+//Returns (compression, compressed data), if compression is not supported by
+//this build then the data is returned uncompressed
+pub fn compress(data: &[u8], compression: Compression) -> (Compression, Vec<u8>) {
+    match compression {
+        #[cfg(feature = "compression")]
+        Compression::Deflate => (
+            Compression::Deflate,
+            miniz_oxide::deflate::compress_to_vec(data, 6),
+        ),
+        _ => (Compression::None, data.to_vec()),
+    }
+}
+
+pub fn decompress(data: &[u8], compression: Compression) -> Result<Vec<u8>, String> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        #[cfg(feature = "compression")]
+        Compression::Deflate => miniz_oxide::inflate::decompress_to_vec(data)
+            .map_err(|e| format!("failed to decompress: {e}")),
+        #[cfg(not(feature = "compression"))]
+        Compression::Deflate => Err("compression is not supported by this build".to_string()),
+    }
+}
+
+//Describes the layout of a versioned save file:
+//header, compression type, crc32 checksum of the stored data, stored data
+//Older versions of the file are missing the fields that were added later
+pub struct FileFormat {
+    pub magic: [u8; 4],
+    pub version: u16,
+    //Version that added the checksum
+    pub checksum_version: u16,
+    //Version that added the compression type
+    pub compression_version: u16,
+}
+
+impl FileFormat {
+    pub fn encode(&self, data: &[u8], compression: Compression) -> Vec<u8> {
+        let (compression, stored) = compress(data, compression);
+        let mut bytes = header_bytes(&self.magic, self.version);
+        bytes.push(compression.to_u8());
+        bytes.extend(crc32(&stored).to_be_bytes());
+        bytes.extend(stored);
+        bytes
+    }
+
+    //Returns (version, data), if verify is false then the checksum is ignored
+    pub fn decode(&self, bytes: &[u8], verify: bool) -> Result<(u16, Vec<u8>), String> {
+        let (version, header_sz) = read_header(bytes, &self.magic);
+        if version > self.version {
+            return Err(format!("unsupported version {version}"));
+        }
+        let mut data = &bytes[header_sz..];
+
+        let mut compression = Compression::None;
+        if version >= self.compression_version {
+            let compression_type = *data.first().ok_or("missing compression type")?;
+            compression = Compression::from_u8(compression_type)?;
+            data = &data[1..];
+        }
+
+        if version >= self.checksum_version {
+            if data.len() < size_of::<u32>() {
+                return Err("missing checksum".to_string());
+            }
+            let checksum = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            data = &data[size_of::<u32>()..];
+            if verify && crc32(data) != checksum {
+                return Err("checksum does not match, file is corrupted".to_string());
+            }
+        }
+
+        Ok((version, decompress(data, compression)?))
+    }
+}
@@ -146,3 +146,64 @@ fn test_bin_data4() {
         }
     }
 }
+
+#[test]
+fn test_header() {
+    let mut bytes = header_bytes(b"TEST", 3);
+    bytes.extend([1, 2, 3]);
+    assert_eq!(read_header(&bytes, b"TEST"), (3, HEADER_SIZE));
+    assert_eq!(&bytes[HEADER_SIZE..], &[1, 2, 3]);
+
+    //No header
+    assert_eq!(read_header(&[0, 0, 0, 13, 1, 2, 3], b"TEST"), (0, 0));
+    assert_eq!(read_header(&[], b"TEST"), (0, 0));
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xcbf43926);
+}
+
+#[cfg(test)]
+const TEST_FORMAT: FileFormat = FileFormat {
+    magic: *b"TEST",
+    version: 3,
+    checksum_version: 2,
+    compression_version: 3,
+};
+
+#[test]
+fn test_checksum() {
+    let data = [1, 2, 3, 4];
+    let mut bytes = TEST_FORMAT.encode(&data, Compression::None);
+    let res = TEST_FORMAT.decode(&bytes, true);
+    assert_eq!(res, Ok((3, data.to_vec())));
+
+    //Corrupt the data
+    let last = bytes.len() - 1;
+    bytes[last] = 0;
+    assert!(TEST_FORMAT.decode(&bytes, true).is_err());
+    assert!(TEST_FORMAT.decode(&bytes, false).is_ok());
+
+    //Older files have no checksum
+    let mut bytes = header_bytes(b"TEST", 1);
+    bytes.extend([5, 6]);
+    let res = TEST_FORMAT.decode(&bytes, true);
+    assert_eq!(res, Ok((1, vec![5, 6])));
+
+    //Newer files can not be read
+    let bytes = header_bytes(b"TEST", 4);
+    assert!(TEST_FORMAT.decode(&bytes, true).is_err());
+}
+
+#[test]
+fn test_compression() {
+    let data: Vec<u8> = (0..4096).map(|i| (i / 64) as u8).collect();
+    let bytes = TEST_FORMAT.encode(&data, Compression::Deflate);
+    if cfg!(feature = "compression") {
+        assert!(bytes.len() < data.len());
+    }
+    let res = TEST_FORMAT.decode(&bytes, true);
+    assert_eq!(res, Ok((3, data)));
+}
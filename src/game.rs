@@ -8,9 +8,11 @@ pub mod input;
 pub mod inventory;
 pub mod inventory_screen;
 pub mod load;
+pub mod migrate;
 pub mod physics;
 pub mod player;
 pub mod save;
+pub mod save_thread;
 pub mod settings;
 pub mod update;
 
@@ -21,7 +23,6 @@ use self::settings::Settings;
 use crate::game::inventory::Hotbar;
 use crate::impfile;
 use crate::voxel::block_info::{load_block_info, BlockInfo, BlockInfoTable};
-use crate::voxel::world::WorldGenType;
 use crate::voxel::Block;
 use crate::{assets::texture::load_image_pixels, game::player::PLAYER_HEIGHT, World};
 use assets::models::ModelManager;
@@ -54,7 +55,7 @@ pub enum BlockMenuShape {
 //Application config values, these are not meant to be changed by normal users
 struct Config {
     font_path: String,
-    block_menu: Vec<u8>,
+    block_menu: Vec<u16>,
 }
 
 impl Config {
@@ -154,6 +155,7 @@ pub struct Game {
 
 impl Game {
     //Create game state
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             paused: false,
@@ -212,15 +214,17 @@ impl Game {
         self.mousey = 0.0;
     }
 
-    //Generate world
+    //Generate world, returns an error if the generator options are invalid
     pub fn generate_world(
         &mut self,
         seed: u32,
         range: i32,
-        gen_type: WorldGenType,
+        gen_type: &str,
+        gen_options: &str,
         game_mode: GameMode,
-    ) {
-        self.world = World::new(seed, range, gen_type, game_mode);
+    ) -> Result<(), String> {
+        self.world = World::new(seed, range, gen_type, gen_options, game_mode)?;
+        self.world.compression = self.settings.get_compression();
         eprintln!("Created world with seed: {}", self.world.get_seed());
         self.world.generate_world();
 
@@ -237,6 +241,7 @@ impl Game {
         if self.game_mode() == GameMode::Creative {
             self.player.hotbar = Hotbar::init_hotbar();
         }
+        Ok(())
     }
 
     pub fn load_config(&mut self, path: &str) {
@@ -251,7 +256,7 @@ impl Game {
         self.cfg.block_menu = e
             .get_var("block_menu")
             .split(",")
-            .map(|s| s.parse::<u8>().unwrap_or(1))
+            .map(|s| s.parse::<u16>().unwrap_or(1))
             .collect();
     }
 
@@ -259,7 +264,7 @@ impl Game {
         self.settings = Settings::load(path);
     }
 
-    pub fn get_block_menu(&self) -> &[u8] {
+    pub fn get_block_menu(&self) -> &[u16] {
         &self.cfg.block_menu
     }
 
@@ -275,11 +280,12 @@ impl Game {
         self.world.game_mode
     }
 
-    pub fn load_block_info(&mut self, path: &str) {
-        self.block_info = load_block_info(path);
+    pub fn load_block_info(&mut self, item_alias_path: &str) -> Result<(), String> {
+        self.block_info = load_block_info(item_alias_path)?;
+        Ok(())
     }
 
-    pub fn get_block_info(&self, id: u8) -> BlockInfo {
+    pub fn get_block_info(&self, id: u16) -> BlockInfo {
         self.block_info
             .get(&id)
             .cloned()
@@ -41,18 +41,21 @@ impl Game {
         self.fonts.clone()
     }
 
-    pub fn load_assets(&mut self) {
+    //Returns an error if the block drops are invalid
+    pub fn load_assets(&mut self) -> Result<(), String> {
         load_font(&self.cfg.font_path, &mut self.fonts);
         self.models.add_default_models();
         self.shaders.load_shaders("assets/shaders.impfile");
         self.textures.load_textures("assets/textures.impfile");
 
-        self.load_block_info("assets/block_info.impfile");
+        self.load_block_info("assets/item_aliases.impfile")
+            .map_err(|msg| format!("invalid block drops:\n{msg}"))?;
         self.recipe_table
             .load_recipes("assets/item_aliases.impfile", "assets/recipes.impfile");
         self.recipe_table
             .load_furnace("assets/item_aliases.impfile", "assets/furnace.impfile");
         self.leftover_table =
             load_leftover_table("assets/item_aliases.impfile", "assets/leftovers.impfile");
+        Ok(())
     }
 }
@@ -7,6 +7,7 @@ pub struct ModelManager {
 }
 
 impl ModelManager {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             vaos: HashMap::new(),
@@ -24,6 +24,7 @@ pub struct ShaderManager {
 }
 
 impl ShaderManager {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             shaders: HashMap::new(),
@@ -35,6 +35,7 @@ fn load_texture(path: &str) -> Texture {
 }
 
 impl TextureManager {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             textures: HashMap::new(),
@@ -52,7 +52,7 @@ pub fn get_action_icon_positions(
         .collect()
 }
 
-pub fn get_positions(gamestate: &Game, startx: f32, starty: f32) -> Vec<(u8, Vector2<f32>)> {
+pub fn get_positions(gamestate: &Game, startx: f32, starty: f32) -> Vec<(u16, Vector2<f32>)> {
     let mut positions = vec![];
 
     for (i, block) in gamestate.get_block_menu().iter().enumerate() {
@@ -92,7 +92,7 @@ pub fn get_selected<T>(menu: &[(T, Vector2<f32>)], mousex: f32, mousey: f32) ->
     None
 }
 
-pub fn select_block(gamestate: &mut Game, menu: &[(u8, Vector2<f32>)], mousex: f32, mousey: f32) {
+pub fn select_block(gamestate: &mut Game, menu: &[(u16, Vector2<f32>)], mousex: f32, mousey: f32) {
     if let Some(i) = get_selected(menu, mousex, mousey) {
         if gamestate.get_mouse_state(MouseButton::Left) == KeyState::JustPressed {
             let mut block = Block::new_id(menu[i].0);
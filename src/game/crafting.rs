@@ -7,7 +7,7 @@ use super::inventory::{
 use crate::{
     game::crafting::tool_recipes::generate_tool_recipes,
     impfile::{self, Entry},
-    voxel::{Block, FULL_BLOCK, SLAB, STAIR},
+    voxel::{block_registry::get_block_registry, Block, FULL_BLOCK, SLAB, STAIR},
 };
 use std::collections::HashMap;
 
@@ -45,10 +45,24 @@ fn add_block_variants(aliases: &mut ItemAliases, name: &str, item: Item) {
 pub fn load_item_aliases(path: &str) -> ItemAliases {
     let mut aliases = ItemAliases::new();
 
+    //The names of the blocks are also aliases
+    if let Some(registry) = get_block_registry() {
+        for def in registry.iter() {
+            let item = Item::Block(Block::new_id(def.id), 1);
+            aliases.insert(def.name.clone(), item);
+            add_block_variants(&mut aliases, &def.name, item);
+        }
+    }
+
     let entries = impfile::parse_file(path);
     for e in entries {
         let vars = e.get_all_vars();
         for (name, val) in vars {
+            if aliases.contains_key(&name) {
+                eprintln!("E: item alias '{name}' in {path} is already the name of a block");
+                continue;
+            }
+
             if let Ok(item) = string_to_item_err(&val) {
                 let reduced = reduce_amt(item);
                 aliases.insert(name.clone(), reduced);
@@ -68,6 +82,7 @@ pub struct Recipe {
 }
 
 impl Recipe {
+    #[allow(clippy::result_unit_err)]
     pub fn from_entry(entry: &impfile::Entry, item_aliases: &ItemAliases) -> Result<Self, ()> {
         let w = entry.get_var("width").parse::<usize>().unwrap_or(1);
         let h = entry.get_var("height").parse::<usize>().unwrap_or(1);
@@ -237,15 +252,15 @@ fn generate_stair_recipe(block: Block) -> Recipe {
 
 //Generates wool recipes (for combining wool together with dye to color it)
 fn generate_wool_recipes() -> Vec<Recipe> {
-    const WHITE_WOOL_ID: u8 = 57;
-    const WHITE_DYE_ID: u8 = 96;
+    const WHITE_WOOL_ID: u16 = 57;
+    const WHITE_DYE_ID: u16 = 96;
     (1..=11)
         .map(|index| {
             let mut grid = Inventory::empty_with_sz(2, 1);
             //Wool
             grid.set_item(0, 0, Item::Block(Block::new_id(WHITE_WOOL_ID), 1));
-            let dye_id: u8 = WHITE_DYE_ID + index;
-            grid.set_item(1, 0, Item::Sprite(dye_id as u16, 1));
+            let dye_id = WHITE_DYE_ID + index;
+            grid.set_item(1, 0, Item::Sprite(dye_id, 1));
             let dyed_wool = Item::Block(Block::new_id(WHITE_WOOL_ID + index), 1);
             Recipe {
                 ingredients: grid,
@@ -339,6 +354,7 @@ fn add_block_fuel_variants(block: Block, fuel_amt: f32, block_variants: &mut Vec
 }
 
 impl RecipeTable {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             recipes: vec![],
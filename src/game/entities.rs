@@ -1,8 +1,12 @@
 pub mod dropped_item;
+pub mod falling_block;
 pub mod region;
 pub mod save;
 
-use self::dropped_item::DroppedItemTable;
+use self::{
+    dropped_item::DroppedItemTable,
+    falling_block::{FallingBlock, FallingBlockTable},
+};
 use super::{
     physics::{get_block_collision, scan_block_hitbox, Hitbox},
     player::Player,
@@ -265,7 +269,7 @@ impl Entity {
     }
 
     //Returns true if the player is intersecting a specific block type
-    pub fn is_intersecting(&self, world: &World, block_id: u8) -> bool {
+    pub fn is_intersecting(&self, world: &World, block_id: u16) -> bool {
         let ix = self.position.x.floor() as i32;
         let iy = self.position.y.floor() as i32;
         let iz = self.position.z.floor() as i32;
@@ -303,17 +307,32 @@ impl Entity {
 //Entities
 pub struct EntitiesTable {
     pub dropped_items: DroppedItemTable,
+    pub falling_blocks: FallingBlockTable,
 }
 
 impl EntitiesTable {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             dropped_items: DroppedItemTable::new(),
+            falling_blocks: FallingBlockTable::new(),
         }
     }
 
-    pub fn update(&mut self, dt: f32, world: &World, player: &mut Player) {
+    pub fn update(&mut self, dt: f32, world: &mut World, player: &mut Player) {
         self.dropped_items.simulate(dt, world, player);
+
+        //Blocks that started falling since the last update
+        for ((x, y, z), block) in world.take_falling_blocks() {
+            self.falling_blocks
+                .add_block(FallingBlock::new(block, x, y, z));
+        }
+
+        for falling_block in self.falling_blocks.simulate(dt, world) {
+            if let Some(dropped_item) = falling_block.land(world) {
+                self.dropped_items.add_item(dropped_item);
+            }
+        }
     }
 
     pub fn init_empty(&mut self, world: &World) {
@@ -217,6 +217,7 @@ pub struct DroppedItemTable {
 }
 
 impl DroppedItemTable {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             item_list: HashMap::new(),
@@ -0,0 +1,280 @@
+/*
+ * Blocks with gravity (sand) turn into a falling block entity when the block
+ * below them is removed and are placed again where they land. If they land
+ * on a block that is not a full block or inside of a plant or torch then
+ * they break and drop as an item instead.
+ * */
+
+use super::{dropped_item::DroppedItem, Entity, Vec3, GRAVITY};
+use crate::{
+    bin_data::DataTable,
+    game::inventory::Item,
+    voxel::{
+        world::{get_simulation_dist, in_sim_range},
+        Block, World, EMPTY_BLOCK, FULL_BLOCK,
+    },
+};
+use cgmath::vec3;
+use std::collections::HashMap;
+
+//Slightly smaller than a block so that it does not get caught on the blocks
+//next to it while falling
+pub const FALLING_BLOCK_SIZE: f32 = 0.98;
+
+#[derive(Clone)]
+pub struct FallingBlock {
+    pub block: Block,
+    pub entity: Entity,
+    landed: bool,
+}
+
+impl FallingBlock {
+    //(x, y, z) is the position of the block that started falling
+    pub fn new(block: Block, x: i32, y: i32, z: i32) -> Self {
+        Self {
+            block,
+            entity: Entity::new(
+                vec3(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5),
+                vec3(FALLING_BLOCK_SIZE, FALLING_BLOCK_SIZE, FALLING_BLOCK_SIZE),
+                vec3(0.0, 0.0, 0.0),
+            ),
+            landed: false,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, world: &World) {
+        //A block was placed where the falling block is
+        if self.entity.stuck(world) {
+            self.landed = true;
+            return;
+        }
+
+        self.entity.check_y_collision(world);
+        self.entity.translate(dt * 0.5, world);
+        if self.entity.falling {
+            self.entity.velocity.y -= GRAVITY * dt;
+        }
+        self.entity.translate(dt * 0.5, world);
+        self.landed = !self.entity.falling;
+
+        //Fell out of the world
+        if self.pos().y < world.bottom() as f32 {
+            self.entity.destroy();
+        }
+    }
+
+    //Returns the position of the block that the falling block landed in,
+    //the bottom of the entity is slightly above the block it landed on
+    pub fn landing_pos(&self) -> (i32, i32, i32) {
+        let pos = self.pos();
+        let bottom = pos.y - self.entity.dimensions.y / 2.0;
+        (
+            pos.x.floor() as i32,
+            (bottom + 0.25).floor() as i32,
+            pos.z.floor() as i32,
+        )
+    }
+
+    //Places the block where it landed, returns a dropped item if the block
+    //broke instead
+    pub fn land(&self, world: &mut World) -> Option<DroppedItem> {
+        let (x, y, z) = self.landing_pos();
+        let current = world.get_block(x, y, z);
+        let below = world.get_block(x, y - 1, z);
+        //Plants and torches are not replaced
+        let free = !current.fluid_destructibe()
+            && (current.id == EMPTY_BLOCK || current.is_fluid() || current.replaceable());
+        let on_full_block = below.id != EMPTY_BLOCK
+            && below.shape() == FULL_BLOCK
+            && !below.non_voxel_geometry()
+            && !below.no_hitbox();
+
+        if free && on_full_block && !world.out_of_bounds(x, y, z) {
+            world.place_block(x, y, z, self.block);
+            return None;
+        }
+
+        let pos = self.pos();
+        let item = Item::Block(self.block, 1);
+        Some(DroppedItem::new(item, pos.x, pos.y, pos.z))
+    }
+
+    pub fn get_chunk(&self) -> (i32, i32, i32) {
+        self.entity.get_chunk()
+    }
+
+    pub fn destroyed(&self) -> bool {
+        self.entity.destroyed
+    }
+
+    pub fn pos(&self) -> Vec3 {
+        self.entity.position
+    }
+
+    pub fn scale(&self) -> Vec3 {
+        self.entity.dimensions
+    }
+
+    pub fn to_data_table(&self) -> DataTable {
+        let mut data_table = self.entity.to_data_table();
+        data_table.add_int("block", self.block.id as i64);
+        data_table.add_int("geometry", self.block.geometry as i64);
+        data_table
+    }
+
+    pub fn from_data_table(data_table: &DataTable) -> Option<Self> {
+        let entity = Entity::from_data_table(data_table)?;
+        let mut block = Block::new_id(data_table.get_int("block")? as u16);
+        block.geometry = data_table.get_int("geometry").unwrap_or(0) as u8;
+
+        Some(Self {
+            block,
+            entity,
+            landed: false,
+        })
+    }
+}
+
+pub struct FallingBlockTable {
+    block_list: HashMap<(i32, i32, i32), Vec<FallingBlock>>,
+}
+
+impl FallingBlockTable {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            block_list: HashMap::new(),
+        }
+    }
+
+    pub fn add_block(&mut self, falling_block: FallingBlock) {
+        let chunkpos = falling_block.get_chunk();
+        self.block_list
+            .entry(chunkpos)
+            .or_default()
+            .push(falling_block);
+    }
+
+    pub fn blocks(&self) -> &HashMap<(i32, i32, i32), Vec<FallingBlock>> {
+        &self.block_list
+    }
+
+    pub fn remove(&mut self, pos: (i32, i32, i32)) {
+        self.block_list.remove(&pos);
+    }
+
+    //Returns the falling blocks that landed, these are removed from the
+    //table and should be placed in the world
+    pub fn simulate(&mut self, dt: f32, world: &World) -> Vec<FallingBlock> {
+        let sim_dist = get_simulation_dist(world);
+        let center = world.get_center();
+        for (pos, list) in &mut self.block_list {
+            if !world.chunks.contains_key(pos) {
+                continue;
+            }
+
+            //Out of range
+            if !in_sim_range(center, *pos, sim_dist) {
+                continue;
+            }
+
+            for falling_block in list {
+                falling_block.update(dt, world);
+            }
+        }
+
+        let mut landed = vec![];
+        //Falling blocks that are no longer in the correct chunk
+        let mut moved = vec![];
+        for (chunkpos, list) in &mut self.block_list {
+            for falling_block in std::mem::take(list) {
+                if falling_block.destroyed() {
+                    continue;
+                }
+
+                if falling_block.landed {
+                    landed.push(falling_block);
+                } else if falling_block.get_chunk() != *chunkpos {
+                    moved.push(falling_block);
+                } else {
+                    list.push(falling_block);
+                }
+            }
+        }
+        self.block_list.retain(|_, list| !list.is_empty());
+
+        for falling_block in moved {
+            self.add_block(falling_block);
+        }
+        landed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::GameMode,
+        voxel::{init_test_registries, SLAB},
+    };
+
+    //Simulates the falling blocks until they land, returns the items
+    //dropped by blocks that broke
+    fn simulate_until_landed(table: &mut FallingBlockTable, world: &mut World) -> Vec<DroppedItem> {
+        let mut dropped = vec![];
+        for _ in 0..200 {
+            for falling_block in table.simulate(1.0 / 60.0, world) {
+                dropped.extend(falling_block.land(world));
+            }
+
+            if table.blocks().is_empty() {
+                break;
+            }
+        }
+        dropped
+    }
+
+    #[test]
+    fn test_falling_blocks() {
+        init_test_registries();
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
+        let mut table = FallingBlockTable::new();
+
+        //Lands on stone and is placed again
+        world.set_block(0, 0, 0, Block::new_id(2));
+        table.add_block(FallingBlock::new(Block::new_id(11), 0, 8, 0));
+        //Lands on a slab and breaks
+        let mut slab = Block::new_id(2);
+        slab.set_shape(SLAB);
+        world.set_block(4, 0, 0, slab);
+        table.add_block(FallingBlock::new(Block::new_id(11), 4, 8, 0));
+        //Lands in a plant and breaks
+        world.set_block(8, 0, 0, Block::new_id(1));
+        world.set_block(8, 1, 0, Block::new_id(54));
+        table.add_block(FallingBlock::new(Block::new_id(11), 8, 8, 0));
+
+        let dropped = simulate_until_landed(&mut table, &mut world);
+        assert!(table.blocks().is_empty());
+        assert_eq!(world.get_block(0, 1, 0).id, 11);
+        assert_eq!(world.get_block(4, 1, 0).id, EMPTY_BLOCK);
+        assert_eq!(world.get_block(8, 1, 0).id, 54);
+        assert_eq!(dropped.len(), 2);
+        for dropped_item in dropped {
+            assert!(matches!(dropped_item.item, Item::Block(block, 1) if block.id == 11));
+        }
+    }
+
+    #[test]
+    fn test_falling_block_data_table() {
+        let mut block = Block::new_id(11);
+        block.geometry = 3;
+        let mut falling_block = FallingBlock::new(block, 1, 2, 3);
+        falling_block.entity.velocity.y = -5.0;
+
+        let data_table = falling_block.to_data_table();
+        let loaded = FallingBlock::from_data_table(&data_table).unwrap();
+        assert_eq!(loaded.block, block);
+        assert_eq!(loaded.pos(), falling_block.pos());
+        assert_eq!(loaded.entity.velocity, falling_block.entity.velocity);
+    }
+}
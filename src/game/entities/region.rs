@@ -1,19 +1,26 @@
-use super::{dropped_item::DroppedItem, EntitiesTable, ENTITIES_PATH};
+use super::{dropped_item::DroppedItem, falling_block::FallingBlock, EntitiesTable, ENTITIES_PATH};
 use crate::{
-    bin_data,
+    bin_data::{self, Compression, FileFormat},
+    game::save::{read_file_or_backup, write_file_atomic},
     voxel::region::{
         chunkpos_to_regionpos, regionpos_to_chunkpos, save::region_file_name, REGION_SIZE_I32,
     },
 };
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{Read, Write},
-    path::Path,
+use std::{collections::HashSet, path::Path};
+
+//Version 1: added header
+//Version 2: added checksum
+//Version 3: added compression
+pub const ENTITY_REGION_FORMAT: FileFormat = FileFormat {
+    magic: *b"VWEN",
+    version: 3,
+    checksum_version: 2,
+    compression_version: 3,
 };
 
 pub struct EntityRegion {
     pub dropped_items: Vec<DroppedItem>,
+    pub falling_blocks: Vec<FallingBlock>,
     pub loaded: HashSet<(i32, i32, i32)>,
     pub x: i32,
     pub y: i32,
@@ -24,6 +31,7 @@ impl EntityRegion {
     pub fn new(x: i32, y: i32, z: i32) -> Self {
         Self {
             dropped_items: vec![],
+            falling_blocks: vec![],
             loaded: HashSet::new(),
             x,
             y,
@@ -52,6 +60,27 @@ impl EntityRegion {
         }
     }
 
+    pub fn add_falling_block(&mut self, falling_block: FallingBlock) {
+        if falling_block.destroyed() {
+            return;
+        }
+
+        let (chunkx, chunky, chunkz) = falling_block.get_chunk();
+        let (x, y, z) = chunkpos_to_regionpos(chunkx, chunky, chunkz);
+        //Not in this region, ignore
+        if x != self.x || y != self.y || z != self.z {
+            return;
+        }
+        self.loaded.insert((chunkx, chunky, chunkz));
+        self.falling_blocks.push(falling_block);
+    }
+
+    pub fn add_falling_block_list(&mut self, falling_blocks: &[FallingBlock]) {
+        for falling_block in falling_blocks {
+            self.add_falling_block(falling_block.clone());
+        }
+    }
+
     pub fn get_data(&self) -> Vec<u8> {
         let mut data = vec![];
 
@@ -65,10 +94,21 @@ impl EntityRegion {
             &dropped_item_tables,
         ));
 
+        //Add falling blocks
+        let falling_block_tables: Vec<_> = self
+            .falling_blocks
+            .iter()
+            .map(|falling_block| falling_block.to_data_table())
+            .collect();
+        data.extend(bin_data::get_table_list_bytes(
+            "falling_blocks",
+            &falling_block_tables,
+        ));
+
         data
     }
 
-    pub fn save_region(&self, worldpath: &str) -> Result<(), String> {
+    pub fn save_region(&self, worldpath: &str, compression: Compression) -> Result<(), String> {
         let entities_dir_path = worldpath.to_string() + ENTITIES_PATH;
         let entities_path =
             entities_dir_path.clone() + region_file_name(self.x, self.y, self.z).as_str();
@@ -80,20 +120,12 @@ impl EntityRegion {
             }
         }
 
-        let data_to_write = self.get_data();
-        match File::create(&entities_path) {
-            Ok(mut file) => {
-                if let Err(msg) = file.write_all(&data_to_write) {
-                    eprintln!("Error when saving {}, {}, {}", self.x, self.y, self.z);
-                    eprintln!("E: {msg}");
-                    return Err(entities_path);
-                }
-            }
-            Err(msg) => {
-                eprintln!("Failed to save entities {}, {}, {}", self.x, self.y, self.z);
-                eprintln!("{msg}");
-                return Err(entities_path);
-            }
+        let data = self.get_data();
+        let bytes = ENTITY_REGION_FORMAT.encode(&data, compression);
+        if let Err(msg) = write_file_atomic(&entities_path, &bytes) {
+            eprintln!("Failed to save entities {}, {}, {}", self.x, self.y, self.z);
+            eprintln!("E: {msg}");
+            return Err(entities_path);
         }
 
         Ok(())
@@ -107,28 +139,27 @@ impl EntityRegion {
                 .filter_map(DroppedItem::from_data_table)
                 .collect();
         }
+
+        //Regions saved before falling blocks were added do not have any
+        if let Some(falling_blocks) = parsed_data.get("falling_blocks") {
+            region.falling_blocks = falling_blocks
+                .iter()
+                .filter_map(FallingBlock::from_data_table)
+                .collect();
+        }
         region
     }
 
+    fn from_file_bytes(bytes: &[u8], x: i32, y: i32, z: i32) -> Result<Self, String> {
+        let (_version, data) = ENTITY_REGION_FORMAT.decode(bytes, true)?;
+        let mut stream = bin_data::ByteStream::new(data);
+        let parsed_data = bin_data::parse_binary_data(&mut stream);
+        Ok(Self::from_data_tables(parsed_data, x, y, z))
+    }
+
     pub fn load_region(worldpath: &str, x: i32, y: i32, z: i32) -> Option<Self> {
         let path = format!("{worldpath}{ENTITIES_PATH}{}", region_file_name(x, y, z));
-        match File::open(&path) {
-            Ok(mut file) => {
-                let mut buf = vec![];
-                if let Err(msg) = file.read_to_end(&mut buf) {
-                    eprintln!("Failed to load {path}!");
-                    eprintln!("{msg}");
-                }
-                if buf.is_empty() {
-                    return None;
-                }
-
-                let mut stream = bin_data::ByteStream::new(buf);
-                let parsed_data = bin_data::parse_binary_data(&mut stream);
-                Some(Self::from_data_tables(parsed_data, x, y, z))
-            }
-            Err(_msg) => None,
-        }
+        read_file_or_backup(&path, |bytes| Self::from_file_bytes(bytes, x, y, z))?.ok()
     }
 }
 
@@ -143,6 +174,12 @@ pub fn get_region_entities(region: &mut EntityRegion, entities_table: &EntitiesT
                     region.add_dropped_item_list(list);
                     region.loaded.insert(pos);
                 }
+
+                //Add falling blocks
+                if let Some(list) = entities_table.falling_blocks.blocks().get(&pos) {
+                    region.add_falling_block_list(list);
+                    region.loaded.insert(pos);
+                }
             }
         }
     }
@@ -159,9 +196,22 @@ fn merge_regions(updated: &mut EntityRegion, original: &EntityRegion) {
         dropped_items.push(dropped_item.clone());
     }
     updated.add_dropped_item_list(&dropped_items);
+
+    //Merge falling blocks
+    let falling_blocks: Vec<_> = original
+        .falling_blocks
+        .iter()
+        .filter(|falling_block| !updated.loaded.contains(&falling_block.get_chunk()))
+        .cloned()
+        .collect();
+    updated.add_falling_block_list(&falling_blocks);
 }
 
-pub fn serialize_entities(worldpath: &str, mut region: EntityRegion) -> Result<(), String> {
+pub fn serialize_entities(
+    worldpath: &str,
+    mut region: EntityRegion,
+    compression: Compression,
+) -> Result<(), String> {
     let x = region.x;
     let y = region.y;
     let z = region.z;
@@ -170,5 +220,5 @@ pub fn serialize_entities(worldpath: &str, mut region: EntityRegion) -> Result<(
         merge_regions(&mut region, &original);
     }
 
-    region.save_region(worldpath)
+    region.save_region(worldpath, compression)
 }
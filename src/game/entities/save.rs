@@ -1,21 +1,27 @@
 use super::{
-    region::{get_region_entities, serialize_entities, EntityRegion},
+    region::{get_region_entities, EntityRegion},
     EntitiesTable,
 };
-use crate::voxel::{
-    region::{chunkpos_to_regionpos, regionpos_to_chunkpos, REGION_SIZE_I32},
-    world::{get_simulation_dist, in_sim_range},
-    World,
+use crate::{
+    game::save_thread::RegionKind,
+    voxel::{
+        region::{chunkpos_to_regionpos, regionpos_to_chunkpos, REGION_SIZE_I32},
+        world::{get_simulation_dist, in_sim_range},
+        World,
+    },
 };
 use std::collections::HashSet;
 
-fn save_entity_region(worldpath: &str, region: EntityRegion) {
-    if let Err(path) = serialize_entities(worldpath, region) {
-        if let Err(msg) = std::fs::remove_file(&path) {
-            eprintln!("Failed to remove {path}");
-            eprintln!("{msg}");
-        }
-    }
+fn save_entity_region(world: &World, region: EntityRegion) {
+    world
+        .saver
+        .save_entities(&world.path, region, world.compression);
+}
+
+//Waits for the entity region to be written if it is still being saved
+fn load_entity_region(world: &World, x: i32, y: i32, z: i32) -> Option<EntityRegion> {
+    world.saver.wait_for(RegionKind::Entities, x, y, z);
+    EntityRegion::load_region(&world.path, x, y, z)
 }
 
 impl EntitiesTable {
@@ -30,14 +36,19 @@ impl EntitiesTable {
         for (rx, ry, rz) in regions_to_save {
             let mut region = EntityRegion::new(rx, ry, rz);
             get_region_entities(&mut region, self);
-            save_entity_region(&world.path, region);
+            save_entity_region(world, region);
         }
     }
 
     //Save everything that is loaded
     pub fn save_all(&self, world: &World) {
         let mut regions_to_save = HashSet::new();
-        for (x, y, z) in self.dropped_items.items().keys().copied() {
+        let loaded = self
+            .dropped_items
+            .items()
+            .keys()
+            .chain(self.falling_blocks.blocks().keys());
+        for (x, y, z) in loaded.copied() {
             let regionpos = chunkpos_to_regionpos(x, y, z);
             regions_to_save.insert(regionpos);
         }
@@ -45,7 +56,7 @@ impl EntitiesTable {
         for (rx, ry, rz) in regions_to_save {
             let mut region = EntityRegion::new(rx, ry, rz);
             get_region_entities(&mut region, self);
-            save_entity_region(&world.path, region);
+            save_entity_region(world, region);
         }
 
         eprintln!("Saved entities.");
@@ -55,7 +66,12 @@ impl EntitiesTable {
         let mut to_deload = vec![];
         let mut regions_to_save = HashSet::new();
 
-        for pos in self.dropped_items.items().keys().copied() {
+        let loaded = self
+            .dropped_items
+            .items()
+            .keys()
+            .chain(self.falling_blocks.blocks().keys());
+        for pos in loaded.copied() {
             if world.is_loaded(pos) {
                 continue;
             }
@@ -67,11 +83,12 @@ impl EntitiesTable {
         for (rx, ry, rz) in regions_to_save {
             let mut region = EntityRegion::new(rx, ry, rz);
             get_region_entities(&mut region, self);
-            save_entity_region(&world.path, region);
+            save_entity_region(world, region);
         }
 
         for pos in to_deload {
             self.dropped_items.remove(pos);
+            self.falling_blocks.remove(pos);
         }
     }
 
@@ -80,6 +97,10 @@ impl EntitiesTable {
             self.dropped_items.add_item(dropped_item.clone());
         }
 
+        for falling_block in &region.falling_blocks {
+            self.falling_blocks.add_block(falling_block.clone());
+        }
+
         let (startx, starty, startz) = regionpos_to_chunkpos(region.x, region.y, region.z);
         for x in startx..(startx + REGION_SIZE_I32) {
             for y in starty..(starty + REGION_SIZE_I32) {
@@ -96,7 +117,7 @@ impl EntitiesTable {
             return false;
         }
 
-        if let Some(region) = EntityRegion::load_region(&world.path, x, y, z) {
+        if let Some(region) = load_entity_region(world, x, y, z) {
             self.add_region(region);
             return true;
         }
@@ -146,7 +167,7 @@ impl EntitiesTable {
         }
 
         for (x, y, z) in to_load {
-            if let Some(region) = EntityRegion::load_region(&world.path, x, y, z) {
+            if let Some(region) = load_entity_region(world, x, y, z) {
                 self.add_region(region);
             }
         }
@@ -67,7 +67,7 @@ pub fn update_game(gamestate: &mut Game, chunktables: &mut ChunkTables, dt: f32)
     }
     gamestate
         .entities
-        .update(dt, &gamestate.world, &mut gamestate.player);
+        .update(dt, &mut gamestate.world, &mut gamestate.player);
     //Destroy and place blocks
     gamestate.build(chunktables, dt);
     gamestate.update_build_cooldown(dt);
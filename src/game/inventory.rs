@@ -23,7 +23,7 @@ pub enum Item {
     //Atlas (or id), food info
     Food(u16, FoodInfo),
     //Block id
-    Bucket(u8),
+    Bucket(u16),
     Empty,
 }
 
@@ -120,11 +120,12 @@ pub fn item_to_string(item: Item) -> String {
     }
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn string_to_item_err(s: &str) -> Result<Item, ()> {
     let tokens: Vec<String> = s.split(",").map(|s| s.to_string()).collect();
 
     if tokens.len() == 4 && tokens[0] == "block" {
-        let id = tokens[1].parse::<u8>().unwrap_or(1);
+        let id = tokens[1].parse::<u16>().unwrap_or(1);
         let geometry = tokens[2].parse::<u8>().unwrap_or(0);
         let amt = tokens[3].parse::<u8>().unwrap_or(1);
 
@@ -153,7 +154,7 @@ pub fn string_to_item_err(s: &str) -> Result<Item, ()> {
         let info = string_to_food_info(&tokens[2]).map_err(|_| ())?;
         Ok(Item::Food(id, info))
     } else if tokens.len() == 2 && tokens[0] == "bucket" {
-        let blockid = tokens[1].parse::<u8>().unwrap_or(0);
+        let blockid = tokens[1].parse::<u16>().unwrap_or(0);
         Ok(Item::Bucket(blockid))
     } else if tokens.len() == 1 && tokens[0] == "empty" {
         Ok(Item::Empty)
@@ -385,6 +386,7 @@ pub struct Inventory {
 }
 
 impl Inventory {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             height: 0,
@@ -27,6 +27,7 @@ impl Display for FoodInfo {
     }
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn string_to_food_info(s: &str) -> Result<FoodInfo, ()> {
     let tokens: Vec<String> = s.split("/").map(|s| s.to_string()).collect();
 
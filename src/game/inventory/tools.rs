@@ -89,6 +89,7 @@ pub fn tool_type_to_string(tool_type: ToolType) -> String {
     .to_string()
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn string_to_tool_type(s: &str) -> Result<ToolType, ()> {
     match s {
         "pickaxe" => Ok(ToolType::Pickaxe),
@@ -100,6 +101,7 @@ pub fn string_to_tool_type(s: &str) -> Result<ToolType, ()> {
     }
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn string_to_tool_material(s: &str) -> Result<ToolMaterial, ()> {
     match s {
         "wood" => Ok(ToolMaterial::Wood),
@@ -188,6 +190,7 @@ fn parse_tool_info_material(tokens: &[String]) -> Result<ToolInfo, ()> {
     Ok(ToolInfo::new_tool(info_tool_type, material))
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn string_to_tool_info(s: &str) -> Result<ToolInfo, ()> {
     let tokens: Vec<String> = s.split("/").map(|s| s.to_string()).collect();
 
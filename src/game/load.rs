@@ -1,5 +1,6 @@
 use super::{
     inventory::{Hotbar, Inventory},
+    migrate::migrate_world,
     player::Player,
     Camera, Game,
 };
@@ -45,18 +46,23 @@ fn load_inventory(path: &str) -> (Hotbar, Inventory) {
 }
 
 impl Game {
-    pub fn load_world(&mut self, world_path: &str) {
+    //Returns an error if the world could not be upgraded to the current
+    //save format, in that case nothing is loaded
+    pub fn load_world(&mut self, world_path: &str) -> Result<(), String> {
+        migrate_world(world_path)?;
+        let range = self.settings.get_range() as i32;
+        let world = World::load_world_metadata(world_path, range)?;
         let cam_path = world_path.to_string() + "camera.impfile";
         self.cam = load_camera(&cam_path);
         let player_path = world_path.to_string() + "player.impfile";
         self.player = load_player(&player_path);
         let inventory_path = world_path.to_string() + "inventory.impfile";
         (self.player.hotbar, self.player.inventory) = load_inventory(&inventory_path);
-        let range = self.settings.get_range() as i32;
-        self.world = World::load_world_metadata(world_path, range);
+        self.world = world;
+        self.world.compression = self.settings.get_compression();
         self.world.load_chunks();
-        self.world.init_block_light();
-        self.world.init_sky_light();
+        self.world.init_light();
         self.entities.load(&self.world);
+        Ok(())
     }
 }
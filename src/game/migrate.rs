@@ -0,0 +1,202 @@
+/*
+ * Upgrades worlds saved by older versions of the game to the current save
+ * format. Each migration step upgrades a world by exactly one version and the
+ * steps are run in order until the world is up to date.
+ * */
+
+use super::{
+    entities::{region::EntityRegion, ENTITIES_PATH},
+    save::CHUNK_PATH,
+};
+use crate::{
+    bin_data::Compression,
+    impfile::{self, Entry},
+    voxel::region::{save::parse_region_file_name, Region},
+};
+use std::{fs::File, io::Write, path::Path};
+
+//Version of the world save format, this should be incremented every time
+//the layout of any save file changes and a migration step should be added
+pub const WORLD_FORMAT_VERSION: u32 = 5;
+pub const BACKUP_PATH: &str = "backups/";
+
+type Migration = fn(&str) -> Result<(), String>;
+
+//MIGRATIONS[i] upgrades a world from version i to version i + 1
+const MIGRATIONS: [Migration; WORLD_FORMAT_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+];
+
+fn world_metadata_path(world_path: &str) -> String {
+    world_path.to_string() + "world.impfile"
+}
+
+//Replaces the world entry, any other entries (such as the terrain settings)
+//are kept
+fn write_world_metadata(world_path: &str, entry: &Entry) -> Result<(), String> {
+    let path = world_metadata_path(world_path);
+    let mut metadata_str = entry.to_impfile_string();
+    for other in impfile::parse_file(&path).iter().skip(1) {
+        metadata_str += "\n\n";
+        metadata_str += &other.to_impfile_string();
+    }
+    let mut file = File::create(path).map_err(|e| e.to_string())?;
+    impfile::write_comment(&mut file, "World metadata");
+    file.write_all(metadata_str.as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+//Returns the world metadata entry if it exists
+fn load_world_metadata_entry(world_path: &str) -> Option<Entry> {
+    let path = world_metadata_path(world_path);
+    if !Path::new(&path).exists() {
+        return None;
+    }
+    impfile::parse_file(&path).into_iter().next()
+}
+
+//Worlds saved before the format version was added are version 0
+pub fn get_format_version(entry: &Entry) -> u32 {
+    entry.get_var("format_version").parse::<u32>().unwrap_or(0)
+}
+
+//Returns the positions of all region files in a directory
+fn get_region_files(dir_path: &str) -> Vec<(i32, i32, i32)> {
+    match std::fs::read_dir(dir_path) {
+        Ok(dir) => dir
+            .flatten()
+            .filter_map(|entry| parse_region_file_name(&entry.file_name().to_string_lossy()))
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dst).map_err(|e| e.to_string())?;
+    for entry in std::fs::read_dir(src).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let dst_path = dst.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &dst_path)?;
+        } else {
+            std::fs::copy(entry.path(), &dst_path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+//Copies the world into the backup directory, returns the path of the backup
+fn backup_world(world_path: &str, version: u32) -> Result<String, String> {
+    let world_name = Path::new(world_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or("world".to_string());
+    let name = format!("{BACKUP_PATH}{world_name}_v{version}");
+    let mut path = name.clone();
+    let mut id = 1;
+    while Path::new(&path).exists() {
+        path = format!("{name}_{id}");
+        id += 1;
+    }
+    copy_dir(Path::new(world_path), Path::new(&path))?;
+    Ok(path)
+}
+
+//Loads every region and entity region and saves it again in the current format
+fn resave_regions(world_path: &str) -> Result<(), String> {
+    let compression = Compression::default();
+    let chunk_dir = world_path.to_string() + CHUNK_PATH;
+    for (x, y, z) in get_region_files(&chunk_dir) {
+        if let Some(region) = Region::load_region(world_path, x, y, z) {
+            region.save_region(world_path, compression)?;
+        }
+    }
+
+    let entities_dir = world_path.to_string() + ENTITIES_PATH;
+    for (x, y, z) in get_region_files(&entities_dir) {
+        if let Some(region) = EntityRegion::load_region(world_path, x, y, z) {
+            region.save_region(world_path, compression)?;
+        }
+    }
+
+    Ok(())
+}
+
+//Version 1 adds a header to region files and entity region files,
+//the "default" generation type is also renamed to "old"
+fn migrate_v0_to_v1(world_path: &str) -> Result<(), String> {
+    resave_regions(world_path)?;
+
+    if let Some(mut entry) = load_world_metadata_entry(world_path) {
+        if entry.get_var("gen_type") == "default" {
+            entry.add_string("gen_type", "old");
+            write_world_metadata(world_path, &entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+//Version 2 adds checksums to region files and entity region files
+fn migrate_v1_to_v2(world_path: &str) -> Result<(), String> {
+    resave_regions(world_path)
+}
+
+//Version 3 adds the compression type to region files and entity region files
+fn migrate_v2_to_v3(world_path: &str) -> Result<(), String> {
+    resave_regions(world_path)
+}
+
+//Version 4 stores each chunk in a region file separately
+fn migrate_v3_to_v4(world_path: &str) -> Result<(), String> {
+    resave_regions(world_path)
+}
+
+//Version 5 stores block ids in region files as u16
+fn migrate_v4_to_v5(world_path: &str) -> Result<(), String> {
+    resave_regions(world_path)
+}
+
+//Upgrades a world to the current format version, a backup of the world is
+//made before anything is modified. Returns an error if the world is from a
+//newer version of the game or if a migration step failed.
+pub fn migrate_world(world_path: &str) -> Result<(), String> {
+    let entry = match load_world_metadata_entry(world_path) {
+        Some(entry) => entry,
+        //Nothing to migrate
+        None => return Ok(()),
+    };
+
+    let version = get_format_version(&entry);
+    if version == WORLD_FORMAT_VERSION {
+        return Ok(());
+    } else if version > WORLD_FORMAT_VERSION {
+        return Err(format!(
+            "{world_path} was saved with format version {version}, \
+            only versions up to {WORLD_FORMAT_VERSION} are supported"
+        ));
+    }
+
+    let backup_path = backup_world(world_path, version)?;
+    eprintln!("Backed up {world_path} to {backup_path}");
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let next_version = i as u32 + 1;
+        eprintln!("Migrating {world_path} to version {next_version}...");
+        migration(world_path).map_err(|msg| {
+            format!("Failed to migrate to version {next_version}: {msg} (backup: {backup_path})")
+        })?;
+
+        //Record the progress so that an interrupted migration can continue
+        //from this step the next time the world is loaded
+        let mut entry = load_world_metadata_entry(world_path).unwrap_or(Entry::new("world"));
+        entry.add_integer("format_version", next_version as i64);
+        write_world_metadata(world_path, &entry)?;
+    }
+
+    Ok(())
+}
@@ -66,7 +66,7 @@ pub struct Player {
     //None if no block is open
     //Some(position) if a block is opened
     pub opened_block: Option<(i32, i32, i32)>,
-    pub opened_block_id: u8,
+    pub opened_block_id: u16,
     //Flying
     pub spacebar_timer: f32,
     pub flying: bool,
@@ -4,6 +4,10 @@ use std::{fs::File, io::Write, path::Path};
 
 pub const SAVE_PATH: &str = "saves/";
 pub const CHUNK_PATH: &str = "chunkdata/";
+//Extension for files that are being written
+pub const TMP_EXT: &str = ".tmp";
+//Extension for the previous copy of a file
+pub const BACKUP_EXT: &str = ".bak";
 
 pub fn create_save_dir() {
     if Path::new(SAVE_PATH).exists() {
@@ -43,6 +47,69 @@ pub fn create_world_dir(world_path: &str) -> Result<(), String> {
     Ok(())
 }
 
+//Writes the bytes to a temporary file that is then renamed to path, this
+//way the file at path is never partially written. The previous version of
+//the file is kept as a backup in case the new file gets corrupted.
+pub fn write_file_atomic(path: &str, bytes: &[u8]) -> Result<(), String> {
+    let tmp_path = format!("{path}{TMP_EXT}");
+    let res = File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    if let Err(msg) = res {
+        if let Err(msg) = std::fs::remove_file(&tmp_path) {
+            eprintln!("Failed to remove {tmp_path}");
+            eprintln!("{msg}");
+        }
+        return Err(msg.to_string());
+    }
+
+    replace_file(&tmp_path, path)
+}
+
+//Renames the finished temporary file to path, the previous version of the
+//file becomes the backup
+pub fn replace_file(tmp_path: &str, path: &str) -> Result<(), String> {
+    if Path::new(path).exists() {
+        let backup_path = format!("{path}{BACKUP_EXT}");
+        std::fs::rename(path, backup_path).map_err(|e| e.to_string())?;
+    }
+    std::fs::rename(tmp_path, path).map_err(|e| e.to_string())
+}
+
+//Reads and parses a file written by write_file_atomic, if the file is missing
+//or fails to parse then the backup is used instead.
+//Returns None if neither the file nor the backup exist
+pub fn read_file_or_backup<T>(
+    path: &str,
+    parse: impl Fn(&[u8]) -> Result<T, String>,
+) -> Option<Result<T, String>> {
+    let backup_path = format!("{path}{BACKUP_EXT}");
+    let mut res = None;
+    for file_path in [path, &backup_path] {
+        if !Path::new(file_path).exists() {
+            continue;
+        }
+
+        let parsed = std::fs::read(file_path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| parse(&bytes));
+        match parsed {
+            Ok(val) => {
+                if file_path != path {
+                    eprintln!("Loaded backup: {file_path}");
+                }
+                return Some(Ok(val));
+            }
+            Err(msg) => {
+                eprintln!("E: failed to load {file_path}: {msg}");
+                res = Some(Err(msg));
+            }
+        }
+    }
+    res
+}
+
 impl Game {
     fn save_player(&self) {
         //Save player
@@ -116,5 +183,7 @@ impl Game {
         self.save_inventory();
         self.entities.save_all(&self.world);
         self.world.save_all();
+        //Make sure everything is written before the world is closed
+        self.world.saver.flush();
     }
 }
@@ -0,0 +1,171 @@
+/*
+ * Region files are written on a separate thread so that saving does not
+ * cause the game loop to stutter. Regions are sent to the writer thread over
+ * a channel and are written in the order they were sent. The thread is only
+ * started once something needs to be saved.
+ * */
+
+use super::entities::region::{serialize_entities, EntityRegion};
+use crate::{
+    bin_data::Compression,
+    voxel::region::{save::serialize_region, Region},
+};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    thread::JoinHandle,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RegionKind {
+    Chunks,
+    Entities,
+}
+
+type RegionKey = (RegionKind, i32, i32, i32);
+//Number of jobs that still need to be written for each region
+type PendingJobs = Arc<Mutex<HashMap<RegionKey, usize>>>;
+
+enum SaveJob {
+    Chunks(String, Region, Compression),
+    Entities(String, EntityRegion, Compression),
+    //Sends a message back once every job sent before it has been written
+    Flush(Sender<()>),
+}
+
+struct Writer {
+    sender: Sender<SaveJob>,
+    handle: JoinHandle<()>,
+}
+
+pub struct SaveThread {
+    writer: OnceLock<Writer>,
+    pending: PendingJobs,
+}
+
+fn finish_job(pending: &PendingJobs, key: RegionKey) {
+    let mut pending = pending.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(count) = pending.get_mut(&key) {
+        *count -= 1;
+        if *count == 0 {
+            pending.remove(&key);
+        }
+    }
+}
+
+fn run_writer(receiver: Receiver<SaveJob>, pending: PendingJobs) {
+    for job in receiver {
+        match job {
+            SaveJob::Chunks(world_path, region, compression) => {
+                if let Err(path) = serialize_region(&world_path, &region, compression) {
+                    eprintln!("E: failed to save {path}");
+                }
+                let key = (RegionKind::Chunks, region.x, region.y, region.z);
+                finish_job(&pending, key);
+            }
+            SaveJob::Entities(world_path, region, compression) => {
+                let key = (RegionKind::Entities, region.x, region.y, region.z);
+                if let Err(path) = serialize_entities(&world_path, region, compression) {
+                    eprintln!("E: failed to save {path}");
+                }
+                finish_job(&pending, key);
+            }
+            SaveJob::Flush(done) => {
+                //The other end might have stopped waiting, ignore the error
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+impl SaveThread {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            writer: OnceLock::new(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn send(&self, key: RegionKey, job: SaveJob) {
+        let writer = self.writer.get_or_init(|| {
+            let (sender, receiver) = unbounded();
+            let pending = self.pending.clone();
+            let handle = std::thread::spawn(move || run_writer(receiver, pending));
+            Writer { sender, handle }
+        });
+
+        *self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key)
+            .or_insert(0) += 1;
+        if writer.sender.send(job).is_err() {
+            eprintln!("E: save thread stopped, failed to save region");
+            finish_job(&self.pending, key);
+        }
+    }
+
+    pub fn save_region(&self, world_path: &str, region: Region, compression: Compression) {
+        let key = (RegionKind::Chunks, region.x, region.y, region.z);
+        self.send(
+            key,
+            SaveJob::Chunks(world_path.to_string(), region, compression),
+        );
+    }
+
+    pub fn save_entities(&self, world_path: &str, region: EntityRegion, compression: Compression) {
+        let key = (RegionKind::Entities, region.x, region.y, region.z);
+        self.send(
+            key,
+            SaveJob::Entities(world_path.to_string(), region, compression),
+        );
+    }
+
+    //Returns true if the region is waiting to be written
+    pub fn is_pending(&self, kind: RegionKind, x: i32, y: i32, z: i32) -> bool {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(&(kind, x, y, z))
+    }
+
+    //Blocks until everything that has been sent so far has been written
+    pub fn flush(&self) {
+        let writer = match self.writer.get() {
+            Some(writer) => writer,
+            //Nothing has been saved
+            None => return,
+        };
+
+        let (sender, receiver) = unbounded();
+        if writer.sender.send(SaveJob::Flush(sender)).is_err() {
+            return;
+        }
+        if receiver.recv().is_err() {
+            eprintln!("E: save thread stopped before finishing");
+        }
+    }
+
+    //Should be called before loading a region from the disk so that the
+    //file is not read while it is still waiting to be written
+    pub fn wait_for(&self, kind: RegionKind, x: i32, y: i32, z: i32) {
+        if self.is_pending(kind, x, y, z) {
+            self.flush();
+        }
+    }
+}
+
+impl Drop for SaveThread {
+    //Finish writing everything before the thread is stopped
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            drop(writer.sender);
+            if writer.handle.join().is_err() {
+                eprintln!("E: save thread panicked");
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::impfile::{self, Entry};
+use crate::{
+    bin_data::Compression,
+    impfile::{self, Entry},
+};
 use std::{fs::File, io::Write};
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -27,18 +30,22 @@ fn cloud_display_to_string(cloud_display: CloudDisplay) -> String {
 
 pub const MIN_RENDER_DIST: u32 = 3;
 pub const DEFAULT_RENDER_DIST: u32 = 7;
-pub const MAX_RENDER_DIST: u32 = 16;
+pub const MAX_RENDER_DIST: u32 = 20;
 
 pub struct Settings {
     pub cloud_display: CloudDisplay,
     pub render_distance: u32,
+    //Whether region files should be compressed when they are saved
+    pub compress_saves: bool,
 }
 
 impl Settings {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             cloud_display: CloudDisplay::Fancy,
             render_distance: DEFAULT_RENDER_DIST,
+            compress_saves: true,
         }
     }
 
@@ -46,6 +53,14 @@ impl Settings {
         self.render_distance.clamp(MIN_RENDER_DIST, MAX_RENDER_DIST)
     }
 
+    pub fn get_compression(&self) -> Compression {
+        if self.compress_saves {
+            Compression::default()
+        } else {
+            Compression::None
+        }
+    }
+
     pub fn load(path: &str) -> Self {
         let entries = impfile::parse_file(path);
         if entries.len() != 1 {
@@ -61,6 +76,10 @@ impl Settings {
                 .parse::<u32>()
                 .unwrap_or(DEFAULT_RENDER_DIST)
                 .clamp(MIN_RENDER_DIST, MAX_RENDER_DIST),
+            compress_saves: entries[0]
+                .get_var("compress_saves")
+                .parse::<bool>()
+                .unwrap_or(true),
         }
     }
 
@@ -71,6 +90,7 @@ impl Settings {
             "cloud_display",
             &cloud_display_to_string(self.cloud_display),
         );
+        entry.add_bool("compress_saves", self.compress_saves);
 
         let settings_entry_str = entry.to_impfile_string();
         let res = match File::create(path) {
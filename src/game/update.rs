@@ -470,7 +470,7 @@ impl Game {
         true
     }
 
-    fn use_bucket(&mut self, chunktables: &mut ChunkTables, blockid: u8) {
+    fn use_bucket(&mut self, chunktables: &mut ChunkTables, blockid: u16) {
         if !self.get_mouse_state(MouseButtonRight).is_held() {
             self.build_cooldown = 0.0;
             return;
@@ -724,7 +724,13 @@ impl Game {
 
         let path = self.world.path.clone();
         let range = self.settings.get_range() as i32;
-        let mut temp_world = World::load_world_metadata(&path, range);
+        let mut temp_world = match World::load_world_metadata(&path, range) {
+            Ok(world) => world,
+            Err(msg) => {
+                eprintln!("E: failed to load world for respawning: {msg}");
+                return;
+            }
+        };
         let pos = self.player.position;
         temp_world.load_for_respawn(pos.x, pos.y, pos.z);
         //Attempt to set up player y position
@@ -750,11 +756,17 @@ impl Game {
         }
 
         self.entities = EntitiesTable::new();
-        self.world = World::load_world_metadata(&path, range);
+        self.world = match World::load_world_metadata(&path, range) {
+            Ok(world) => world,
+            Err(msg) => {
+                eprintln!("E: failed to load world for respawning: {msg}");
+                return;
+            }
+        };
+        self.world.compression = self.settings.get_compression();
         self.world.update_generation_queue(self.player.position);
         self.world.load_chunks();
-        self.world.init_block_light();
-        self.world.init_sky_light();
+        self.world.init_light();
         self.entities.load(&self.world);
 
         //Set up camera
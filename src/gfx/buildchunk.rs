@@ -1,12 +1,13 @@
 mod addvertices;
 
 use super::face_data::FACE_INDICES;
+use crate::voxel::block_registry::{get_block_textures, TextureLayout};
 use crate::voxel::{Chunk, CHUNK_SIZE_I32};
 pub use addvertices::add_block_vertices_flat;
 pub use addvertices::add_nonvoxel_vertices;
 use addvertices::{
-    add_block_vertices_default, add_block_vertices_furnace_rotated, add_block_vertices_grass,
-    add_block_vertices_log, add_block_vertices_plant, add_block_vertices_trans, add_fluid_vertices,
+    add_block_vertices_furnace_rotated, add_block_vertices_grass, add_block_vertices_log,
+    add_block_vertices_plant, add_block_vertices_trans, add_fluid_vertices,
 };
 
 pub type Int3 = (i32, i32, i32);
@@ -31,49 +32,15 @@ pub fn add_block_vertices(
         return;
     }
 
-    //TODO: add a better way of specifying how the faces of the blocks are textured
-    //(probably as some kind of resource file) additionally, the unlabelled constants
-    //should probably be deleted at some point
-    match block.id {
-        1 => {
-            //Grass
-            add_block_vertices_grass(chunk, adj_chunks, xyz, vert_data, 17, 4, 254);
+    let textures = get_block_textures(block.id);
+    match textures.layout {
+        TextureLayout::Log => add_block_vertices_log(chunk, adj_chunks, xyz, vert_data, textures),
+        TextureLayout::Front => {
+            add_block_vertices_furnace_rotated(chunk, adj_chunks, xyz, vert_data, textures)
         }
-        8 => {
-            //Log
-            add_block_vertices_log(chunk, adj_chunks, xyz, vert_data, 24, 25);
-        }
-        37 => {
-            //Chest
-            add_block_vertices_furnace_rotated(chunk, adj_chunks, xyz, vert_data, 38, 39);
-        }
-        40 | 70 => {
-            //Furnace/lit furnace
-            add_block_vertices_furnace_rotated(chunk, adj_chunks, xyz, vert_data, 41, 42);
-        }
-        43 => {
-            //Farmland
-            add_block_vertices_grass(chunk, adj_chunks, xyz, vert_data, 44, 43, 43);
-        }
-        45 => {
-            //Dry Farmland
-            add_block_vertices_grass(chunk, adj_chunks, xyz, vert_data, 46, 45, 45);
-        }
-        82 => {
-            //Hay bale
-            add_block_vertices_log(chunk, adj_chunks, xyz, vert_data, 83, 84);
-        }
-        87 => {
-            //Snow "grass" block
-            add_block_vertices_grass(chunk, adj_chunks, xyz, vert_data, 86, 4, 251);
-        }
-        88 => {
-            //Cactus
-            add_block_vertices_log(chunk, adj_chunks, xyz, vert_data, 89, 88);
-        }
-        _ => {
-            //Everything else
-            add_block_vertices_default(chunk, adj_chunks, xyz, vert_data);
+        TextureLayout::Plant => add_block_vertices_plant(chunk, xyz, vert_data, textures),
+        TextureLayout::Cube => {
+            add_block_vertices_grass(chunk, adj_chunks, xyz, vert_data, textures)
         }
     }
 }
@@ -99,15 +66,10 @@ pub fn add_block_vertices_transparent(
         return;
     }
 
-    match block.id {
-        //Glass
-        9 => add_block_vertices_trans(chunk, adj_chunks, xyz, vert_data, Some(252), Some(253)),
-        //Plants
-        47..=56 | 69 | 90 | 92 | 99..=102 | 104 | 106 | 108 => {
-            add_block_vertices_plant(chunk, xyz, vert_data)
-        }
-        //Everything else
-        _ => add_block_vertices_trans(chunk, adj_chunks, xyz, vert_data, None, None),
+    let textures = get_block_textures(block.id);
+    match textures.layout {
+        TextureLayout::Plant => add_block_vertices_plant(chunk, xyz, vert_data, textures),
+        _ => add_block_vertices_trans(chunk, adj_chunks, xyz, vert_data, textures),
     }
 }
 
@@ -12,11 +12,13 @@ mod transparent;
 use self::stairgeometry::StairInfo;
 
 use super::{ChunkData, Int3};
-use crate::gfx::face_data::{
-    Face, BACK_FACE, BOTTOM_FACE, FRONT_FACE, LEFT_FACE, RIGHT_FACE, TOP_FACE,
-};
+use crate::gfx::face_data::{Face, LEFT_FACE};
+use crate::voxel::biome_registry::get_biome_registry;
+use crate::voxel::block_registry::get_block_textures;
 use crate::voxel::light::Light;
-use crate::voxel::{out_of_bounds, rotate_orientation, wrap_coord, Block, Chunk, EMPTY_BLOCK};
+use crate::voxel::{
+    out_of_bounds, rotate_orientation, wrap_coord, Block, Chunk, CHUNK_SIZE_I32, EMPTY_BLOCK,
+};
 pub use fluid::add_fluid_vertices;
 pub use furnace::add_block_vertices_furnace_rotated;
 pub use grass::add_block_vertices_grass;
@@ -33,15 +35,42 @@ struct FaceInfo {
     block_texture_id: u8,
 }
 
+//The block registry only allows textures in the atlas so this only matters
+//for blocks that are missing from the registry, they use the last texture
+fn texture_index(blockid: u16) -> u8 {
+    u8::try_from(blockid).unwrap_or(u8::MAX)
+}
+
 impl FaceInfo {
-    fn new(blocki: u8, facei: u8) -> Self {
+    fn new(blocki: u16, facei: u8) -> Self {
         Self {
             face_id: facei,
-            block_texture_id: blocki,
+            block_texture_id: texture_index(blocki),
+        }
+    }
+
+    //The face id only uses the lowest 2 bits, the index of the biome tint
+    //is stored above it (see chunkvert.glsl)
+    fn tinted(self, tint_index: u8) -> Self {
+        Self {
+            face_id: self.face_id | (tint_index << 2),
+            ..self
         }
     }
 }
 
+//Returns the index of the grass or leaf tint of the biome of the column,
+//0 if the chunk does not have biomes
+fn biome_tint(chunk: &Chunk, xyz: Int3, leaves: bool) -> u8 {
+    let (x, _, z) = xyz;
+    let pos = chunk.get_chunk_pos();
+    let biome = chunk.get_biome(pos.x * CHUNK_SIZE_I32 + x, pos.z * CHUNK_SIZE_I32 + z);
+    match (biome, get_biome_registry()) {
+        (Some(biome), Some(biomes)) => biomes.tint_index(biome, leaves),
+        _ => 0,
+    }
+}
+
 fn apply_geometry(block: Block, xyz: Int3, vert_data: &mut ChunkData, light: Light) {
     match block.shape() {
         1 => {
@@ -159,37 +188,6 @@ fn add_face(
     apply_geometry(block, xyz, vert_data, light);
 }
 
-//Default function for adding block vertices, all faces have the same texture
-pub fn add_block_vertices_default(
-    chunk: &Chunk,
-    adj_chunks: [Option<&Chunk>; 6],
-    xyz: Int3,
-    vert_data: &mut ChunkData,
-) {
-    let (x, y, z) = xyz;
-    let block = chunk.get_block_relative(x as usize, y as usize, z as usize);
-    if block.id == EMPTY_BLOCK {
-        return;
-    }
-
-    let facex = FaceInfo::new(block.id, 0);
-    let facey = FaceInfo::new(block.id, 1);
-    let facez = FaceInfo::new(block.id, 2);
-
-    #[rustfmt::skip]
-    add_face(chunk, adj_chunks[0], xyz, (0, 1, 0), vert_data, &TOP_FACE, facey);
-    #[rustfmt::skip]
-    add_face(chunk, adj_chunks[1], xyz, (0, -1, 0), vert_data, &BOTTOM_FACE, facey);
-    #[rustfmt::skip]
-    add_face(chunk, adj_chunks[2], xyz, (-1, 0, 0), vert_data, &LEFT_FACE, facex);
-    #[rustfmt::skip]
-    add_face(chunk, adj_chunks[3], xyz, (1, 0, 0), vert_data, &RIGHT_FACE, facex);
-    #[rustfmt::skip]
-    add_face(chunk, adj_chunks[4], xyz, (0, 0, -1), vert_data, &FRONT_FACE, facez);
-    #[rustfmt::skip]
-    add_face(chunk, adj_chunks[5], xyz, (0, 0, 1), vert_data, &BACK_FACE, facez);
-}
-
 //Adds front face
 pub fn add_block_vertices_flat(chunk: &Chunk, xyz: Int3, vert_data: &mut ChunkData) {
     let (x, y, z) = xyz;
@@ -200,7 +198,7 @@ pub fn add_block_vertices_flat(chunk: &Chunk, xyz: Int3, vert_data: &mut ChunkDa
         return;
     }
 
-    let facex = FaceInfo::new(blockid, 0);
+    let facex = FaceInfo::new(get_block_textures(blockid).side, 0);
     #[rustfmt::skip]
     add_face(chunk, None, xyz, (-1, 0, 0), vert_data, &LEFT_FACE, facex);
 }
@@ -1,6 +1,8 @@
 use super::{ChunkData, Face, FaceInfo, Int3};
 use crate::gfx::face_data::{BACK_FACE, BOTTOM_FACE, FRONT_FACE, LEFT_FACE, RIGHT_FACE, TOP_FACE};
-use crate::voxel::{out_of_bounds, wrap_coord, Chunk, EMPTY_BLOCK};
+use crate::voxel::{
+    block_registry::get_block_textures, out_of_bounds, wrap_coord, Chunk, EMPTY_BLOCK,
+};
 
 fn add_face_fluid(
     chunk: &Chunk,
@@ -81,9 +83,10 @@ pub fn add_fluid_vertices(
         return;
     }
 
-    let facex = FaceInfo::new(blockid, 0);
-    let facey = FaceInfo::new(blockid, 1);
-    let facez = FaceInfo::new(blockid, 2);
+    let textures = get_block_textures(blockid);
+    let facex = FaceInfo::new(textures.side, 0);
+    let facey = FaceInfo::new(textures.top, 1);
+    let facez = FaceInfo::new(textures.side, 2);
 
     #[rustfmt::skip]
     add_face_fluid(chunk, adj_chunks[0], xyz, (0, 1, 0), vert_data, &TOP_FACE, facey);
@@ -1,19 +1,17 @@
-use super::{add_face, ChunkData, FaceInfo, Int3};
+use super::{add_face, biome_tint, ChunkData, FaceInfo, Int3};
 use crate::gfx::face_data::{BACK_FACE, BOTTOM_FACE, FRONT_FACE, LEFT_FACE, RIGHT_FACE, TOP_FACE};
-use crate::voxel::{Chunk, EMPTY_BLOCK};
+use crate::voxel::{block_registry::BlockTextures, Chunk, EMPTY_BLOCK};
 
 //This is for adding vertices such that the resulting block is textured like a
-//grass block (this function can also be used as for other blocks that are
-//textured similarly I just didn't really have a good name for this)
-//By default, the "blockid" will be used as the side textures
+//grass block (the top, bottom and sides can have different textures and the
+//sides of slabs can use a separate texture), this is used for every opaque
+//block with the cube layout
 pub fn add_block_vertices_grass(
     chunk: &Chunk,
     adj_chunks: [Option<&Chunk>; 6],
     xyz: Int3,
     vert_data: &mut ChunkData,
-    top: u8,
-    bot: u8,
-    side_half: u8,
+    textures: BlockTextures,
 ) {
     let (x, y, z) = xyz;
     let block = chunk.get_block_relative(x as usize, y as usize, z as usize);
@@ -22,15 +20,19 @@ pub fn add_block_vertices_grass(
     }
 
     let side = if block.shape() == 1 && block.orientation().is_multiple_of(3) {
-        side_half
+        textures.slab[0].unwrap_or(textures.side)
     } else {
-        block.id
+        textures.side
     };
 
     let facex = FaceInfo::new(side, 0);
     let facez = FaceInfo::new(side, 2);
-    let topface = FaceInfo::new(top, 1);
-    let botface = FaceInfo::new(bot, 1);
+    let mut topface = FaceInfo::new(textures.top, 1);
+    //Grass
+    if block.id == 1 {
+        topface = topface.tinted(biome_tint(chunk, xyz, false));
+    }
+    let botface = FaceInfo::new(textures.bottom, 1);
 
     #[rustfmt::skip]
     add_face(chunk, adj_chunks[0], xyz, (0, 1, 0), vert_data, &TOP_FACE, topface);
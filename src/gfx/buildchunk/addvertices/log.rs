@@ -1,19 +1,20 @@
 use super::{add_face, ChunkData, FaceInfo, Int3};
 use crate::gfx::face_data::{BACK_FACE, BOTTOM_FACE, FRONT_FACE, LEFT_FACE, RIGHT_FACE, TOP_FACE};
-use crate::voxel::{Chunk, EMPTY_BLOCK};
+use crate::voxel::{block_registry::BlockTextures, Chunk, EMPTY_BLOCK};
 
 //This is for adding vertices such that the resulting block is textured like a
 //log (this function can also be used as for other blocks that are
 //textured similarly I just didn't really have a good name for this)
-//By default, the "blockid" will be used as the side textures
+//The top texture is used on the ends of the log
 pub fn add_block_vertices_log(
     chunk: &Chunk,
     adj_chunks: [Option<&Chunk>; 6],
     xyz: Int3,
     vert_data: &mut ChunkData,
-    faceytexture: u8,
-    sidetexture_rotated: u8,
+    textures: BlockTextures,
 ) {
+    let faceytexture = textures.top;
+    let sidetexture_rotated = textures.rotated;
     let (x, y, z) = xyz;
     let block = chunk.get_block_relative(x as usize, y as usize, z as usize);
     if block.id == EMPTY_BLOCK {
@@ -22,13 +23,13 @@ pub fn add_block_vertices_log(
 
     let (facex, facey, facez) = match block.orientation() % 3 {
         0 => (
-            FaceInfo::new(block.id, 0),
+            FaceInfo::new(textures.side, 0),
             FaceInfo::new(faceytexture, 1),
-            FaceInfo::new(block.id, 2),
+            FaceInfo::new(textures.side, 2),
         ),
         1 => (
             FaceInfo::new(faceytexture, 0),
-            FaceInfo::new(block.id, 1),
+            FaceInfo::new(textures.side, 1),
             FaceInfo::new(sidetexture_rotated, 2),
         ),
         2 => (
@@ -37,9 +38,9 @@ pub fn add_block_vertices_log(
             FaceInfo::new(faceytexture, 2),
         ),
         _ => (
-            FaceInfo::new(block.id, 0),
+            FaceInfo::new(textures.side, 0),
             FaceInfo::new(faceytexture, 1),
-            FaceInfo::new(block.id, 2),
+            FaceInfo::new(textures.side, 2),
         ),
     };
 
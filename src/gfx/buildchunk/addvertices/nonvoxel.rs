@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
+use super::texture_index;
 use crate::gfx::buildchunk::{ChunkData, Int3};
 use crate::gfx::models::{CUBE, CUBE_INDICES, CUBE_TEX_INDICES, QUAD_INDICES, TEX_COORDS};
+use crate::voxel::block_registry::get_block_textures;
 use crate::voxel::light::Light;
 use crate::voxel::{Block, Chunk};
 use cgmath::{Deg, Matrix4, Vector2, Vector3, Vector4};
@@ -35,7 +37,7 @@ fn tc_to_u8(tc: f32) -> (u8, u8) {
 
 fn add_mesh_to_chunk(
     xyz: Int3,
-    id: u8,
+    id: u16,
     vertices: &[Vert],
     tc: &[Tc],
     vert_data: &mut ChunkData,
@@ -76,7 +78,7 @@ fn add_mesh_to_chunk(
         vert_data.push(vertx | (fx1 << 6));
         vert_data.push(verty | (fy1 << 6));
         vert_data.push(vertz | (fz1 << 6));
-        vert_data.push(id);
+        vert_data.push(texture_index(id));
         //Sky light and red channel
         vert_data.push(((light.r() as u8) << 4) | (light.skylight() as u8));
         vert_data.push(fraction | (tcx1 << 6) | (tcy1 << 7));
@@ -508,7 +510,7 @@ pub fn add_nonvoxel_vertices(
     chunk: &Chunk,
     xyz: Int3,
     vert_data: &mut ChunkData,
-    cached_meshes: &mut HashMap<(u8, u8), BlockMesh>,
+    cached_meshes: &mut HashMap<(u16, u8), BlockMesh>,
 ) {
     let (x, y, z) = xyz;
     let block = chunk.get_block_relative(x as usize, y as usize, z as usize);
@@ -517,13 +519,7 @@ pub fn add_nonvoxel_vertices(
         return;
     }
 
-    let id = match block.id {
-        //Fence and gate
-        76 | 78 => 6,
-        //Door
-        79 => 80,
-        _ => block.id,
-    };
+    let id = get_block_textures(block.id).side;
 
     let light = chunk.get_light_relative(x as usize, y as usize, z as usize);
     let key = (block.id, block.geometry);
@@ -544,6 +540,8 @@ pub fn add_nonvoxel_vertices(
         78 => gen_gate_vertices(block),
         //Bottom door
         79 | 81 => gen_door_vertices(block),
+        //Wire
+        113 | 114 => gen_seed_vertices(block),
         //Top door
         _ => (vec![], vec![]),
     };
@@ -3,7 +3,7 @@ use crate::gfx::face_data::{
     Face, DIAGONAL_FACE_1, DIAGONAL_FACE_1_REVERSED, DIAGONAL_FACE_2, DIAGONAL_FACE_2_REVERSED,
 };
 use crate::voxel::light::Light;
-use crate::voxel::{Chunk, EMPTY_BLOCK};
+use crate::voxel::{block_registry::BlockTextures, Chunk, EMPTY_BLOCK};
 
 fn add_face_plant(
     xyz: Int3,
@@ -28,7 +28,12 @@ fn add_face_plant(
     }
 }
 
-pub fn add_block_vertices_plant(chunk: &Chunk, xyz: Int3, vert_data: &mut ChunkData) {
+pub fn add_block_vertices_plant(
+    chunk: &Chunk,
+    xyz: Int3,
+    vert_data: &mut ChunkData,
+    textures: BlockTextures,
+) {
     let (x, y, z) = xyz;
     let blockid = chunk
         .get_block_relative(x as usize, y as usize, z as usize)
@@ -37,8 +42,8 @@ pub fn add_block_vertices_plant(chunk: &Chunk, xyz: Int3, vert_data: &mut ChunkD
         return;
     }
 
-    let facex = FaceInfo::new(blockid, 0);
-    let facez = FaceInfo::new(blockid, 2);
+    let facex = FaceInfo::new(textures.side, 0);
+    let facez = FaceInfo::new(textures.side, 2);
     let light = chunk.get_light_relative(x as usize, y as usize, z as usize);
     add_face_plant(xyz, vert_data, &DIAGONAL_FACE_1, facex, light);
     add_face_plant(xyz, vert_data, &DIAGONAL_FACE_2, facez, light);
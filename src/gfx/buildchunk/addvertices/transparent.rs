@@ -1,11 +1,11 @@
 use super::stairgeometry::StairInfo;
 use super::{add_stair_geometry, get_adj_block, get_adj_light};
-use super::{apply_geometry, skipface::skip_face_trans, ChunkData, FaceInfo, Int3};
+use super::{apply_geometry, biome_tint, skipface::skip_face_trans, ChunkData, FaceInfo, Int3};
 use crate::gfx::face_data::{
     Face, BACK_FACE, BOTTOM_FACE, FRONT_FACE, LEFT_FACE, RIGHT_FACE, TOP_FACE,
 };
 use crate::voxel::light::Light;
-use crate::voxel::{Chunk, EMPTY_BLOCK};
+use crate::voxel::{block_registry::BlockTextures, Chunk, EMPTY_BLOCK};
 
 fn add_face_transparent(
     chunk: &Chunk,
@@ -62,9 +62,9 @@ pub fn add_block_vertices_trans(
     adj_chunks: [Option<&Chunk>; 6],
     xyz: Int3,
     vert_data: &mut ChunkData,
-    slab_side1: Option<u8>,
-    slab_side2: Option<u8>,
+    textures: BlockTextures,
 ) {
+    let [slab_side1, slab_side2] = textures.slab;
     let (x, y, z) = xyz;
     let block = chunk.get_block_relative(x as usize, y as usize, z as usize);
     if block.id == EMPTY_BLOCK {
@@ -73,33 +73,41 @@ pub fn add_block_vertices_trans(
 
     let facex = if block.shape() == 1 {
         match block.orientation() % 3 {
-            0 => FaceInfo::new(slab_side1.unwrap_or(block.id), 0),
-            1 => FaceInfo::new(block.id, 0),
-            2 => FaceInfo::new(slab_side2.unwrap_or(block.id), 0),
-            _ => FaceInfo::new(block.id, 0), //Unreachable
+            0 => FaceInfo::new(slab_side1.unwrap_or(textures.side), 0),
+            1 => FaceInfo::new(textures.side, 0),
+            2 => FaceInfo::new(slab_side2.unwrap_or(textures.side), 0),
+            _ => FaceInfo::new(textures.side, 0), //Unreachable
         }
     } else {
-        FaceInfo::new(block.id, 0)
+        FaceInfo::new(textures.side, 0)
     };
     let facey = if block.shape() == 1 {
         match block.orientation() % 3 {
-            0 => FaceInfo::new(block.id, 1),
-            1 => FaceInfo::new(slab_side1.unwrap_or(block.id), 1),
-            2 => FaceInfo::new(slab_side2.unwrap_or(block.id), 1),
-            _ => FaceInfo::new(block.id, 1), //Unreachable
+            0 => FaceInfo::new(textures.top, 1),
+            1 => FaceInfo::new(slab_side1.unwrap_or(textures.side), 1),
+            2 => FaceInfo::new(slab_side2.unwrap_or(textures.side), 1),
+            _ => FaceInfo::new(textures.top, 1), //Unreachable
         }
     } else {
-        FaceInfo::new(block.id, 1)
+        FaceInfo::new(textures.top, 1)
     };
     let facez = if block.shape() == 1 {
         match block.orientation() % 3 {
-            0 => FaceInfo::new(slab_side1.unwrap_or(block.id), 2),
-            1 => FaceInfo::new(slab_side2.unwrap_or(block.id), 2),
-            2 => FaceInfo::new(block.id, 2),
-            _ => FaceInfo::new(block.id, 2), //Unreachable
+            0 => FaceInfo::new(slab_side1.unwrap_or(textures.side), 2),
+            1 => FaceInfo::new(slab_side2.unwrap_or(textures.side), 2),
+            2 => FaceInfo::new(textures.side, 2),
+            _ => FaceInfo::new(textures.side, 2), //Unreachable
         }
     } else {
-        FaceInfo::new(block.id, 2)
+        FaceInfo::new(textures.side, 2)
+    };
+
+    //Leaves
+    let (facex, facey, facez) = if block.id == 7 {
+        let tint = biome_tint(chunk, xyz, true);
+        (facex.tinted(tint), facey.tinted(tint), facez.tinted(tint))
+    } else {
+        (facex, facey, facez)
     };
 
     #[rustfmt::skip]
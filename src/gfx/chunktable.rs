@@ -8,6 +8,7 @@ use crate::assets::shader::ShaderProgram;
 use crate::game::inventory::Item;
 use crate::game::physics::Hitbox;
 use crate::game::Game;
+use crate::voxel::biome_registry::get_biome_registry;
 use crate::voxel::{
     world_to_chunk_position, wrap_coord, Block, Chunk, ChunkPos, World, CHUNK_SIZE_I32,
 };
@@ -67,6 +68,21 @@ pub fn set_dyn_light(gamestate: &Game, shader: &ShaderProgram) {
     shader.uniform_vec3f("lightcolor", r, g, b);
 }
 
+//Send the grass and leaf tints of the biomes to the chunk shader
+fn set_biome_tints(shader: &ShaderProgram) {
+    let Some(biomes) = get_biome_registry() else {
+        return;
+    };
+    let tints: Vec<[f32; 3]> = biomes
+        .tints()
+        .iter()
+        .map(|tint| tint.map(|channel| channel as f32 / 255.0))
+        .collect();
+    if !tints.is_empty() {
+        shader.uniform_vec3f_array("biometints", &tints);
+    }
+}
+
 const BUF_COUNT: usize = 3;
 
 pub struct ChunkVao {
@@ -189,6 +205,7 @@ pub struct ChunkVaoTable {
 
 impl ChunkVaoTable {
     //Create a new chunk vao table
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             vaos: HashMap::new(),
@@ -476,6 +493,9 @@ impl ChunkVaoTable {
             gamestate.cam.position.z,
         );
         chunkshader.uniform_float("skybrightness", get_sky_brightness(gamestate.world.time));
+        if shaderid == "chunk" {
+            set_biome_tints(&chunkshader);
+        }
 
         //Set fog color
         set_fog(gamestate, &chunkshader, get_skycolor(gamestate.world.time));
@@ -611,6 +631,7 @@ pub struct ChunkTables {
 }
 
 impl ChunkTables {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             chunk_vaos: ChunkVaoTable::new(),
@@ -1,5 +1,6 @@
 pub mod block_menu;
 pub mod dropped_items;
+pub mod falling_blocks;
 mod hand;
 pub mod hud;
 pub mod inventory;
@@ -16,11 +17,13 @@ use crate::{game::Game, EMPTY_BLOCK};
 pub use block_menu::display_block_menu;
 use cgmath::{vec3, Deg, Matrix4, SquareMatrix, Vector3};
 pub use dropped_items::display_dropped_items;
+pub use falling_blocks::display_falling_blocks;
 pub use hand::display_hand_item;
 pub use inventory::{display_hotbar, display_inventory_screen, display_mouse_item};
 
 pub fn display_entities(gamestate: &Game) {
     display_dropped_items(gamestate);
+    display_falling_blocks(gamestate);
 }
 
 pub fn display_selected_outline(gamestate: &Game) {
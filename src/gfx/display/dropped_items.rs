@@ -13,15 +13,16 @@ use crate::{
     gfx::{
         buildchunk::{
             add_block_vertices, add_block_vertices_fluid, add_block_vertices_transparent,
-            get_indices,
+            get_indices, ChunkData,
         },
         chunktable::{set_dyn_light, set_fog, ChunkVao},
         frustum::Frustum,
     },
     voxel::{
+        block_registry::get_block_textures,
         light::LU,
         world::{get_simulation_dist, in_sim_range},
-        Chunk,
+        Block, Chunk,
     },
 };
 use cgmath::{vec3, Deg, Matrix4, SquareMatrix};
@@ -40,7 +41,7 @@ fn get_flat_dropped_transform(pos: Vec3, scale: Vec3, yaw: f32, pitch: f32) -> M
     transform
 }
 
-fn get_block_dropped_transform(scale: Vec3, yaw: f32) -> Matrix4<f32> {
+pub fn get_block_dropped_transform(scale: Vec3, yaw: f32) -> Matrix4<f32> {
     let mut transform = Matrix4::identity();
     transform = transform * Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z);
     transform = transform * Matrix4::from_angle_y(Deg(yaw));
@@ -56,6 +57,40 @@ fn get_frustum_hitbox(dropped_item: &DroppedItem) -> Hitbox {
     hitbox
 }
 
+//Returns the vertices of a single block that is lit by the light at pos,
+//the block is at (1, 1, 1) so it needs to be translated by (-1.5, -1.5, -1.5)
+//to be centered on pos
+pub fn gen_block_vertices(gamestate: &Game, block: Block, pos: Vec3) -> ChunkData {
+    let light = gamestate.world.get_light(
+        pos.x.floor() as i32,
+        pos.y.floor() as i32,
+        pos.z.floor() as i32,
+    );
+    let lu = LU::new(
+        Some(light.sky()),
+        Some(light.r()),
+        Some(light.g()),
+        Some(light.b()),
+    );
+
+    let mut chunk = Chunk::new(0, 0, 0);
+    //Fill in the light for the chunk
+    for x in 0..=2 {
+        for y in 0..=2 {
+            for z in 0..=2 {
+                chunk.update_light(x, y, z, lu);
+            }
+        }
+    }
+    chunk.set_block_relative(1, 1, 1, block);
+    let mut vert_data = vec![];
+    let adj_chunks = [None; 6];
+    add_block_vertices(&chunk, adj_chunks, (1, 1, 1), &mut vert_data);
+    add_block_vertices_transparent(&chunk, adj_chunks, (1, 1, 1), &mut vert_data);
+    add_block_vertices_fluid(&chunk, adj_chunks, (1, 1, 1), &mut vert_data);
+    vert_data
+}
+
 pub fn display_dropped_items(gamestate: &Game) {
     let frustum = Frustum::new(&gamestate.cam, gamestate.aspect);
     let campos = gamestate.cam.position;
@@ -143,7 +178,7 @@ pub fn display_dropped_items(gamestate: &Game) {
                     if !block.is_flat_item() {
                         continue;
                     }
-                    (block.id as u16, amt)
+                    (get_block_textures(block.id).side, amt)
                 }
                 _ => continue,
             };
@@ -209,37 +244,9 @@ pub fn display_dropped_items(gamestate: &Game) {
                 _ => continue,
             };
 
-            //Get lighting for item
             let pos = dropped_item.pos() + vec3(0.0, 0.2, 0.0);
             chunkshader.uniform_vec3f("chunkpos", pos.x, pos.y, pos.z);
-            let light = gamestate.world.get_light(
-                pos.x.floor() as i32,
-                pos.y.floor() as i32,
-                pos.z.floor() as i32,
-            );
-            let lu = LU::new(
-                Some(light.sky()),
-                Some(light.r()),
-                Some(light.g()),
-                Some(light.b()),
-            );
-
-            let mut chunk = Chunk::new(0, 0, 0);
-            //Fill in the light for the chunk
-            for x in 0..=2 {
-                for y in 0..=2 {
-                    for z in 0..=2 {
-                        chunk.update_light(x, y, z, lu);
-                    }
-                }
-            }
-            chunk.set_block_relative(1, 1, 1, block);
-            let mut vert_data = vec![];
-            let adj_chunks = [None; 6];
-            add_block_vertices(&chunk, adj_chunks, (1, 1, 1), &mut vert_data);
-            add_block_vertices_transparent(&chunk, adj_chunks, (1, 1, 1), &mut vert_data);
-            add_block_vertices_fluid(&chunk, adj_chunks, (1, 1, 1), &mut vert_data);
-
+            let vert_data = gen_block_vertices(gamestate, block, pos);
             if vert_data.is_empty() {
                 continue;
             }
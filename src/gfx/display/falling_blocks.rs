@@ -0,0 +1,56 @@
+use super::{
+    dropped_items::{gen_block_vertices, get_block_dropped_transform},
+    get_sky_brightness, get_skycolor,
+};
+use crate::{
+    game::Game,
+    gfx::{
+        buildchunk::get_indices,
+        chunktable::{set_dyn_light, set_fog, ChunkVao},
+        frustum::Frustum,
+    },
+    voxel::world::{get_simulation_dist, in_sim_range},
+};
+
+pub fn display_falling_blocks(gamestate: &Game) {
+    let frustum = Frustum::new(&gamestate.cam, gamestate.aspect);
+    let campos = gamestate.cam.position;
+
+    gamestate.textures.bind("blocks");
+    let chunkshader = gamestate.shaders.use_program("droppedblock");
+    chunkshader.uniform_matrix4f("persp", &gamestate.persp);
+    chunkshader.uniform_matrix4f("view", &gamestate.cam.get_view());
+    chunkshader.uniform_float("skybrightness", get_sky_brightness(gamestate.world.time));
+    chunkshader.uniform_vec3f("campos", campos.x, campos.y, campos.z);
+    set_dyn_light(gamestate, &chunkshader);
+    set_fog(gamestate, &chunkshader, get_skycolor(gamestate.world.time));
+
+    let center = gamestate.world.get_center();
+    let sim_dist = get_simulation_dist(&gamestate.world);
+    for (pos, list) in gamestate.entities.falling_blocks.blocks() {
+        if !in_sim_range(center, *pos, sim_dist) {
+            continue;
+        }
+
+        for falling_block in list {
+            //Frustum culling
+            if !frustum.intersects(&falling_block.entity.get_hitbox()) {
+                continue;
+            }
+
+            let pos = falling_block.pos();
+            chunkshader.uniform_vec3f("chunkpos", pos.x, pos.y, pos.z);
+            let vert_data = gen_block_vertices(gamestate, falling_block.block, pos);
+            if vert_data.is_empty() {
+                continue;
+            }
+
+            let transform = get_block_dropped_transform(falling_block.scale(), 0.0);
+            chunkshader.uniform_matrix4f("transform", &transform);
+            let face_count = vert_data.len() / (7 * 4);
+            let vao = ChunkVao::generate_new(&vert_data, &get_indices(face_count), 7);
+            vao.draw();
+            vao.delete();
+        }
+    }
+}
@@ -36,7 +36,7 @@ fn get_block(x: i32, y: i32, z: i32, chunks: &[Option<&Chunk>]) -> Block {
     }
 }
 
-fn get_vertex_height(x: i32, y: i32, z: i32, chunks: &[Option<&Chunk>], voxel_id: u8) -> u8 {
+fn get_vertex_height(x: i32, y: i32, z: i32, chunks: &[Option<&Chunk>], voxel_id: u16) -> u8 {
     let mut total = 0;
     let mut count = 0;
 
@@ -84,7 +84,7 @@ pub fn generate_fluid_vertex_data(
     chunk: &Chunk,
     adj_chunks: [Option<&Chunk>; 6],
     world: &World,
-    voxel_id: u8,
+    voxel_id: u16,
 ) -> (ChunkData, Indices, i32) {
     let mut chunk_vert_data = vec![];
 
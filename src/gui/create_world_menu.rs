@@ -1,7 +1,9 @@
 use super::{init_egui_input_state, menu_text, set_ui_gl_state, transparent_frame};
 use crate::game::{save, EventHandler, Game, GameMode};
 use crate::gfx;
-use crate::voxel::world::WorldGenType;
+use crate::voxel::world::chunk_generator::{
+    get_generator_info, get_generators, GeneratorInfo, DEFAULT_GENERATOR,
+};
 use egui_backend::egui::{self, vec2, Color32, Pos2};
 use egui_gl_glfw as egui_backend;
 use glfw::{Context, Glfw, PWindow};
@@ -11,7 +13,10 @@ use std::hash::{DefaultHasher, Hash, Hasher};
 struct CreateWorldMenuState {
     world_name: String,
     seed: String,
-    gen_type: WorldGenType,
+    gen_type: String,
+    gen_options: String,
+    //Shown if the generator options are invalid
+    gen_options_error: String,
     game_mode: GameMode,
     create_world: bool,
     quit_to_menu: bool,
@@ -23,7 +28,9 @@ impl CreateWorldMenuState {
         Self {
             world_name: "New World".to_string(),
             seed: "".to_string(),
-            gen_type: WorldGenType::DefaultGen,
+            gen_type: DEFAULT_GENERATOR.to_string(),
+            gen_options: String::new(),
+            gen_options_error: String::new(),
             game_mode: GameMode::Survival,
             create_world: false,
             quit_to_menu: false,
@@ -48,33 +55,56 @@ fn convert_string_to_seed(seed: String) -> u32 {
 }
 
 //For when the user wants to create a new world
-fn create_new_world(menu_state: &mut CreateWorldMenuState, gamestate: &mut Game) {
+fn create_new_world(
+    menu_state: &mut CreateWorldMenuState,
+    gamestate: &mut Game,
+) -> Result<(), String> {
     let seed = if !menu_state.seed.is_empty() {
         convert_string_to_seed(menu_state.seed.clone())
     } else {
         fastrand::u32(..)
     };
     let range = gamestate.settings.get_range() as i32;
-    gamestate.generate_world(seed, range, menu_state.gen_type, menu_state.game_mode);
-    gamestate.world.init_block_light();
-    gamestate.world.init_sky_light();
+    gamestate.generate_world(
+        seed,
+        range,
+        &menu_state.gen_type,
+        &menu_state.gen_options,
+        menu_state.game_mode,
+    )?;
+    gamestate.world.init_light();
+    Ok(())
 }
 
 fn world_gen_option(
     ui: &mut egui::Ui,
     menu_state: &mut CreateWorldMenuState,
-    gen_type: WorldGenType,
+    generator: GeneratorInfo,
 ) {
-    let selected = menu_state.gen_type == gen_type;
-    let gen_type_str = match gen_type {
-        WorldGenType::Flat => "Flat",
-        WorldGenType::OldGen => "Old",
-        WorldGenType::DefaultGen => "Default",
-        WorldGenType::Skyblock => "Skyblock",
+    let selected = menu_state.gen_type == generator.name;
+    let text = menu_text(generator.display_name, 20.0, Color32::WHITE);
+    if ui.radio(selected, text).clicked() && !selected {
+        menu_state.gen_type = generator.name.to_string();
+        menu_state.gen_options = generator.default_options.to_string();
+        menu_state.gen_options_error.clear();
+    }
+}
+
+//Returns false if the options for the selected generator are invalid
+fn check_gen_options(menu_state: &mut CreateWorldMenuState) -> bool {
+    let Some(generator) = get_generator_info(&menu_state.gen_type) else {
+        return true;
     };
-    let text = menu_text(gen_type_str, 20.0, Color32::WHITE);
-    if ui.radio(selected, text).clicked() {
-        menu_state.gen_type = gen_type;
+
+    match (generator.create)(&menu_state.gen_options) {
+        Ok(_) => {
+            menu_state.gen_options_error.clear();
+            true
+        }
+        Err(msg) => {
+            menu_state.gen_options_error = msg;
+            false
+        }
     }
 }
 
@@ -96,10 +126,24 @@ fn display_create_world(
         ui.label(menu_text("World Generation", 24.0, Color32::WHITE));
 
         //Radio options for world generation
-        world_gen_option(ui, menu_state, WorldGenType::DefaultGen);
-        world_gen_option(ui, menu_state, WorldGenType::OldGen);
-        world_gen_option(ui, menu_state, WorldGenType::Skyblock);
-        world_gen_option(ui, menu_state, WorldGenType::Flat);
+        for generator in get_generators() {
+            world_gen_option(ui, menu_state, generator);
+        }
+
+        //Generator options
+        if let Some(generator) = get_generator_info(&menu_state.gen_type) {
+            if !generator.options_label.is_empty() {
+                ui.label(menu_text(generator.options_label, 20.0, Color32::WHITE));
+                let gen_options_edit = egui::TextEdit::singleline(&mut menu_state.gen_options)
+                    .font(egui::TextStyle::Heading);
+                ui.add(gen_options_edit);
+            }
+        }
+
+        if !menu_state.gen_options_error.is_empty() {
+            let error = &menu_state.gen_options_error;
+            ui.label(menu_text(error, 16.0, Color32::RED));
+        }
 
         ui.add_space(8.0);
         ui.label(menu_text("Game Mode", 24.0, Color32::WHITE));
@@ -126,15 +170,20 @@ fn display_create_world(
             .button(menu_text("Create", 24.0, Color32::WHITE))
             .clicked()
             && !menu_state.world_name.is_empty()
+            && check_gen_options(menu_state)
         {
-            menu_state.create_world = true;
             let path = save::get_world_path(&menu_state.world_name);
-            create_new_world(menu_state, gamestate);
-            gamestate.world.path = path;
-            eprintln!("Created world: {}", gamestate.world.path);
-            if let Err(msg) = save::create_world_dir(&gamestate.world.path) {
-                eprintln!("Error: failed to create world: {msg}");
-                menu_state.create_world = false;
+            match create_new_world(menu_state, gamestate) {
+                Ok(()) => {
+                    menu_state.create_world = true;
+                    gamestate.world.path = path;
+                    eprintln!("Created world: {}", gamestate.world.path);
+                    if let Err(msg) = save::create_world_dir(&gamestate.world.path) {
+                        eprintln!("Error: failed to create world: {msg}");
+                        menu_state.create_world = false;
+                    }
+                }
+                Err(msg) => menu_state.gen_options_error = msg,
             }
         }
 
@@ -1,5 +1,5 @@
 use super::transparent_frame;
-use crate::game::Game;
+use crate::{game::Game, voxel::biome_registry::get_biome_def};
 use egui_backend::egui::{Color32, RichText};
 use egui_gl_glfw as egui_backend;
 use egui_gl_glfw::egui;
@@ -30,10 +30,20 @@ fn debug_info(
     let pixels_per_point = format!("ctx.pixels_per_point() = {}", ctx.pixels_per_point(),);
     let paused = format!("paused = {}", gamestate.is_paused());
     let fps_text = format!("{fps} FPS");
-    let chunk_updates = format!("chunk updates: {}", gamestate.world.get_chunk_updates());
+    let scheduled_ticks = format!("scheduled ticks: {}", gamestate.world.get_scheduled_ticks());
     let seed_text = format!("seed: {}", gamestate.world.get_seed());
     let days_passed = format!("days passed: {}", gamestate.world.days_passed);
     let time_text = format!("current time: {:.2}", gamestate.world.time);
+    let (blockx, blocky, blockz) = (
+        playerx.floor() as i32,
+        playery.floor() as i32,
+        playerz.floor() as i32,
+    );
+    let biome = gamestate.world.get_biome(blockx, blocky, blockz);
+    let biome_name = biome
+        .map(|id| get_biome_def(id).map_or("unknown", |def| def.name.as_str()))
+        .unwrap_or("none");
+    let biome_text = format!("biome: {biome_name}");
     let dropped_item_text = format!(
         "dropped items | total: {} | in world: {}",
         gamestate.entities.get_dropped_item_count(),
@@ -51,11 +61,12 @@ fn debug_info(
             ui.heading(debug_text(&native_pixels_per_point));
             ui.heading(debug_text(&pixels_per_point));
             ui.heading(debug_text(&paused));
-            ui.heading(debug_text(&chunk_updates));
+            ui.heading(debug_text(&scheduled_ticks));
             ui.heading(debug_text(&fps_text));
             ui.heading(debug_text(&seed_text));
             ui.heading(debug_text(&days_passed));
             ui.heading(debug_text(&time_text));
+            ui.heading(debug_text(&biome_text));
             ui.heading(debug_text(&dropped_item_text));
         });
 }
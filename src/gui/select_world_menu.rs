@@ -86,8 +86,10 @@ fn display_select_world(
         {
             let path = SAVE_PATH.to_string() + menu_state.selected_world.clone().as_str() + "/";
             eprintln!("Attempting to load {path}...");
-            gamestate.load_world(&path);
-            menu_state.create_world = true;
+            match gamestate.load_world(&path) {
+                Ok(()) => menu_state.create_world = true,
+                Err(msg) => eprintln!("E: failed to load {path}: {msg}"),
+            }
         }
 
         //Delete world
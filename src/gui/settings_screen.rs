@@ -56,6 +56,11 @@ fn display_settings_menu(ui: &mut Ui, settings: &mut Settings) {
         settings.cloud_display = CloudDisplay::Disabled;
     }
 
+    ui.add_space(24.0);
+    ui.heading(menu_text("Saves", 32.0, Color32::WHITE));
+    let text = menu_text("Compress region files", 20.0, Color32::WHITE);
+    ui.checkbox(&mut settings.compress_saves, text);
+
     ui.add_space(24.0);
     if ui
         .button(menu_text("Reset to Defaults", 24.0, Color32::WHITE))
@@ -0,0 +1,13 @@
+pub mod assets;
+pub mod bin_data;
+pub mod game;
+pub mod gfx;
+pub mod gui;
+pub mod impfile;
+pub mod voxel;
+
+use game::Game;
+use voxel::{World, CHUNK_SIZE_F32, EMPTY_BLOCK};
+
+pub const CFG_PATH: &str = "cfg.impfile";
+pub const SETTINGS_PATH: &str = "settings.impfile";
@@ -1,19 +1,16 @@
 #![windows_subsystem = "windows"]
 
-mod assets;
-mod bin_data;
-mod game;
-mod gfx;
-mod gui;
-mod impfile;
-mod voxel;
-
-use game::{save, Game};
-use gui::main_menu::MainMenuOutput;
-use voxel::{flags::init_voxel_flags, World, CHUNK_SIZE_F32, EMPTY_BLOCK};
-
-const CFG_PATH: &str = "cfg.impfile";
-const SETTINGS_PATH: &str = "settings.impfile";
+use voxelworld::{
+    game::{self, save, Game},
+    gui::{self, main_menu::MainMenuOutput},
+    voxel::{
+        self,
+        biome_registry::init_biome_registry,
+        block_registry::init_block_registry,
+        world::{check_ore_gen, ORE_GEN_PATH},
+    },
+    CFG_PATH, SETTINGS_PATH,
+};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -29,14 +26,30 @@ fn main() {
     let (mut window, events) = game::init_window(&mut glfw);
     //Initialize gl
     gl::load_with(|s| window.get_proc_address(s) as *const _);
-    //Initialize voxel flags
-    init_voxel_flags();
+    //Initialize the block registry
+    if let Err(msg) = init_block_registry("assets/blocks.impfile") {
+        eprintln!("E: failed to load blocks: {msg}");
+        std::process::exit(1);
+    }
+    //Initialize the biome registry (requires the block registry)
+    if let Err(msg) = init_biome_registry("assets/biomes.impfile") {
+        eprintln!("E: failed to load biomes: {msg}");
+        std::process::exit(1);
+    }
+    //Check the ore generation file (requires the block and biome registries)
+    if let Err(msg) = check_ore_gen(ORE_GEN_PATH) {
+        eprintln!("E: failed to load ore generation: {msg}");
+        std::process::exit(1);
+    }
     //Initialize game state
     let mut gamestate = Game::new();
     gamestate.init();
     gamestate.load_config(CFG_PATH);
     gamestate.load_settings(SETTINGS_PATH);
-    gamestate.load_assets();
+    if let Err(msg) = gamestate.load_assets() {
+        eprintln!("E: failed to load assets: {msg}");
+        std::process::exit(1);
+    }
     gamestate.init_mouse_pos(&window);
 
     while !window.should_close() {
@@ -1,4 +1,6 @@
+pub mod biome_registry;
 pub mod block_info;
+pub mod block_registry;
 pub mod build;
 pub mod chunk;
 pub mod coordinates;
@@ -9,15 +11,16 @@ pub mod region;
 pub mod tile_data;
 pub mod world;
 
+use self::block_registry::get_block_def;
 pub use self::build::{destroy_block, place_block};
 pub use self::coordinates::{out_of_bounds, world_to_chunk_position, wrap_coord, ChunkPos};
 use self::flags::{
     get_flag, CAN_ROTATE_FLAG, CAN_USE, CONNECT_FLAG, FLAT_ITEM, FLUID, FLUID_DESTRUCTIBLE,
-    NON_VOXEL, NO_HITBOX, OPEN_INVENTORY, REPLACEABLE, ROTATE_Y_ONLY, TRANSPARENT_FLAG,
+    GRAVITY, NON_VOXEL, NO_HITBOX, OPEN_INVENTORY, REPLACEABLE, ROTATE_Y_ONLY, TRANSPARENT_FLAG,
 };
 use self::light::LightSrc;
 use cgmath::Vector3;
-pub use chunk::Chunk;
+pub use chunk::{Chunk, ScheduledTick};
 pub use world::World;
 
 pub const CHUNK_SIZE: usize = 16;
@@ -25,13 +28,17 @@ pub const CHUNK_SIZE_I32: i32 = CHUNK_SIZE as i32;
 pub const CHUNK_SIZE_F32: f32 = CHUNK_SIZE as f32;
 pub const CHUNK_LEN: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
 pub const CHUNK_LEN_U32: u32 = CHUNK_LEN as u32;
-pub const EMPTY_BLOCK: u8 = 0;
-pub const INDESTRUCTIBLE: u8 = 3;
+pub const EMPTY_BLOCK: u16 = 0;
+pub const INDESTRUCTIBLE: u16 = 3;
 
 //Block shapes
 pub const FULL_BLOCK: u8 = 0;
 pub const SLAB: u8 = 1;
 pub const STAIR: u8 = 2;
+//Bit of the geometry between the reflection and the shape that some blocks
+//use to store their state (powered doors and gates, extended pistons), it is
+//cleared when the block becomes an item
+pub const STATE_BIT: u8 = 1 << 4;
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Axis {
@@ -43,7 +50,7 @@ pub enum Axis {
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Block {
     //Block id
-    pub id: u8,
+    pub id: u16,
     //Represents geometry of the block
     //For liquid: 7 = still, 8 = flowing under
     //First 3 bits = shape
@@ -60,12 +67,13 @@ pub struct Block {
 
 impl Block {
     //Create a new empty block
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self { id: 0, geometry: 0 }
     }
 
     //Create a new block with an id
-    pub fn new_id(blockid: u8) -> Self {
+    pub fn new_id(blockid: u16) -> Self {
         Self {
             id: blockid,
             geometry: 0,
@@ -73,7 +81,7 @@ impl Block {
     }
 
     //Create a new fluid block
-    pub fn new_fluid(blockid: u8) -> Self {
+    pub fn new_fluid(blockid: u16) -> Self {
         Self {
             id: blockid,
             geometry: 7,
@@ -81,7 +89,7 @@ impl Block {
     }
 
     //Create a new block with id and orientation
-    pub fn new_id_orientation(blockid: u8, block_orientation: u8) -> Self {
+    pub fn new_id_orientation(blockid: u16, block_orientation: u8) -> Self {
         Self {
             id: blockid,
             geometry: block_orientation,
@@ -199,30 +207,19 @@ impl Block {
         get_flag(self.id) & OPEN_INVENTORY != 0
     }
 
+    //Returns if the block falls when there is nothing below it (sand), only
+    //full blocks fall
+    pub fn has_gravity(&self) -> bool {
+        if self.shape() != FULL_BLOCK {
+            return false;
+        }
+        get_flag(self.id) & GRAVITY != 0
+    }
+
     //Returns Some(light_level) if the block emits light
     //Returns None otherwise
     pub fn light_src(&self) -> Option<LightSrc> {
-        match self.id {
-            //Lava
-            13 => Some(LightSrc::new(15, 8, 0)),
-            //Red ore
-            23 => Some(LightSrc::new(6, 0, 0)),
-            //Lit Furnace
-            70 => Some(LightSrc::new(15, 12, 0)),
-            //Normal Torch
-            71 => Some(LightSrc::new(15, 15, 15)),
-            //Red torch
-            72 => Some(LightSrc::new(15, 0, 0)),
-            //Green torch
-            73 => Some(LightSrc::new(0, 15, 0)),
-            //Blue torch
-            74 => Some(LightSrc::new(0, 3, 15)),
-            //Uranium
-            94 => Some(LightSrc::new(0, 8, 0)),
-            //Uranium block
-            95 => Some(LightSrc::new(0, 12, 0)),
-            _ => None,
-        }
+        get_block_def(self.id).and_then(|def| def.light)
     }
 }
 
@@ -263,3 +260,14 @@ pub fn rotate_orientation_reverse(orientation: u8) -> u8 {
         _ => orientation,
     }
 }
+
+//Loads the blocks and biomes for tests, the registries are shared by every
+//test so this can be called any number of times
+#[cfg(test)]
+pub fn init_test_registries() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        block_registry::init_block_registry("assets/blocks.impfile").unwrap();
+        biome_registry::init_biome_registry("assets/biomes.impfile").unwrap();
+    });
+}
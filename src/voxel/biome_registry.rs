@@ -0,0 +1,560 @@
+/*
+ * The biome registry contains the definition of every biome (the blocks on
+ * the surface, the trees and plants that generate and the tint of grass and
+ * leaves), it is built from a biome definition file when the game starts and
+ * requires the block registry to already be loaded.
+ * The biome of a column is the first biome in the file that matches the
+ * temperature, elevation and mountain noise of that column.
+ * */
+
+use super::{
+    block_registry::{get_block_registry, BlockRegistry},
+    Block,
+};
+use crate::impfile::{self, Entry};
+use std::sync::OnceLock;
+
+static BIOME_REGISTRY: OnceLock<BiomeRegistry> = OnceLock::new();
+
+//Plants are chosen with a random number from 0 to PLANT_ROLLS - 1
+pub const PLANT_ROLLS: i32 = 80;
+//Chunk meshes store the index of a tint in 6 bits and 0 means no tint
+pub const MAX_TINTS: usize = 63;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TreeKind {
+    Oak,
+    SnowyOak,
+    BigOak,
+    GiantOak,
+    Spruce,
+    GiantSpruce,
+    Cactus,
+}
+
+fn parse_tree_kind(name: &str) -> Result<TreeKind, String> {
+    match name {
+        "oak" => Ok(TreeKind::Oak),
+        "snowy_oak" => Ok(TreeKind::SnowyOak),
+        "big_oak" => Ok(TreeKind::BigOak),
+        "giant_oak" => Ok(TreeKind::GiantOak),
+        "spruce" => Ok(TreeKind::Spruce),
+        "giant_spruce" => Ok(TreeKind::GiantSpruce),
+        "cactus" => Ok(TreeKind::Cactus),
+        _ => Err(format!("unknown tree kind '{name}'")),
+    }
+}
+
+//Dungeons generate underground in every biome so they can not be listed in a
+//biome definition
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StructureKind {
+    Village,
+    Dungeon,
+    RuinedTower,
+    DesertWell,
+}
+
+fn parse_structure_kind(name: &str) -> Result<StructureKind, String> {
+    match name {
+        "village" => Ok(StructureKind::Village),
+        "ruined_tower" => Ok(StructureKind::RuinedTower),
+        "desert_well" => Ok(StructureKind::DesertWell),
+        _ => Err(format!("unknown structure '{name}'")),
+    }
+}
+
+//Information about a column of terrain used to select its biome
+#[derive(Clone, Copy, Debug)]
+pub struct ColumnInfo {
+    pub temperature: i32,
+    //Height of the terrain relative to sea level
+    pub height: i32,
+    pub mountain: bool,
+    pub beach: bool,
+    pub river: bool,
+    pub lake: bool,
+}
+
+//A biome is only selected if every condition that is set is met
+#[derive(Clone, Default, Debug)]
+struct BiomeConditions {
+    min_temperature: Option<i32>,
+    max_temperature: Option<i32>,
+    min_height: Option<i32>,
+    max_height: Option<i32>,
+    mountain: Option<bool>,
+    beach: Option<bool>,
+    river: Option<bool>,
+    lake: Option<bool>,
+}
+
+impl BiomeConditions {
+    fn is_empty(&self) -> bool {
+        self.min_temperature.is_none()
+            && self.max_temperature.is_none()
+            && self.min_height.is_none()
+            && self.max_height.is_none()
+            && self.mountain.is_none()
+            && self.beach.is_none()
+            && self.river.is_none()
+            && self.lake.is_none()
+    }
+
+    fn matches(&self, column: &ColumnInfo) -> bool {
+        self.min_temperature.is_none_or(|t| column.temperature >= t)
+            && self.max_temperature.is_none_or(|t| column.temperature <= t)
+            && self.min_height.is_none_or(|h| column.height >= h)
+            && self.max_height.is_none_or(|h| column.height <= h)
+            && self.mountain.is_none_or(|m| column.mountain == m)
+            && self.beach.is_none_or(|b| column.beach == b)
+            && self.river.is_none_or(|r| column.river == r)
+            && self.lake.is_none_or(|l| column.lake == l)
+    }
+}
+
+#[derive(Clone)]
+pub struct BiomeDef {
+    pub name: String,
+    pub id: u8,
+    conditions: BiomeConditions,
+    pub surface: Block,
+    //Blocks right beneath the surface
+    pub under: Block,
+    //Surface block used on mountains above the snow line
+    pub peak: Option<Block>,
+    //Snow on top of the surface and ice on top of water
+    pub snow: bool,
+    pub trees: Vec<TreeKind>,
+    //Only every nth tree position has a tree
+    pub tree_spacing: usize,
+    //Chance that a plant position has a plant
+    pub plant_density: f64,
+    //(block id, weight)
+    pub plants: Vec<(u16, i32)>,
+    //Structures that can start in this biome
+    pub structures: Vec<StructureKind>,
+    pub grass_tint: [u8; 3],
+    pub leaf_tint: [u8; 3],
+}
+
+fn parse_value<T: std::str::FromStr>(var: &str, val: &str) -> Result<T, String> {
+    val.parse::<T>()
+        .map_err(|_| format!("invalid value '{val}' for {var}"))
+}
+
+fn parse_block(blocks: &BlockRegistry, name: &str) -> Result<Block, String> {
+    blocks
+        .get_id(name)
+        .map(Block::new_id)
+        .ok_or(format!("unknown block '{name}'"))
+}
+
+fn parse_tint(val: &str) -> Result<[u8; 3], String> {
+    let channels: Vec<u8> = val
+        .split(',')
+        .map(|s| s.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(format!("invalid tint '{val}', expected r,g,b (0 to 255)"))?;
+    channels
+        .try_into()
+        .map_err(|_| format!("invalid tint '{val}', expected r,g,b (0 to 255)"))
+}
+
+//Plants are a comma separated list of `block_name/weight`
+fn parse_plants(blocks: &BlockRegistry, val: &str) -> Result<Vec<(u16, i32)>, String> {
+    let mut plants = vec![];
+    for plant in val.split(',').filter(|s| !s.trim().is_empty()) {
+        let (name, weight) = plant
+            .split_once('/')
+            .ok_or(format!("invalid plant '{plant}', expected block/weight"))?;
+        let id = parse_block(blocks, name.trim())?.id;
+        let weight = weight
+            .trim()
+            .parse::<i32>()
+            .ok()
+            .filter(|w| *w > 0)
+            .ok_or(format!("invalid weight for plant '{plant}'"))?;
+        plants.push((id, weight));
+    }
+    Ok(plants)
+}
+
+impl BiomeDef {
+    fn from_entry(entry: &Entry, blocks: &BlockRegistry) -> Result<Self, String> {
+        let id_str = entry.get_var("id");
+        if id_str.is_empty() {
+            return Err("missing id".to_string());
+        }
+
+        let mut def = Self {
+            name: entry.get_name(),
+            id: parse_value("id", &id_str)?,
+            conditions: BiomeConditions::default(),
+            surface: parse_block(blocks, "grass")?,
+            under: parse_block(blocks, "dirt")?,
+            peak: None,
+            snow: false,
+            trees: vec![],
+            tree_spacing: 1,
+            plant_density: 0.0,
+            plants: vec![],
+            structures: vec![],
+            grass_tint: [255; 3],
+            leaf_tint: [255; 3],
+        };
+
+        for (var, val) in entry.get_all_vars() {
+            let conditions = &mut def.conditions;
+            match var.as_str() {
+                "id" => {}
+                "min_temperature" => conditions.min_temperature = Some(parse_value(&var, &val)?),
+                "max_temperature" => conditions.max_temperature = Some(parse_value(&var, &val)?),
+                "min_height" => conditions.min_height = Some(parse_value(&var, &val)?),
+                "max_height" => conditions.max_height = Some(parse_value(&var, &val)?),
+                "mountain" => conditions.mountain = Some(parse_value(&var, &val)?),
+                "beach" => conditions.beach = Some(parse_value(&var, &val)?),
+                "river" => conditions.river = Some(parse_value(&var, &val)?),
+                "lake" => conditions.lake = Some(parse_value(&var, &val)?),
+                "surface" => def.surface = parse_block(blocks, &val)?,
+                "under" => def.under = parse_block(blocks, &val)?,
+                "peak" => def.peak = Some(parse_block(blocks, &val)?),
+                "snow" => def.snow = parse_value(&var, &val)?,
+                "trees" => {
+                    def.trees = val
+                        .split(',')
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|s| parse_tree_kind(s.trim()))
+                        .collect::<Result<Vec<TreeKind>, String>>()?;
+                }
+                "tree_spacing" => {
+                    def.tree_spacing = parse_value::<usize>(&var, &val)?.max(1);
+                }
+                "plant_density" => {
+                    def.plant_density = parse_value::<f64>(&var, &val)?;
+                    if !(0.0..=1.0).contains(&def.plant_density) {
+                        return Err("plant_density must be between 0 and 1".to_string());
+                    }
+                }
+                "plants" => def.plants = parse_plants(blocks, &val)?,
+                "structures" => {
+                    def.structures = val
+                        .split(',')
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|s| parse_structure_kind(s.trim()))
+                        .collect::<Result<Vec<StructureKind>, String>>()?;
+                }
+                "grass_tint" => def.grass_tint = parse_tint(&val)?,
+                "leaf_tint" => def.leaf_tint = parse_tint(&val)?,
+                _ => return Err(format!("unknown variable '{var}'")),
+            }
+        }
+
+        Ok(def)
+    }
+
+    //rand_val should only depend on the position of the tree, returns the
+    //tree that generates at that position
+    pub fn get_tree(&self, rand_val: usize) -> Option<TreeKind> {
+        if self.trees.is_empty() || !rand_val.is_multiple_of(self.tree_spacing) {
+            return None;
+        }
+        Some(self.trees[(rand_val / self.tree_spacing) % self.trees.len()])
+    }
+
+    //rand_val should be between 0 and PLANT_ROLLS - 1, returns the id of the
+    //plant that should be placed
+    pub fn get_plant(&self, rand_val: i32) -> Option<u16> {
+        let limit = (self.plant_density * PLANT_ROLLS as f64).round() as i32;
+        if rand_val >= limit {
+            return None;
+        }
+
+        let total: i32 = self.plants.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rand_val * total / limit;
+        for (id, weight) in &self.plants {
+            if roll < *weight {
+                return Some(*id);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+pub struct BiomeRegistry {
+    //In the order that they are defined
+    biomes: Vec<BiomeDef>,
+    //Indexed by biome id, index into `biomes`
+    ids: Vec<Option<usize>>,
+    //Every grass and leaf tint that is not white
+    tints: Vec<[u8; 3]>,
+}
+
+impl BiomeRegistry {
+    //Builds the registry from a list of biome definitions, returns an error
+    //that lists every invalid or conflicting definition
+    pub fn from_entries(entries: &[Entry], blocks: &BlockRegistry) -> Result<Self, String> {
+        let mut registry = Self {
+            biomes: vec![],
+            ids: vec![None; u8::MAX as usize + 1],
+            tints: vec![],
+        };
+
+        let mut errors = vec![];
+        for entry in entries {
+            let name = entry.get_name();
+            let def = match BiomeDef::from_entry(entry, blocks) {
+                Ok(def) => def,
+                Err(msg) => {
+                    errors.push(format!("biome '{name}': {msg}"));
+                    continue;
+                }
+            };
+
+            if registry.biomes.iter().any(|biome| biome.name == name) {
+                errors.push(format!("biome '{name}' is defined more than once"));
+                continue;
+            }
+
+            if let Some(index) = registry.ids[def.id as usize] {
+                errors.push(format!(
+                    "biomes '{}' and '{name}' both have the id {}",
+                    registry.biomes[index].name, def.id
+                ));
+                continue;
+            }
+
+            for tint in [def.grass_tint, def.leaf_tint] {
+                if tint != [255; 3] && !registry.tints.contains(&tint) {
+                    registry.tints.push(tint);
+                }
+            }
+            registry.ids[def.id as usize] = Some(registry.biomes.len());
+            registry.biomes.push(def);
+        }
+
+        if registry.tints.len() > MAX_TINTS {
+            errors.push(format!(
+                "there are {} different tints, at most {MAX_TINTS} are allowed",
+                registry.tints.len()
+            ));
+        }
+
+        //Every column needs to have a biome
+        match registry.biomes.last() {
+            Some(last) if !last.conditions.is_empty() => errors.push(format!(
+                "the last biome ('{}') can not have conditions",
+                last.name
+            )),
+            None => errors.push("no biomes are defined".to_string()),
+            _ => {}
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+
+        Ok(registry)
+    }
+
+    pub fn load(path: &str, blocks: &BlockRegistry) -> Result<Self, String> {
+        let entries = impfile::parse_file(path);
+        Self::from_entries(&entries, blocks)
+            .map_err(|msg| format!("invalid biomes in {path}:\n{msg}"))
+    }
+
+    pub fn get(&self, id: u8) -> Option<&BiomeDef> {
+        self.ids[id as usize].map(|index| &self.biomes[index])
+    }
+
+    //Returns the index of the grass or leaf tint of the biome in tints()
+    //plus 1, 0 if the tint is white or the biome does not exist
+    pub fn tint_index(&self, id: u8, leaves: bool) -> u8 {
+        let Some(biome) = self.get(id) else {
+            return 0;
+        };
+        let tint = if leaves {
+            biome.leaf_tint
+        } else {
+            biome.grass_tint
+        };
+        self.tints
+            .iter()
+            .position(|t| *t == tint)
+            .map(|i| i as u8 + 1)
+            .unwrap_or(0)
+    }
+
+    pub fn tints(&self) -> &[[u8; 3]] {
+        &self.tints
+    }
+
+    //Returns the first biome that matches the column
+    pub fn select(&self, column: &ColumnInfo) -> &BiomeDef {
+        self.biomes
+            .iter()
+            .find(|biome| biome.conditions.matches(column))
+            .unwrap_or(self.default_biome())
+    }
+
+    //The last biome is used for columns that do not match any other biome
+    pub fn default_biome(&self) -> &BiomeDef {
+        &self.biomes[self.biomes.len() - 1]
+    }
+
+    //Iterates through the biomes in the order that they are defined
+    pub fn iter(&self) -> impl Iterator<Item = &BiomeDef> {
+        self.biomes.iter()
+    }
+}
+
+//This function should be called at the start of the game after the block
+//registry is initialized
+pub fn init_biome_registry(path: &str) -> Result<(), String> {
+    let blocks = get_block_registry().ok_or("blocks have not been loaded")?;
+    let registry = BiomeRegistry::load(path, blocks)?;
+    let count = registry.iter().count();
+    BIOME_REGISTRY
+        .set(registry)
+        .map_err(|_| "biome registry is already initialized".to_string())?;
+    eprintln!("Loaded {count} biomes");
+    Ok(())
+}
+
+//Returns None if the registry has not been initialized
+pub fn get_biome_registry() -> Option<&'static BiomeRegistry> {
+    BIOME_REGISTRY.get()
+}
+
+pub fn get_biome_def(id: u8) -> Option<&'static BiomeDef> {
+    BIOME_REGISTRY.get()?.get(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn biome_entry(name: &str, vars: &[(&str, &str)]) -> Entry {
+        let vars = vars
+            .iter()
+            .map(|(var, val)| (var.to_string(), val.to_string()))
+            .collect();
+        Entry::from_vec(name, vars)
+    }
+
+    fn test_blocks() -> BlockRegistry {
+        BlockRegistry::load("assets/blocks.impfile").unwrap()
+    }
+
+    #[test]
+    fn test_biome_registry() {
+        let blocks = test_blocks();
+        let entries = [
+            biome_entry(
+                "desert",
+                &[
+                    ("id", "2"),
+                    ("min_temperature", "76"),
+                    ("surface", "sand"),
+                    ("trees", "cactus"),
+                    ("tree_spacing", "3"),
+                ],
+            ),
+            biome_entry("ocean", &[("id", "1"), ("max_height", "-1")]),
+            biome_entry(
+                "plains",
+                &[
+                    ("id", "0"),
+                    ("trees", "oak,snowy_oak"),
+                    ("plant_density", "0.5"),
+                    ("plants", "tall_grass/3,red_flower/1"),
+                    ("structures", "village,ruined_tower"),
+                    ("grass_tint", "10,20,30"),
+                ],
+            ),
+        ];
+        let registry = BiomeRegistry::from_entries(&entries, &blocks).unwrap();
+        let column = |temperature, height| ColumnInfo {
+            temperature,
+            height,
+            mountain: false,
+            beach: false,
+            river: false,
+            lake: false,
+        };
+        assert_eq!(registry.select(&column(80, -5)).name, "desert");
+        assert_eq!(registry.select(&column(50, -5)).name, "ocean");
+        assert_eq!(registry.select(&column(50, 5)).name, "plains");
+        assert_eq!(registry.get(1).unwrap().name, "ocean");
+        assert!(registry.get(3).is_none());
+
+        let desert = registry.get(2).unwrap();
+        assert_eq!(desert.surface.id, 11);
+        assert_eq!(desert.under.id, 4);
+        assert_eq!(desert.get_tree(3), Some(TreeKind::Cactus));
+        assert_eq!(desert.get_tree(4), None);
+        assert_eq!(desert.get_plant(0), None);
+
+        let plains = registry.get(0).unwrap();
+        assert_eq!(plains.get_tree(1), Some(TreeKind::SnowyOak));
+        assert_eq!(plains.grass_tint, [10, 20, 30]);
+        assert_eq!(registry.tints(), [[10, 20, 30]]);
+        assert_eq!(registry.tint_index(0, false), 1);
+        //White leaves are not tinted
+        assert_eq!(registry.tint_index(0, true), 0);
+        assert_eq!(registry.tint_index(5, false), 0);
+        let structures = [StructureKind::Village, StructureKind::RuinedTower];
+        assert_eq!(plains.structures, structures);
+        assert!(desert.structures.is_empty());
+        assert_eq!(plains.get_plant(0), Some(49));
+        assert_eq!(plains.get_plant(29), Some(49));
+        assert_eq!(plains.get_plant(30), Some(54));
+        assert_eq!(plains.get_plant(39), Some(54));
+        assert_eq!(plains.get_plant(40), None);
+    }
+
+    #[test]
+    fn test_biome_registry_errors() {
+        let blocks = test_blocks();
+        let entries = [
+            biome_entry("a", &[("id", "1"), ("surface", "not_a_block")]),
+            biome_entry("b", &[("id", "1"), ("trees", "palm")]),
+            biome_entry("b2", &[("id", "1"), ("structures", "dungeon")]),
+            biome_entry("c", &[("id", "1"), ("plants", "tall_grass")]),
+            biome_entry("d", &[("id", "2"), ("max_height", "0")]),
+            biome_entry("e", &[("id", "2")]),
+            biome_entry("f", &[("id", "3"), ("min_temperature", "10")]),
+        ];
+        let msg = BiomeRegistry::from_entries(&entries, &blocks)
+            .err()
+            .unwrap();
+        assert!(msg.contains("biome 'a': unknown block 'not_a_block'"));
+        assert!(msg.contains("biome 'b': unknown tree kind 'palm'"));
+        assert!(msg.contains("biome 'b2': unknown structure 'dungeon'"));
+        assert!(msg.contains("biome 'c': invalid plant 'tall_grass'"));
+        assert!(msg.contains("biomes 'd' and 'e' both have the id 2"));
+        assert!(msg.contains("the last biome ('f') can not have conditions"));
+    }
+
+    #[test]
+    fn test_load_biomes_file() {
+        let blocks = test_blocks();
+        let registry = BiomeRegistry::load("assets/biomes.impfile", &blocks).unwrap();
+        let column = ColumnInfo {
+            temperature: 50,
+            height: 10,
+            mountain: false,
+            beach: false,
+            river: false,
+            lake: false,
+        };
+        assert_eq!(registry.select(&column).name, "plains");
+        let lake = ColumnInfo {
+            height: -3,
+            lake: true,
+            ..column
+        };
+        assert_eq!(registry.select(&lake).name, "lake");
+    }
+}
@@ -1,18 +1,14 @@
-use super::{Block, EMPTY_BLOCK, FULL_BLOCK};
-use crate::{
-    game::{
-        crafting::{load_item_aliases, ItemAliases},
-        inventory::{
-            item_to_string, reduce_amt, string_to_item_err,
-            tools::{string_to_tool_type, ToolType},
-            Item,
-        },
-    },
-    impfile::{self, Entry},
+use super::{
+    block_registry::{get_block_registry, BlockDef, BlockRegistry, DROP_PREFIX},
+    Block, FULL_BLOCK, STATE_BIT,
+};
+use crate::game::{
+    crafting::{load_item_aliases, ItemAliases},
+    inventory::{item_to_string, reduce_amt, string_to_item_err, tools::ToolType, Item},
 };
 use std::collections::HashMap;
 
-pub type BlockInfoTable = HashMap<u8, BlockInfo>;
+pub type BlockInfoTable = HashMap<u16, BlockInfo>;
 
 #[derive(Clone)]
 pub struct BlockDrop {
@@ -65,6 +61,8 @@ fn block_to_item(block: Block) -> Item {
 
     //Reset block reflection
     block_copy.set_reflection(0);
+    //Reset the state of doors and pistons so that they can stack
+    block_copy.geometry &= !STATE_BIT;
 
     //Reset block orientation
     match block.shape() {
@@ -165,82 +163,24 @@ impl BlockInfo {
     }
 }
 
-fn update_info<T>(table: &mut BlockInfoTable, id: u8, update_fn: T)
-where
-    T: Fn(&mut BlockInfo),
-{
-    if let Some(info) = table.get_mut(&id) {
-        update_fn(info);
-    } else {
-        let mut info = BlockInfo::default();
-        update_fn(&mut info);
-        table.insert(id, info);
-    }
-}
-
-//Updates block info based on a list of ids
-fn update_info_list<T>(table: &mut BlockInfoTable, ids: &[u8], update_fn: T)
-where
-    T: Fn(&mut BlockInfo),
-{
-    for id in ids.iter().copied() {
-        update_info(table, id, &update_fn);
-    }
-}
-
-fn parse_block_list(val: &str, item_aliases: &ItemAliases) -> Vec<u8> {
-    val.split(",")
-        .map(|s| parse_block_id(s, item_aliases))
-        .filter_map(|b| b.ok())
-        .collect()
-}
-
-fn update_break_time(entry: &Entry, table: &mut BlockInfoTable) {
-    let alias_path = entry.get_var("alias_path");
-    let item_aliases = if !alias_path.is_empty() {
-        load_item_aliases(&alias_path)
-    } else {
-        HashMap::new()
-    };
-
-    let vars = entry.get_all_vars();
-    for (name, val) in vars {
-        let break_time = name.parse::<f32>();
-        if let Ok(break_time) = break_time {
-            let blocks = parse_block_list(&val, &item_aliases);
-            update_info_list(table, &blocks, |info| {
-                info.break_time = break_time;
-            })
-        }
-    }
-}
-
-fn parse_item_str_aliased(s: &str, item_aliases: &ItemAliases) -> Result<Item, ()> {
+fn parse_item_str_aliased(s: &str, item_aliases: &ItemAliases) -> Result<Item, String> {
     //Prioritize item alias
     if let Some(item) = item_aliases.get(s) {
         return Ok(*item);
     }
-    string_to_item_err(s)
+    string_to_item_err(s).map_err(|_| format!("unknown item '{s}'"))
 }
 
-fn parse_block_id(s: &str, item_aliases: &ItemAliases) -> Result<u8, ()> {
-    if let Ok(item) = parse_item_str_aliased(s, item_aliases) {
-        return match item {
-            Item::Block(block, _) => Ok(block.id),
-            _ => Err(()),
-        };
-    }
-    s.parse::<u8>().map_err(|_| ())
-}
-
-fn parse_weight(s: &str, item_aliases: &ItemAliases) -> Result<(Item, f32), ()> {
+fn parse_weight(s: &str, item_aliases: &ItemAliases) -> Result<(Item, f32), String> {
     let data: Vec<String> = s.split("/").map(|s| s.to_string()).collect();
     //data must only have 2 components (item and weight)
     if data.len() != 2 {
-        return Err(());
+        return Err(format!("invalid drop '{s}', expected item/weight"));
     }
     let block_drop = parse_item_str_aliased(&data[0], item_aliases)?;
-    let weight = data[1].parse::<f32>().map_err(|_| ())?;
+    let weight = data[1]
+        .parse::<f32>()
+        .map_err(|_| format!("invalid weight in '{s}'"))?;
     Ok((block_drop, weight))
 }
 
@@ -248,7 +188,7 @@ fn parse_drops(
     held_str: &str,
     drop_list: &str,
     item_aliases: &ItemAliases,
-) -> Result<(Vec<String>, WeightTable), ()> {
+) -> Result<(Vec<String>, WeightTable), String> {
     let pickaxes = [
         "wood_pickaxe".to_string(),
         "stone_pickaxe".to_string(),
@@ -268,96 +208,76 @@ fn parse_drops(
             "diamond+" => pickaxes[4..].to_vec(),
             _ => vec![s.to_string()],
         })
-        .map(|s| parse_item_str_aliased(&s, item_aliases))
-        .filter_map(|parsed| parsed.ok())
-        .map(item_to_string)
-        .collect();
+        .map(|s| parse_item_str_aliased(&s, item_aliases).map(item_to_string))
+        .collect::<Result<_, _>>()?;
     let weight_table: WeightTable = drop_list
         .split("|")
         .map(|s| parse_weight(s, item_aliases))
-        .filter_map(|weight| weight.ok())
-        .map(|(i, w)| BlockDrop { item: i, weight: w })
-        .collect();
+        .map(|weight| weight.map(|(i, w)| BlockDrop { item: i, weight: w }))
+        .collect::<Result<_, _>>()?;
     Ok((held, weight_table))
 }
 
-fn update_block_drops(entry: &Entry, table: &mut BlockInfoTable) {
-    let path = entry.get_var("path");
-    if path.is_empty() {
-        return;
-    }
-    let block_drops = impfile::parse_file(&path);
-    let alias_path = entry.get_var("alias_path");
-    if alias_path.is_empty() {
-        return;
-    }
-    let item_aliases = load_item_aliases(&alias_path);
-    for e in block_drops {
-        let block_id = parse_block_id(&e.get_name(), &item_aliases).unwrap_or(EMPTY_BLOCK);
-        //If it wasn't parsed, ignore it
-        //This should be the case if it is an empty block
-        if block_id == EMPTY_BLOCK {
-            continue;
-        }
-        //Parse the drops based on the item the player is holding
-        let drops: Vec<(Vec<String>, WeightTable)> = e
-            .get_all_vars()
-            .iter()
-            .map(|(name, val)| parse_drops(name, val, &item_aliases))
-            .filter_map(|drops| drops.ok())
-            .collect();
-        for (held_items, weights) in drops {
-            update_info_list(table, &[block_id], |info| {
-                if let Some(block_drops) = &mut info.block_drops {
-                    for held in &held_items {
-                        block_drops.insert(held.clone(), weights.clone());
-                    }
-                } else {
-                    let mut block_drops = HashMap::new();
-                    for held in &held_items {
-                        block_drops.insert(held.clone(), weights.clone());
-                    }
-                    info.block_drops = Some(block_drops);
-                }
-            });
-        }
+//Parses the drops of a block, the drops are indexed by the held item
+fn parse_block_drops(
+    def: &BlockDef,
+    item_aliases: &ItemAliases,
+) -> Result<Option<HashMap<String, WeightTable>>, String> {
+    if def.drops.is_empty() {
+        return Ok(None);
     }
-}
 
-fn update_preferred_tool(entry: &Entry, table: &mut BlockInfoTable) {
-    let alias_path = entry.get_var("alias_path");
-    let item_aliases = if !alias_path.is_empty() {
-        load_item_aliases(&alias_path)
-    } else {
-        HashMap::new()
-    };
-    for (blocks_str, tool_str) in entry.get_all_vars() {
-        let tool = string_to_tool_type(&tool_str);
-        if let Ok(tool) = tool {
-            let blocks = parse_block_list(&blocks_str, &item_aliases);
-            update_info_list(table, &blocks, |info| {
-                info.preferred_tool = Some(tool);
-            });
+    let mut block_drops = HashMap::new();
+    //Parse the drops based on the item the player is holding
+    for (held_str, drop_list) in &def.drops {
+        let (held_items, weights) = parse_drops(held_str, drop_list, item_aliases)
+            .map_err(|msg| format!("'{DROP_PREFIX}{held_str}': {msg}"))?;
+        for held in held_items {
+            block_drops.insert(held, weights.clone());
         }
     }
+    Ok(Some(block_drops))
 }
 
-//id -> block info
-pub fn load_block_info(path: &str) -> BlockInfoTable {
+//Returns an error that lists every block with invalid drops
+fn block_info_from_registry(
+    registry: &BlockRegistry,
+    item_aliases: &ItemAliases,
+) -> Result<BlockInfoTable, String> {
     let mut table = BlockInfoTable::new();
+    let mut errors = vec![];
+    for def in registry.iter() {
+        let block_drops = match parse_block_drops(def, item_aliases) {
+            Ok(block_drops) => block_drops,
+            Err(msg) => {
+                errors.push(format!("block '{}': {msg}", def.name));
+                continue;
+            }
+        };
+        let info = BlockInfo {
+            break_time: def.break_time,
+            preferred_tool: def.tool,
+            block_drops,
+        };
+        table.insert(def.id, info);
+    }
 
-    let entries = impfile::parse_file(path);
-
-    for e in entries {
-        match e.get_name().as_str() {
-            "break_time" => update_break_time(&e, &mut table),
-            "drops" => update_block_drops(&e, &mut table),
-            "tools" => update_preferred_tool(&e, &mut table),
-            _ => {}
-        }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
     }
 
-    table
+    Ok(table)
+}
+
+//id -> block info, this is built from the block registry
+pub fn load_block_info(alias_path: &str) -> Result<BlockInfoTable, String> {
+    let registry = match get_block_registry() {
+        Some(registry) => registry,
+        None => return Ok(BlockInfoTable::new()),
+    };
+
+    let item_aliases = load_item_aliases(alias_path);
+    block_info_from_registry(registry, &item_aliases)
 }
 
 pub fn get_drop(table: &BlockInfoTable, held_item: Item, block: Block) -> Item {
@@ -372,3 +292,61 @@ pub fn get_drop(table: &BlockInfoTable, held_item: Item, block: Block) -> Item {
         _ => block_to_item(block),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{impfile::Entry, voxel::init_test_registries};
+
+    fn block_entry(name: &str, vars: &[(&str, &str)]) -> Entry {
+        let vars = vars
+            .iter()
+            .map(|(var, val)| (var.to_string(), val.to_string()))
+            .collect();
+        Entry::from_vec(name, vars)
+    }
+
+    #[test]
+    fn test_block_drop_errors() {
+        let entries = [
+            block_entry("stone", &[("id", "2"), ("drop:empty", "block,3,0,1/1.0")]),
+            block_entry("coal_ore", &[("id", "5"), ("drop:empty", "coal/1.0")]),
+            block_entry("gravel", &[("id", "6"), ("drop:empty", "block,6,0,1")]),
+            block_entry("sand", &[("id", "11"), ("drop:spoon", "empty/1.0")]),
+        ];
+        let registry = BlockRegistry::from_entries(&entries).unwrap();
+        let msg = block_info_from_registry(&registry, &ItemAliases::new())
+            .err()
+            .unwrap();
+        assert!(!msg.contains("'stone'"));
+        assert!(msg.contains("block 'coal_ore': 'drop:empty': unknown item 'coal'"));
+        assert!(msg.contains("block 'gravel': 'drop:empty': invalid drop 'block,6,0,1'"));
+        assert!(msg.contains("block 'sand': 'drop:spoon': unknown item 'spoon'"));
+
+        let registry = BlockRegistry::from_entries(&entries[..1]).unwrap();
+        let table = block_info_from_registry(&registry, &ItemAliases::new()).unwrap();
+        assert_eq!(
+            table.get(&2).unwrap().block_drops.as_ref().unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_load_block_info() {
+        init_test_registries();
+        let table = load_block_info("assets/item_aliases.impfile").unwrap();
+        assert_eq!(table.len(), get_block_registry().unwrap().iter().count());
+    }
+
+    #[test]
+    fn test_block_to_item() {
+        //An extended piston facing right
+        let mut piston = Block::new_id(121);
+        piston.set_orientation(1);
+        piston.geometry |= STATE_BIT;
+        let Item::Block(block, 1) = block_to_item(piston) else {
+            panic!("piston should drop itself");
+        };
+        assert_eq!(block, Block::new_id(121));
+    }
+}
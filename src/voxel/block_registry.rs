@@ -0,0 +1,403 @@
+/*
+ * The block registry contains the definition of every block in the game
+ * (name, flags, light, textures, break time, tool and drops), it is built
+ * from a block definition file when the game starts.
+ * */
+
+use super::{flags::flag_from_str, light::LightSrc, EMPTY_BLOCK};
+use crate::{
+    game::inventory::tools::{string_to_tool_type, ToolType},
+    impfile::{self, Entry},
+};
+use std::{collections::HashMap, sync::OnceLock};
+
+static BLOCK_REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+
+//Variables that start with this list what a block drops, the rest of the
+//name is the item that is held when the block is broken
+pub const DROP_PREFIX: &str = "drop:";
+
+//How the faces of a block are textured
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextureLayout {
+    //Sides, top and bottom
+    Cube,
+    //The top texture is used on the ends of the log and the rotated
+    //texture is used on the sides when the log is on its side
+    Log,
+    //Like a cube but one of the sides uses the front texture, the front
+    //faces the player when the block is placed
+    Front,
+    //Two diagonal faces
+    Plant,
+}
+
+//The block texture atlas is a 16 x 16 grid and the meshes store the texture
+//index in a single byte so textures past this can not be used
+pub const TEXTURE_COUNT: u16 = 256;
+
+//Indices into the block texture atlas
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BlockTextures {
+    pub layout: TextureLayout,
+    pub side: u16,
+    pub top: u16,
+    pub bottom: u16,
+    pub front: u16,
+    pub rotated: u16,
+    //Textures for the sides of slabs
+    pub slab: [Option<u16>; 2],
+}
+
+impl BlockTextures {
+    //Every face uses the same texture
+    pub fn new(texture: u16) -> Self {
+        Self {
+            layout: TextureLayout::Cube,
+            side: texture,
+            top: texture,
+            bottom: texture,
+            front: texture,
+            rotated: texture,
+            slab: [None, None],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BlockDef {
+    pub name: String,
+    pub id: u16,
+    pub flags: u16,
+    pub light: Option<LightSrc>,
+    pub textures: BlockTextures,
+    pub break_time: f32,
+    pub tool: Option<ToolType>,
+    //(held items, drop list), these are parsed when the block info table is
+    //loaded since they can refer to item aliases
+    pub drops: Vec<(String, String)>,
+}
+
+fn parse_texture(name: &str, val: &str) -> Result<u16, String> {
+    val.parse::<u16>()
+        .ok()
+        .filter(|texture| *texture < TEXTURE_COUNT)
+        .ok_or(format!(
+            "invalid texture index '{val}' for '{name}' (0 to {})",
+            TEXTURE_COUNT - 1
+        ))
+}
+
+fn parse_light(val: &str) -> Result<LightSrc, String> {
+    let channels: Vec<u16> = val
+        .split(',')
+        .map(|s| s.parse::<u16>().ok().filter(|v| *v <= 15))
+        .collect::<Option<Vec<u16>>>()
+        .ok_or(format!("invalid light '{val}', expected r,g,b (0 to 15)"))?;
+    if channels.len() != 3 {
+        return Err(format!("invalid light '{val}', expected r,g,b (0 to 15)"));
+    }
+    Ok(LightSrc::new(channels[0], channels[1], channels[2]))
+}
+
+fn parse_layout(val: &str) -> Result<TextureLayout, String> {
+    match val {
+        "cube" => Ok(TextureLayout::Cube),
+        "log" => Ok(TextureLayout::Log),
+        "front" => Ok(TextureLayout::Front),
+        "plant" => Ok(TextureLayout::Plant),
+        _ => Err(format!("unknown layout '{val}'")),
+    }
+}
+
+impl BlockDef {
+    //Returns an error if any variable is invalid or unknown
+    fn from_entry(entry: &Entry) -> Result<Self, String> {
+        let name = entry.get_name();
+        let id_str = entry.get_var("id");
+        if id_str.is_empty() {
+            return Err("missing id".to_string());
+        }
+        let id = id_str
+            .parse::<u16>()
+            .map_err(|_| format!("invalid id '{id_str}'"))?;
+        if id == EMPTY_BLOCK {
+            return Err(format!("id {EMPTY_BLOCK} is reserved for empty blocks"));
+        }
+
+        let mut def = Self {
+            name,
+            id,
+            flags: 0,
+            light: None,
+            textures: BlockTextures::new(id),
+            break_time: 0.0,
+            tool: None,
+            drops: vec![],
+        };
+
+        let mut vars = entry.get_all_vars();
+        vars.sort();
+        //Textures that default to other textures are set after everything
+        //else has been read
+        let mut top = None;
+        let mut bottom = None;
+        let mut front = None;
+        let mut rotated = None;
+        for (var, val) in vars {
+            match var.as_str() {
+                "id" => {}
+                "flags" => {
+                    for flag in val.split(',').filter(|s| !s.is_empty()) {
+                        def.flags |= flag_from_str(flag).ok_or(format!("unknown flag '{flag}'"))?;
+                    }
+                }
+                "light" => def.light = Some(parse_light(&val)?),
+                "layout" => def.textures.layout = parse_layout(&val)?,
+                "texture" => def.textures.side = parse_texture(&var, &val)?,
+                "texture_top" => top = Some(parse_texture(&var, &val)?),
+                "texture_bottom" => bottom = Some(parse_texture(&var, &val)?),
+                "texture_front" => front = Some(parse_texture(&var, &val)?),
+                "texture_rotated" => rotated = Some(parse_texture(&var, &val)?),
+                "texture_slab" => {
+                    let textures: Vec<&str> = val.split(',').collect();
+                    if textures.len() > 2 {
+                        return Err(format!("too many slab textures '{val}'"));
+                    }
+                    for (i, texture) in textures.iter().enumerate() {
+                        def.textures.slab[i] = Some(parse_texture(&var, texture)?);
+                    }
+                }
+                "break_time" => {
+                    def.break_time = val
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|t| *t >= 0.0)
+                        .ok_or(format!("invalid break time '{val}'"))?;
+                }
+                "tool" => {
+                    let tool =
+                        string_to_tool_type(&val).map_err(|_| format!("unknown tool '{val}'"))?;
+                    def.tool = Some(tool);
+                }
+                _ => {
+                    if let Some(held) = var.strip_prefix(DROP_PREFIX) {
+                        def.drops.push((held.to_string(), val));
+                    } else {
+                        return Err(format!("unknown variable '{var}'"));
+                    }
+                }
+            }
+        }
+
+        let textures = &mut def.textures;
+        //Blocks use the texture with the same index as their id by default
+        if textures.side >= TEXTURE_COUNT {
+            return Err(format!(
+                "blocks with an id past {} need to set 'texture'",
+                TEXTURE_COUNT - 1
+            ));
+        }
+        textures.top = top.unwrap_or(textures.side);
+        textures.bottom = bottom.unwrap_or(textures.top);
+        textures.front = front.unwrap_or(textures.side);
+        textures.rotated = rotated.unwrap_or(textures.side);
+        //Blocks with a single slab texture use it for every slab
+        if textures.slab[1].is_none() {
+            textures.slab[1] = textures.slab[0];
+        }
+
+        Ok(def)
+    }
+}
+
+pub struct BlockRegistry {
+    //Indexed by block id
+    blocks: Vec<Option<BlockDef>>,
+    ids: HashMap<String, u16>,
+}
+
+impl BlockRegistry {
+    //Builds the registry from a list of block definitions, returns an error
+    //that lists every invalid or conflicting definition
+    pub fn from_entries(entries: &[Entry]) -> Result<Self, String> {
+        let mut registry = Self {
+            blocks: vec![],
+            ids: HashMap::new(),
+        };
+
+        let mut errors = vec![];
+        for entry in entries {
+            let name = entry.get_name();
+            let def = match BlockDef::from_entry(entry) {
+                Ok(def) => def,
+                Err(msg) => {
+                    errors.push(format!("block '{name}': {msg}"));
+                    continue;
+                }
+            };
+
+            if registry.ids.contains_key(&name) {
+                errors.push(format!("block '{name}' is defined more than once"));
+                continue;
+            }
+
+            let index = def.id as usize;
+            if let Some(Some(other)) = registry.blocks.get(index) {
+                errors.push(format!(
+                    "blocks '{}' and '{name}' both have the id {}",
+                    other.name, def.id
+                ));
+                continue;
+            }
+
+            if registry.blocks.len() <= index {
+                registry.blocks.resize(index + 1, None);
+            }
+            registry.ids.insert(name, def.id);
+            registry.blocks[index] = Some(def);
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+
+        Ok(registry)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let entries = impfile::parse_file(path);
+        if entries.is_empty() {
+            return Err(format!("no blocks are defined in {path}"));
+        }
+        Self::from_entries(&entries).map_err(|msg| format!("invalid blocks in {path}:\n{msg}"))
+    }
+
+    pub fn get(&self, id: u16) -> Option<&BlockDef> {
+        self.blocks.get(id as usize).and_then(|def| def.as_ref())
+    }
+
+    //Returns the id of the block with the name
+    pub fn get_id(&self, name: &str) -> Option<u16> {
+        self.ids.get(name).copied()
+    }
+
+    //Iterates through the blocks in order of id
+    pub fn iter(&self) -> impl Iterator<Item = &BlockDef> {
+        self.blocks.iter().flatten()
+    }
+}
+
+//This function should be called at the start of the game
+pub fn init_block_registry(path: &str) -> Result<(), String> {
+    let registry = BlockRegistry::load(path)?;
+    let count = registry.iter().count();
+    BLOCK_REGISTRY
+        .set(registry)
+        .map_err(|_| "block registry is already initialized".to_string())?;
+    eprintln!("Loaded {count} blocks");
+    Ok(())
+}
+
+//Returns None if the registry has not been initialized
+pub fn get_block_registry() -> Option<&'static BlockRegistry> {
+    BLOCK_REGISTRY.get()
+}
+
+pub fn get_block_def(id: u16) -> Option<&'static BlockDef> {
+    BLOCK_REGISTRY.get()?.get(id)
+}
+
+//Blocks that are not in the registry use the texture with the same index as
+//their id on every face
+pub fn get_block_textures(id: u16) -> BlockTextures {
+    get_block_def(id)
+        .map(|def| def.textures)
+        .unwrap_or(BlockTextures::new(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::flags::{FLUID, PLANT_FLAGS, REPLACEABLE};
+
+    fn block_entry(name: &str, vars: &[(&str, &str)]) -> Entry {
+        let vars = vars
+            .iter()
+            .map(|(var, val)| (var.to_string(), val.to_string()))
+            .collect();
+        Entry::from_vec(name, vars)
+    }
+
+    #[test]
+    fn test_block_registry() {
+        let entries = [
+            block_entry("water", &[("id", "12"), ("flags", "fluid")]),
+            block_entry(
+                "tall_grass",
+                &[
+                    ("id", "300"),
+                    ("flags", "plant,replaceable"),
+                    ("layout", "plant"),
+                    ("texture", "49"),
+                    ("light", "1,2,3"),
+                    ("break_time", "0.5"),
+                    ("tool", "hoe"),
+                    ("drop:empty", "seeds/1.0|empty/7.0"),
+                ],
+            ),
+        ];
+        let registry = BlockRegistry::from_entries(&entries).unwrap();
+        assert_eq!(registry.get_id("water"), Some(12));
+        assert_eq!(registry.get(12).unwrap().flags, FLUID);
+        assert!(registry.get(13).is_none());
+        assert!(registry.get(1000).is_none());
+
+        let def = registry.get(300).unwrap();
+        assert_eq!(def.flags, PLANT_FLAGS | REPLACEABLE);
+        assert_eq!(def.textures.layout, TextureLayout::Plant);
+        assert_eq!(def.textures.side, 49);
+        assert_eq!(def.textures.bottom, 49);
+        assert_eq!(def.light.map(|l| (l.r, l.g, l.b)), Some((1, 2, 3)));
+        assert_eq!(def.break_time, 0.5);
+        assert_eq!(def.tool, Some(ToolType::Hoe));
+        assert_eq!(def.drops.len(), 1);
+        assert_eq!(registry.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_block_registry_errors() {
+        let entries = [
+            block_entry("stone", &[("id", "2")]),
+            block_entry("cobblestone", &[("id", "2")]),
+            block_entry("air", &[("id", "0")]),
+            block_entry("dirt", &[("id", "4"), ("flags", "soft")]),
+            block_entry("sand", &[("id", "11"), ("colour", "yellow")]),
+            block_entry("gravel", &[]),
+            block_entry("glass", &[("id", "9"), ("texture", "256")]),
+            block_entry("clay", &[("id", "256")]),
+        ];
+        let msg = BlockRegistry::from_entries(&entries).err().unwrap();
+        assert!(msg.contains("'stone' and 'cobblestone' both have the id 2"));
+        assert!(msg.contains("block 'air': id 0 is reserved"));
+        assert!(msg.contains("block 'dirt': unknown flag 'soft'"));
+        assert!(msg.contains("block 'sand': unknown variable 'colour'"));
+        assert!(msg.contains("block 'gravel': missing id"));
+        assert!(msg.contains("block 'glass': invalid texture index '256'"));
+        assert!(msg.contains("block 'clay': blocks with an id past 255 need to set 'texture'"));
+
+        let entries = [
+            block_entry("stone", &[("id", "2")]),
+            block_entry("stone", &[("id", "3")]),
+        ];
+        let msg = BlockRegistry::from_entries(&entries).err().unwrap();
+        assert!(msg.contains("block 'stone' is defined more than once"));
+    }
+
+    #[test]
+    fn test_load_blocks_file() {
+        let registry = BlockRegistry::load("assets/blocks.impfile").unwrap();
+        assert_eq!(registry.get_id("grass"), Some(1));
+        assert!(registry.get(13).unwrap().light.is_some());
+    }
+}
@@ -555,6 +555,11 @@ pub fn interact_with_block(
                 };
                 b
             }
+            //Lever
+            109 => Block::new_id(110),
+            110 => Block::new_id(109),
+            //Button, it is released by a scheduled tick
+            111 | 112 => Block::new_id(112),
             _ => block,
         };
 
@@ -1,4 +1,5 @@
 mod light;
+pub mod palette;
 pub mod save;
 mod update_tile_data;
 
@@ -7,6 +8,7 @@ use super::{
     tile_data::TileData,
     world_to_chunk_position, Block, ChunkPos, CHUNK_SIZE, CHUNK_SIZE_I32, EMPTY_BLOCK,
 };
+use palette::BlockStorage;
 use std::collections::HashMap;
 
 fn out_of_bounds(index_x: i32, index_y: i32, index_z: i32) -> bool {
@@ -25,12 +27,20 @@ fn pos_to_index(x: i32, y: i32, z: i32, pos: ChunkPos) -> (i32, i32, i32) {
     (index_x, index_y, index_z)
 }
 
+//A block update that should happen on a specific world tick, id is the id
+//of the block when the tick was scheduled
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScheduledTick {
+    pub id: u16,
+    pub due: u64,
+}
+
 #[derive(Clone, Debug)]
 pub struct Chunk {
     //Chunks are CHUNK_SIZE x CHUNK_SIZE x CHUNK_SIZE cubes
     //For convention, assume that x is left to right, z is
     //forward and backwards, and y is up and down
-    blocks: Vec<Block>,
+    blocks: BlockStorage,
     //Stores the light data for each block
     light: Vec<Light>,
     //Integer position of the chunk, this is corner that has the lowest value
@@ -40,28 +50,34 @@ pub struct Chunk {
     iy: i32,
     iz: i32,
     data: HashMap<(i32, i32, i32), TileData>,
+    //Biome id of each column (indexed by z * CHUNK_SIZE + x), this is empty
+    //if the chunk was generated without biomes
+    biomes: Vec<u8>,
+    //Block updates that are waiting to happen in this chunk, these are
+    //stored in the chunk so that they get saved and unloaded with it
+    ticks: HashMap<(i32, i32, i32), ScheduledTick>,
+    //Set if the chunk was saved before ticks were stored, the chunk then
+    //needs to be scanned for blocks that should be updated
+    missing_ticks: bool,
 }
 
 impl Chunk {
     pub fn new(x: i32, y: i32, z: i32) -> Self {
         Self {
-            blocks: vec![],
+            blocks: BlockStorage::new(),
             light: vec![],
             ix: x,
             iy: y,
             iz: z,
             data: HashMap::new(),
+            biomes: vec![],
+            ticks: HashMap::new(),
+            missing_ticks: false,
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        for b in &self.blocks {
-            if b.id != EMPTY_BLOCK {
-                return false;
-            }
-        }
-
-        true
+        self.blocks.all(|b| b.id == EMPTY_BLOCK)
     }
 
     //Frees memory if the chunk is completely empty
@@ -89,7 +105,7 @@ impl Chunk {
         let index_y = CHUNK_SIZE * CHUNK_SIZE * y;
         let index_z = CHUNK_SIZE * z;
         let index_x = x;
-        self.blocks[index_x + index_y + index_z]
+        self.blocks.get(index_x + index_y + index_z)
     }
 
     pub fn get_light_relative(&self, x: usize, y: usize, z: usize) -> Light {
@@ -126,7 +142,7 @@ impl Chunk {
 
     pub fn set_block_relative(&mut self, x: usize, y: usize, z: usize, block: Block) {
         if self.blocks.is_empty() && block.id != EMPTY_BLOCK {
-            self.blocks = vec![Block::new(); CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE];
+            self.blocks = BlockStorage::filled(Block::new());
         } else if !self.blocks.is_empty() && block.id == EMPTY_BLOCK {
             self.handle_empty();
         }
@@ -143,7 +159,7 @@ impl Chunk {
         let index_y = CHUNK_SIZE * CHUNK_SIZE * y;
         let index_z = CHUNK_SIZE * z;
         let index_x = x;
-        self.blocks[index_x + index_y + index_z] = block;
+        self.blocks.set(index_x + index_y + index_z, block);
     }
 
     //x, y, and z are absolute world positions
@@ -208,6 +224,69 @@ impl Chunk {
         self.data.get(&(x, y, z)).cloned()
     }
 
+    //Ignored if there is not a biome for every column in the chunk
+    pub fn set_biomes(&mut self, biomes: Vec<u8>) {
+        if biomes.len() == CHUNK_SIZE * CHUNK_SIZE {
+            self.biomes = biomes;
+        }
+    }
+
+    pub fn get_biomes(&self) -> &[u8] {
+        &self.biomes
+    }
+
+    //x and z are absolute world positions
+    pub fn get_biome(&self, x: i32, z: i32) -> Option<u8> {
+        let (index_x, _, index_z) = pos_to_index(x, 0, z, self.get_chunk_pos());
+        if out_of_bounds(index_x, 0, index_z) {
+            return None;
+        }
+
+        let index = index_z as usize * CHUNK_SIZE + index_x as usize;
+        self.biomes.get(index).copied()
+    }
+
+    //Returns true if the tick was added, if there already is a tick for the
+    //same block at the position then only the earlier of the two is kept,
+    //a tick for a different block is replaced since it would be ignored
+    //If the position is not in the chunk, ignore it
+    pub fn schedule_tick(&mut self, x: i32, y: i32, z: i32, tick: ScheduledTick) -> bool {
+        let (index_x, index_y, index_z) = pos_to_index(x, y, z, self.get_chunk_pos());
+        if out_of_bounds(index_x, index_y, index_z) {
+            return false;
+        }
+
+        match self.ticks.get(&(x, y, z)) {
+            Some(scheduled) if scheduled.id == tick.id && scheduled.due <= tick.due => false,
+            _ => {
+                self.ticks.insert((x, y, z), tick);
+                true
+            }
+        }
+    }
+
+    pub fn get_scheduled_tick(&self, x: i32, y: i32, z: i32) -> Option<ScheduledTick> {
+        self.ticks.get(&(x, y, z)).copied()
+    }
+
+    pub fn remove_scheduled_tick(&mut self, x: i32, y: i32, z: i32) {
+        self.ticks.remove(&(x, y, z));
+    }
+
+    pub fn set_missing_ticks(&mut self) {
+        self.missing_ticks = true;
+    }
+
+    //Returns true if the chunk needs to be scanned for ticks, this only
+    //returns true once
+    pub fn take_missing_ticks(&mut self) -> bool {
+        std::mem::take(&mut self.missing_ticks)
+    }
+
+    pub fn scheduled_ticks(&self) -> impl Iterator<Item = ((i32, i32, i32), ScheduledTick)> + '_ {
+        self.ticks.iter().map(|(pos, tick)| (*pos, *tick))
+    }
+
     pub fn get_chunk_pos(&self) -> ChunkPos {
         ChunkPos::new(self.ix, self.iy, self.iz)
     }
@@ -33,6 +33,12 @@ impl Chunk {
         !self.light.is_empty()
     }
 
+    //Removes the light in the chunk so that it is calculated again the next
+    //time that the chunk is loaded
+    pub fn clear_light(&mut self) {
+        self.light = vec![];
+    }
+
     //Returns Some(y) if a block is found in a column,
     //None otherwise
     pub fn get_tallest_sky_block(&self, x: i32, z: i32) -> Option<i32> {
@@ -0,0 +1,263 @@
+/*
+ * Block storage for chunks, instead of storing every block in the chunk the
+ * blocks are stored as a palette of the distinct blocks in the chunk and an
+ * index into the palette for each block. The indices are bit packed so a
+ * chunk that only has a few different blocks takes up much less memory.
+ * */
+
+use crate::voxel::{Block, CHUNK_LEN};
+use std::ops::Index;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+//Returns the number of bits needed to index a palette of size len, this is
+//always a power of 2 (or 0) so that an index never crosses into another word
+fn bits_for_len(len: usize) -> usize {
+    let mut bits = 0;
+    while (1 << bits) < len {
+        bits = if bits == 0 { 1 } else { bits * 2 };
+    }
+    bits
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockStorage {
+    palette: Vec<Block>,
+    //Number of bits used for each index
+    bits: usize,
+    indices: Vec<u64>,
+}
+
+impl BlockStorage {
+    //Storage with nothing allocated, every block is empty
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            palette: vec![],
+            bits: 0,
+            indices: vec![],
+        }
+    }
+
+    //Storage filled with a single block
+    pub fn filled(block: Block) -> Self {
+        Self {
+            palette: vec![block],
+            bits: 0,
+            indices: vec![],
+        }
+    }
+
+    //Creates the storage from (count, block) pairs
+    pub fn from_rle(blocks: &[(u16, Block)]) -> Self {
+        let mut palette = vec![];
+        for (_, block) in blocks {
+            if !palette.contains(block) {
+                palette.push(*block);
+            }
+        }
+
+        let mut storage = Self {
+            bits: bits_for_len(palette.len()),
+            palette,
+            indices: vec![],
+        };
+        storage.indices = vec![0; storage.word_count()];
+        let mut index = 0;
+        for (count, block) in blocks {
+            let palette_index = storage.palette_index(*block).unwrap_or(0);
+            for _ in 0..*count {
+                if index >= CHUNK_LEN {
+                    break;
+                }
+                storage.set_index(index, palette_index);
+                index += 1;
+            }
+        }
+        storage
+    }
+
+    //Returns 0 if nothing is allocated, CHUNK_LEN otherwise
+    pub fn len(&self) -> usize {
+        if self.palette.is_empty() {
+            0
+        } else {
+            CHUNK_LEN
+        }
+    }
+
+    //Returns true if nothing is allocated
+    pub fn is_empty(&self) -> bool {
+        self.palette.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.palette.clear();
+        self.indices.clear();
+        self.bits = 0;
+    }
+
+    //Returns the number of distinct blocks that can be in the storage
+    //without having to increase the size of the indices
+    pub fn palette_capacity(&self) -> usize {
+        1 << self.bits
+    }
+
+    fn word_count(&self) -> usize {
+        CHUNK_LEN * self.bits / WORD_BITS
+    }
+
+    fn palette_index(&self, block: Block) -> Option<usize> {
+        self.palette.iter().position(|b| *b == block)
+    }
+
+    fn get_index(&self, index: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_word = WORD_BITS / self.bits;
+        let word = self.indices[index / per_word];
+        let shift = (index % per_word) * self.bits;
+        ((word >> shift) & ((1 << self.bits) - 1)) as usize
+    }
+
+    fn set_index(&mut self, index: usize, palette_index: usize) {
+        if self.bits == 0 {
+            return;
+        }
+        let per_word = WORD_BITS / self.bits;
+        let shift = (index % per_word) * self.bits;
+        let mask = ((1u64 << self.bits) - 1) << shift;
+        let word = &mut self.indices[index / per_word];
+        *word = (*word & !mask) | ((palette_index as u64) << shift);
+    }
+
+    //Removes any unused blocks from the palette and repacks the indices so
+    //that the palette can fit at least min_len blocks
+    fn repack(&mut self, min_len: usize) {
+        let mut used = vec![false; self.palette.len()];
+        for i in 0..CHUNK_LEN {
+            used[self.get_index(i)] = true;
+        }
+
+        let mut remap = vec![0; self.palette.len()];
+        let mut palette = vec![];
+        for (i, block) in self.palette.iter().enumerate() {
+            if used[i] {
+                remap[i] = palette.len();
+                palette.push(*block);
+            }
+        }
+
+        let mut repacked = Self {
+            bits: bits_for_len(min_len.max(palette.len())),
+            palette,
+            indices: vec![],
+        };
+        repacked.indices = vec![0; repacked.word_count()];
+        for i in 0..CHUNK_LEN {
+            repacked.set_index(i, remap[self.get_index(i)]);
+        }
+        *self = repacked;
+    }
+
+    //index must be less than CHUNK_LEN
+    pub fn get(&self, index: usize) -> Block {
+        if self.palette.is_empty() {
+            return Block::new();
+        }
+        self.palette[self.get_index(index)]
+    }
+
+    //index must be less than CHUNK_LEN
+    pub fn set(&mut self, index: usize, block: Block) {
+        if self.palette.is_empty() {
+            *self = Self::filled(Block::new());
+        }
+
+        let palette_index = match self.palette_index(block) {
+            Some(palette_index) => palette_index,
+            None => {
+                if self.palette.len() >= self.palette_capacity() {
+                    //Try to make room by removing unused blocks first
+                    self.repack(self.palette.len());
+                    if self.palette.len() >= self.palette_capacity() {
+                        self.repack(self.palette.len() + 1);
+                    }
+                }
+                self.palette.push(block);
+                self.palette.len() - 1
+            }
+        };
+        self.set_index(index, palette_index);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Block> + '_ {
+        (0..self.len()).map(|i| &self[i])
+    }
+
+    //Returns true if every block in the storage satisfies f
+    pub fn all(&self, f: impl Fn(Block) -> bool) -> bool {
+        //Check the palette first to avoid going through every block
+        if self.palette.iter().all(|block| f(*block)) {
+            return true;
+        }
+        self.iter().all(|block| f(*block))
+    }
+}
+
+impl Index<usize> for BlockStorage {
+    type Output = Block;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.palette[self.get_index(index)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette() {
+        let mut storage = BlockStorage::new();
+        assert!(storage.is_empty());
+        assert_eq!(storage.get(5), Block::new());
+
+        //Fill with more blocks than fit in a byte to force repacking
+        for i in 0..CHUNK_LEN {
+            storage.set(
+                i,
+                Block::new_id_orientation((i % 300) as u16, (i / 256) as u8),
+            );
+        }
+        for i in 0..CHUNK_LEN {
+            let block = Block::new_id_orientation((i % 300) as u16, (i / 256) as u8);
+            assert_eq!(storage.get(i), block);
+        }
+        assert_eq!(storage.palette_capacity(), 1 << 16);
+
+        //Overwriting everything should shrink the palette again on the next
+        //repack
+        for i in 0..CHUNK_LEN {
+            storage.set(i, Block::new_id((i % 3) as u16));
+        }
+        storage.repack(0);
+        assert_eq!(storage.palette_capacity(), 4);
+        for i in 0..CHUNK_LEN {
+            assert_eq!(storage.get(i), Block::new_id((i % 3) as u16));
+        }
+    }
+
+    #[test]
+    fn test_palette_rle() {
+        let storage = BlockStorage::from_rle(&[(16, Block::new_id(1)), (4080, Block::new())]);
+        assert_eq!(storage.palette_capacity(), 2);
+        assert_eq!(storage.get(15), Block::new_id(1));
+        assert_eq!(storage.get(16), Block::new());
+
+        let storage = BlockStorage::from_rle(&[(4096, Block::new_id(2))]);
+        assert_eq!(storage.palette_capacity(), 1);
+        assert!(storage.all(|block| block.id == 2));
+    }
+}
@@ -1,10 +1,21 @@
-use super::Chunk;
+use super::{palette::BlockStorage, pos_to_index, Chunk, ScheduledTick};
 use crate::{
     bin_data::DataTable,
-    voxel::{Block, CHUNK_SIZE},
+    voxel::{light::Light, Block, CHUNK_SIZE, CHUNK_SIZE_I32},
 };
 use std::collections::HashMap;
 
+//This should be incremented whenever the way light is calculated changes so
+//that saved light data gets recalculated when it is loaded
+pub const LIGHT_VERSION: u16 = 1;
+
+//Index, block id and due tick
+const TICK_SIZE: usize = 2 * size_of::<u16>() + size_of::<u64>();
+
+fn read_u16(bytes: &[u8], index: usize) -> u16 {
+    u16::from_be_bytes([bytes[index], bytes[index + 1]])
+}
+
 impl Chunk {
     pub fn tiles_to_data_tables(&self) -> Vec<DataTable> {
         let mut data_tables = vec![];
@@ -19,23 +30,16 @@ impl Chunk {
             return Self::new(x, y, z);
         }
 
-        let mut chunk_blocks = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE);
-        for (count, block) in blocks {
-            for _ in 0..*count {
-                if chunk_blocks.len() >= CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
-                    break;
-                }
-                chunk_blocks.push(*block);
-            }
-        }
-
         Self {
-            blocks: chunk_blocks,
+            blocks: BlockStorage::from_rle(blocks),
             light: vec![],
             ix: x,
             iy: y,
             iz: z,
             data: HashMap::new(),
+            biomes: vec![],
+            ticks: HashMap::new(),
+            missing_ticks: false,
         }
     }
 
@@ -44,7 +48,7 @@ impl Chunk {
 
         let mut count = 0u16;
         let mut block = Block::new();
-        for b in &self.blocks {
+        for b in self.blocks.iter() {
             if *b != block {
                 if count != 0 {
                     data.push((count, block));
@@ -65,19 +69,101 @@ impl Chunk {
         data
     }
 
-    //Returns (len, counts, data)
+    //Returns the light version followed by the light data run length encoded
+    //as (count, light) pairs, returns nothing if the light is not initialized
+    pub fn light_to_bytes(&self) -> Vec<u8> {
+        if self.light.is_empty() {
+            return vec![];
+        }
+
+        let mut runs = vec![];
+        let mut count = 0u16;
+        let mut light = self.light[0];
+        for l in &self.light {
+            if *l != light {
+                runs.push((count, light));
+                light = *l;
+                count = 0;
+            }
+            count += 1;
+        }
+        runs.push((count, light));
+
+        let mut bytes = Vec::with_capacity(size_of::<u16>() * (runs.len() * 2 + 1));
+        bytes.extend(LIGHT_VERSION.to_be_bytes());
+        for (count, light) in runs {
+            bytes.extend(count.to_be_bytes());
+            bytes.extend(light.to_u16().to_be_bytes());
+        }
+        bytes
+    }
+
+    //Loads light created by light_to_bytes, if the data is from a different
+    //version or is invalid then it is ignored and the light stays uninitialized
+    pub fn light_from_bytes(&mut self, bytes: &[u8]) {
+        if bytes.len() < size_of::<u16>() || read_u16(bytes, 0) != LIGHT_VERSION {
+            return;
+        }
+
+        let mut light = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE);
+        for run in bytes[size_of::<u16>()..].chunks_exact(2 * size_of::<u16>()) {
+            let count = read_u16(run, 0) as usize;
+            let l = Light::from_u16(read_u16(run, size_of::<u16>()));
+            if light.len() + count > CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
+                return;
+            }
+            light.extend(std::iter::repeat_n(l, count));
+        }
+
+        if light.len() == CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
+            self.light = light;
+        }
+    }
+
+    //Returns the scheduled ticks as (index, id, due) where index is the
+    //index of the block in the chunk
+    pub fn ticks_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.ticks.len() * TICK_SIZE);
+        for ((x, y, z), tick) in &self.ticks {
+            let (ix, iy, iz) = pos_to_index(*x, *y, *z, self.get_chunk_pos());
+            let index = (iy * CHUNK_SIZE_I32 * CHUNK_SIZE_I32 + iz * CHUNK_SIZE_I32 + ix) as u16;
+            bytes.extend(index.to_be_bytes());
+            bytes.extend(tick.id.to_be_bytes());
+            bytes.extend(tick.due.to_be_bytes());
+        }
+        bytes
+    }
+
+    //Loads scheduled ticks created by ticks_to_bytes
+    pub fn ticks_from_bytes(&mut self, bytes: &[u8]) {
+        for tick in bytes.chunks_exact(TICK_SIZE) {
+            let index = read_u16(tick, 0) as i32;
+            let id = read_u16(tick, size_of::<u16>());
+            let mut due = [0u8; size_of::<u64>()];
+            due.copy_from_slice(&tick[2 * size_of::<u16>()..]);
+            let x = self.ix * CHUNK_SIZE_I32 + index % CHUNK_SIZE_I32;
+            let y = self.iy * CHUNK_SIZE_I32 + index / (CHUNK_SIZE_I32 * CHUNK_SIZE_I32);
+            let z = self.iz * CHUNK_SIZE_I32 + (index / CHUNK_SIZE_I32) % CHUNK_SIZE_I32;
+            let due = u64::from_be_bytes(due);
+            self.schedule_tick(x, y, z, ScheduledTick { id, due });
+        }
+    }
+
+    //Returns (len, counts, data), data contains the ids of the blocks
+    //followed by the geometry of the blocks
     pub fn get_chunk_bytes(&self) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
         let rle = self.rle_encode();
         let mut counts = Vec::<u8>::with_capacity(rle.len() * size_of::<u16>());
-        let mut blockdata = Vec::<u8>::with_capacity(rle.len());
+        let mut ids = Vec::<u8>::with_capacity(rle.len() * size_of::<u16>());
+        let mut geometry = Vec::<u8>::with_capacity(rle.len());
         for (count, b) in rle {
-            let count_bytes = count.to_be_bytes();
-            counts.push(count_bytes[0]);
-            counts.push(count_bytes[1]);
-            blockdata.push(b.id);
-            blockdata.push(b.geometry);
+            counts.extend(count.to_be_bytes());
+            ids.extend(b.id.to_be_bytes());
+            geometry.push(b.geometry);
         }
         let len = (counts.len() as u16 / size_of::<u16>() as u16).to_be_bytes();
+        let mut blockdata = ids;
+        blockdata.extend(geometry);
 
         (len.to_vec(), counts, blockdata)
     }
@@ -86,6 +172,7 @@ impl Chunk {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::voxel::light::LU;
 
     #[test]
     fn test_rle() {
@@ -99,6 +186,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_light_bytes() {
+        let mut testchunk = Chunk::new(0, 0, 0);
+        let mut chunk2 = Chunk::new(0, 0, 0);
+        chunk2.light_from_bytes(&testchunk.light_to_bytes());
+        assert!(!chunk2.light_initialized());
+
+        testchunk.update_light(1, 2, 3, LU::new(Some(15), Some(4), None, Some(2)));
+        chunk2.light_from_bytes(&testchunk.light_to_bytes());
+        assert!(chunk2.light_initialized());
+        assert_eq!(chunk2.light, testchunk.light);
+
+        //Data from a different version is ignored
+        let mut bytes = testchunk.light_to_bytes();
+        bytes[1] += 1;
+        let mut chunk3 = Chunk::new(0, 0, 0);
+        chunk3.light_from_bytes(&bytes);
+        assert!(!chunk3.light_initialized());
+    }
+
     #[test]
     fn test_rle_empty() {
         let testchunk = Chunk::new(0, 0, 0);
@@ -108,4 +215,24 @@ mod tests {
         assert_eq!(chunk2.blocks.len(), 0);
         assert_eq!(testchunk.blocks.len(), chunk2.blocks.len());
     }
+
+    #[test]
+    fn test_ticks_bytes() {
+        let mut testchunk = Chunk::new(-1, 2, 0);
+        let tick = ScheduledTick {
+            id: 12,
+            due: 1 << 40,
+        };
+        assert!(testchunk.schedule_tick(-1, 33, 5, tick));
+        assert!(testchunk.schedule_tick(-16, 47, 15, ScheduledTick { id: 13, due: 3 }));
+        //Outside of the chunk
+        assert!(!testchunk.schedule_tick(0, 33, 5, tick));
+        //Only the earliest tick is kept
+        assert!(!testchunk.schedule_tick(-16, 47, 15, ScheduledTick { id: 13, due: 4 }));
+
+        let mut chunk2 = Chunk::new(-1, 2, 0);
+        chunk2.ticks_from_bytes(&testchunk.ticks_to_bytes());
+        assert_eq!(chunk2.ticks, testchunk.ticks);
+        assert_eq!(chunk2.get_scheduled_tick(-1, 33, 5), Some(tick));
+    }
 }
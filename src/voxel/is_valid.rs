@@ -1,6 +1,6 @@
 use super::{orientation_to_normal, World, EMPTY_BLOCK};
 
-fn check_below_valid(world: &World, x: i32, y: i32, z: i32, valid_blocks: &[u8]) -> bool {
+fn check_below_valid(world: &World, x: i32, y: i32, z: i32, valid_blocks: &[u16]) -> bool {
     let below = world.get_block(x, y - 1, z);
     if below.shape() != 0 {
         return false;
@@ -33,7 +33,7 @@ type ValidBlockFn = fn(&World, i32, i32, i32) -> bool;
 
 //Returns a function that checks if a block in a position (x, y, z) is valid
 //If None is returned, then we assume that this block can be placed anywhere
-pub fn get_check_valid_fn(block: u8) -> Option<ValidBlockFn> {
+pub fn get_check_valid_fn(block: u16) -> Option<ValidBlockFn> {
     match block {
         //Sapling and Grass
         47 | 49 => Some(|world, x, y, z| check_below_valid(world, x, y, z, &[1, 4, 17])),
@@ -69,6 +69,14 @@ pub fn get_check_valid_fn(block: u8) -> Option<ValidBlockFn> {
         90 => Some(|world, x, y, z| check_below_valid(world, x, y, z, &[1, 4, 11, 17])),
         //Snowy sapling can be placed on snowy grass and snow
         92 => Some(|world, x, y, z| check_below_valid(world, x, y, z, &[1, 4, 17, 86, 87])),
+        //Wire
+        113 | 114 => Some(|world, x, y, z| {
+            let below = world.get_block(x, y - 1, z);
+            if below.shape() != 0 {
+                return false;
+            }
+            !below.transparent() && below.id != EMPTY_BLOCK
+        }),
         //Cotton
         99..=102 => Some(|world, x, y, z| check_below_valid(world, x, y, z, &[1, 4, 17, 43, 45])),
         _ => None,
@@ -92,6 +92,15 @@ impl Light {
         Self { light_data: 0 }
     }
 
+    //Used for saving light data
+    pub fn from_u16(light_data: u16) -> Self {
+        Self { light_data }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        self.light_data
+    }
+
     //Get the channel values
     get_channel!(blue, 0);
     get_channel!(green, 4);
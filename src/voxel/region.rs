@@ -1,11 +1,32 @@
 use super::{tile_data::TileData, world_to_chunk_position, Chunk};
+use crate::bin_data::FileFormat;
 use std::collections::HashMap;
 
 pub mod load;
 pub mod save;
+pub mod table;
 
 pub const REGION_SIZE: usize = 4;
 pub const REGION_SIZE_I32: i32 = REGION_SIZE as i32;
+//Version 1: added header
+//Version 2: added checksum
+//Version 3: added compression
+//Version 4: chunks are stored separately with an offset table
+//Version 5: block ids are stored as u16
+//Version 6: chunks store the biome of each column after their light
+//Version 7: chunks store their scheduled ticks after their biomes
+pub const REGION_MAGIC: [u8; 4] = *b"VWRG";
+pub const REGION_VERSION: u16 = 7;
+//First version that stores chunks with an offset table
+pub const OFFSET_TABLE_VERSION: u16 = 4;
+//Layout of region files before version 4, the whole region is stored as a
+//single block of data
+pub const LEGACY_REGION_FORMAT: FileFormat = FileFormat {
+    magic: REGION_MAGIC,
+    version: 3,
+    checksum_version: 2,
+    compression_version: 3,
+};
 
 fn chunk_to_region(x: i32) -> i32 {
     if x < 0 && x % REGION_SIZE_I32 != 0 {
@@ -138,17 +159,27 @@ pub fn get_region_chunks_remove(region: &mut Region, chunks: &mut HashMap<(i32,
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::voxel::Block;
+    use crate::{
+        bin_data::Compression,
+        voxel::{init_test_registries, Block, ScheduledTick},
+    };
     use std::collections::HashMap;
 
     fn init_chunks(chunkx: i32, chunky: i32, chunkz: i32) -> HashMap<(i32, i32, i32), Chunk> {
+        //Fluids depend on the block registry
+        init_test_registries();
         let mut chunks = HashMap::new();
         let mut id = 1;
         for x in chunkx..(chunkx + REGION_SIZE_I32) {
             for y in chunky..(chunky + REGION_SIZE_I32) {
                 for z in chunkz..(chunkz + REGION_SIZE_I32) {
                     let mut chunk = Chunk::new(0, 0, 0);
-                    chunk.set_block_relative(0, 0, 0, Block::new_id(id));
+                    //Fluids without a level are loaded as still fluids
+                    let mut block = Block::new_id(id);
+                    if block.is_fluid() {
+                        block = Block::new_fluid(id);
+                    }
+                    chunk.set_block_relative(0, 0, 0, block);
                     chunks.insert((x, y, z), chunk);
                     id += 1;
                 }
@@ -230,4 +261,102 @@ mod tests {
         let p = chunkpos_to_regionpos(4, 4, 4);
         assert_eq!(p, (1, 1, 1));
     }
+
+    #[test]
+    fn test_update_region_file() {
+        let world_path = std::env::temp_dir()
+            .join(format!("voxelworld_test_region_{}/", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let mut region = Region::new(0, 0, 0);
+        let mut chunks = init_chunks(0, 0, 0);
+        get_region_chunks(&mut region, &chunks);
+        region.save_region(&world_path, Compression::None).unwrap();
+        let len = std::fs::metadata(world_path.clone() + "chunkdata/region_0_0_0")
+            .unwrap()
+            .len();
+
+        //Only the updated chunk should be appended to the file
+        let mut updated = Region::new(0, 0, 0);
+        let chunk = chunks.get_mut(&(1, 2, 3)).unwrap();
+        chunk.set_block_relative(0, 0, 0, Block::new_id(300));
+        chunk.set_biomes((0..=255).collect());
+        updated.set_chunk(1, 2, 3, Some(chunk.clone()));
+        save::serialize_region(&world_path, &updated, Compression::None).unwrap();
+        let new_len = std::fs::metadata(world_path.clone() + "chunkdata/region_0_0_0")
+            .unwrap()
+            .len();
+        assert!(new_len > len && new_len < 2 * len);
+        //The full save is kept as the backup
+        let backup_len = std::fs::metadata(world_path.clone() + "chunkdata/region_0_0_0.bak")
+            .unwrap()
+            .len();
+        assert_eq!(backup_len, len);
+        assert!(
+            !std::path::Path::new(&(world_path.clone() + "chunkdata/region_0_0_0.tmp")).exists()
+        );
+
+        let loaded = Region::load_chunk(&world_path, 1, 2, 3).unwrap();
+        assert_eq!(loaded.get_block_relative(0, 0, 0), Block::new_id(300));
+        assert_eq!(loaded.get_biome(16, 16 * 3 + 15), Some(240));
+        assert_eq!(
+            Region::load_chunk(&world_path, 0, 0, 0)
+                .unwrap()
+                .get_biome(0, 0),
+            None
+        );
+        assert!(Region::load_chunk(&world_path, 4, 0, 0).is_none());
+        let loaded = Region::load_region(&world_path, 0, 0, 0).unwrap();
+        check_region_eq_chunks(loaded, chunks);
+        std::fs::remove_dir_all(&world_path).unwrap();
+    }
+
+    #[test]
+    fn test_region_versions() {
+        let world_path = std::env::temp_dir()
+            .join(format!("voxelworld_test_versions_{}/", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let mut chunk = Chunk::new(0, 0, 0);
+        chunk.set_block_relative(0, 0, 0, Block::new_id(1));
+        chunk.set_biomes(vec![3; 256]);
+        let tick = ScheduledTick { id: 1, due: 5 };
+        chunk.schedule_tick(1, 2, 3, tick);
+        let mut region = Region::new(0, 0, 0);
+        region.set_chunk(0, 0, 0, Some(chunk));
+        region.save_region(&world_path, Compression::None).unwrap();
+
+        let mut loaded = Region::load_chunk(&world_path, 0, 0, 0).unwrap();
+        assert_eq!(loaded.get_biome(0, 0), Some(3));
+        assert_eq!(loaded.get_scheduled_tick(1, 2, 3), Some(tick));
+        assert!(!loaded.take_missing_ticks());
+
+        //Sections added in later versions are not read from older files
+        let path = world_path.clone() + "chunkdata/region_0_0_0";
+        let set_version = |version: u16| {
+            let mut bytes = std::fs::read(&path).unwrap();
+            bytes[4..6].copy_from_slice(&version.to_be_bytes());
+            std::fs::write(&path, bytes).unwrap();
+        };
+        set_version(6);
+        let mut loaded = Region::load_chunk(&world_path, 0, 0, 0).unwrap();
+        assert_eq!(loaded.get_biome(0, 0), Some(3));
+        assert_eq!(loaded.get_scheduled_tick(1, 2, 3), None);
+        assert!(loaded.take_missing_ticks());
+        set_version(5);
+        let mut loaded = Region::load_chunk(&world_path, 0, 0, 0).unwrap();
+        assert_eq!(loaded.get_block_relative(0, 0, 0), Block::new_id(1));
+        assert_eq!(loaded.get_biome(0, 0), None);
+        assert!(loaded.take_missing_ticks());
+        std::fs::remove_dir_all(&world_path).unwrap();
+    }
+
+    #[test]
+    fn test_region_file_name() {
+        let name = save::region_file_name(-1, 2, -30);
+        assert_eq!(save::parse_region_file_name(&name), Some((-1, 2, -30)));
+        assert_eq!(save::parse_region_file_name("region_1_2"), None);
+        assert_eq!(save::parse_region_file_name("region_a_b_c"), None);
+        assert_eq!(save::parse_region_file_name("world.impfile"), None);
+    }
 }
@@ -1,16 +1,19 @@
-use super::{Region, REGION_SIZE_I32};
+use super::{
+    chunkpos_to_regionpos, regionpos_to_chunkpos,
+    save::region_file_name,
+    table::{decode_record, read_table, table_index, TableEntry, DATA_START, TABLE_ENTRY_SIZE},
+    Region, LEGACY_REGION_FORMAT, OFFSET_TABLE_VERSION, REGION_MAGIC, REGION_SIZE, REGION_SIZE_I32,
+    REGION_VERSION,
+};
 use crate::{
-    bin_data::{parse_binary_data, ByteStream},
-    game::save::CHUNK_PATH,
+    bin_data::{parse_binary_data, read_header, ByteStream, HEADER_SIZE},
+    game::save::{read_file_or_backup, BACKUP_EXT, CHUNK_PATH},
     voxel::{tile_data::TileData, Block, Chunk},
 };
-use std::{fs::File, io::Read};
-
-fn read_bytes(bytes: &mut [u8], file: &mut File) {
-    if let Err(msg) = file.read(bytes) {
-        eprintln!("Error when loading chunk: {msg}");
-    }
-}
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+};
 
 fn get_val<T: Copy>(data: &[T], index: usize) -> Option<T> {
     if index >= data.len() {
@@ -31,6 +34,52 @@ fn bytes_to_u16(bytes: &[u8]) -> Vec<u16> {
     vals
 }
 
+fn new_saved_block(id: u16, geometry: u8) -> Block {
+    let mut block = Block::new_id_orientation(id, geometry);
+    if block.is_fluid() && block.geometry == 0 {
+        block.geometry = 7;
+    }
+    block
+}
+
+//Reads len (count, block) pairs, the counts are stored first followed by
+//the block data, this is the layout used before version 5 where the id and
+//geometry of each block are packed into a u16
+fn rle_from_u16(data: &[u16], start: usize, len: u16) -> Vec<(u16, Block)> {
+    let mut blocks = vec![];
+    for i in 0..len {
+        let count = get_val(data, start + i as usize).unwrap_or(0);
+        let block_data = get_val(data, start + i as usize + len as usize).unwrap_or(0);
+        let id = block_data >> 8;
+        let geometry = (block_data & 0xff) as u8;
+        blocks.push((count, new_saved_block(id, geometry)));
+    }
+    blocks
+}
+
+//Reads len (count, block) pairs where the counts are followed by the ids and
+//then the geometry of each block, returns the pairs and the number of bytes
+//that were read
+fn rle_from_bytes(bytes: &[u8], len: u16) -> (Vec<(u16, Block)>, usize) {
+    let len = len as usize;
+    let counts = bytes_to_u16(get_slice(bytes, 0, len * size_of::<u16>()));
+    let ids = bytes_to_u16(get_slice(
+        bytes,
+        len * size_of::<u16>(),
+        len * size_of::<u16>(),
+    ));
+    let geometry = get_slice(bytes, 2 * len * size_of::<u16>(), len);
+    let blocks = (0..len)
+        .map(|i| {
+            let count = get_val(&counts, i).unwrap_or(0);
+            let id = get_val(&ids, i).unwrap_or(0);
+            let geometry = get_val(geometry, i).unwrap_or(0);
+            (count, new_saved_block(id, geometry))
+        })
+        .collect();
+    (blocks, 2 * len * size_of::<u16>() + len)
+}
+
 fn region_from_bytes(data: &[u16], x: i32, y: i32, z: i32) -> Region {
     let mut region = Region::new(x, y, z);
 
@@ -53,77 +102,269 @@ fn region_from_bytes(data: &[u16], x: i32, y: i32, z: i32) -> Region {
             continue;
         }
 
-        let mut blocks = vec![];
-        let start = index;
-        for i in 0..len {
-            let count = get_val(data, start + i as usize).unwrap_or(0);
-            let block_data = get_val(data, start + i as usize + len as usize).unwrap_or(0);
-            let id = (block_data >> 8) as u8;
-            let geometry = (block_data & 0xff) as u8;
-            let mut block = Block::new_id_orientation(id, geometry);
-            if block.is_fluid() && block.geometry == 0 {
-                block.geometry = 7;
-            }
-            blocks.push((count, block));
-            index += 2;
-        }
+        let blocks = rle_from_u16(data, index, len);
+        index += 2 * len as usize;
 
         let chunkx = x * REGION_SIZE_I32 + ix as i32;
         let chunky = y * REGION_SIZE_I32 + iy as i32;
         let chunkz = z * REGION_SIZE_I32 + iz as i32;
-        let chunk = Chunk::from_rle(chunkx, chunky, chunkz, &blocks);
+        let mut chunk = Chunk::from_rle(chunkx, chunky, chunkz, &blocks);
+        chunk.set_missing_ticks();
         region.set_chunk(chunkx, chunky, chunkz, Some(chunk));
     }
 
     region
 }
 
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    let mut buf = [0u8; size_of::<u32>()];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = get_val(bytes, index + i).unwrap_or(0);
+    }
+    u32::from_be_bytes(buf)
+}
+
+//Returns a slice of at most len bytes starting at index
+fn get_slice(bytes: &[u8], index: usize, len: usize) -> &[u8] {
+    let start = index.min(bytes.len());
+    let end = (index + len).min(bytes.len());
+    &bytes[start..end]
+}
+
 impl Region {
-    pub fn load_region(world_dir_path: &str, x: i32, y: i32, z: i32) -> Option<Self> {
-        let path = world_dir_path.to_string()
-            + CHUNK_PATH
-            + "region_"
-            + x.to_string().as_str()
-            + "_"
-            + y.to_string().as_str()
-            + "_"
-            + z.to_string().as_str();
-
-        match File::open(&path) {
-            Ok(mut file) => {
-                //Block data
-                let mut sz_bytes = [0u8; size_of::<u32>()];
-                read_bytes(&mut sz_bytes, &mut file);
-                let sz = u32::from_be_bytes(sz_bytes);
-                let mut block_bytes = vec![0u8; sz as usize];
-                read_bytes(&mut block_bytes, &mut file);
-                let chunk_data = bytes_to_u16(&block_bytes);
-                let mut region = region_from_bytes(&chunk_data, x, y, z);
-
-                let mut sz_bytes = [0u8; size_of::<u32>()];
-                read_bytes(&mut sz_bytes, &mut file);
-                let sz = u32::from_be_bytes(sz_bytes);
-                //No tile data to read, return
-                if sz == 0 {
-                    return Some(region);
-                }
+    //Parses the contents of a region file, this is done after the header
+    //has been removed from the bytes
+    fn from_bytes(bytes: &[u8], x: i32, y: i32, z: i32) -> Self {
+        //Block data
+        let mut index = 0;
+        let sz = read_u32(bytes, index) as usize;
+        index += size_of::<u32>();
+        let chunk_data = bytes_to_u16(get_slice(bytes, index, sz));
+        index += sz;
+        let mut region = region_from_bytes(&chunk_data, x, y, z);
+
+        let sz = read_u32(bytes, index) as usize;
+        index += size_of::<u32>();
+        //No tile data to read, return
+        if sz == 0 {
+            return region;
+        }
+
+        for ((x, y, z), tile_data) in tile_data_from_bytes(get_slice(bytes, index, sz)) {
+            region.set_tile_data(x, y, z, tile_data);
+        }
+
+        region
+    }
+
+    //Parses a region file saved before version 4, if verify is true then an
+    //error is returned if the checksum does not match
+    fn from_legacy_file_bytes(
+        bytes: &[u8],
+        x: i32,
+        y: i32,
+        z: i32,
+        verify: bool,
+    ) -> Result<Self, String> {
+        let (_version, data) = LEGACY_REGION_FORMAT.decode(bytes, verify)?;
+        //Files without a checksum can at least be checked for truncation
+        if verify && read_u32(&data, 0) as usize + size_of::<u32>() > data.len() {
+            return Err("file is truncated".to_string());
+        }
+        Ok(Self::from_bytes(&data, x, y, z))
+    }
+
+    //Parses a region file including the header, if verify is true then an
+    //error is returned if any checksum does not match, otherwise chunks
+    //that can not be read are skipped
+    fn from_file_bytes(bytes: &[u8], x: i32, y: i32, z: i32, verify: bool) -> Result<Self, String> {
+        let (version, _) = read_header(bytes, &REGION_MAGIC);
+        if version > REGION_VERSION {
+            return Err(format!("unsupported version {version}"));
+        } else if version < OFFSET_TABLE_VERSION {
+            return Self::from_legacy_file_bytes(bytes, x, y, z, verify);
+        }
 
-                let mut tile_data_bytes = vec![0u8; sz as usize];
-                read_bytes(&mut tile_data_bytes, &mut file);
-                let mut byte_stream = ByteStream::new(tile_data_bytes);
-                let parsed = parse_binary_data(&mut byte_stream);
-                if let Some(tile_data_list) = parsed.get("tile_data") {
-                    tile_data_list
-                        .iter()
-                        .filter_map(TileData::from_data_table)
-                        .for_each(|((x, y, z), tile_data)| {
-                            region.set_tile_data(x, y, z, tile_data);
-                        });
+        let mut region = Region::new(x, y, z);
+        let table = read_table(&bytes[HEADER_SIZE..])?;
+        let (startx, starty, startz) = regionpos_to_chunkpos(x, y, z);
+        for ix in 0..REGION_SIZE {
+            for iy in 0..REGION_SIZE {
+                for iz in 0..REGION_SIZE {
+                    let entry = table[table_index(ix, iy, iz)];
+                    if entry.is_empty() {
+                        continue;
+                    }
+                    let chunkx = startx + ix as i32;
+                    let chunky = starty + iy as i32;
+                    let chunkz = startz + iz as i32;
+                    let chunk = entry
+                        .get_record(bytes)
+                        .and_then(|record| decode_record(record, entry.checksum, verify))
+                        .map(|data| chunk_from_bytes(&data, version, chunkx, chunky, chunkz));
+                    match chunk {
+                        Ok(chunk) => region.set_relative_chunk(ix, iy, iz, Some(chunk)),
+                        Err(msg) if verify => return Err(msg),
+                        Err(msg) => eprintln!("E: skipped chunk {chunkx} {chunky} {chunkz}: {msg}"),
+                    }
                 }
+            }
+        }
+        Ok(region)
+    }
+
+    pub fn load_region(world_dir_path: &str, x: i32, y: i32, z: i32) -> Option<Self> {
+        let path = world_dir_path.to_string() + CHUNK_PATH + region_file_name(x, y, z).as_str();
+
+        match read_file_or_backup(&path, |bytes| Self::from_file_bytes(bytes, x, y, z, true))? {
+            Ok(region) => Some(region),
+            Err(_msg) => {
+                //Neither the file nor the backup are intact, load whatever
+                //can still be read so that the terrain does not get
+                //generated again over the blocks that were saved
+                eprintln!("E: {path} is corrupted, attempting to recover it");
+                let bytes = std::fs::read(&path).ok()?;
+                Self::from_file_bytes(&bytes, x, y, z, false).ok()
+            }
+        }
+    }
 
-                Some(region)
+    //Loads a single chunk from a region file without reading the rest of the
+    //region, if the chunk is corrupted then the backup is used instead.
+    //Returns None if the chunk has not been saved
+    pub fn load_chunk(world_dir_path: &str, x: i32, y: i32, z: i32) -> Option<Chunk> {
+        let (rx, ry, rz) = chunkpos_to_regionpos(x, y, z);
+        let path = world_dir_path.to_string() + CHUNK_PATH + region_file_name(rx, ry, rz).as_str();
+        let backup_path = format!("{path}{BACKUP_EXT}");
+        for file_path in [&path, &backup_path] {
+            match read_chunk_from_file(file_path, x, y, z) {
+                Ok(chunk) => return chunk,
+                Err(msg) => eprintln!("E: failed to load chunk from {file_path}: {msg}"),
             }
-            Err(_msg) => None,
         }
+        None
+    }
+
+    //Returns Ok(version) if the region file exists and is not corrupted
+    pub fn check_region_file(world_dir_path: &str, x: i32, y: i32, z: i32) -> Result<u16, String> {
+        let path = world_dir_path.to_string() + CHUNK_PATH + region_file_name(x, y, z).as_str();
+        let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+        Self::from_file_bytes(&bytes, x, y, z, true)?;
+        Ok(read_header(&bytes, &REGION_MAGIC).0)
+    }
+}
+
+fn tile_data_from_bytes(bytes: &[u8]) -> Vec<((i32, i32, i32), TileData)> {
+    let mut byte_stream = ByteStream::new(bytes.to_vec());
+    let parsed = parse_binary_data(&mut byte_stream);
+    match parsed.get("tile_data") {
+        Some(tile_data_list) => tile_data_list
+            .iter()
+            .filter_map(TileData::from_data_table)
+            .collect(),
+        None => vec![],
+    }
+}
+
+//Parses the data of a single chunk record: the number of runs, the run
+//lengths, the block ids, the block geometry, the size of the tile data, the
+//tile data, the size of the light data, the light data, the size of the
+//biome data and the biome data (version 6), the size of the scheduled ticks
+//and the scheduled ticks (version 7)
+fn chunk_from_bytes(bytes: &[u8], version: u16, x: i32, y: i32, z: i32) -> Chunk {
+    let len = get_val(&bytes_to_u16(get_slice(bytes, 0, size_of::<u16>())), 0).unwrap_or(0);
+    let mut index = size_of::<u16>();
+    let blocks = if version < 5 {
+        let block_data_sz = 2 * len as usize * size_of::<u16>();
+        let block_data = bytes_to_u16(get_slice(bytes, index, block_data_sz));
+        index += block_data_sz;
+        rle_from_u16(&block_data, 0, len)
+    } else {
+        let (blocks, sz) = rle_from_bytes(&bytes[index.min(bytes.len())..], len);
+        index += sz;
+        blocks
+    };
+    let mut chunk = Chunk::from_rle(x, y, z, &blocks);
+
+    let sz = read_u32(bytes, index) as usize;
+    index += size_of::<u32>();
+    for ((x, y, z), tile_data) in tile_data_from_bytes(get_slice(bytes, index, sz)) {
+        chunk.set_tile_data(x, y, z, Some(tile_data));
+    }
+    index += sz;
+
+    //Chunks saved before light was stored do not have any light data
+    let sz = read_u32(bytes, index) as usize;
+    index += size_of::<u32>();
+    chunk.light_from_bytes(get_slice(bytes, index, sz));
+    index += sz;
+
+    //Chunks saved before version 6 do not have any biome data
+    if version < 6 {
+        chunk.set_missing_ticks();
+        return chunk;
+    }
+    let sz = read_u32(bytes, index) as usize;
+    index += size_of::<u32>();
+    chunk.set_biomes(get_slice(bytes, index, sz).to_vec());
+    index += sz;
+
+    //Chunks saved before version 7 do not have any scheduled ticks so they
+    //need to be scanned for blocks to update
+    if version < 7 {
+        chunk.set_missing_ticks();
+        return chunk;
+    }
+    let sz = read_u32(bytes, index) as usize;
+    index += size_of::<u32>();
+    chunk.ticks_from_bytes(get_slice(bytes, index, sz));
+    chunk
+}
+
+//Returns Ok(None) if the file does not exist or the chunk is not saved in it
+fn read_chunk_from_file(path: &str, x: i32, y: i32, z: i32) -> Result<Option<Chunk>, String> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(msg) if msg.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(msg) => return Err(msg.to_string()),
+    };
+
+    let mut header = [0u8; HEADER_SIZE];
+    let (rx, ry, rz) = chunkpos_to_regionpos(x, y, z);
+    let (startx, starty, startz) = regionpos_to_chunkpos(rx, ry, rz);
+    let (ix, iy, iz) = (x - startx, y - starty, z - startz);
+    let (version, _) = match file.read_exact(&mut header) {
+        Ok(()) => read_header(&header, &REGION_MAGIC),
+        Err(_) => (0, 0),
+    };
+    if version < OFFSET_TABLE_VERSION {
+        //Older files have to be read in full
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let region = Region::from_file_bytes(&bytes, rx, ry, rz, true)?;
+        let chunk = region.get_relative_chunk(ix as usize, iy as usize, iz as usize);
+        return Ok(chunk.cloned());
+    } else if version > REGION_VERSION {
+        return Err(format!("unsupported version {version}"));
+    }
+
+    let index = table_index(ix as usize, iy as usize, iz as usize);
+    let mut entry_bytes = [0u8; TABLE_ENTRY_SIZE];
+    file.seek(SeekFrom::Start(
+        (HEADER_SIZE + index * TABLE_ENTRY_SIZE) as u64,
+    ))
+    .and_then(|_| file.read_exact(&mut entry_bytes))
+    .map_err(|e| e.to_string())?;
+    let entry = TableEntry::from_bytes(&entry_bytes);
+    if entry.is_empty() {
+        return Ok(None);
+    } else if (entry.offset as usize) < DATA_START {
+        return Err("chunk record is out of bounds".to_string());
     }
+
+    let mut record = vec![0u8; entry.len as usize];
+    file.seek(SeekFrom::Start(entry.offset as u64))
+        .and_then(|_| file.read_exact(&mut record))
+        .map_err(|e| e.to_string())?;
+    let data = decode_record(&record, entry.checksum, true)?;
+    Ok(Some(chunk_from_bytes(&data, version, x, y, z)))
 }
@@ -1,23 +1,108 @@
-use super::{Region, REGION_SIZE};
-use crate::{bin_data::get_table_list_bytes, game::save::CHUNK_PATH};
-use std::{fs::File, io::Write, path::Path};
+use super::{
+    table::{
+        encode_record, read_table, table_bytes, table_index, TableEntry, DATA_START,
+        TABLE_ENTRY_SIZE, TABLE_LEN,
+    },
+    Region, REGION_MAGIC, REGION_SIZE, REGION_VERSION,
+};
+use crate::{
+    bin_data::{get_table_list_bytes, header_bytes, read_header, Compression, HEADER_SIZE},
+    game::save::{replace_file, write_file_atomic, CHUNK_PATH, TMP_EXT},
+    voxel::Chunk,
+};
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
 
 pub fn region_file_name(x: i32, y: i32, z: i32) -> String {
     format!("region_{x}_{y}_{z}")
 }
 
+//Returns the region position from a file name created by region_file_name
+//Returns None if the name is not a region file name
+pub fn parse_region_file_name(name: &str) -> Option<(i32, i32, i32)> {
+    let coords: Vec<&str> = name.strip_prefix("region_")?.split('_').collect();
+    if coords.len() != 3 {
+        return None;
+    }
+    let x = coords[0].parse::<i32>().ok()?;
+    let y = coords[1].parse::<i32>().ok()?;
+    let z = coords[2].parse::<i32>().ok()?;
+    Some((x, y, z))
+}
+
+//Returns the data stored in a chunk record
+fn chunk_to_bytes(chunk: &Chunk) -> Vec<u8> {
+    let (len, counts, data) = chunk.get_chunk_bytes();
+    let tile_data_bytes = get_table_list_bytes("tile_data", &chunk.tiles_to_data_tables());
+    let mut bytes = vec![];
+    //Blocks
+    bytes.extend(len);
+    bytes.extend(counts);
+    bytes.extend(data);
+    //Tile data
+    bytes.extend((tile_data_bytes.len() as u32).to_be_bytes());
+    bytes.extend(tile_data_bytes);
+    //Light
+    let light_bytes = chunk.light_to_bytes();
+    bytes.extend((light_bytes.len() as u32).to_be_bytes());
+    bytes.extend(light_bytes);
+    //Biomes
+    let biomes = chunk.get_biomes();
+    bytes.extend((biomes.len() as u32).to_be_bytes());
+    bytes.extend(biomes);
+    //Scheduled ticks
+    let tick_bytes = chunk.ticks_to_bytes();
+    bytes.extend((tick_bytes.len() as u32).to_be_bytes());
+    bytes.extend(tick_bytes);
+    bytes
+}
+
+fn region_file_path(worldpath: &str, x: i32, y: i32, z: i32) -> String {
+    worldpath.to_string() + CHUNK_PATH + region_file_name(x, y, z).as_str()
+}
+
+//Returns (table index, record, checksum) for every chunk in the region
+fn get_records(region: &Region, compression: Compression) -> Vec<(usize, Vec<u8>, u32)> {
+    let mut records = vec![];
+    for ix in 0..REGION_SIZE {
+        for iy in 0..REGION_SIZE {
+            for iz in 0..REGION_SIZE {
+                if let Some(chunk) = region.get_relative_chunk(ix, iy, iz) {
+                    let (record, checksum) = encode_record(&chunk_to_bytes(chunk), compression);
+                    records.push((table_index(ix, iy, iz), record, checksum));
+                }
+            }
+        }
+    }
+    records
+}
+
 impl Region {
-    fn get_chunk_data(&self, ix: usize, iy: usize, iz: usize) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
-        if let Some(chunk) = self.get_relative_chunk(ix, iy, iz) {
-            chunk.get_chunk_bytes()
-        } else {
-            (0xffffu16.to_be_bytes().to_vec(), vec![], vec![])
+    //Returns the contents of the region file including the header
+    fn to_file_bytes(&self, compression: Compression) -> Vec<u8> {
+        let mut table = vec![TableEntry::default(); TABLE_LEN];
+        let mut data = vec![];
+        for (index, record, checksum) in get_records(self, compression) {
+            table[index] = TableEntry {
+                offset: (DATA_START + data.len()) as u32,
+                len: record.len() as u32,
+                checksum,
+            };
+            data.extend(record);
         }
+
+        let mut bytes = header_bytes(&REGION_MAGIC, REGION_VERSION);
+        bytes.extend(table_bytes(&table));
+        bytes.extend(data);
+        bytes
     }
 
-    pub fn save_region(&self, worldpath: &str) -> Result<(), String> {
+    pub fn save_region(&self, worldpath: &str, compression: Compression) -> Result<(), String> {
         let chunk_dir_path = worldpath.to_string() + CHUNK_PATH;
-        let chunk_path = chunk_dir_path.clone() + region_file_name(self.x, self.y, self.z).as_str();
+        let chunk_path = region_file_path(worldpath, self.x, self.y, self.z);
         if !Path::new(&chunk_dir_path).exists() {
             if let Err(msg) = std::fs::create_dir_all(&chunk_dir_path) {
                 eprintln!("E: Failed to create chunk dir");
@@ -26,70 +111,97 @@ impl Region {
             }
         }
 
-        let mut data_to_write = vec![];
-        for ix in 0..REGION_SIZE {
-            for iy in 0..REGION_SIZE {
-                for iz in 0..REGION_SIZE {
-                    let (len, counts, data) = self.get_chunk_data(ix, iy, iz);
-                    data_to_write.extend(len);
-                    data_to_write.extend((ix as u16).to_be_bytes());
-                    data_to_write.extend((iy as u16).to_be_bytes());
-                    data_to_write.extend((iz as u16).to_be_bytes());
-                    data_to_write.extend(counts);
-                    data_to_write.extend(data);
-                }
-            }
+        let bytes = self.to_file_bytes(compression);
+        if let Err(msg) = write_file_atomic(&chunk_path, &bytes) {
+            eprintln!("Failed to save region {}, {}, {}", self.x, self.y, self.z);
+            eprintln!("E: {msg}");
+            return Err(chunk_path);
         }
 
-        let mut tile_data = vec![];
-        for chunk in self.chunks.iter().flatten() {
-            tile_data.extend(chunk.tiles_to_data_tables());
+        Ok(())
+    }
+
+    //Writes the chunks in the region to an existing region file without
+    //rewriting the other chunks in the file. The file is copied and updated
+    //and then the copy replaces the original like in write_file_atomic so
+    //that the backup is always the previous version of the file.
+    //Returns Ok(false) if the region file needs to be saved in full instead
+    fn update_region_file(&self, path: &str, compression: Compression) -> Result<bool, String> {
+        let tmp_path = format!("{path}{TMP_EXT}");
+        match std::fs::copy(path, &tmp_path) {
+            Ok(_) => {}
+            Err(msg) if msg.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(msg) => return Err(msg.to_string()),
         }
-        let tile_data_bytes = get_table_list_bytes("tile_data", &tile_data);
-
-        match File::create(&chunk_path) {
-            Ok(mut file) => {
-                //Write blocks
-                let data_sz = data_to_write.len() as u32;
-                let data_sz_bytes = data_sz.to_be_bytes();
-                if let Err(msg) = file.write_all(&data_sz_bytes) {
-                    eprintln!("Error when saving {}, {}, {}", self.x, self.y, self.z);
-                    eprintln!("E: {msg}");
-                    return Err(chunk_path);
-                }
-                if let Err(msg) = file.write_all(&data_to_write) {
-                    eprintln!("Error when saving {}, {}, {}", self.x, self.y, self.z);
-                    eprintln!("E: {msg}");
-                    return Err(chunk_path);
-                }
 
-                //No tile data to write, early return
-                if tile_data.is_empty() {
-                    return Ok(());
-                }
+        let res = self.append_chunks(&tmp_path, compression);
+        if let Ok(true) = res {
+            replace_file(&tmp_path, path)?;
+        } else if let Err(msg) = std::fs::remove_file(&tmp_path) {
+            eprintln!("Failed to remove {tmp_path}");
+            eprintln!("{msg}");
+        }
+        res
+    }
 
-                //Write tile data
-                let data_sz = tile_data_bytes.len() as u32;
-                let data_sz_bytes = data_sz.to_be_bytes();
-                if let Err(msg) = file.write_all(&data_sz_bytes) {
-                    eprintln!("Error when saving {}, {}, {}", self.x, self.y, self.z);
-                    eprintln!("E: {msg}");
-                    return Err(chunk_path);
-                }
-                if let Err(msg) = file.write_all(&tile_data_bytes) {
-                    eprintln!("Error when saving {}, {}, {}", self.x, self.y, self.z);
-                    eprintln!("E: {msg}");
-                    return Err(chunk_path);
-                }
-            }
-            Err(msg) => {
-                eprintln!("Failed to save chunk {}, {}, {}", self.x, self.y, self.z);
-                eprintln!("{msg}");
-                return Err(chunk_path);
-            }
+    //Appends the chunks in the region to the end of the file and updates
+    //their entries in the offset table.
+    //Returns Ok(false) if the region file needs to be saved in full instead
+    fn append_chunks(&self, path: &str, compression: Compression) -> Result<bool, String> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+
+        let mut bytes = vec![0u8; DATA_START];
+        if file.read_exact(&mut bytes).is_err() {
+            return Ok(false);
+        }
+        if read_header(&bytes, &REGION_MAGIC).0 != REGION_VERSION {
+            return Ok(false);
         }
+        let mut table = read_table(&bytes[HEADER_SIZE..])?;
 
-        Ok(())
+        let records = get_records(self, compression);
+        //If most of the region changed, it is simpler to rewrite everything
+        if records.len() > TABLE_LEN / 2 {
+            return Ok(false);
+        }
+
+        let mut end = file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        let mut data = vec![];
+        let mut updated = vec![];
+        for (index, record, checksum) in records {
+            table[index] = TableEntry {
+                offset: (end + data.len() as u64) as u32,
+                len: record.len() as u32,
+                checksum,
+            };
+            updated.push(index);
+            data.extend(record);
+        }
+        end += data.len() as u64;
+
+        //Unused records are only removed when the file is saved in full,
+        //rewrite the file once more than half of it is unused
+        let used: u64 = table.iter().map(|entry| entry.len as u64).sum();
+        if end > u32::MAX as u64 || end - (DATA_START as u64) > 2 * used {
+            return Ok(false);
+        }
+
+        file.write_all(&data)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| e.to_string())?;
+        for index in updated {
+            let offset = HEADER_SIZE + index * TABLE_ENTRY_SIZE;
+            file.seek(SeekFrom::Start(offset as u64))
+                .and_then(|_| file.write_all(&table[index].to_bytes()))
+                .map_err(|e| e.to_string())?;
+        }
+        file.sync_data().map_err(|e| e.to_string())?;
+
+        Ok(true)
     }
 }
 
@@ -109,7 +221,19 @@ fn merge_regions(updated: &mut Region, original: &Region) {
     }
 }
 
-pub fn serialize_region(world_dir_path: &str, region: &Region) -> Result<(), String> {
+pub fn serialize_region(
+    world_dir_path: &str,
+    region: &Region,
+    compression: Compression,
+) -> Result<(), String> {
+    //Only write the chunks in the region if possible
+    let path = region_file_path(world_dir_path, region.x, region.y, region.z);
+    match region.update_region_file(&path, compression) {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(msg) => eprintln!("E: failed to update {path}: {msg}"),
+    }
+
     let mut region_clone = region.clone();
     let x = region.x;
     let y = region.y;
@@ -119,5 +243,5 @@ pub fn serialize_region(world_dir_path: &str, region: &Region) -> Result<(), Str
         merge_regions(&mut region_clone, &original);
     }
 
-    region_clone.save_region(world_dir_path)
+    region_clone.save_region(world_dir_path, compression)
 }
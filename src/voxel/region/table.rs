@@ -0,0 +1,111 @@
+/*
+ * Starting from version 4, region files store every chunk separately so that
+ * a single chunk can be read or rewritten without touching the rest of the
+ * region. The layout of the file is:
+ * header, offset table, chunk records
+ * The offset table has an entry for every chunk in the region, each entry is
+ * the offset, length and crc32 checksum of the record. An offset of 0 means
+ * that the chunk is not stored in the file. A record is the compression type
+ * followed by the (compressed) chunk data. Rewritten chunks are appended to
+ * the end of the file and the old record is left unused until the region is
+ * saved in full again.
+ * */
+
+use super::REGION_SIZE;
+use crate::bin_data::{compress, crc32, decompress, Compression, HEADER_SIZE};
+
+pub const TABLE_LEN: usize = REGION_SIZE * REGION_SIZE * REGION_SIZE;
+pub const TABLE_ENTRY_SIZE: usize = 3 * size_of::<u32>();
+pub const TABLE_SIZE: usize = TABLE_LEN * TABLE_ENTRY_SIZE;
+//Offset of the first chunk record
+pub const DATA_START: usize = HEADER_SIZE + TABLE_SIZE;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct TableEntry {
+    pub offset: u32,
+    pub len: u32,
+    pub checksum: u32,
+}
+
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[index],
+        bytes[index + 1],
+        bytes[index + 2],
+        bytes[index + 3],
+    ])
+}
+
+impl TableEntry {
+    pub fn is_empty(&self) -> bool {
+        self.offset == 0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            offset: read_u32(bytes, 0),
+            len: read_u32(bytes, 4),
+            checksum: read_u32(bytes, 8),
+        }
+    }
+
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(TABLE_ENTRY_SIZE);
+        bytes.extend(self.offset.to_be_bytes());
+        bytes.extend(self.len.to_be_bytes());
+        bytes.extend(self.checksum.to_be_bytes());
+        bytes
+    }
+
+    //Returns the bytes of the record in the file
+    pub fn get_record<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], String> {
+        let start = self.offset as usize;
+        let end = start + self.len as usize;
+        if start < DATA_START || end > bytes.len() {
+            return Err("chunk record is out of bounds".to_string());
+        }
+        Ok(&bytes[start..end])
+    }
+}
+
+//Table index of a chunk with relative position (x, y, z) in the region
+pub fn table_index(x: usize, y: usize, z: usize) -> usize {
+    x * REGION_SIZE * REGION_SIZE + y * REGION_SIZE + z
+}
+
+//Parses the offset table, bytes should start right after the header
+pub fn read_table(bytes: &[u8]) -> Result<Vec<TableEntry>, String> {
+    if bytes.len() < TABLE_SIZE {
+        return Err("offset table is truncated".to_string());
+    }
+
+    let table = bytes[..TABLE_SIZE]
+        .chunks_exact(TABLE_ENTRY_SIZE)
+        .map(TableEntry::from_bytes)
+        .collect();
+    Ok(table)
+}
+
+pub fn table_bytes(table: &[TableEntry]) -> Vec<u8> {
+    table.iter().flat_map(|entry| entry.to_bytes()).collect()
+}
+
+//Returns (record, checksum)
+pub fn encode_record(data: &[u8], compression: Compression) -> (Vec<u8>, u32) {
+    let (compression, stored) = compress(data, compression);
+    let mut record = Vec::with_capacity(stored.len() + 1);
+    record.push(compression.to_u8());
+    record.extend(stored);
+    let checksum = crc32(&record);
+    (record, checksum)
+}
+
+//Returns the chunk data in a record, if verify is false then the checksum is
+//ignored
+pub fn decode_record(record: &[u8], checksum: u32, verify: bool) -> Result<Vec<u8>, String> {
+    if verify && crc32(record) != checksum {
+        return Err("checksum does not match, chunk is corrupted".to_string());
+    }
+    let compression_type = *record.first().ok_or("missing compression type")?;
+    decompress(&record[1..], Compression::from_u8(compression_type)?)
+}
@@ -16,6 +16,7 @@ pub struct TileData {
 }
 
 impl TileData {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             inventory: Inventory::new(),
@@ -1,25 +1,36 @@
 pub mod block_update;
+pub mod chunk_generator;
 mod default_world;
 mod flat_world;
 mod gen_more;
+mod heightmap_world;
 pub mod light;
 mod old_world;
 mod save;
 mod skyblock_world;
-use crate::{game::GameMode, gfx::display::get_sky_brightness};
+pub mod terrain_settings;
+pub mod trees;
+use crate::{
+    bin_data::Compression,
+    game::{save_thread::SaveThread, GameMode},
+    gfx::display::get_sky_brightness,
+};
 
 use super::{
     light::{Light, SkyLightMap, LU},
     region::{chunkpos_to_regionpos, get_region_chunks, get_region_chunks_remove, Region},
     tile_data::TileData,
-    world_to_chunk_position, wrap_coord, Block, Chunk, CHUNK_SIZE_I32, FULL_BLOCK,
+    world_to_chunk_position, Block, Chunk, FULL_BLOCK,
 };
+use block_update::{NeighborChanges, TickQueue};
+use chunk_generator::{create_chunk_generator, ChunkGenerator};
+pub use default_world::{check_ore_gen, ORE_GEN_PATH};
 use gen_more::LoadChunkQueue;
 use noise::{Fbm, NoiseFn, Perlin, Simplex};
+use old_world::OldGenerator;
 use std::collections::{HashMap, HashSet};
+use terrain_settings::TerrainSettings;
 
-pub const OCTAVES: usize = 5;
-pub const PERSISTENCE: f64 = 0.5;
 const DEFAULT_TIME: f32 = 0.04;
 const MINUTES_PER_DAY: f32 = 20.0;
 const DAY_NIGHT_SPEED: f32 = 1.0 / (MINUTES_PER_DAY * 60.0);
@@ -34,31 +45,37 @@ pub struct WorldGenerator {
     pub elevation: Fbm<Perlin>,
     pub temperature: Perlin,
     pub mountain: Fbm<Simplex>,
+    pub river: Perlin,
+    pub lake: Perlin,
+    pub settings: TerrainSettings,
     world_seed: u32,
 }
 
 impl WorldGenerator {
-    fn new(seed: u32) -> Self {
-        let mut terrain_noise = Fbm::new(seed);
-        terrain_noise.octaves = OCTAVES;
-        terrain_noise.persistence = PERSISTENCE;
+    fn new(seed: u32, settings: TerrainSettings) -> Self {
+        let mut terrain_noise = Fbm::new(seed.wrapping_add(settings.terrain_seed));
+        terrain_noise.octaves = settings.terrain_octaves;
+        terrain_noise.persistence = settings.terrain_persistence;
 
-        let mut elevation_noise = Fbm::new(seed + 5);
-        elevation_noise.octaves = 3;
-        elevation_noise.persistence = 0.25;
+        let mut elevation_noise = Fbm::new(seed.wrapping_add(settings.elevation_seed));
+        elevation_noise.octaves = settings.elevation_octaves;
+        elevation_noise.persistence = settings.elevation_persistence;
 
-        let mut mountain_noise = Fbm::new(seed + 6);
-        mountain_noise.octaves = 4;
-        mountain_noise.persistence = 0.6;
+        let mut mountain_noise = Fbm::new(seed.wrapping_add(settings.mountain_seed));
+        mountain_noise.octaves = settings.mountain_octaves;
+        mountain_noise.persistence = settings.mountain_persistence;
 
         Self {
             terrain_generator: terrain_noise,
-            noise_cave_generator: Perlin::new(seed + 1),
-            tree_generator: Perlin::new(seed + 2),
-            steepness: Perlin::new(seed + 3),
+            noise_cave_generator: Perlin::new(seed.wrapping_add(settings.cave_seed)),
+            tree_generator: Perlin::new(seed.wrapping_add(settings.tree_seed)),
+            steepness: Perlin::new(seed.wrapping_add(settings.steepness_seed)),
             elevation: elevation_noise,
-            temperature: Perlin::new(seed + 4),
+            temperature: Perlin::new(seed.wrapping_add(settings.temperature_seed)),
             mountain: mountain_noise,
+            river: Perlin::new(seed.wrapping_add(settings.river_seed)),
+            lake: Perlin::new(seed.wrapping_add(settings.lake_seed)),
+            settings,
             world_seed: seed,
         }
     }
@@ -91,14 +108,17 @@ impl WorldGenerator {
         let clamped = ((normalized - 0.3) / 0.7).clamp(0.0, 1.0);
         clamped * 2.0 - 1.0
     }
-}
 
-#[derive(Copy, Clone, PartialEq, Eq)]
-pub enum WorldGenType {
-    OldGen,
-    Flat,
-    DefaultGen,
-    Skyblock,
+    //Rivers follow the values that are close to 0
+    pub fn get_river(&self, x: i32, z: i32) -> f64 {
+        let point = [x as f64 / 320.0, z as f64 / 320.0];
+        self.river.get(point)
+    }
+
+    pub fn get_lake(&self, x: i32, z: i32) -> f64 {
+        let point = [x as f64 / 80.0, z as f64 / 80.0];
+        self.lake.get(point)
+    }
 }
 
 //World struct
@@ -117,23 +137,32 @@ pub struct World {
     pub chunk_cache: HashMap<(i32, i32, i32), Chunk>,
     world_generator: WorldGenerator,
     world_seed: u32,
-    pub gen_type: WorldGenType,
+    //Name of the chunk generator
+    pub gen_type: String,
+    pub gen_options: String,
+    generator: Box<dyn ChunkGenerator>,
     //World path
     pub path: String,
     //Block update timer
     block_update_timer: f32,
     random_update_timer: f32,
-    //Updating chunks
+    //Chunks that have scheduled ticks that need to be added to tick_queue
     updating: HashSet<(i32, i32, i32)>,
     in_update_range: HashSet<(i32, i32, i32)>,
+    //Scheduled block ticks ordered by (due tick, position), the ticks are
+    //stored in the chunks so entries can be out of date and are checked
+    //against the chunk when they are removed from the queue
+    tick_queue: TickQueue,
+    //Blocks that were changed by neighbour updates since the last frame
+    neighbor_changes: NeighborChanges,
     ticks: u64,
     //Day/night cycle
     pub time: f32, //A number between 0.0 and 1.0
     pub days_passed: u64,
     //Chunks that experienced block update and need to be saved
     to_save: HashSet<(i32, i32, i32)>,
-    //Chunks that are to be removed from cache and need to be saved
-    removed_from_cache: Vec<Region>,
+    //Writes regions and entity regions to the disk
+    pub saver: SaveThread,
     //List of chunk coordinates that are too be loaded
     to_load: LoadChunkQueue,
     //This is a list of chunks that should be updated in the chunk vao table,
@@ -146,11 +175,14 @@ pub struct World {
     chunktable_update_list: HashSet<(i32, i32, i32)>,
     //World game mode
     pub game_mode: GameMode,
+    //Compression used when saving region files
+    pub compression: Compression,
 }
 
 impl World {
     //Create an empty world
     pub fn empty() -> Self {
+        let generator: Box<dyn ChunkGenerator> = Box::new(OldGenerator);
         Self {
             chunks: HashMap::new(),
             skylightmap: HashMap::new(),
@@ -159,27 +191,39 @@ impl World {
             centery: 0,
             centerz: 0,
             chunk_cache: HashMap::new(),
-            world_generator: WorldGenerator::new(0),
-            gen_type: WorldGenType::OldGen,
+            world_generator: WorldGenerator::new(0, generator.terrain_settings()),
+            gen_type: "old".to_string(),
+            gen_options: String::new(),
+            generator,
             world_seed: 0,
             path: String::new(),
             block_update_timer: 0.0,
             random_update_timer: 0.0,
             updating: HashSet::new(),
             in_update_range: HashSet::new(),
+            tick_queue: TickQueue::new(),
+            neighbor_changes: NeighborChanges::default(),
             ticks: 0,
             time: DEFAULT_TIME,
             days_passed: 0,
             to_save: HashSet::new(),
-            removed_from_cache: vec![],
+            saver: SaveThread::new(),
             to_load: LoadChunkQueue::new(),
             chunktable_update_list: HashSet::new(),
             game_mode: GameMode::Creative, //Default to creative mode
+            compression: Compression::default(),
         }
     }
 
     //Create a new chunk from a chunk render distance (range)
-    pub fn new(seed: u32, chunk_range: i32, generation: WorldGenType, mode: GameMode) -> Self {
+    //Returns an error if the generator options are invalid
+    pub fn new(
+        seed: u32,
+        chunk_range: i32,
+        generation: &str,
+        options: &str,
+        mode: GameMode,
+    ) -> Result<Self, String> {
         //Create chunk list
         let mut chunklist = HashMap::new();
         for y in -chunk_range..=chunk_range {
@@ -190,7 +234,8 @@ impl World {
             }
         }
 
-        Self {
+        let (gen_type, gen_options, generator) = create_chunk_generator(generation, options)?;
+        Ok(Self {
             chunks: chunklist,
             skylightmap: HashMap::new(),
             range: chunk_range,
@@ -198,23 +243,28 @@ impl World {
             centery: 0,
             centerz: 0,
             chunk_cache: HashMap::new(),
-            world_generator: WorldGenerator::new(seed),
-            gen_type: generation,
+            world_generator: WorldGenerator::new(seed, generator.terrain_settings()),
+            gen_type,
+            gen_options,
+            generator,
             world_seed: seed,
             path: String::new(),
             block_update_timer: 0.0,
             random_update_timer: 0.0,
             updating: HashSet::new(),
             in_update_range: HashSet::new(),
+            tick_queue: TickQueue::new(),
+            neighbor_changes: NeighborChanges::default(),
             ticks: 0,
             time: DEFAULT_TIME,
             days_passed: 0,
             to_save: HashSet::new(),
-            removed_from_cache: vec![],
+            saver: SaveThread::new(),
             to_load: LoadChunkQueue::new(),
             chunktable_update_list: HashSet::new(),
             game_mode: mode,
-        }
+            compression: Compression::default(),
+        })
     }
 
     pub fn get_range(&self) -> i32 {
@@ -232,40 +282,18 @@ impl World {
         self.chunks.get_mut(&(ix, iy, iz))
     }
 
-    //Sets a block based on position,
+    //Sets a block based on position, schedules ticks for the block and the
+    //blocks around it and notifies the blocks next to it about the change,
     //does nothing if the position is out of range for the world
     pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: Block) {
         let (chunkx, chunky, chunkz) = world_to_chunk_position(x, y, z);
-
-        let ix = wrap_coord(x);
-        let iy = wrap_coord(y);
-        let iz = wrap_coord(z);
-        for dx in -1i32..=1 {
-            for dy in -1i32..=1 {
-                for dz in -1i32..=1 {
-                    if (dx == -1 && ix != 0) || (dx == 1 && ix != CHUNK_SIZE_I32 - 1) {
-                        continue;
-                    }
-
-                    if (dy == -1 && iy != 0) || (dy == 1 && iy != CHUNK_SIZE_I32 - 1) {
-                        continue;
-                    }
-
-                    if (dz == -1 && iz != 0) || (dz == 1 && iz != CHUNK_SIZE_I32 - 1) {
-                        continue;
-                    }
-
-                    self.updating
-                        .insert((chunkx + dx, chunky + dy, chunkz + dz));
-                }
-            }
-        }
-
         self.to_save.insert((chunkx, chunky, chunkz));
         let chunk = self.get_mut_chunk(chunkx, chunky, chunkz);
         if let Some(chunk) = chunk {
             chunk.set_block(x, y, z, block);
         }
+        self.schedule_ticks_around(x, y, z);
+        self.notify_neighbors(x, y, z);
     }
 
     //Returns a block based on position
@@ -279,6 +307,13 @@ impl World {
         Block::new()
     }
 
+    //Returns the biome id of the column at (x, z), None if the chunk at the
+    //position is not loaded or was generated without biomes
+    pub fn get_biome(&self, x: i32, y: i32, z: i32) -> Option<u8> {
+        let (chunkx, chunky, chunkz) = world_to_chunk_position(x, y, z);
+        self.get_chunk(chunkx, chunky, chunkz)?.get_biome(x, z)
+    }
+
     //Returns the light value for a position
     //Returns black if the position is out of range in the world
     pub fn get_light(&self, x: i32, y: i32, z: i32) -> Light {
@@ -316,11 +351,6 @@ impl World {
 
     //If the cache gets too large, attempt to delete some sections
     pub fn clean_cache(&mut self) {
-        if !self.removed_from_cache.is_empty() {
-            eprintln!("Cleaning cache...");
-            eprintln!("Regions left to save: {}", self.removed_from_cache.len());
-        }
-
         //Keep track of the loaded regions
         let mut active_regions = HashSet::new();
         for (x, y, z) in self.chunks.keys() {
@@ -328,19 +358,6 @@ impl World {
             active_regions.insert(regionpos);
         }
 
-        //Remove regions and save them to the harddrive
-        let mut time_passed = 0.0;
-        let start = std::time::Instant::now();
-        while !self.removed_from_cache.is_empty() && time_passed < 0.0005 {
-            let region_to_save = self.removed_from_cache.last();
-            if let Some(region_to_save) = region_to_save {
-                save::save_region(region_to_save, &self.path);
-                self.removed_from_cache.pop();
-            }
-            let now = std::time::Instant::now();
-            time_passed = (now - start).as_secs_f32();
-        }
-
         //If we have not hit our maximum cache size, then don't bother attempting
         //to remove anything
         if self.chunk_cache.len() <= self.get_max_cache_sz() {
@@ -366,8 +383,7 @@ impl World {
         }
 
         eprintln!("Starting cache size: {}", self.chunk_cache.len());
-        //Generate region data and save it into the removed_from_cache list to
-        //be saved to the disk later
+        //Generate region data and send it to the save thread
         for (rx, ry, rz) in removed_regions {
             let mut region = Region::new(rx, ry, rz);
             //This should not run
@@ -375,7 +391,7 @@ impl World {
             //This will take chunks from the cache and add it to the region
             //while also removing those chunks from the cache.
             get_region_chunks_remove(&mut region, &mut self.chunk_cache);
-            self.removed_from_cache.push(region);
+            self.saver.save_region(&self.path, region, self.compression);
         }
         eprintln!("Final cache size: {}", self.chunk_cache.len());
     }
@@ -388,12 +404,8 @@ impl World {
 
     //Generate world
     pub fn generate_world(&mut self) {
-        match self.gen_type {
-            WorldGenType::OldGen => self.gen_old(),
-            WorldGenType::Flat => self.gen_flat(),
-            WorldGenType::DefaultGen => self.gen_default(),
-            WorldGenType::Skyblock => self.gen_skyblock(),
-        }
+        let positions = self.chunks.drain().map(|(pos, _)| pos).collect();
+        self.generate_chunks(&positions);
     }
 
     //Returns seed of world
@@ -414,9 +426,9 @@ impl World {
         ]
     }
 
-    //Returns how many chunks are updating
-    pub fn get_chunk_updates(&self) -> usize {
-        self.updating.len()
+    //Returns how many block ticks are waiting in the queue
+    pub fn get_scheduled_ticks(&self) -> usize {
+        self.tick_queue.len()
     }
 
     //Updates day night cycle
@@ -448,6 +460,7 @@ impl World {
     pub fn set_tile_data(&mut self, x: i32, y: i32, z: i32, tile_data: Option<TileData>) {
         let (chunkx, chunky, chunkz) = world_to_chunk_position(x, y, z);
         if let Some(chunk) = self.chunks.get_mut(&(chunkx, chunky, chunkz)) {
+            self.to_save.insert((chunkx, chunky, chunkz));
             chunk.set_tile_data(x, y, z, tile_data);
         }
     }
@@ -483,10 +496,7 @@ impl World {
     //Returns the coordinates for the bottom of the world
     //beyond this point you can not place any blocks
     pub fn bottom(&self) -> i32 {
-        match self.gen_type {
-            WorldGenType::Flat | WorldGenType::OldGen | WorldGenType::DefaultGen => -128,
-            WorldGenType::Skyblock => -32,
-        }
+        self.generator.bottom()
     }
 }
 
@@ -1,3 +1,6 @@
+mod neighbor_update;
+mod piston;
+mod power;
 pub mod rand_block_update;
 mod simulations;
 pub mod update_tile_data;
@@ -6,17 +9,27 @@ use super::World;
 use crate::{
     gfx::ChunkTables,
     voxel::{
-        is_valid::get_check_valid_fn, world_to_chunk_position, wrap_coord, Block, CHUNK_SIZE_I32,
+        world_to_chunk_position, wrap_coord, Block, Chunk, ScheduledTick, CHUNK_SIZE_I32,
         EMPTY_BLOCK,
     },
 };
+pub use neighbor_update::NeighborChanges;
+use piston::PISTON_DELAY;
+use power::{release_button, update_logic_gate, BUTTON_DELAY, GATE_DELAY};
 pub use simulations::run_test_simulations;
-use std::collections::{HashMap, HashSet};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
 
 pub const BLOCK_UPDATE_INTERVAL: f32 = 0.2;
+//Lava is only updated every LAVA_TICK_DELAY ticks
+const LAVA_TICK_DELAY: u64 = 5;
 const ADJ: [(i32, i32, i32); 4] = [(1, 0, 0), (0, 0, 1), (-1, 0, 0), (0, 0, -1)];
 
 type UpdateList = HashMap<(i32, i32, i32), Block>;
+//Min heap of (due tick, position)
+pub type TickQueue = BinaryHeap<Reverse<(u64, (i32, i32, i32))>>;
 
 pub fn get_chunktable_updates(x: i32, y: i32, z: i32, update_mesh: &mut HashSet<(i32, i32, i32)>) {
     let (chunkx, chunky, chunkz) = world_to_chunk_position(x, y, z);
@@ -44,7 +57,7 @@ pub fn get_chunktable_updates(x: i32, y: i32, z: i32, update_mesh: &mut HashSet<
     }
 }
 
-fn add_water_tile(x: i32, y: i32, z: i32, level: u8, id: u8, to_update: &mut UpdateList) {
+fn add_water_tile(x: i32, y: i32, z: i32, level: u8, id: u16, to_update: &mut UpdateList) {
     let mut water = Block::new_fluid(id);
     water.geometry = level;
 
@@ -220,131 +233,225 @@ fn update_lava(world: &World, x: i32, y: i32, z: i32, to_update: &mut UpdateList
     if updated {
         return;
     }
-    let mut update_list = UpdateList::new();
-    update_fluid(world, x, y, z, &mut update_list, 2);
-    if world.ticks.is_multiple_of(5) {
-        for (pos, block) in update_list {
-            let (px, py, pz) = pos;
-            add_water_tile(px, py, pz, block.geometry, block.id, to_update);
-        }
-    } else if !update_list.is_empty() {
-        let mut updated = false;
-        for ((x, y, z), block) in update_list {
-            if world.get_block(x, y, z) != block {
-                updated = true;
-            }
-        }
-
-        if !updated {
-            return;
-        }
-
-        let mut block2 = world.get_block(x, y, z);
-        block2.geometry |= 1 << 7;
-        to_update.insert((x, y, z), block2);
-    }
+    update_fluid(world, x, y, z, to_update, 2);
 }
 
-fn update_farmland(world: &World, x: i32, y: i32, z: i32, to_update: &mut UpdateList) {
-    let above = world.get_block(x, y + 1, z);
-    if above.transparent() || above.id == EMPTY_BLOCK {
+//Meant to be called when player breaks ice
+//Checks if the block below is non-empty, if that's the case, then spawn water
+//where the ice was broken
+pub fn break_ice(world: &mut World, x: i32, y: i32, z: i32) {
+    let below = world.get_block(x, y - 1, z);
+    //Ignore if below is an empty block
+    if below.id == EMPTY_BLOCK {
         return;
     }
-    to_update.insert((x, y, z), Block::new_id(4));
+    let water = Block::new_fluid(12);
+    world.set_block(x, y, z, water);
 }
 
-fn update_plant(world: &World, x: i32, y: i32, z: i32, id: u8, to_update: &mut UpdateList) {
-    if let Some(check_valid) = get_check_valid_fn(id) {
-        if check_valid(world, x, y, z) {
-            return;
+//How a block is updated when one of its scheduled ticks is due
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TickKind {
+    Water,
+    Lava,
+    Button,
+    LogicGate,
+    Piston,
+}
+
+impl TickKind {
+    //Returns how many ticks after being scheduled the block should be updated
+    fn delay(self) -> u64 {
+        match self {
+            Self::Water => 1,
+            //Lava flows slower than water
+            Self::Lava => LAVA_TICK_DELAY,
+            Self::Button => BUTTON_DELAY,
+            Self::LogicGate => GATE_DELAY,
+            Self::Piston => PISTON_DELAY,
         }
-        to_update.insert((x, y, z), Block::new_id(EMPTY_BLOCK));
     }
 }
 
-//Connect fences
-fn update_fence(world: &World, x: i32, y: i32, z: i32, to_update: &mut UpdateList) {
-    let mut block = world.get_block(x, y, z);
-
-    let geometry = block.geometry;
-    block.geometry = 0;
-    ADJ.iter()
-        .map(|(dx, dy, dz)| (x + dx, y + dy, z + dz))
-        .map(|(x, y, z)| world.get_block(x, y, z))
-        .enumerate()
-        .for_each(|(i, b)| {
-            if b.id == EMPTY_BLOCK {
-                return;
-            }
-            if b.shape() != 0 {
-                return;
-            }
-            if b.transparent() && b.id != block.id && b.id != 78 {
-                return;
-            }
-            block.geometry |= 1 << i;
-        });
+//Returns None if the block does not need to be updated, blocks that only
+//change when the blocks next to them change are handled by neighbour updates
+//instead
+fn tick_kind(block: Block) -> Option<TickKind> {
+    if block.shape() != 0 {
+        return None;
+    }
 
-    if block.geometry == geometry {
-        return;
+    match block.id {
+        12 => Some(TickKind::Water),
+        13 => Some(TickKind::Lava),
+        //Pressed button
+        112 => Some(TickKind::Button),
+        //Repeater and inverter
+        115..=118 => Some(TickKind::LogicGate),
+        //Piston and sticky piston
+        121 | 122 => Some(TickKind::Piston),
+        _ => None,
     }
+}
 
-    to_update.insert((x, y, z), block);
+//Pistons are not updated here since they change the world directly
+fn update_block(world: &World, x: i32, y: i32, z: i32, kind: TickKind, to_update: &mut UpdateList) {
+    match kind {
+        TickKind::Water => update_water(world, x, y, z, to_update),
+        TickKind::Lava => update_lava(world, x, y, z, to_update),
+        TickKind::Button => release_button(x, y, z, to_update),
+        TickKind::LogicGate => update_logic_gate(world, x, y, z, to_update),
+        TickKind::Piston => {}
+    }
 }
 
-//Meant to be called when player breaks ice
-//Checks if the block below is non-empty, if that's the case, then spawn water
-//where the ice was broken
-pub fn break_ice(world: &mut World, x: i32, y: i32, z: i32) {
-    let below = world.get_block(x, y - 1, z);
-    //Ignore if below is an empty block
-    if below.id == EMPTY_BLOCK {
-        return;
+//Schedules a tick for every block in the chunk that gets updated
+fn schedule_chunk_ticks(chunk: &mut Chunk, ticks: u64) {
+    let pos = chunk.get_chunk_pos();
+    let (posx, posy, posz) = (
+        pos.x * CHUNK_SIZE_I32,
+        pos.y * CHUNK_SIZE_I32,
+        pos.z * CHUNK_SIZE_I32,
+    );
+    for x in posx..(posx + CHUNK_SIZE_I32) {
+        for y in posy..(posy + CHUNK_SIZE_I32) {
+            for z in posz..(posz + CHUNK_SIZE_I32) {
+                let block = chunk.get_block(x, y, z);
+                if let Some(kind) = tick_kind(block) {
+                    let due = ticks + kind.delay();
+                    chunk.schedule_tick(x, y, z, ScheduledTick { id: block.id, due });
+                }
+            }
+        }
     }
-    let water = Block::new_fluid(12);
-    world.set_block(x, y, z, water);
 }
 
 impl World {
-    //Returns true if at least one block updated, otherwise false
-    fn update_chunk(&mut self, chunkx: i32, chunky: i32, chunkz: i32, to_update: &mut UpdateList) {
-        if let Some(chunk) = self.chunks.get(&(chunkx, chunky, chunkz)) {
-            if chunk.is_empty() {
-                return;
+    fn in_sim_range(&self, chunkx: i32, chunky: i32, chunkz: i32, chunk_sim_dist: i32) -> bool {
+        (chunkx - self.centerx).abs() <= chunk_sim_dist
+            && (chunky - self.centery).abs() <= chunk_sim_dist
+            && (chunkz - self.centerz).abs() <= chunk_sim_dist
+    }
+
+    //Schedules a tick for the block at (x, y, z) if it is a block that gets
+    //updated, does nothing if the chunk is not loaded
+    pub fn schedule_tick(&mut self, x: i32, y: i32, z: i32) {
+        let block = self.get_block(x, y, z);
+        let Some(kind) = tick_kind(block) else {
+            return;
+        };
+
+        let due = self.ticks + kind.delay();
+        let tick = ScheduledTick { id: block.id, due };
+        let (chunkx, chunky, chunkz) = world_to_chunk_position(x, y, z);
+        if let Some(chunk) = self.get_mut_chunk(chunkx, chunky, chunkz) {
+            if chunk.schedule_tick(x, y, z, tick) {
+                self.tick_queue.push(Reverse((due, (x, y, z))));
+                self.to_save.insert((chunkx, chunky, chunkz));
+            }
+        }
+    }
+
+    //Fluids flow based on the blocks next to them and the blocks diagonally
+    //below them so every block in the 3x3x3 cube around a changed block is
+    //scheduled
+    pub fn schedule_ticks_around(&mut self, x: i32, y: i32, z: i32) {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    self.schedule_tick(x + dx, y + dy, z + dz);
+                }
             }
         }
+    }
 
-        let startx = chunkx * CHUNK_SIZE_I32;
-        let starty = chunky * CHUNK_SIZE_I32;
-        let startz = chunkz * CHUNK_SIZE_I32;
+    //Adds the ticks stored in chunks that entered the simulation range or
+    //were loaded to the queue, chunks that are in range but not loaded yet
+    //are kept until they are loaded
+    fn queue_chunk_ticks(&mut self, chunk_sim_dist: i32) {
+        if self.updating.is_empty() {
+            return;
+        }
 
-        for x in startx..(startx + CHUNK_SIZE_I32) {
-            for y in starty..(starty + CHUNK_SIZE_I32) {
-                for z in startz..(startz + CHUNK_SIZE_I32) {
-                    let block = self.get_block(x, y, z);
-                    if block.shape() != 0 {
-                        continue;
-                    }
-                    match block.id {
-                        //Water
-                        12 => update_water(self, x, y, z, to_update),
-                        //Lava
-                        13 => update_lava(self, x, y, z, to_update),
-                        //Farmland
-                        43 | 45 => update_farmland(self, x, y, z, to_update),
-                        //Plants, torches, ladders
-                        47..=56 | 69 | 71..=75 | 77 | 88 | 90 | 92 | 98..=108 => {
-                            update_plant(self, x, y, z, block.id, to_update)
-                        }
-                        //Fence
-                        76 => update_fence(self, x, y, z, to_update),
-                        _ => {}
-                    }
+        //A chunk can be queued again while its ticks are still in the queue
+        //(update_all_chunks or leaving and entering the range) so ticks that
+        //are already queued are skipped
+        let mut queued: HashSet<(u64, (i32, i32, i32))> =
+            self.tick_queue.iter().map(|Reverse(tick)| *tick).collect();
+        let updating = std::mem::take(&mut self.updating);
+        for (x, y, z) in updating {
+            if !self.in_sim_range(x, y, z, chunk_sim_dist) {
+                continue;
+            }
+
+            let Some(chunk) = self.chunks.get_mut(&(x, y, z)) else {
+                self.updating.insert((x, y, z));
+                continue;
+            };
+
+            //Chunks saved before ticks were stored have every block that
+            //needs to be updated scheduled
+            if chunk.take_missing_ticks() {
+                schedule_chunk_ticks(chunk, self.ticks);
+                self.to_save.insert((x, y, z));
+            }
+
+            for (pos, tick) in chunk.scheduled_ticks() {
+                if queued.insert((tick.due, pos)) {
+                    self.tick_queue.push(Reverse((tick.due, pos)));
                 }
             }
         }
     }
 
+    //Removes all ticks that are due from the queue and adds the results of
+    //the block updates to to_update, ticks in chunks outside of the
+    //simulation range stay in the chunk until it is back in range
+    fn run_scheduled_ticks(&mut self, chunk_sim_dist: i32, to_update: &mut UpdateList) {
+        let mut pistons = vec![];
+        while let Some(Reverse((due, (x, y, z)))) = self.tick_queue.peek().copied() {
+            if due > self.ticks {
+                break;
+            }
+            self.tick_queue.pop();
+
+            let (chunkx, chunky, chunkz) = world_to_chunk_position(x, y, z);
+            if !self.in_sim_range(chunkx, chunky, chunkz, chunk_sim_dist) {
+                continue;
+            }
+
+            let Some(chunk) = self.get_mut_chunk(chunkx, chunky, chunkz) else {
+                continue;
+            };
+
+            //The tick was rescheduled or already happened
+            let tick = match chunk.get_scheduled_tick(x, y, z) {
+                Some(tick) if tick.due == due => tick,
+                _ => continue,
+            };
+            chunk.remove_scheduled_tick(x, y, z);
+            self.to_save.insert((chunkx, chunky, chunkz));
+
+            //Ignore the tick if the block was replaced since then
+            let block = self.get_block(x, y, z);
+            let Some(kind) = tick_kind(block).filter(|_| block.id == tick.id) else {
+                continue;
+            };
+
+            if kind == TickKind::Piston {
+                pistons.push((x, y, z));
+                continue;
+            }
+            update_block(self, x, y, z, kind, to_update);
+        }
+
+        //Pistons change the world directly so they are moved after every
+        //other block has been updated
+        for (x, y, z) in pistons {
+            self.update_piston(x, y, z);
+        }
+    }
+
     //Returns a vec of destroyed blocks
     //Vec<(block, x, y, z)>
     pub fn update_blocks(
@@ -353,29 +460,28 @@ impl World {
         chunktables: &mut ChunkTables,
         chunk_sim_dist: i32,
     ) -> Vec<((i32, i32, i32), Block)> {
+        let mut destroyed = vec![];
         self.block_update_timer += dt;
-        if self.block_update_timer <= BLOCK_UPDATE_INTERVAL {
-            return vec![];
+        if self.block_update_timer > BLOCK_UPDATE_INTERVAL {
+            self.block_update_timer = 0.0;
+            destroyed.extend(self.tick_blocks(chunktables, chunk_sim_dist));
         }
+        //Blocks changed by neighbour updates are handled every frame so that
+        //the changes show up right away
+        destroyed.extend(self.apply_neighbor_changes(chunktables));
+        destroyed
+    }
 
+    fn tick_blocks(
+        &mut self,
+        chunktables: &mut ChunkTables,
+        chunk_sim_dist: i32,
+    ) -> Vec<((i32, i32, i32), Block)> {
         self.ticks += 1;
-        self.block_update_timer = 0.0;
-
+        self.queue_chunk_ticks(chunk_sim_dist);
         let mut to_update = UpdateList::new();
         let mut update_mesh = HashSet::<(i32, i32, i32)>::new();
-        for x in (self.centerx - chunk_sim_dist)..=(self.centerx + chunk_sim_dist) {
-            for y in (self.centery - chunk_sim_dist)..=(self.centery + chunk_sim_dist) {
-                for z in (self.centerz - chunk_sim_dist)..=(self.centerz + chunk_sim_dist) {
-                    if !self.updating.contains(&(x, y, z)) {
-                        continue;
-                    }
-
-                    self.update_chunk(x, y, z, &mut to_update);
-                }
-            }
-        }
-
-        self.updating.clear();
+        self.run_scheduled_ticks(chunk_sim_dist, &mut to_update);
 
         let mut light_updates = vec![];
         let mut destroyed = vec![];
@@ -384,13 +490,6 @@ impl World {
                 continue;
             }
 
-            if block.geometry & (1 << 7) != 0 && block.is_fluid() {
-                let mut block2 = block;
-                block2.geometry &= !(1 << 7);
-                self.set_block(x, y, z, block2);
-                continue;
-            }
-
             let prev_block = self.get_block(x, y, z);
             if block.is_fluid() || block.id == EMPTY_BLOCK {
                 destroyed.push(((x, y, z), prev_block))
@@ -410,7 +509,7 @@ impl World {
         destroyed
     }
 
-    //Add all chunks to the updating list
+    //Queue the scheduled ticks of all loaded chunks
     pub fn update_all_chunks(&mut self) {
         for chunkpos in self.chunks.keys() {
             self.updating.insert(*chunkpos);
@@ -435,3 +534,130 @@ impl World {
         self.in_update_range = update_range;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::GameMode, voxel::init_test_registries};
+
+    fn run_tick(world: &mut World) {
+        world.ticks += 1;
+        world.queue_chunk_ticks(1);
+        let mut to_update = UpdateList::new();
+        world.run_scheduled_ticks(1, &mut to_update);
+        for ((x, y, z), block) in to_update {
+            if world.get_block(x, y, z) != block {
+                world.set_block(x, y, z, block);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scheduled_ticks() {
+        init_test_registries();
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(x, 0, z, Block::new_id(2));
+            }
+        }
+        //Stone does not get updated
+        assert_eq!(world.get_scheduled_ticks(), 0);
+
+        world.set_block(8, 1, 8, Block::new_fluid(12));
+        let tick = world
+            .get_chunk(0, 0, 0)
+            .unwrap()
+            .get_scheduled_tick(8, 1, 8);
+        assert_eq!(tick, Some(ScheduledTick { id: 12, due: 1 }));
+
+        for _ in 0..32 {
+            run_tick(&mut world);
+        }
+        assert_eq!(world.get_block(9, 1, 8).id, 12);
+        assert_eq!(world.get_block(8, 1, 14).id, 12);
+        assert_eq!(world.get_block(8, 1, 15).id, EMPTY_BLOCK);
+        //The water should stop flowing and not schedule any more ticks
+        assert_eq!(world.tick_queue.len(), 0);
+        let chunk = world.get_chunk(0, 0, 0).unwrap();
+        assert_eq!(chunk.scheduled_ticks().count(), 0);
+
+        //Ticks outside of the simulation range stay in the chunk
+        world.set_block(8, 1, 8, Block::new_id(2));
+        world.centerx = 4;
+        run_tick(&mut world);
+        assert_eq!(world.get_block(9, 1, 8).id, 12);
+        let chunk = world.get_chunk(0, 0, 0).unwrap();
+        assert!(chunk.get_scheduled_tick(9, 1, 8).is_some());
+        //Once the chunk is back in range the ticks are queued again
+        world.centerx = 0;
+        world.updating.insert((0, 0, 0));
+        for _ in 0..32 {
+            run_tick(&mut world);
+        }
+        assert_eq!(world.get_block(9, 1, 8).id, EMPTY_BLOCK);
+    }
+
+    #[test]
+    fn test_replace_block_with_pending_tick() {
+        init_test_registries();
+        let mut world = World::new(0, 0, "flat", "", GameMode::Creative).unwrap();
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(x, 0, z, Block::new_id(2));
+            }
+        }
+        world.set_block(8, 1, 8, Block::new_fluid(12));
+        //Replace the water with lava before the water is updated, the lava
+        //should still be updated even though its tick is due later
+        world.set_block(8, 1, 8, Block::new_fluid(13));
+        let tick = world
+            .get_chunk(0, 0, 0)
+            .unwrap()
+            .get_scheduled_tick(8, 1, 8);
+        let lava_tick = ScheduledTick {
+            id: 13,
+            due: LAVA_TICK_DELAY,
+        };
+        assert_eq!(tick, Some(lava_tick));
+
+        for _ in 0..(LAVA_TICK_DELAY * 2) {
+            run_tick(&mut world);
+        }
+        assert_eq!(world.get_block(9, 1, 8).id, 13);
+    }
+
+    #[test]
+    fn test_queue_chunk_ticks() {
+        init_test_registries();
+        let mut world = World::new(0, 0, "flat", "", GameMode::Creative).unwrap();
+        world.set_block(8, 0, 8, Block::new_id(2));
+        world.set_block(8, 1, 8, Block::new_fluid(12));
+        let queued = world.tick_queue.len();
+        assert!(queued > 0);
+        //Queueing the chunk again does not add the same ticks twice
+        world.update_all_chunks();
+        world.queue_chunk_ticks(0);
+        assert_eq!(world.tick_queue.len(), queued);
+
+        //Chunks saved before ticks were stored are scanned for blocks that
+        //need to be updated
+        let mut chunk = Chunk::new(0, 0, 0);
+        chunk.set_block(2, 3, 4, Block::new_fluid(13));
+        chunk.set_block(2, 2, 4, Block::new_id(2));
+        chunk.set_missing_ticks();
+        world.chunks.insert((0, 0, 0), chunk);
+        world.tick_queue.clear();
+        world.updating.insert((0, 0, 0));
+        world.queue_chunk_ticks(0);
+        let chunk = world.get_chunk(0, 0, 0).unwrap();
+        let tick = ScheduledTick {
+            id: 13,
+            due: LAVA_TICK_DELAY,
+        };
+        assert_eq!(chunk.get_scheduled_tick(2, 3, 4), Some(tick));
+        assert_eq!(chunk.scheduled_ticks().count(), 1);
+        assert_eq!(world.tick_queue.len(), 1);
+        assert!(world.to_save.contains(&(0, 0, 0)));
+    }
+}
@@ -0,0 +1,329 @@
+/*
+ * Neighbour updates, whenever a block is set the block and the six blocks
+ * next to it are notified so that blocks that depend on the blocks around
+ * them (plants, torches, doors, fences, farmland, wire, piston heads, sand)
+ * change right away instead of waiting for a scheduled tick.
+ * */
+
+use super::{
+    get_chunktable_updates,
+    piston::update_piston_head,
+    power::{get_power, get_wire, power_door},
+    World, ADJ,
+};
+use crate::{
+    gfx::ChunkTables,
+    voxel::{is_valid::get_check_valid_fn, Block, EMPTY_BLOCK},
+};
+use std::collections::{HashSet, VecDeque};
+
+//The block itself followed by the six blocks next to it
+const NOTIFY: [(i32, i32, i32); 7] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+type NeighborUpdateFn = fn(&mut World, i32, i32, i32);
+
+#[derive(Default)]
+pub struct NeighborChanges {
+    //Positions of the blocks that were changed, the light and chunk meshes
+    //around these need to be updated
+    changed: Vec<(i32, i32, i32)>,
+    //Blocks that were broken and should drop an item
+    dropped: Vec<((i32, i32, i32), Block)>,
+    //Blocks that started falling and should become falling block entities
+    falling: Vec<((i32, i32, i32), Block)>,
+    //Blocks that still need to be notified, changes are handled in order
+    //instead of recursively so that large networks of wire can not overflow
+    //the stack
+    queue: VecDeque<(i32, i32, i32)>,
+    notifying: bool,
+}
+
+//Sets the block and remembers the change so that the light and chunk meshes
+//get updated
+pub(super) fn replace_block(world: &mut World, x: i32, y: i32, z: i32, block: Block) {
+    if world.get_block(x, y, z) == block {
+        return;
+    }
+    world.neighbor_changes.changed.push((x, y, z));
+    world.set_block(x, y, z, block);
+}
+
+//Breaks the block and drops it as an item
+pub(super) fn pop_block(world: &mut World, x: i32, y: i32, z: i32) {
+    let block = world.get_block(x, y, z);
+    world.neighbor_changes.dropped.push(((x, y, z), block));
+    replace_block(world, x, y, z, Block::new());
+}
+
+//Plants, torches, ladders and doors break if the block they are placed on
+//is no longer valid
+fn update_supported(world: &mut World, x: i32, y: i32, z: i32) {
+    let block = world.get_block(x, y, z);
+    if let Some(check_valid) = get_check_valid_fn(block.id) {
+        if !check_valid(world, x, y, z) {
+            pop_block(world, x, y, z);
+        }
+    }
+}
+
+//Doors open when either half is powered
+fn update_door(world: &mut World, x: i32, y: i32, z: i32) {
+    update_supported(world, x, y, z);
+    let bottom = world.get_block(x, y, z);
+    let top = world.get_block(x, y + 1, z);
+    if bottom.id != 79 || top.id != 81 {
+        return;
+    }
+
+    let powered = get_power(world, x, y, z) > 0 || get_power(world, x, y + 1, z) > 0;
+    replace_block(world, x, y, z, power_door(bottom, powered));
+    replace_block(world, x, y + 1, z, power_door(top, powered));
+}
+
+//The top of a door is removed along with the bottom, only the bottom drops
+//the door so that a broken door does not drop twice
+fn update_door_top(world: &mut World, x: i32, y: i32, z: i32) {
+    if world.get_block(x, y - 1, z).id != 79 {
+        replace_block(world, x, y, z, Block::new());
+        return;
+    }
+    update_door(world, x, y - 1, z);
+}
+
+fn update_gate(world: &mut World, x: i32, y: i32, z: i32) {
+    let powered = get_power(world, x, y, z) > 0;
+    let gate = power_door(world.get_block(x, y, z), powered);
+    replace_block(world, x, y, z, gate);
+}
+
+fn update_wire(world: &mut World, x: i32, y: i32, z: i32) {
+    update_supported(world, x, y, z);
+    if world.get_block(x, y, z).id == EMPTY_BLOCK {
+        return;
+    }
+    let wire = get_wire(world, x, y, z);
+    replace_block(world, x, y, z, wire);
+}
+
+fn update_lamp(world: &mut World, x: i32, y: i32, z: i32) {
+    let id = if get_power(world, x, y, z) > 0 {
+        120
+    } else {
+        119
+    };
+    replace_block(world, x, y, z, Block::new_id(id));
+}
+
+fn update_farmland(world: &mut World, x: i32, y: i32, z: i32) {
+    let above = world.get_block(x, y + 1, z);
+    if above.transparent() || above.id == EMPTY_BLOCK {
+        return;
+    }
+    replace_block(world, x, y, z, Block::new_id(4));
+}
+
+//Sand falls if there is nothing below it, the block is removed and the game
+//turns it into a falling block entity
+fn update_gravity(world: &mut World, x: i32, y: i32, z: i32) {
+    let block = world.get_block(x, y, z);
+    let below = world.get_block(x, y - 1, z);
+    if !block.has_gravity() || world.out_of_bounds(x, y - 1, z) {
+        return;
+    }
+
+    if below.id == EMPTY_BLOCK || below.is_fluid() || below.replaceable() {
+        world.neighbor_changes.falling.push(((x, y, z), block));
+        replace_block(world, x, y, z, Block::new());
+    }
+}
+
+//Connect fences
+fn update_fence(world: &mut World, x: i32, y: i32, z: i32) {
+    let mut block = world.get_block(x, y, z);
+    block.geometry = 0;
+    ADJ.iter()
+        .map(|(dx, dy, dz)| (x + dx, y + dy, z + dz))
+        .map(|(x, y, z)| world.get_block(x, y, z))
+        .enumerate()
+        .for_each(|(i, b)| {
+            if b.id == EMPTY_BLOCK {
+                return;
+            }
+            if b.shape() != 0 {
+                return;
+            }
+            if b.transparent() && b.id != block.id && b.id != 78 {
+                return;
+            }
+            block.geometry |= 1 << i;
+        });
+    replace_block(world, x, y, z, block);
+}
+
+//Returns the function that is called when a block next to a block with
+//this id changes, None if the block does not care about its neighbours
+fn get_neighbor_update_fn(id: u16) -> Option<NeighborUpdateFn> {
+    match id {
+        //Farmland
+        43 | 45 => Some(update_farmland),
+        //Fence
+        76 => Some(update_fence),
+        //Gate
+        78 => Some(update_gate),
+        //Door
+        79 => Some(update_door),
+        81 => Some(update_door_top),
+        //Wire
+        113 | 114 => Some(update_wire),
+        //Lamp
+        119 | 120 => Some(update_lamp),
+        //Piston head
+        123 | 124 => Some(update_piston_head),
+        //Sand
+        _ if Block::new_id(id).has_gravity() => Some(update_gravity),
+        //Plants, torches, ladders
+        _ if get_check_valid_fn(id).is_some() => Some(update_supported),
+        _ => None,
+    }
+}
+
+impl World {
+    //Runs the neighbour update for the block at (x, y, z) and the blocks
+    //next to it, blocks that change because of this notify their own
+    //neighbours so changes can spread (a column of sugar cane breaks at once)
+    pub fn notify_neighbors(&mut self, x: i32, y: i32, z: i32) {
+        for (dx, dy, dz) in NOTIFY {
+            self.neighbor_changes
+                .queue
+                .push_back((x + dx, y + dy, z + dz));
+        }
+
+        //Already handling the queue further up the stack
+        if self.neighbor_changes.notifying {
+            return;
+        }
+
+        self.neighbor_changes.notifying = true;
+        while let Some((x, y, z)) = self.neighbor_changes.queue.pop_front() {
+            let block = self.get_block(x, y, z);
+            if block.shape() != 0 {
+                continue;
+            }
+
+            if let Some(update) = get_neighbor_update_fn(block.id) {
+                update(self, x, y, z);
+            }
+        }
+        self.neighbor_changes.notifying = false;
+    }
+
+    //Updates the light and chunk meshes for blocks that were changed by
+    //neighbour updates, returns the blocks that were broken
+    pub fn apply_neighbor_changes(
+        &mut self,
+        chunktables: &mut ChunkTables,
+    ) -> Vec<((i32, i32, i32), Block)> {
+        let changed = std::mem::take(&mut self.neighbor_changes.changed);
+        let dropped = std::mem::take(&mut self.neighbor_changes.dropped);
+        if changed.is_empty() {
+            return dropped;
+        }
+
+        let mut update_mesh = HashSet::<(i32, i32, i32)>::new();
+        for (x, y, z) in &changed {
+            get_chunktable_updates(*x, *y, *z, &mut update_mesh);
+        }
+        update_mesh.extend(self.update_block_light(&changed));
+        for (x, y, z) in update_mesh {
+            chunktables.update_table(self, x, y, z);
+        }
+        dropped
+    }
+
+    //Returns the blocks that started falling since the last call
+    pub fn take_falling_blocks(&mut self) -> Vec<((i32, i32, i32), Block)> {
+        std::mem::take(&mut self.neighbor_changes.falling)
+    }
+
+    //Sets a block outside of a block update (a falling block landing), the
+    //light and chunk meshes are updated along with the neighbour changes
+    pub fn place_block(&mut self, x: i32, y: i32, z: i32, block: Block) {
+        replace_block(self, x, y, z, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::GameMode, voxel::init_test_registries};
+
+    #[test]
+    fn test_neighbor_updates() {
+        init_test_registries();
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
+
+        //Torch on top of stone
+        world.set_block(0, 0, 0, Block::new_id(2));
+        world.set_block(0, 1, 0, Block::new_id(71));
+        world.set_block(0, 0, 0, Block::new());
+        assert_eq!(world.get_block(0, 1, 0).id, EMPTY_BLOCK);
+
+        //Sugar cane breaks all at once
+        world.set_block(4, -1, 0, Block::new_id(2));
+        world.set_block(4, 0, 0, Block::new_id(11));
+        for y in 1..4 {
+            world.set_block(4, y, 0, Block::new_id(69));
+        }
+        world.set_block(4, 0, 0, Block::new());
+        for y in 1..4 {
+            assert_eq!(world.get_block(4, y, 0).id, EMPTY_BLOCK);
+        }
+
+        //Door only drops once
+        world.set_block(8, 0, 0, Block::new_id(2));
+        world.set_block(8, 1, 0, Block::new_id(79));
+        world.set_block(8, 2, 0, Block::new_id(81));
+        assert_eq!(world.get_block(8, 2, 0).id, 81);
+        world.set_block(8, 0, 0, Block::new());
+        assert_eq!(world.get_block(8, 1, 0).id, EMPTY_BLOCK);
+        assert_eq!(world.get_block(8, 2, 0).id, EMPTY_BLOCK);
+
+        let dropped: Vec<u16> = world
+            .neighbor_changes
+            .dropped
+            .iter()
+            .map(|(_, block)| block.id)
+            .collect();
+        assert_eq!(dropped, vec![71, 69, 69, 69, 79]);
+
+        //Fences connect to each other
+        world.set_block(0, 0, 8, Block::new_id(76));
+        world.set_block(1, 0, 8, Block::new_id(76));
+        assert_eq!(world.get_block(0, 0, 8).geometry, 1);
+        assert_eq!(world.get_block(1, 0, 8).geometry, 1 << 2);
+        world.set_block(1, 0, 8, Block::new());
+        assert_eq!(world.get_block(0, 0, 8).geometry, 0);
+
+        //Sand falls when the block below it is removed
+        world.set_block(8, 0, 8, Block::new_id(2));
+        world.set_block(8, 1, 8, Block::new_id(11));
+        world.set_block(8, 2, 8, Block::new_id(11));
+        assert!(world.take_falling_blocks().is_empty());
+        world.set_block(8, 0, 8, Block::new());
+        let falling: Vec<_> = world
+            .take_falling_blocks()
+            .iter()
+            .map(|(pos, _)| *pos)
+            .collect();
+        assert_eq!(falling, vec![(8, 1, 8), (8, 2, 8)]);
+        assert_eq!(world.get_block(8, 2, 8).id, EMPTY_BLOCK);
+    }
+}
@@ -0,0 +1,358 @@
+/*
+ * Pistons push the blocks in front of them when they are powered and pull
+ * their head back in when they lose power, sticky pistons also pull the
+ * block in front of the head back with them.
+ *
+ * Moved blocks keep their geometry (slabs, stairs and rotated blocks stay
+ * the same) and their tile data (chests and furnaces keep their items).
+ * Blocks can be pushed into chunks that are only in the chunk cache, those
+ * chunks are saved with the change but do not get neighbour updates until
+ * they are loaded again.
+ * */
+
+use super::{
+    neighbor_update::{pop_block, replace_block},
+    power::get_power,
+    World,
+};
+use crate::voxel::{
+    orientation_to_normal, tile_data::TileData, world_to_chunk_position, Block, EMPTY_BLOCK,
+    INDESTRUCTIBLE, STATE_BIT,
+};
+
+//How many blocks a piston can push at once
+pub const MAX_PUSH: usize = 12;
+pub const PISTON_DELAY: u64 = 1;
+//Pistons remember if they are extended in this bit of their geometry
+const EXTENDED_BIT: u8 = STATE_BIT;
+
+pub fn is_piston(id: u16) -> bool {
+    matches!(id, 121 | 122)
+}
+
+//Piston head = 123, sticky piston head = 124
+fn head_id(piston_id: u16) -> u16 {
+    piston_id + 2
+}
+
+fn is_extended(block: Block) -> bool {
+    is_piston(block.id) && block.geometry & EXTENDED_BIT != 0
+}
+
+fn facing(block: Block) -> (i32, i32, i32) {
+    let normal = orientation_to_normal(block.orientation());
+    (normal.x, normal.y, normal.z)
+}
+
+//Blocks that pushed blocks can move into, fluids and replaceable blocks are
+//overwritten and plants and torches are broken
+fn is_free(block: Block) -> bool {
+    block.id == EMPTY_BLOCK || block.is_fluid() || block.replaceable() || block.fluid_destructibe()
+}
+
+fn can_move(block: Block) -> bool {
+    match block.id {
+        INDESTRUCTIBLE => false,
+        //Doors
+        79 | 81 => false,
+        //Piston heads
+        123 | 124 => false,
+        //Extended pistons
+        121 | 122 => !is_extended(block),
+        _ => true,
+    }
+}
+
+//Piston heads are removed if the piston behind them was broken or
+//retracted, the head is kept if the piston is in a chunk that is not loaded
+pub fn update_piston_head(world: &mut World, x: i32, y: i32, z: i32) {
+    let head = world.get_block(x, y, z);
+    let (dx, dy, dz) = facing(head);
+    let Some(piston) = world.get_block_any(x - dx, y - dy, z - dz) else {
+        return;
+    };
+
+    if is_extended(piston)
+        && head_id(piston.id) == head.id
+        && piston.orientation() == head.orientation()
+    {
+        return;
+    }
+    replace_block(world, x, y, z, Block::new());
+}
+
+impl World {
+    //Returns the block from either a loaded chunk or a chunk in the cache,
+    //None if the chunk is in neither
+    fn get_block_any(&self, x: i32, y: i32, z: i32) -> Option<Block> {
+        let pos = world_to_chunk_position(x, y, z);
+        self.chunks
+            .get(&pos)
+            .or_else(|| self.chunk_cache.get(&pos))
+            .map(|chunk| chunk.get_block(x, y, z))
+    }
+
+    fn get_tile_data_any(&self, x: i32, y: i32, z: i32) -> Option<TileData> {
+        let pos = world_to_chunk_position(x, y, z);
+        self.chunks
+            .get(&pos)
+            .or_else(|| self.chunk_cache.get(&pos))?
+            .get_tile_data(x, y, z)
+    }
+
+    //Blocks in loaded chunks notify their neighbours and have their light
+    //and chunk meshes updated, blocks in cached chunks are only saved and
+    //the light in the chunk is cleared so that it is recalculated on load
+    fn set_block_any(&mut self, x: i32, y: i32, z: i32, block: Block, tile: Option<TileData>) {
+        let pos = world_to_chunk_position(x, y, z);
+        if self.chunks.contains_key(&pos) {
+            self.set_tile_data(x, y, z, tile);
+            replace_block(self, x, y, z, block);
+        } else if let Some(chunk) = self.chunk_cache.get_mut(&pos) {
+            chunk.set_block(x, y, z, block);
+            chunk.set_tile_data(x, y, z, tile);
+            chunk.clear_light();
+            self.to_save.insert(pos);
+        }
+    }
+
+    fn move_block(&mut self, (x, y, z): (i32, i32, i32), (tx, ty, tz): (i32, i32, i32)) {
+        let block = self.get_block_any(x, y, z).unwrap_or(Block::new());
+        let tile = self.get_tile_data_any(x, y, z);
+        self.set_block_any(tx, ty, tz, block, tile);
+        self.set_block_any(x, y, z, Block::new(), None);
+    }
+
+    //Extends the piston if it is powered and retracts it if it is not
+    pub fn update_piston(&mut self, x: i32, y: i32, z: i32) {
+        let mut piston = self.get_block(x, y, z);
+        if !is_piston(piston.id) {
+            return;
+        }
+
+        let (dx, dy, dz) = facing(piston);
+        let Some(front) = self.get_block_any(x + dx, y + dy, z + dz) else {
+            return;
+        };
+
+        //The head was broken so the piston is retracted
+        let has_head =
+            front.id == head_id(piston.id) && front.orientation() == piston.orientation();
+        if is_extended(piston) && !has_head {
+            piston.geometry &= !EXTENDED_BIT;
+            replace_block(self, x, y, z, piston);
+        }
+
+        let powered = get_power(self, x, y, z) > 0;
+        if powered && !is_extended(piston) {
+            self.extend_piston(x, y, z, piston);
+        } else if !powered && is_extended(piston) {
+            self.retract_piston(x, y, z, piston);
+        }
+    }
+
+    //Does nothing if there are too many blocks in the way, one of them can
+    //not be moved or the blocks reach a chunk that is not loaded
+    fn extend_piston(&mut self, x: i32, y: i32, z: i32, mut piston: Block) {
+        let (dx, dy, dz) = facing(piston);
+        let mut pushed = vec![];
+        let mut end = (x + dx, y + dy, z + dz);
+        loop {
+            let Some(block) = self.get_block_any(end.0, end.1, end.2) else {
+                return;
+            };
+
+            if is_free(block) {
+                break;
+            }
+
+            if !can_move(block) || pushed.len() >= MAX_PUSH {
+                return;
+            }
+            pushed.push(end);
+            end = (end.0 + dx, end.1 + dy, end.2 + dz);
+        }
+
+        //Plants and torches at the end of the line drop as items, blocks in
+        //chunks that are not loaded are overwritten
+        let (ex, ey, ez) = end;
+        let crushed = self.get_block(ex, ey, ez);
+        if crushed.fluid_destructibe() && !self.out_of_bounds(ex, ey, ez) {
+            pop_block(self, ex, ey, ez);
+        }
+
+        //Move the furthest block first so that every block moves into an
+        //empty space
+        for &(px, py, pz) in pushed.iter().rev() {
+            self.move_block((px, py, pz), (px + dx, py + dy, pz + dz));
+        }
+
+        piston.geometry |= EXTENDED_BIT;
+        replace_block(self, x, y, z, piston);
+        let head = Block::new_id_orientation(head_id(piston.id), piston.orientation());
+        self.set_block_any(x + dx, y + dy, z + dz, head, None);
+    }
+
+    fn retract_piston(&mut self, x: i32, y: i32, z: i32, mut piston: Block) {
+        let (dx, dy, dz) = facing(piston);
+        let (hx, hy, hz) = (x + dx, y + dy, z + dz);
+        piston.geometry &= !EXTENDED_BIT;
+        replace_block(self, x, y, z, piston);
+        //Heads in loaded chunks are removed by their neighbour update
+        if self.get_block_any(hx, hy, hz).map(|b| b.id) == Some(head_id(piston.id)) {
+            self.set_block_any(hx, hy, hz, Block::new(), None);
+        }
+
+        //Sticky piston
+        if piston.id != 122 {
+            return;
+        }
+
+        let pulled = (hx + dx, hy + dy, hz + dz);
+        let Some(block) = self.get_block_any(pulled.0, pulled.1, pulled.2) else {
+            return;
+        };
+        let head_empty = self.get_block_any(hx, hy, hz).map(|b| b.id) == Some(EMPTY_BLOCK);
+        if head_empty && !is_free(block) && can_move(block) {
+            self.move_block(pulled, (hx, hy, hz));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::{inventory::Item, GameMode},
+        voxel::{init_test_registries, CHUNK_SIZE_I32, SLAB},
+    };
+
+    fn new_piston(id: u16, orientation: u8) -> Block {
+        Block::new_id_orientation(id, orientation)
+    }
+
+    //Puts 5 iron ingots in the first slot of a chest or furnace
+    fn fill_tile_data(world: &mut World, x: i32, y: i32, z: i32) {
+        world.init_tile_data(x, y, z);
+        let mut tile = world.get_tile_data(x, y, z).unwrap();
+        tile.inventory.set_item(0, 0, Item::Sprite(7, 5));
+        world.set_tile_data(x, y, z, Some(tile));
+    }
+
+    fn has_items(tile: Option<TileData>) -> bool {
+        let item = tile.map(|tile| tile.inventory.get_item(0, 0));
+        matches!(item, Some(Item::Sprite(7, 5)))
+    }
+
+    #[test]
+    fn test_piston() {
+        init_test_registries();
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
+
+        //Piston facing +x right before a chunk boundary pushes a chest with
+        //items and a slab into the next chunk
+        let x = CHUNK_SIZE_I32 - 3;
+        world.set_block(x, 0, 0, new_piston(121, 1));
+        world.set_block(x + 1, 0, 0, Block::new_id(37));
+        fill_tile_data(&mut world, x + 1, 0, 0);
+        let mut slab = Block::new_id(2);
+        slab.set_shape(SLAB);
+        slab.set_orientation(3);
+        world.set_block(x + 2, 0, 0, slab);
+        //Torch at the end of the line is broken
+        world.set_block(x + 3, 0, 0, Block::new_id(71));
+
+        //Power the piston with a lever
+        world.set_block(x, 1, 0, Block::new_id(110));
+        world.update_piston(x, 0, 0);
+        assert!(is_extended(world.get_block(x, 0, 0)));
+        assert_eq!(world.get_block(x + 1, 0, 0), new_piston(123, 1));
+        assert_eq!(world.get_block(x + 2, 0, 0).id, 37);
+        assert!(has_items(world.get_tile_data(x + 2, 0, 0)));
+        assert!(world.get_tile_data(x + 1, 0, 0).is_none());
+        assert_eq!(world.get_block(x + 3, 0, 0), slab);
+
+        //Turning the lever off retracts the piston
+        world.set_block(x, 1, 0, Block::new_id(109));
+        world.update_piston(x, 0, 0);
+        assert!(!is_extended(world.get_block(x, 0, 0)));
+        assert_eq!(world.get_block(x + 1, 0, 0).id, EMPTY_BLOCK);
+        assert_eq!(world.get_block(x + 2, 0, 0).id, 37);
+
+        //Sticky pistons pull the block back
+        world.set_block(x, 0, 0, new_piston(122, 1));
+        world.set_block(x, 1, 0, Block::new_id(110));
+        world.update_piston(x, 0, 0);
+        assert_eq!(world.get_block(x + 1, 0, 0), new_piston(124, 1));
+        world.set_block(x, 1, 0, Block::new_id(109));
+        world.update_piston(x, 0, 0);
+        assert_eq!(world.get_block(x + 1, 0, 0).id, 37);
+        assert!(has_items(world.get_tile_data(x + 1, 0, 0)));
+        assert_eq!(world.get_block(x + 2, 0, 0).id, EMPTY_BLOCK);
+        assert_eq!(world.get_block(x + 3, 0, 0), slab);
+
+        //Breaking an extended piston removes its head
+        world.set_block(x, 1, 0, Block::new_id(110));
+        world.update_piston(x, 0, 0);
+        assert_eq!(world.get_block(x + 1, 0, 0).id, 124);
+        world.set_block(x, 0, 0, Block::new());
+        assert_eq!(world.get_block(x + 1, 0, 0).id, EMPTY_BLOCK);
+
+        //Pistons can not push too many blocks or indestructible blocks
+        let z = 8;
+        world.set_block(0, 0, z, new_piston(121, 2));
+        world.set_block(0, 1, z, Block::new_id(110));
+        for i in 1..=(MAX_PUSH as i32 + 1) {
+            world.set_block(0, 0, z + i, Block::new_id(2));
+        }
+        world.update_piston(0, 0, z);
+        assert!(!is_extended(world.get_block(0, 0, z)));
+        world.set_block(0, 0, z + 2, Block::new_id(INDESTRUCTIBLE));
+        world.set_block(0, 0, z + 3, Block::new());
+        world.update_piston(0, 0, z);
+        assert!(!is_extended(world.get_block(0, 0, z)));
+    }
+
+    #[test]
+    fn test_piston_chunk_cache() {
+        init_test_registries();
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
+
+        //Move the chunk in front of the piston into the cache
+        let x = CHUNK_SIZE_I32 - 2;
+        let pos = world_to_chunk_position(x + 2, 0, 0);
+        world.init_light();
+        let chunk = world.chunks.remove(&pos).unwrap();
+        assert!(chunk.light_initialized());
+        world.add_to_chunk_cache(chunk);
+        world.to_save.clear();
+
+        world.set_block(x, 0, 0, new_piston(122, 1));
+        world.set_block(x + 1, 0, 0, Block::new_id(40));
+        fill_tile_data(&mut world, x + 1, 0, 0);
+        world.set_block(x, 1, 0, Block::new_id(110));
+        world.update_piston(x, 0, 0);
+        assert_eq!(world.get_block(x + 1, 0, 0).id, 124);
+        let cached = world.chunk_cache.get(&pos).unwrap();
+        assert_eq!(cached.get_block(x + 2, 0, 0).id, 40);
+        assert!(has_items(cached.get_tile_data(x + 2, 0, 0)));
+        //The saved light is out of date
+        assert!(!cached.light_initialized());
+        assert!(world.to_save.contains(&pos));
+
+        //Pull it back out of the cached chunk
+        world.set_block(x, 1, 0, Block::new_id(109));
+        world.update_piston(x, 0, 0);
+        assert_eq!(world.get_block(x + 1, 0, 0).id, 40);
+        assert!(has_items(world.get_tile_data(x + 1, 0, 0)));
+        let cached = world.chunk_cache.get(&pos).unwrap();
+        assert_eq!(cached.get_block(x + 2, 0, 0).id, EMPTY_BLOCK);
+
+        //Chunks that are neither loaded nor cached block the piston
+        world.chunk_cache.remove(&pos);
+        world.set_block(x, 1, 0, Block::new_id(110));
+        world.update_piston(x, 0, 0);
+        assert!(!is_extended(world.get_block(x, 0, 0)));
+        assert_eq!(world.get_block(x + 1, 0, 0).id, 40);
+    }
+}
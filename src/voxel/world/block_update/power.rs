@@ -0,0 +1,245 @@
+/*
+ * Power network used for building contraptions. Levers and buttons power
+ * the blocks next to them, wire carries the signal and loses a level of
+ * power for every block that it travels, repeaters and inverters pass the
+ * signal on after a short delay and lamps, doors and gates are turned on
+ * when they are powered.
+ *
+ * Wire, lamps, doors and gates change right away through neighbour updates
+ * while buttons, repeaters and inverters use scheduled ticks so that all of
+ * the state is stored in the chunks and survives saving and unloading.
+ * */
+
+use super::{UpdateList, World};
+use crate::voxel::{orientation_to_normal, Block, STATE_BIT};
+
+pub const MAX_POWER: u8 = 15;
+//How many ticks it takes for a repeater or inverter to change
+pub const GATE_DELAY: u64 = 1;
+//How many ticks a button stays pressed
+pub const BUTTON_DELAY: u64 = 5;
+//Doors and gates remember if they were powered in this bit of their geometry
+//so that they only open or close when their power changes, this allows them
+//to still be opened and closed by hand
+const POWERED_BIT: u8 = STATE_BIT;
+const ADJ: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+fn is_wire(id: u16) -> bool {
+    matches!(id, 113 | 114)
+}
+
+//Repeaters and inverters power the block in the direction they are facing
+//and take their input from the block behind them
+fn output_dir(block: Block) -> (i32, i32, i32) {
+    let normal = orientation_to_normal(block.orientation());
+    (normal.x, normal.y, normal.z)
+}
+
+//Returns the power that `block` gives to the block in direction `dir`
+fn emitted_power(block: Block, dir: (i32, i32, i32)) -> u8 {
+    match block.id {
+        //Lever and button that are on
+        110 | 112 => MAX_POWER,
+        //Wire
+        113 | 114 => block.geometry & MAX_POWER,
+        //Repeater and inverter that are on
+        116 | 118 if output_dir(block) == dir => MAX_POWER,
+        _ => 0,
+    }
+}
+
+//Returns the highest power that the blocks next to (x, y, z) give to it
+pub fn get_power(world: &World, x: i32, y: i32, z: i32) -> u8 {
+    ADJ.iter()
+        .map(|(dx, dy, dz)| {
+            let block = world.get_block(x + dx, y + dy, z + dz);
+            emitted_power(block, (-dx, -dy, -dz))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+//Wire is powered by the highest power next to it, power that comes from
+//other wire is one level lower
+pub fn get_wire(world: &World, x: i32, y: i32, z: i32) -> Block {
+    let power = ADJ
+        .iter()
+        .map(|(dx, dy, dz)| {
+            let block = world.get_block(x + dx, y + dy, z + dz);
+            let power = emitted_power(block, (-dx, -dy, -dz));
+            if is_wire(block.id) {
+                power.saturating_sub(1)
+            } else {
+                power
+            }
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut wire = Block::new_id(if power > 0 { 114 } else { 113 });
+    wire.geometry = power;
+    wire
+}
+
+//Returns the door or gate opened if it became powered or closed if it is no
+//longer powered, otherwise the block is unchanged
+pub fn power_door(block: Block, powered: bool) -> Block {
+    if (block.geometry & POWERED_BIT != 0) == powered {
+        return block;
+    }
+
+    let mut door = block;
+    door.geometry ^= POWERED_BIT;
+    door.set_reflection(powered as u8);
+    door
+}
+
+//Repeaters copy their input and inverters invert it
+pub fn update_logic_gate(world: &World, x: i32, y: i32, z: i32, to_update: &mut UpdateList) {
+    let block = world.get_block(x, y, z);
+    let (dx, dy, dz) = output_dir(block);
+    let input = world.get_block(x - dx, y - dy, z - dz);
+    let powered = emitted_power(input, (dx, dy, dz)) > 0;
+    let id = match block.id {
+        115 | 116 if powered => 116,
+        115 | 116 => 115,
+        117 | 118 if powered => 117,
+        117 | 118 => 118,
+        _ => return,
+    };
+
+    if id != block.id {
+        let mut gate = block;
+        gate.id = id;
+        to_update.insert((x, y, z), gate);
+    }
+}
+
+pub fn release_button(x: i32, y: i32, z: i32, to_update: &mut UpdateList) {
+    to_update.insert((x, y, z), Block::new_id(111));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::GameMode, voxel::init_test_registries};
+
+    fn run_tick(world: &mut World) {
+        world.ticks += 1;
+        world.queue_chunk_ticks(1);
+        let mut to_update = UpdateList::new();
+        world.run_scheduled_ticks(1, &mut to_update);
+        for ((x, y, z), block) in to_update {
+            world.set_block(x, y, z, block);
+        }
+    }
+
+    fn test_world() -> World {
+        init_test_registries();
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(x, 0, z, Block::new_id(2));
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn test_wire() {
+        let mut world = test_world();
+        world.set_block(0, 1, 0, Block::new_id(109));
+        for x in 1..=5 {
+            world.set_block(x, 1, 0, Block::new_id(113));
+        }
+        world.set_block(6, 1, 0, Block::new_id(119));
+        assert_eq!(world.get_block(6, 1, 0).id, 119);
+
+        world.set_block(0, 1, 0, Block::new_id(110));
+        for x in 1..=5 {
+            let wire = world.get_block(x, 1, 0);
+            assert_eq!(wire.id, 114);
+            assert_eq!(wire.geometry, MAX_POWER + 1 - x as u8);
+        }
+        assert_eq!(world.get_block(6, 1, 0).id, 120);
+
+        //Wire loses power along the way
+        for x in 1..16 {
+            world.set_block(x, 1, 1, Block::new_id(113));
+        }
+        world.set_block(0, 1, 1, Block::new_id(110));
+        assert_eq!(world.get_block(15, 1, 1).geometry, 1);
+        world.set_block(15, 1, 2, Block::new_id(113));
+        assert_eq!(world.get_block(15, 1, 2).id, 113);
+
+        //Wire in a loop turns off
+        world.set_block(0, 1, 0, Block::new_id(109));
+        world.set_block(0, 1, 1, Block::new_id(109));
+        for x in 1..16 {
+            assert_eq!(world.get_block(x, 1, 0).geometry, 0);
+            assert_eq!(world.get_block(x, 1, 1).geometry, 0);
+        }
+        assert_eq!(world.get_block(6, 1, 0).id, 119);
+
+        //Wire breaks without a block below it
+        world.set_block(3, 0, 0, Block::new());
+        assert_eq!(world.get_block(3, 1, 0).id, 0);
+    }
+
+    #[test]
+    fn test_logic_gates() {
+        let mut world = test_world();
+        //Repeater facing +x
+        world.set_block(0, 1, 0, Block::new_id(110));
+        world.set_block(1, 1, 0, Block::new_id_orientation(115, 1));
+        world.set_block(2, 1, 0, Block::new_id(119));
+        //Inverter facing +z without an input
+        world.set_block(0, 1, 4, Block::new_id_orientation(117, 2));
+        world.set_block(0, 1, 5, Block::new_id(119));
+        assert_eq!(world.get_block(2, 1, 0).id, 119);
+        run_tick(&mut world);
+        assert_eq!(world.get_block(1, 1, 0).id, 116);
+        assert_eq!(world.get_block(2, 1, 0).id, 120);
+        assert_eq!(world.get_block(0, 1, 5).id, 120);
+
+        //The repeater only powers the block in front of it
+        world.set_block(1, 1, 1, Block::new_id(119));
+        assert_eq!(world.get_block(1, 1, 1).id, 119);
+
+        world.set_block(0, 1, 3, Block::new_id(110));
+        run_tick(&mut world);
+        assert_eq!(world.get_block(0, 1, 4).id, 117);
+        assert_eq!(world.get_block(0, 1, 5).id, 119);
+    }
+
+    #[test]
+    fn test_button_and_door() {
+        let mut world = test_world();
+        world.set_block(4, 1, 4, Block::new_id(79));
+        world.set_block(4, 2, 4, Block::new_id(81));
+        world.set_block(3, 1, 4, Block::new_id(112));
+        assert_eq!(world.get_block(4, 1, 4).reflection(), 1);
+        assert_eq!(world.get_block(4, 2, 4).reflection(), 1);
+
+        for _ in 0..BUTTON_DELAY {
+            run_tick(&mut world);
+        }
+        assert_eq!(world.get_block(3, 1, 4).id, 111);
+        assert_eq!(world.get_block(4, 1, 4).reflection(), 0);
+        assert_eq!(world.get_block(4, 2, 4).reflection(), 0);
+
+        //Doors can still be opened by hand
+        let mut door = world.get_block(4, 2, 4);
+        door.set_reflection(1);
+        world.set_block(4, 2, 4, door);
+        world.set_block(5, 1, 4, Block::new_id(2));
+        assert_eq!(world.get_block(4, 2, 4).reflection(), 1);
+    }
+}
@@ -1,7 +1,7 @@
 use super::{get_chunktable_updates, UpdateList, World, CHUNK_SIZE_I32};
 use crate::{
     gfx::ChunkTables,
-    voxel::{Block, EMPTY_BLOCK},
+    voxel::{biome_registry::TreeKind, Block, EMPTY_BLOCK},
 };
 use std::collections::HashSet;
 
@@ -9,7 +9,7 @@ pub const RANDOM_UPDATE_INTERVAL: f32 = 0.25;
 
 //This function was technically created for growing wheat but can also be used
 //for other crops as well (such as cotton/flowers)
-fn grow_wheat(world: &World, x: i32, y: i32, z: i32, id: u8, to_update: &mut UpdateList) {
+fn grow_wheat(world: &World, x: i32, y: i32, z: i32, id: u16, to_update: &mut UpdateList) {
     let below = world.get_block(x, y - 1, z);
     let skip_dry_farmland = below.id == 45 && fastrand::i32(0..12) < 11;
     let skip_wet_farmland = below.id == 43 && fastrand::i32(0..10) < 7;
@@ -182,76 +182,52 @@ fn sapling_replaceable(block: Block) -> bool {
         || block.id == 91
 }
 
-fn sapling_place_leaves(
-    world: &World,
-    to_update: &mut UpdateList,
-    x: i32,
-    y: i32,
-    z: i32,
-    leaf_id: u8,
-) {
-    if world.get_block(x, y, z).id != EMPTY_BLOCK {
-        return;
-    }
-    to_update.insert((x, y, z), Block::new_id(leaf_id));
-}
-
-fn grow_leaves(
-    world: &World,
-    to_update: &mut UpdateList,
-    starty: i32,
-    xyz: (i32, i32, i32),
-    height: i32,
-    leaf_id: u8,
-) {
-    let (x, y, z) = xyz;
-    if y == starty + height {
-        sapling_place_leaves(world, to_update, x, y, z, leaf_id);
-        sapling_place_leaves(world, to_update, x - 1, y, z, leaf_id);
-        sapling_place_leaves(world, to_update, x + 1, y, z, leaf_id);
-        sapling_place_leaves(world, to_update, x, y, z - 1, leaf_id);
-        sapling_place_leaves(world, to_update, x, y, z + 1, leaf_id);
-    } else if y == starty + height - 1 {
-        for ix in (x - 1)..=(x + 1) {
-            for iz in (z - 1)..=(z + 1) {
-                sapling_place_leaves(world, to_update, ix, y, iz, leaf_id);
-            }
-        }
-    } else if y >= starty + height - 3 {
-        for ix in (x - 2)..=(x + 2) {
-            for iz in (z - 2)..=(z + 2) {
-                sapling_place_leaves(world, to_update, ix, y, iz, leaf_id);
-            }
-        }
-    }
+//Returns the corner of the 2x2 square of saplings that (x, y, z) is part of
+fn find_sapling_square(world: &World, x: i32, y: i32, z: i32, id: u16) -> Option<(i32, i32)> {
+    [(-1, -1), (-1, 0), (0, -1), (0, 0)]
+        .iter()
+        .map(|(dx, dz)| (x + dx, z + dz))
+        .find(|(cx, cz)| {
+            (0..2).all(|ix| (0..2).all(|iz| world.get_block(cx + ix, y, cz + iz).id == id))
+        })
 }
 
-fn grow_sapling(world: &World, x: i32, y: i32, z: i32, to_update: &mut UpdateList, leaf_id: u8) {
+fn grow_sapling(world: &World, x: i32, y: i32, z: i32, to_update: &mut UpdateList, id: u16) {
     if fastrand::i32(0..12) != 0 {
         return;
     }
 
-    let below = world.get_block(x, y - 1, z);
-    if below.id == EMPTY_BLOCK {
+    let square = find_sapling_square(world, x, y, z, id);
+    let mut rng = fastrand::Rng::new();
+    let Some(kind) = TreeKind::from_sapling(id, square.is_some(), &mut rng) else {
         return;
-    }
+    };
+    let template = kind.template();
+    let (x, z) = square.unwrap_or((x, z));
 
-    for vx in (x - 2)..=(x + 2) {
-        for vz in (z - 2)..=(z + 2) {
-            for vy in (y + 1)..=(y + 5) {
-                if !sapling_replaceable(world.get_block(vx, vy, vz)) {
-                    return;
-                }
+    for ix in x..(x + template.width) {
+        for iz in z..(z + template.width) {
+            if world.get_block(ix, y - 1, iz).id == EMPTY_BLOCK {
+                return;
             }
         }
     }
 
-    let height = fastrand::i32(4..=6);
-    for vy in y..(y + height + 1) {
-        grow_leaves(world, to_update, y, (x, vy, z), height, leaf_id);
+    let blocks = template.gen_blocks(x, y, z, &mut rng);
+    let blocked = blocks
+        .iter()
+        .any(|((ix, iy, iz), _)| !sapling_replaceable(world.get_block(*ix, *iy, *iz)));
+    if blocked {
+        return;
     }
-    for vy in y..(y + height) {
-        to_update.insert((x, vy, z), Block::new_id(8));
+
+    for (pos, block) in blocks {
+        let (ix, iy, iz) = pos;
+        //Leaves do not replace any blocks
+        if block.id == template.leaves && world.get_block(ix, iy, iz).id != EMPTY_BLOCK {
+            continue;
+        }
+        to_update.insert(pos, block);
     }
 }
 
@@ -319,7 +295,7 @@ impl World {
                     //Dry farmland
                     45 => update_dry_farmland(self, x, y, z, to_update),
                     //Sapling
-                    47 => grow_sapling(self, x, y, z, to_update, 7),
+                    47 => grow_sapling(self, x, y, z, to_update, 47),
                     //Growing wheat, cotton, flowers
                     50..=52 | 98..=101 | 103 | 105 | 107 => {
                         grow_wheat(self, x, y, z, block.id, to_update)
@@ -337,7 +313,7 @@ impl World {
                     //Seeds
                     77 => grow_wheat(self, x, y, z, 50 - 1, to_update),
                     //Snow sapling
-                    92 => grow_sapling(self, x, y, z, to_update, 91),
+                    92 => grow_sapling(self, x, y, z, to_update, 92),
                     _ => {}
                 }
             });
@@ -1,7 +1,7 @@
 use super::rand_block_update::RANDOM_UPDATE_INTERVAL;
 use crate::{
     game::GameMode,
-    voxel::{world::WorldGenType, Block, World, EMPTY_BLOCK},
+    voxel::{Block, World, EMPTY_BLOCK},
 };
 
 /*
@@ -14,7 +14,7 @@ fn simulate_sugarcane_growth(iterations: i32) -> String {
     eprintln!("SUGAR CANE GROWTH SIMULATION");
     let mut total = 0.0f32;
     for i in 0..iterations {
-        let mut world = World::new(0, 1, WorldGenType::Flat, GameMode::Creative);
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
         let mut total_time = 0.0;
         for x in 0..16 {
             world.set_block(x, 1, 0, Block::new_id(1));
@@ -51,7 +51,7 @@ fn simulate_cactus_growth(iterations: i32) -> String {
     eprintln!("CACTUS GROWTH SIMULATION");
     let mut total = 0.0f32;
     for i in 0..iterations {
-        let mut world = World::new(0, 1, WorldGenType::Flat, GameMode::Creative);
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
         let mut total_time = 0.0;
         for x in 0..9 {
             for z in 0..9 {
@@ -91,7 +91,7 @@ fn simulate_sapling_growth(iterations: i32) -> String {
     eprintln!("SAPLING GROWTH SIMULATION");
     let mut total = 0.0f32;
     for i in 0..iterations {
-        let mut world = World::new(0, 1, WorldGenType::Flat, GameMode::Creative);
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
         let mut total_time = 0.0;
         world.set_block(0, 2, 0, Block::new_id(47));
         world.set_block(0, 1, 0, Block::new_id(1));
@@ -118,7 +118,7 @@ fn simulate_snow_sapling_growth(iterations: i32) -> String {
     eprintln!("SNOW SAPLING GROWTH SIMULATION");
     let mut total = 0.0f32;
     for i in 0..iterations {
-        let mut world = World::new(0, 1, WorldGenType::Flat, GameMode::Creative);
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
         let mut total_time = 0.0;
         world.set_block(0, 2, 0, Block::new_id(92));
         world.set_block(0, 1, 0, Block::new_id(1));
@@ -144,14 +144,14 @@ fn simulate_snow_sapling_growth(iterations: i32) -> String {
 fn simulate_crop_growth(
     iterations: i32,
     crop_name: &str,
-    seed_id: u8,
-    crop_id: u8,
-    farmland_id: u8,
+    seed_id: u16,
+    crop_id: u16,
+    farmland_id: u16,
 ) -> String {
     eprintln!("{} GROWTH SIMULATION", crop_name.to_uppercase());
     let mut total = 0.0f32;
     for i in 0..iterations {
-        let mut world = World::new(0, 1, WorldGenType::Flat, GameMode::Creative);
+        let mut world = World::new(0, 1, "flat", "", GameMode::Creative).unwrap();
         let mut total_time = 0.0;
         for x in 0..4 {
             for z in 0..4 {
@@ -0,0 +1,297 @@
+/*
+ * World generators are implemented as a ChunkGenerator and are looked up by
+ * the name that is stored in the world metadata (gen_type), custom generators
+ * can be added with register_chunk_generator before a world is created or
+ * loaded and they will then show up in the create world menu.
+ * Generators can also take an options string (for example the layers of a
+ * flat world) which is saved in the world metadata (gen_options).
+ * */
+
+use super::{
+    default_world::DefaultGenerator,
+    flat_world::{FlatGenerator, DEFAULT_FLAT_PRESET},
+    heightmap_world::{HeightmapGenerator, DEFAULT_HEIGHTMAP_PRESET},
+    old_world::OldGenerator,
+    skyblock_world::SkyblockGenerator,
+    terrain_settings::{TerrainSettings, DEFAULT_TERRAIN_PRESET},
+    World, WorldGenerator,
+};
+use crate::voxel::Chunk;
+use crossbeam::thread;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+//Generator used for worlds that do not have a generator
+pub const DEFAULT_GENERATOR: &str = "default2";
+
+pub trait ChunkGenerator: Send + Sync {
+    //Generates the chunks in the column (x, z), every chunk passed in is
+    //empty and is located in that column
+    fn generate_column(
+        &self,
+        x: i32,
+        z: i32,
+        chunks: &mut [Chunk],
+        world_generator: &WorldGenerator,
+    );
+
+    //Returns the y coordinate of the bottom of the world,
+    //blocks can not be placed below this
+    fn bottom(&self) -> i32 {
+        -128
+    }
+
+    //Noise settings for a newly created world, after the world is created
+    //the settings are loaded from the world metadata instead
+    fn terrain_settings(&self) -> TerrainSettings {
+        TerrainSettings::default()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct GeneratorInfo {
+    //Stored in the world metadata
+    pub name: &'static str,
+    //Shown in the create world menu
+    pub display_name: &'static str,
+    //Label for the options in the create world menu, if this is empty
+    //then the generator does not take any options
+    pub options_label: &'static str,
+    pub default_options: &'static str,
+    //Creates the generator from an options string
+    pub create: fn(&str) -> Result<Box<dyn ChunkGenerator>, String>,
+}
+
+const BUILTIN_GENERATORS: [GeneratorInfo; 5] = [
+    GeneratorInfo {
+        name: DEFAULT_GENERATOR,
+        display_name: "Default",
+        options_label: "Terrain",
+        default_options: DEFAULT_TERRAIN_PRESET,
+        create: |options| Ok(Box::new(DefaultGenerator::from_preset(options)?)),
+    },
+    GeneratorInfo {
+        name: "old",
+        display_name: "Old",
+        options_label: "",
+        default_options: "",
+        create: |_| Ok(Box::new(OldGenerator)),
+    },
+    GeneratorInfo {
+        name: "skyblock",
+        display_name: "Skyblock",
+        options_label: "",
+        default_options: "",
+        create: |_| Ok(Box::new(SkyblockGenerator)),
+    },
+    GeneratorInfo {
+        name: "flat",
+        display_name: "Flat",
+        options_label: "Layers",
+        default_options: DEFAULT_FLAT_PRESET,
+        create: |options| Ok(Box::new(FlatGenerator::from_preset(options)?)),
+    },
+    GeneratorInfo {
+        name: "heightmap",
+        display_name: "Heightmap",
+        options_label: "Options",
+        default_options: DEFAULT_HEIGHTMAP_PRESET,
+        create: |options| Ok(Box::new(HeightmapGenerator::from_preset(options)?)),
+    },
+];
+
+static CUSTOM_GENERATORS: Mutex<Vec<GeneratorInfo>> = Mutex::new(Vec::new());
+
+//Returns every registered generator, the built in generators come first
+pub fn get_generators() -> Vec<GeneratorInfo> {
+    let mut generators = BUILTIN_GENERATORS.to_vec();
+    if let Ok(custom) = CUSTOM_GENERATORS.lock() {
+        generators.extend(custom.iter().copied());
+    }
+    generators
+}
+
+pub fn get_generator_info(name: &str) -> Option<GeneratorInfo> {
+    get_generators().into_iter().find(|info| info.name == name)
+}
+
+//Fails if a generator with the same name already exists
+pub fn register_chunk_generator(info: GeneratorInfo) -> Result<(), String> {
+    if get_generator_info(info.name).is_some() {
+        return Err(format!("generator '{}' already exists", info.name));
+    }
+
+    CUSTOM_GENERATORS
+        .lock()
+        .map_err(|_| "failed to lock generator registry".to_string())?
+        .push(info);
+    Ok(())
+}
+
+//Returns the name and options of the generator that was created, this is
+//the default generator if `name` is empty.
+//Returns an error if the generator does not exist or the options are
+//invalid, they are not replaced with the defaults since they would then be
+//saved over the original generator and options
+pub fn create_chunk_generator(
+    name: &str,
+    options: &str,
+) -> Result<(String, String, Box<dyn ChunkGenerator>), String> {
+    let info = if name.is_empty() {
+        get_generator_info(DEFAULT_GENERATOR).expect("default generator should always exist")
+    } else {
+        get_generator_info(name).ok_or(format!("unknown world generator '{name}'"))?
+    };
+
+    //Worlds created before generators had options use the defaults
+    let options = if options.is_empty() || info.name != name {
+        info.default_options
+    } else {
+        options
+    };
+
+    let generator = (info.create)(options)
+        .map_err(|msg| format!("invalid options for world generator '{}': {msg}", info.name))?;
+    Ok((info.name.to_string(), options.to_string(), generator))
+}
+
+//Runs gen_chunk on every chunk in a separate thread
+pub fn gen_chunks_parallel<F>(chunks: &mut [Chunk], gen_chunk: F)
+where
+    F: Fn(&mut Chunk) + Sync,
+{
+    let gen_chunk = &gen_chunk;
+    thread::scope(|s| {
+        for chunk in chunks.iter_mut() {
+            s.spawn(move |_| gen_chunk(chunk));
+        }
+    })
+    .expect("Failed to generate new chunks!");
+}
+
+impl World {
+    //Generates new chunks at the positions and adds them to the world,
+    //positions that already have a chunk are skipped, returns the number
+    //of chunks that were generated
+    pub fn generate_chunks(&mut self, positions: &HashSet<(i32, i32, i32)>) -> usize {
+        let mut columns: HashMap<(i32, i32), Vec<Chunk>> = HashMap::new();
+        for (x, y, z) in positions.iter().copied() {
+            if self.chunks.contains_key(&(x, y, z)) {
+                continue;
+            }
+            columns.entry((x, z)).or_default().push(Chunk::new(x, y, z));
+        }
+
+        let mut generated_count = 0;
+        for ((x, z), mut chunks) in columns {
+            self.generator
+                .generate_column(x, z, &mut chunks, &self.world_generator);
+            generated_count += chunks.len();
+            for chunk in chunks {
+                let chunkpos = chunk.get_chunk_pos();
+                self.chunks
+                    .insert((chunkpos.x, chunkpos.y, chunkpos.z), chunk);
+            }
+        }
+        generated_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::GameMode,
+        impfile::Entry,
+        voxel::{init_test_registries, Block, CHUNK_SIZE_I32},
+    };
+
+    struct StoneGenerator;
+
+    impl ChunkGenerator for StoneGenerator {
+        fn generate_column(&self, _: i32, _: i32, chunks: &mut [Chunk], _: &WorldGenerator) {
+            gen_chunks_parallel(chunks, |chunk| {
+                let pos = chunk.get_chunk_pos();
+                if pos.y < 0 {
+                    let (x, y, z) = (pos.x, pos.y, pos.z);
+                    let sz = CHUNK_SIZE_I32;
+                    chunk.set_block(x * sz, y * sz, z * sz, Block::new_id(2));
+                }
+            });
+        }
+
+        fn bottom(&self) -> i32 {
+            -16
+        }
+    }
+
+    #[test]
+    fn test_custom_generator() {
+        init_test_registries();
+        let info = GeneratorInfo {
+            name: "test_stone",
+            display_name: "Stone",
+            options_label: "",
+            default_options: "",
+            create: |_| Ok(Box::new(StoneGenerator)),
+        };
+        register_chunk_generator(info).unwrap();
+        assert!(register_chunk_generator(info).is_err());
+        assert!(get_generators()
+            .iter()
+            .any(|info| info.name == "test_stone"));
+
+        let mut world = World::new(0, 1, "test_stone", "", GameMode::Creative).unwrap();
+        world.generate_world();
+        assert_eq!(world.gen_type, "test_stone");
+        assert_eq!(world.bottom(), -16);
+        assert_eq!(world.chunks.len(), 27);
+        assert_eq!(world.get_block(0, -16, 0).id, 2);
+        assert_eq!(world.get_block(0, 0, 0).id, 0);
+
+        let msg = World::new(0, 1, "does_not_exist", "", GameMode::Creative)
+            .err()
+            .unwrap();
+        assert_eq!(msg, "unknown world generator 'does_not_exist'");
+        let world = World::new(0, 1, "", "", GameMode::Creative).unwrap();
+        assert_eq!(world.gen_type, DEFAULT_GENERATOR);
+    }
+
+    #[test]
+    fn test_invalid_generator_options() {
+        init_test_registries();
+        let msg = World::new(0, 1, "flat", "3*not_a_block", GameMode::Creative)
+            .err()
+            .unwrap();
+        assert!(msg.contains("invalid options for world generator 'flat'"));
+
+        //Saved worlds with invalid options are not loaded with the defaults
+        let world_path = std::env::temp_dir()
+            .join(format!(
+                "voxelworld_test_gen_options_{}/",
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .to_string();
+        std::fs::create_dir_all(&world_path).unwrap();
+        let mut entry = Entry::new("world");
+        entry.add_string("gen_type", "flat");
+        entry.add_string("gen_options", "3*not_a_block");
+        std::fs::write(
+            world_path.clone() + "world.impfile",
+            entry.to_impfile_string(),
+        )
+        .unwrap();
+        assert!(World::load_world_metadata(&world_path, 1).is_err());
+        //Or with the default generator if their generator does not exist
+        let mut entry = Entry::new("world");
+        entry.add_string("gen_type", "does_not_exist");
+        std::fs::write(
+            world_path.clone() + "world.impfile",
+            entry.to_impfile_string(),
+        )
+        .unwrap();
+        assert!(World::load_world_metadata(&world_path, 1).is_err());
+        std::fs::remove_dir_all(&world_path).unwrap();
+    }
+}
@@ -2,29 +2,36 @@
  * Default world generation
  * */
 
+mod carvers;
 mod gen_trees;
 mod ore;
 mod plants;
+mod structures;
 mod terrain;
 
 use self::{
+    carvers::{CarverCache, Carvers},
     gen_trees::get_tree_gen_info,
-    ore::{generate_aqua_ore, generate_clay, generate_magma_blocks, generate_ore},
+    ore::{generate_ores, OreTable},
     plants::{generate_plants, generate_sugarcane, get_plant_positions, get_water_adjacent},
-    terrain::{get_height, get_mountain, get_temperature},
+    structures::ColumnStructures,
+    terrain::{get_height, get_mountain, get_temperature, get_water_body, WaterBody},
 };
-use crossbeam::{queue::ArrayQueue, thread};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
-use super::{World, WorldGenerator};
+use super::{
+    chunk_generator::{gen_chunks_parallel, ChunkGenerator},
+    terrain_settings::TerrainSettings,
+    WorldGenerator,
+};
+use crate::voxel::biome_registry::{get_biome_registry, BiomeDef, BiomeRegistry, ColumnInfo};
+use crate::voxel::block_registry::get_block_registry;
 use crate::voxel::CHUNK_SIZE_I32;
 use crate::voxel::{Block, Chunk, EMPTY_BLOCK, INDESTRUCTIBLE};
 use gen_trees::generate_trees;
-use terrain::{add_to_heightmap, generate_heightmap, is_noise_cave, HeightMap};
+pub use ore::{check_ore_gen, ORE_GEN_PATH};
+use terrain::{add_to_heightmap, is_noise_cave, HeightMap};
 
-const SEA_LEVEL: i32 = 0;
-const SAND_LEVEL: i32 = SEA_LEVEL + 1;
-const LAVA_LEVEL: i32 = -50;
 const BOTTOM_OF_WORLD: i32 = -64;
 
 struct GenInfoTable {
@@ -32,9 +39,10 @@ struct GenInfoTable {
     mountain: HeightMap,
     temperature: HeightMap,
     tree_positions: HashMap<(i32, i32), Vec<(i32, i32)>>,
-    tree_heights: HashMap<(i32, i32), Vec<i32>>,
     plant_positions: HashMap<(i32, i32), Vec<(i32, i32)>>,
     sugarcane_positions: HashMap<(i32, i32), Vec<(i32, i32)>>,
+    carvers: HashMap<(i32, i32), Carvers>,
+    structures: HashMap<(i32, i32), ColumnStructures>,
 }
 
 struct GenInfo<'a> {
@@ -42,9 +50,10 @@ struct GenInfo<'a> {
     mountain: &'a [i32],
     temperature: &'a [i32],
     tree_positions: &'a [(i32, i32)],
-    tree_heights: &'a [i32],
     plant_positions: &'a [(i32, i32)],
     sugarcane_positions: &'a [(i32, i32)],
+    carvers: &'a Carvers,
+    structures: &'a ColumnStructures,
 }
 
 impl GenInfoTable {
@@ -54,22 +63,13 @@ impl GenInfoTable {
             mountain: HeightMap::new(),
             temperature: HeightMap::new(),
             tree_positions: HashMap::new(),
-            tree_heights: HashMap::new(),
             plant_positions: HashMap::new(),
             sugarcane_positions: HashMap::new(),
+            carvers: HashMap::new(),
+            structures: HashMap::new(),
         }
     }
 
-    fn generate_heights(
-        &mut self,
-        positions: &Vec<(i32, i32, i32)>,
-        world_generator: &WorldGenerator,
-    ) {
-        self.heightmap = generate_heightmap(positions, world_generator, get_height);
-        self.mountain = generate_heightmap(positions, world_generator, get_mountain);
-        self.temperature = generate_heightmap(positions, world_generator, get_temperature);
-    }
-
     fn add_heights(&mut self, x: i32, z: i32, world_generator: &WorldGenerator) {
         add_to_heightmap(x, z, &mut self.heightmap, world_generator, get_height);
         add_to_heightmap(x, z, &mut self.mountain, world_generator, get_mountain);
@@ -82,45 +82,13 @@ impl GenInfoTable {
         );
     }
 
-    fn generate_trees(
-        &mut self,
-        positions: &Vec<(i32, i32, i32)>,
-        world_generator: &WorldGenerator,
-    ) {
-        for (chunkx, _, chunkz) in positions {
-            let pos = (*chunkx, *chunkz);
-            if self.tree_positions.contains_key(&pos) {
-                continue;
-            }
-            let (tree_pos, tree_h) = get_tree_gen_info(*chunkx, *chunkz, world_generator);
-            self.tree_positions.insert(pos, tree_pos);
-            self.tree_heights.insert(pos, tree_h);
-        }
-    }
-
     fn add_trees(&mut self, x: i32, z: i32, world_generator: &WorldGenerator) {
         if self.tree_positions.contains_key(&(x, z)) {
             return;
         }
 
-        let (tree_pos, tree_h) = get_tree_gen_info(x, z, world_generator);
+        let tree_pos = get_tree_gen_info(x, z, world_generator);
         self.tree_positions.insert((x, z), tree_pos);
-        self.tree_heights.insert((x, z), tree_h);
-    }
-
-    fn generate_plants(
-        &mut self,
-        positions: &Vec<(i32, i32, i32)>,
-        world_generator: &WorldGenerator,
-    ) {
-        for (chunkx, _, chunkz) in positions {
-            let pos = (*chunkx, *chunkz);
-            if self.plant_positions.contains_key(&pos) {
-                continue;
-            }
-            let plants = get_plant_positions(*chunkx, *chunkz, world_generator.world_seed);
-            self.plant_positions.insert(pos, plants);
-        }
     }
 
     fn add_plants(&mut self, x: i32, z: i32, world_generator: &WorldGenerator) {
@@ -131,50 +99,65 @@ impl GenInfoTable {
         self.plant_positions.insert((x, z), plants);
     }
 
-    //Assumes that heightmap has already been generated
-    fn generate_sugarcane(&mut self, positions: &Vec<(i32, i32, i32)>) {
-        for (chunkx, _, chunkz) in positions {
-            let pos = (*chunkx, *chunkz);
-            if self.sugarcane_positions.contains_key(&pos) {
-                continue;
-            }
-            let heights = self.heightmap.get(&pos);
-            if let Some(heights) = heights {
-                let water_adjacent = get_water_adjacent(*chunkx, *chunkz, heights);
-                self.sugarcane_positions.insert(pos, water_adjacent);
-            }
-        }
-    }
-
     //Assumes that (x, z) has been generated
-    fn add_sugarcane(&mut self, x: i32, z: i32) {
+    fn add_sugarcane(&mut self, x: i32, z: i32, sea_level: i32) {
         if self.sugarcane_positions.contains_key(&(x, z)) {
             return;
         }
         let heights = self.heightmap.get(&(x, z));
         if let Some(heights) = heights {
-            let water_adjacent = get_water_adjacent(x, z, heights);
+            let water_adjacent = get_water_adjacent(x, z, heights, sea_level);
             self.sugarcane_positions.insert((x, z), water_adjacent);
         }
     }
 
+    fn add_carvers(
+        &mut self,
+        x: i32,
+        z: i32,
+        world_generator: &WorldGenerator,
+        cache: &CarverCache,
+    ) {
+        if self.carvers.contains_key(&(x, z)) {
+            return;
+        }
+        let carvers = Carvers::for_column(x, z, world_generator, cache);
+        self.carvers.insert((x, z), carvers);
+    }
+
+    fn add_structures(
+        &mut self,
+        x: i32,
+        z: i32,
+        world_generator: &WorldGenerator,
+        biomes: &BiomeRegistry,
+    ) {
+        if self.structures.contains_key(&(x, z)) {
+            return;
+        }
+        let structures = ColumnStructures::for_column(x, z, world_generator, biomes);
+        self.structures.insert((x, z), structures);
+    }
+
     fn get(&self, x: i32, z: i32) -> Option<GenInfo<'_>> {
         let h = self.heightmap.get(&(x, z))?;
         let mountain_h = self.mountain.get(&(x, z))?;
         let temp = self.temperature.get(&(x, z))?;
         let trees = self.tree_positions.get(&(x, z))?;
-        let tree_h = self.tree_heights.get(&(x, z))?;
         let plants = self.plant_positions.get(&(x, z))?;
         let sugarcane = self.sugarcane_positions.get(&(x, z))?;
+        let carvers = self.carvers.get(&(x, z))?;
+        let structures = self.structures.get(&(x, z))?;
 
         Some(GenInfo {
             heights: h,
             mountain: mountain_h,
             temperature: temp,
             tree_positions: trees,
-            tree_heights: tree_h,
             plant_positions: plants,
             sugarcane_positions: sugarcane,
+            carvers,
+            structures,
         })
     }
 }
@@ -183,70 +166,103 @@ pub fn is_mountain(mountain_h: i32, terrain_h: i32) -> bool {
     mountain_h - 8 > terrain_h && mountain_h > 10 + terrain_h.max(-16).abs() / 4
 }
 
-fn get_surface_block(temperature: i32, mountain_h: i32, terrain_h: i32) -> Block {
-    if is_mountain(mountain_h, terrain_h) {
-        if mountain_h < (terrain_h + 42).min(50.max(terrain_h + 16)) || mountain_h < 32 {
-            //Stone at lower heights
-            return Block::new_id(2);
-        } else {
-            //Snow
-            return Block::new_id(86);
+fn get_surface_block(biome: &BiomeDef, mountain_h: i32, terrain_h: i32) -> Block {
+    if let Some(peak) = biome.peak {
+        if !(mountain_h < (terrain_h + 42).min(50.max(terrain_h + 16)) || mountain_h < 32) {
+            //Above the snow line
+            return peak;
         }
     }
 
-    let h = terrain_h.max(mountain_h);
-    if temperature > 75 {
-        //Desert, sand
-        Block::new_id(11)
-    } else if temperature < 25 {
-        if h < SEA_LEVEL {
-            return Block::new_id(4);
-        }
-        //Cold, snowy grass
-        Block::new_id(87)
-    } else {
-        if h < SEA_LEVEL {
-            return Block::new_id(4);
-        }
-        Block::new_id(1)
-    }
+    biome.surface
 }
 
-//Blocks such as dirt right beneath the surface layer
-fn get_under_block(temperature: i32, mountain_h: i32, terrain_h: i32) -> Block {
-    if is_mountain(mountain_h, terrain_h) {
-        //Stone
-        return Block::new_id(2);
-    }
+pub fn get_column_biome<'a>(
+    x: i32,
+    z: i32,
+    temperature: i32,
+    (terrain_h, mountain_h): (i32, i32),
+    world_generator: &WorldGenerator,
+    biomes: &'a BiomeRegistry,
+) -> &'a BiomeDef {
+    let sea_level = world_generator.settings.sea_level;
+    let height = terrain_h.max(mountain_h);
+    let water = get_water_body(x, z, height, world_generator);
+    let column = ColumnInfo {
+        temperature,
+        height: height - sea_level,
+        mountain: is_mountain(mountain_h, terrain_h),
+        beach: is_beach(temperature, height, sea_level),
+        river: water == Some(WaterBody::River),
+        lake: water == Some(WaterBody::Lake),
+    };
+    biomes.select(&column)
+}
 
-    if temperature > 75 {
-        //Desert, sand
-        Block::new_id(11)
-    } else {
-        //Dirt
-        Block::new_id(4)
-    }
+//Calculates the biome at (x, z) from the noise
+pub fn get_biome_at<'a>(
+    x: i32,
+    z: i32,
+    world_generator: &WorldGenerator,
+    biomes: &'a BiomeRegistry,
+) -> &'a BiomeDef {
+    let terrain_h = get_height(x, z, world_generator);
+    let mountain_h = get_mountain(x, z, world_generator);
+    let temperature = get_temperature(x, z, world_generator);
+    let heights = (terrain_h, mountain_h);
+    get_column_biome(x, z, temperature, heights, world_generator, biomes)
+}
+
+//Random number generator that only depends on the seed and the position of
+//a chunk column (or region of chunks), this can be used to generate features
+//that span multiple chunks
+fn column_rng(x: i32, z: i32, seed: u32) -> fastrand::Rng {
+    let x = (x as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let z = (z as u32 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    fastrand::Rng::with_seed(x ^ z.rotate_left(32) ^ seed as u64)
 }
 
-fn out_of_bounds(y: i32) -> bool {
-    !(-4..=5).contains(&y)
+fn out_of_bounds(y: i32, settings: &TerrainSettings) -> bool {
+    let max_chunk_y = settings.max_height.div_euclid(CHUNK_SIZE_I32);
+    !(BOTTOM_OF_WORLD.div_euclid(CHUNK_SIZE_I32)..=max_chunk_y).contains(&y)
 }
 
-pub fn is_beach(temperature: i32, height: i32) -> bool {
+pub fn is_beach(temperature: i32, height: i32, sea_level: i32) -> bool {
     ((12..18).contains(&temperature)
         || (30..45).contains(&temperature)
         || (50..55).contains(&temperature)
         || temperature > 65)
-        && height <= SAND_LEVEL
+        && height <= sea_level + 1
 }
 
-fn gen_chunk(chunk: &mut Chunk, gen_info: GenInfo, world_generator: &WorldGenerator) {
+fn gen_chunk(
+    chunk: &mut Chunk,
+    gen_info: GenInfo,
+    world_generator: &WorldGenerator,
+    biomes: &BiomeRegistry,
+    ores: &OreTable,
+) {
     let chunkpos = chunk.get_chunk_pos();
     let posx = chunkpos.x * CHUNK_SIZE_I32;
     let posy = chunkpos.y * CHUNK_SIZE_I32;
     let posz = chunkpos.z * CHUNK_SIZE_I32;
 
-    if out_of_bounds(chunkpos.y) {
+    let settings = &world_generator.settings;
+    let sea_level = settings.sea_level;
+    let column_biomes: Vec<&BiomeDef> = (0..gen_info.heights.len())
+        .map(|i| {
+            let x = posx + i as i32 % CHUNK_SIZE_I32;
+            let z = posz + i as i32 / CHUNK_SIZE_I32;
+            let temperature = gen_info.temperature[i];
+            let heights = (gen_info.heights[i], gen_info.mountain[i]);
+            get_column_biome(x, z, temperature, heights, world_generator, biomes)
+        })
+        .collect();
+    //Empty chunks also store the biome so that it can be looked up anywhere
+    chunk.set_biomes(column_biomes.iter().map(|biome| biome.id).collect());
+    if out_of_bounds(chunkpos.y, settings) {
+        //Structures can still extend above the terrain
+        gen_info.structures.generate(chunk);
         return;
     }
 
@@ -262,13 +278,14 @@ fn gen_chunk(chunk: &mut Chunk, gen_info: GenInfo, world_generator: &WorldGenera
             let terrain_h = gen_info.heights[index];
             let mountain_h = gen_info.mountain[index];
             let height = terrain_h.max(mountain_h);
-            let h = (height + 1).max(SEA_LEVEL + 1);
+            let h = (height + 1).max(sea_level + 1);
 
             if (h + 1).max(mountain_h + 1) < posy {
                 continue;
             }
 
             let temperature = gen_info.temperature[index];
+            let biome = column_biomes[index];
 
             for y in posy..(posy + CHUNK_SIZE_I32).min((h + 1).max(mountain_h + 1)) {
                 let indestructible = (y == BOTTOM_OF_WORLD)
@@ -280,47 +297,45 @@ fn gen_chunk(chunk: &mut Chunk, gen_info: GenInfo, world_generator: &WorldGenera
                     continue;
                 }
 
-                if y > BOTTOM_OF_WORLD && y <= LAVA_LEVEL {
+                if y > BOTTOM_OF_WORLD && y <= settings.lava_level {
                     chunk.set_block(x, y, z, Block::new_fluid(13));
                 }
 
-                if y <= SEA_LEVEL && y > height {
+                if y <= sea_level && y > height {
                     chunk.set_block(x, y, z, Block::new_fluid(12));
-                    if y == SEA_LEVEL && temperature < 25 {
+                    if y == sea_level && biome.snow {
                         //Ice on top of water if we are in a cold biome
                         chunk.set_block(x, y, z, Block::new_id(85));
                     }
                 }
 
                 //Sand
-                if y > height - 4 && y <= height && height <= SEA_LEVEL - 3 {
+                if y > height - 4 && y <= height && height <= sea_level - 3 {
                     chunk.set_block(x, y, z, Block::new_id(11));
                     continue;
                 }
 
                 //Beaches
-                if is_beach(temperature, height) && y > height - 4 && y <= height {
+                if is_beach(temperature, height, sea_level) && y > height - 4 && y <= height {
                     chunk.set_block(x, y, z, Block::new_id(11));
                     continue;
                 }
 
                 //Generate noise caves
-                if is_noise_cave(x, y, z, &world_generator.noise_cave_generator) {
+                if is_noise_cave(x, y, z, world_generator) {
                     continue;
                 }
 
-                if y == height + 1 && temperature < 25 && chunk.get_block(x, y, z).id == EMPTY_BLOCK
-                {
+                if y == height + 1 && biome.snow && chunk.get_block(x, y, z).id == EMPTY_BLOCK {
                     //Snow slabs in cold biomes
                     let mut snow_slab = Block::new_id(86);
                     snow_slab.set_shape(1);
                     chunk.set_block(x, y, z, snow_slab);
                 } else if y == height {
-                    let surface_block = get_surface_block(temperature, mountain_h, terrain_h);
+                    let surface_block = get_surface_block(biome, mountain_h, terrain_h);
                     chunk.set_block(x, y, z, surface_block);
                 } else if y > height - 4 && y < height {
-                    let under_block = get_under_block(temperature, mountain_h, terrain_h);
-                    chunk.set_block(x, y, z, under_block);
+                    chunk.set_block(x, y, z, biome.under);
                 } else if y < height && y > -64 {
                     //Stone
                     chunk.set_block(x, y, z, Block::new_id(2));
@@ -329,12 +344,24 @@ fn gen_chunk(chunk: &mut Chunk, gen_info: GenInfo, world_generator: &WorldGenera
         }
     }
 
+    //Carve tunnels, ravines and lava lakes before ore is generated so that
+    //ore does not fill in the carved out space
+    let column_heights: Vec<i32> = gen_info
+        .heights
+        .iter()
+        .zip(gen_info.mountain)
+        .map(|(terrain_h, mountain_h)| *terrain_h.max(mountain_h))
+        .collect();
+    gen_info
+        .carvers
+        .carve_chunk(chunk, &column_heights, world_generator);
+
     //Generate ore
     for x in posx..(posx + CHUNK_SIZE_I32) {
         for z in posz..(posz + CHUNK_SIZE_I32) {
             let index = ((z - posz) * CHUNK_SIZE_I32 + (x - posx)) as usize;
             let terrain_h = gen_info.heights[index];
-            let mountain_h = (world_generator.get_mountain(x, z) * 80.0) as i32;
+            let mountain_h = get_mountain(x, z, world_generator);
             let height = terrain_h.max(mountain_h);
 
             if height < posy {
@@ -343,12 +370,7 @@ fn gen_chunk(chunk: &mut Chunk, gen_info: GenInfo, world_generator: &WorldGenera
 
             let h = height + 1;
             for y in posy..(posy + CHUNK_SIZE_I32).min(h) {
-                generate_ore(chunk, x, y, z, &mut ore_rng);
-                generate_magma_blocks(chunk, x, y, z, &mut ore_rng);
-                if y == terrain_h || y == terrain_h - 1 {
-                    generate_clay(chunk, x, y, z, &mut rng);
-                    generate_aqua_ore(chunk, x, y, z, &mut rng);
-                }
+                generate_ores(chunk, (x, y, z), &mut ore_rng, ores, settings);
             }
         }
     }
@@ -357,171 +379,205 @@ fn gen_chunk(chunk: &mut Chunk, gen_info: GenInfo, world_generator: &WorldGenera
     generate_trees(
         chunk,
         gen_info.tree_positions,
-        gen_info.tree_heights,
+        gen_info.carvers,
         world_generator,
+        biomes,
     );
     //Generate plants
-    generate_plants(chunk, gen_info.plant_positions, &mut rng, world_generator);
+    generate_plants(
+        chunk,
+        gen_info.plant_positions,
+        &mut rng,
+        gen_info.carvers,
+        world_generator,
+        biomes,
+    );
     //Generate sugar cane
-    generate_sugarcane(chunk, gen_info.sugarcane_positions, &mut rng);
+    generate_sugarcane(
+        chunk,
+        gen_info.sugarcane_positions,
+        &mut rng,
+        gen_info.carvers,
+        sea_level,
+    );
+    //Generate structures
+    gen_info.structures.generate(chunk);
 }
 
-impl World {
-    //Generates a world
-    pub fn gen_default(&mut self) {
-        //Generate height map
-        let positions = self.chunks.keys().copied().collect();
-        let mut gen_info_table = GenInfoTable::new();
-        gen_info_table.generate_heights(&positions, &self.world_generator);
-        gen_info_table.generate_trees(&positions, &self.world_generator);
-        gen_info_table.generate_plants(&positions, &self.world_generator);
-        gen_info_table.generate_sugarcane(&positions);
-
-        for chunk in &mut self.chunks.values_mut() {
-            let pos = chunk.get_chunk_pos();
-            if let Some(gen_info) = gen_info_table.get(pos.x, pos.z) {
-                gen_chunk(chunk, gen_info, &self.world_generator);
-            }
-        }
+pub struct DefaultGenerator {
+    settings: TerrainSettings,
+    biomes: &'static BiomeRegistry,
+    ores: OreTable,
+    carvers: CarverCache,
+}
+
+impl DefaultGenerator {
+    pub fn from_preset(preset: &str) -> Result<Self, String> {
+        let blocks = get_block_registry().ok_or("blocks have not been loaded")?;
+        let biomes = get_biome_registry().ok_or("biomes have not been loaded")?;
+        Ok(Self {
+            settings: TerrainSettings::load_preset(preset)?,
+            biomes,
+            ores: OreTable::load(ORE_GEN_PATH, blocks, biomes)?,
+            carvers: CarverCache::default(),
+        })
     }
+}
 
-    //Generates any missing chunks on load
-    pub fn gen_default_on_load(&mut self) {
-        let mut to_generate = HashSet::new();
-        for y in (self.centery - self.range)..=(self.centery + self.range) {
-            for z in (self.centerz - self.range)..=(self.centerz + self.range) {
-                for x in (self.centerx - self.range)..=(self.centerx + self.range) {
-                    if self.chunks.contains_key(&(x, y, z)) {
-                        continue;
-                    }
-                    to_generate.insert((x, y, z));
-                }
+impl ChunkGenerator for DefaultGenerator {
+    fn generate_column(
+        &self,
+        x: i32,
+        z: i32,
+        chunks: &mut [Chunk],
+        world_generator: &WorldGenerator,
+    ) {
+        //Generate chunk info (this should only contain one element)
+        let mut gen_info_table = GenInfoTable::new();
+        gen_info_table.add_heights(x, z, world_generator);
+        gen_info_table.add_trees(x, z, world_generator);
+        gen_info_table.add_plants(x, z, world_generator);
+        gen_info_table.add_sugarcane(x, z, world_generator.settings.sea_level);
+        gen_info_table.add_carvers(x, z, world_generator, &self.carvers);
+        gen_info_table.add_structures(x, z, world_generator, self.biomes);
+
+        gen_chunks_parallel(chunks, |chunk| {
+            //Should always evaluate to true
+            if let Some(gen_info) = gen_info_table.get(x, z) {
+                gen_chunk(chunk, gen_info, world_generator, self.biomes, &self.ores);
             }
-        }
+        });
+    }
 
-        if to_generate.is_empty() {
-            return;
-        }
+    fn terrain_settings(&self) -> TerrainSettings {
+        self.settings.clone()
+    }
+}
 
-        //Generate new chunks
-        let mut gen_info_table = GenInfoTable::new();
-        let start = std::time::Instant::now();
-        for (chunkx, chunky, chunkz) in &to_generate {
-            let pos = (*chunkx, *chunky, *chunkz);
-            if self.chunks.contains_key(&pos) {
-                continue;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::GameMode,
+        voxel::{init_test_registries, World},
+    };
+    use std::collections::HashSet;
+    use terrain::get_height_mountain;
+
+    #[test]
+    fn test_rivers_across_columns() {
+        init_test_registries();
+
+        let mut world = World::new(0, 1, "default2", "default", GameMode::Creative).unwrap();
+        let world_generator = &world.world_generator;
+        let (x, z) = (-4096..4096)
+            .step_by(4)
+            .map(|x| (x, 0))
+            .find(|(x, z)| {
+                let h = get_height_mountain(*x, *z, world_generator);
+                get_water_body(*x, *z, h, world_generator) == Some(WaterBody::River)
+            })
+            .expect("there should be a river");
+        let (chunkx, chunkz) = (x.div_euclid(CHUNK_SIZE_I32), z.div_euclid(CHUNK_SIZE_I32));
+
+        let mut positions = HashSet::new();
+        for cx in (chunkx - 1)..=(chunkx + 1) {
+            for cz in (chunkz - 1)..=(chunkz + 1) {
+                positions.insert((cx, -1, cz));
+                positions.insert((cx, 0, cz));
             }
-            if *chunky < -4 || *chunky > 4 {
-                continue;
-            }
-            gen_info_table.add_heights(*chunkx, *chunkz, &self.world_generator);
-            gen_info_table.add_trees(*chunkx, *chunkz, &self.world_generator);
-            gen_info_table.add_plants(*chunkx, *chunkz, &self.world_generator);
-            gen_info_table.add_sugarcane(*chunkx, *chunkz);
         }
-        eprintln!(
-            "Took {} ms to generate chunk info",
-            start.elapsed().as_millis()
-        );
+        world.generate_chunks(&positions);
+
+        //Generating the columns one at a time in a different order should
+        //not change the terrain
+        let mut other = World::new(0, 1, "default2", "default", GameMode::Creative).unwrap();
+        let mut sorted: Vec<(i32, i32, i32)> = positions.iter().copied().collect();
+        sorted.sort();
+        for pos in sorted.into_iter().rev() {
+            other.generate_chunks(&HashSet::from([pos]));
+        }
 
-        let start = std::time::Instant::now();
-        let generated = ArrayQueue::new(to_generate.len());
-        let mut generated_count = 0;
-        thread::scope(|s| {
-            for (chunkx, chunky, chunkz) in &to_generate {
-                if self.chunks.contains_key(&(*chunkx, *chunky, *chunkz)) {
-                    continue;
+        let minx = (chunkx - 1) * CHUNK_SIZE_I32;
+        let minz = (chunkz - 1) * CHUNK_SIZE_I32;
+        for bx in minx..(minx + CHUNK_SIZE_I32 * 3) {
+            for bz in minz..(minz + CHUNK_SIZE_I32 * 3) {
+                for by in -CHUNK_SIZE_I32..CHUNK_SIZE_I32 {
+                    assert_eq!(world.get_block(bx, by, bz), other.get_block(bx, by, bz));
                 }
-
-                generated_count += 1;
-
-                s.spawn(|_| {
-                    let mut new_chunk = Chunk::new(*chunkx, *chunky, *chunkz);
-                    //Should always evaluate to true
-                    if let Some(gen_info) = gen_info_table.get(*chunkx, *chunkz) {
-                        gen_chunk(&mut new_chunk, gen_info, &self.world_generator);
-                    }
-                    //This should never fail
-                    generated
-                        .push(new_chunk)
-                        .expect("Error: Failed to push onto ArrayQueue");
-                });
             }
-        })
-        .expect("Failed to generate new chunks!");
-
-        for chunk in generated {
-            let chunkpos = chunk.get_chunk_pos();
-            let pos = (chunkpos.x, chunkpos.y, chunkpos.z);
-            self.chunks.insert(pos, chunk);
         }
-        eprintln!(
-            "Took {} ms to generate {generated_count} new chunks",
-            start.elapsed().as_millis()
-        );
-    }
 
-    pub fn generate_column_default(&mut self, x: i32, z: i32, yvals: &HashSet<i32>) {
-        //Generate chunk info (this should only contain one element)
-        let mut gen_info_table = GenInfoTable::new();
-        gen_info_table.add_heights(x, z, &self.world_generator);
-        gen_info_table.add_trees(x, z, &self.world_generator);
-        gen_info_table.add_plants(x, z, &self.world_generator);
-        gen_info_table.add_sugarcane(x, z);
-
-        //Generate new chunks
-        let start = std::time::Instant::now();
-        let generated = ArrayQueue::new(yvals.len());
-        let mut generated_count = 0;
-        thread::scope(|s| {
-            for y in yvals {
-                if !self.in_range(x, *y, z) {
-                    continue;
-                }
+        //The river is filled with water up to sea level and has a sand bed
+        let sea_level = world.world_generator.settings.sea_level;
+        let water = world.get_block(x, sea_level, z);
+        assert!(water.id == 12 || water.id == 85);
+        let h = get_height_mountain(x, z, &world.world_generator);
+        assert!(h < sea_level);
+        assert!(matches!(world.get_block(x, h, z).id, 11 | 93));
+    }
 
-                if self.chunks.contains_key(&(x, *y, z)) {
-                    continue;
+    #[test]
+    fn test_carvers_across_chunks() {
+        init_test_registries();
+
+        let mut world = World::new(0, 1, "default2", "default", GameMode::Creative).unwrap();
+        let world_generator = &world.world_generator;
+        let settings = &world_generator.settings;
+        //Find a block underground that a tunnel or ravine carves out
+        let cache = CarverCache::default();
+        let (x, y, z) = (0..64)
+            .flat_map(|cx| {
+                let carvers = Carvers::for_column(cx, 0, world_generator, &cache);
+                let posx = cx * CHUNK_SIZE_I32;
+                (0..CHUNK_SIZE_I32)
+                    .flat_map(move |dx| (0..CHUNK_SIZE_I32).map(move |z| (posx + dx, z)))
+                    .filter_map(move |(x, z)| {
+                        let h = get_height_mountain(x, z, world_generator);
+                        ((settings.lava_level + 1)..(h - 4).min(settings.cave_top))
+                            .find(|y| {
+                                carvers.is_carved(x, *y, z)
+                                    && !is_noise_cave(x, *y, z, world_generator)
+                            })
+                            .map(|y| (x, y, z))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .next()
+            .expect("there should be a tunnel");
+
+        let chunkx = x.div_euclid(CHUNK_SIZE_I32);
+        let chunky = y.div_euclid(CHUNK_SIZE_I32);
+        let chunkz = z.div_euclid(CHUNK_SIZE_I32);
+        let mut positions = HashSet::new();
+        for cx in (chunkx - 1)..=(chunkx + 1) {
+            for cy in (chunky - 1)..=(chunky + 1) {
+                for cz in (chunkz - 1)..=(chunkz + 1) {
+                    positions.insert((cx, cy, cz));
                 }
-
-                generated_count += 1;
-
-                s.spawn(|_| {
-                    let mut new_chunk = Chunk::new(x, *y, z);
-                    //Should always evaluate to true
-                    if let Some(gen_info) = gen_info_table.get(x, z) {
-                        gen_chunk(&mut new_chunk, gen_info, &self.world_generator);
-                    }
-                    //This should never fail
-                    generated
-                        .push(new_chunk)
-                        .expect("Error: Failed to push onto ArrayQueue");
-                });
             }
-        })
-        .expect("Failed to generate new chunks!");
-
-        for chunk in generated {
-            let chunkpos = chunk.get_chunk_pos();
-            let pos = (chunkpos.x, chunkpos.y, chunkpos.z);
-            self.chunks.insert(pos, chunk);
         }
-        let time = start.elapsed().as_millis();
-        if time > 15 {
-            //Only report time taken if it exceeds 15 ms
-            eprintln!("Took {time} ms to generate {generated_count} new chunks");
+        world.generate_chunks(&positions);
+        assert_eq!(world.get_block(x, y, z).id, EMPTY_BLOCK);
+
+        //Carvers that cross chunk borders should line up no matter which
+        //order the chunks are generated in
+        let mut other = World::new(0, 1, "default2", "default", GameMode::Creative).unwrap();
+        let mut sorted: Vec<(i32, i32, i32)> = positions.iter().copied().collect();
+        sorted.sort();
+        for pos in sorted.into_iter().rev() {
+            other.generate_chunks(&HashSet::from([pos]));
         }
-    }
 
-    pub fn add_default_chunk(&mut self, chunkx: i32, chunky: i32, chunkz: i32) {
-        let mut chunk = Chunk::new(chunkx, chunky, chunkz);
-        let mut gen_info_table = GenInfoTable::new();
-        gen_info_table.add_heights(chunkx, chunkz, &self.world_generator);
-        gen_info_table.add_trees(chunkx, chunkz, &self.world_generator);
-        gen_info_table.add_plants(chunkx, chunkz, &self.world_generator);
-        gen_info_table.add_sugarcane(chunkx, chunkz);
-        if let Some(gen_info) = gen_info_table.get(chunkx, chunkz) {
-            gen_chunk(&mut chunk, gen_info, &self.world_generator);
+        let minx = (chunkx - 1) * CHUNK_SIZE_I32;
+        let miny = (chunky - 1) * CHUNK_SIZE_I32;
+        let minz = (chunkz - 1) * CHUNK_SIZE_I32;
+        for bx in minx..(minx + CHUNK_SIZE_I32 * 3) {
+            for by in miny..(miny + CHUNK_SIZE_I32 * 3) {
+                for bz in minz..(minz + CHUNK_SIZE_I32 * 3) {
+                    assert_eq!(world.get_block(bx, by, bz), other.get_block(bx, by, bz));
+                }
+            }
         }
-        self.chunks.insert((chunkx, chunky, chunkz), chunk);
     }
 }
@@ -0,0 +1,374 @@
+/*
+ * Carvers cut tunnels, ravines and lava lakes out of the terrain. Every chunk
+ * column can start carvers, the path of a carver only depends on the seed and
+ * the position of the column that it starts in so a column can be generated
+ * by going through all of the carvers that start close enough to reach it.
+ * This means that carvers can span multiple chunks and do not depend on the
+ * order that chunks are generated in.
+ * */
+
+use super::{column_rng, BOTTOM_OF_WORLD};
+use crate::voxel::{
+    world::WorldGenerator, Block, Chunk, CHUNK_SIZE_I32, EMPTY_BLOCK, INDESTRUCTIBLE,
+};
+use fastrand::Rng;
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+//Extra space around the column that carvers are kept for, this is so that
+//trees on the edge of the column can check if they are above a carver
+const COLUMN_MARGIN: f64 = 2.0;
+//Number of steps in the path of a tunnel or ravine
+const TUNNEL_LEN: std::ops::Range<i32> = 60..120;
+const RAVINE_LEN: std::ops::Range<i32> = 80..140;
+//Each step moves at most one block and ravines are the widest carvers (less
+//than 5 blocks) so this is the furthest a carver can reach from its start
+const MAX_CARVER_REACH: i32 = RAVINE_LEN.end + 5 + COLUMN_MARGIN as i32;
+//How far away (in chunks) a carver can start from a column and still reach
+//it, carvers start anywhere in their chunk so this is rounded up
+const CARVER_RANGE: i32 = MAX_CARVER_REACH / CHUNK_SIZE_I32 + 1;
+//Maximum number of columns that the carver cache keeps the carvers of, every
+//column needs the carvers of (2 * CARVER_RANGE + 1)^2 columns around it
+const CARVER_CACHE_SIZE: usize = 8192;
+
+#[derive(Clone, Copy, Debug)]
+enum CarveKind {
+    //Air, becomes lava at or below the lava level
+    Tunnel,
+    //Lava at or below `level`, air above it
+    LavaLake { level: i32 },
+}
+
+//Ellipsoid that is carved out of the terrain
+#[derive(Clone, Copy, Debug)]
+struct CarveShape {
+    x: f64,
+    y: f64,
+    z: f64,
+    //Horizontal radius
+    rxz: f64,
+    //Vertical radius
+    ry: f64,
+    kind: CarveKind,
+}
+
+impl CarveShape {
+    fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+        let dx = (x as f64 + 0.5 - self.x) / self.rxz;
+        let dy = (y as f64 + 0.5 - self.y) / self.ry;
+        let dz = (z as f64 + 0.5 - self.z) / self.rxz;
+        dx * dx + dy * dy + dz * dz < 1.0
+    }
+}
+
+//Area (in block coordinates) that carvers are collected for
+#[derive(Clone, Copy)]
+struct Bounds {
+    minx: f64,
+    minz: f64,
+    maxx: f64,
+    maxz: f64,
+}
+
+impl Bounds {
+    fn intersects(&self, shape: &CarveShape) -> bool {
+        shape.x + shape.rxz >= self.minx
+            && shape.x - shape.rxz <= self.maxx
+            && shape.z + shape.rxz >= self.minz
+            && shape.z - shape.rxz <= self.maxz
+    }
+}
+
+//Generates the path of a tunnel or ravine, `vertical_scale` is how much
+//taller than it is wide the carver is and `turn` is how quickly it can turn
+fn gen_path(
+    rng: &mut Rng,
+    start: (f64, f64, f64),
+    len: i32,
+    thickness: f64,
+    (vertical_scale, turn): (f64, f64),
+    bounds: &Bounds,
+    shapes: &mut Vec<CarveShape>,
+) {
+    let (mut x, mut y, mut z) = start;
+    let mut yaw = rng.f64() * TAU;
+    let mut pitch = (rng.f64() - 0.5) * 0.5 / vertical_scale;
+    let mut yaw_change = 0.0;
+    let mut pitch_change = 0.0;
+    for step in 0..len {
+        let radius = 1.2 + (PI * step as f64 / len as f64).sin() * thickness;
+        x += yaw.cos() * pitch.cos();
+        y += pitch.sin();
+        z += yaw.sin() * pitch.cos();
+
+        pitch = pitch * 0.7 + pitch_change * 0.1 / vertical_scale;
+        yaw += yaw_change * 0.1;
+        pitch_change = pitch_change * 0.9 + (rng.f64() - rng.f64()) * rng.f64() * 2.0;
+        yaw_change = yaw_change * 0.75 + (rng.f64() - rng.f64()) * rng.f64() * 4.0 * turn;
+
+        let shape = CarveShape {
+            x,
+            y,
+            z,
+            rxz: radius,
+            ry: radius * vertical_scale,
+            kind: CarveKind::Tunnel,
+        };
+        if bounds.intersects(&shape) {
+            shapes.push(shape);
+        }
+    }
+}
+
+//Adds the shapes of every carver that starts in the column (x, z) that are
+//inside the bounds
+fn gen_column_carvers(
+    x: i32,
+    z: i32,
+    world_generator: &WorldGenerator,
+    bounds: &Bounds,
+    shapes: &mut Vec<CarveShape>,
+) {
+    let settings = &world_generator.settings;
+    let seed = world_generator
+        .world_seed
+        .wrapping_add(settings.carver_seed);
+    let mut rng = column_rng(x, z, seed);
+    let posx = (x * CHUNK_SIZE_I32) as f64;
+    let posz = (z * CHUNK_SIZE_I32) as f64;
+    let size = CHUNK_SIZE_I32 as f64;
+
+    //Tunnels, biased towards the bottom of the cave range
+    if rng.f64() < settings.tunnel_chance {
+        let cave_height = (settings.cave_top - settings.cave_bottom - 4).max(1) as f64;
+        let y = (settings.cave_bottom + 4) as f64 + rng.f64() * rng.f64() * cave_height;
+        let start = (posx + rng.f64() * size, y, posz + rng.f64() * size);
+        let count = rng.usize(1..=3);
+        for _ in 0..count {
+            let len = rng.i32(TUNNEL_LEN);
+            let thickness = rng.f64() * 2.0 + 0.5;
+            gen_path(&mut rng, start, len, thickness, (1.0, 1.0), bounds, shapes);
+        }
+    }
+
+    //Ravines
+    if rng.f64() < settings.ravine_chance {
+        let y = rng
+            .i32((settings.cave_bottom + 16)..=settings.sea_level.max(settings.cave_bottom + 16));
+        let start = (posx + rng.f64() * size, y as f64, posz + rng.f64() * size);
+        let len = rng.i32(RAVINE_LEN);
+        let thickness = rng.f64() * 2.0 + 1.5;
+        gen_path(&mut rng, start, len, thickness, (3.0, 0.25), bounds, shapes);
+    }
+
+    //Lava lakes, these are always below the lava level
+    let lowest = BOTTOM_OF_WORLD + 6;
+    if rng.f64() < settings.lava_lake_chance && settings.lava_level >= lowest {
+        let level = rng.i32(lowest..=settings.lava_level);
+        let shape = CarveShape {
+            x: posx + rng.f64() * size,
+            y: level as f64 + 1.0,
+            z: posz + rng.f64() * size,
+            rxz: 5.0 + rng.f64() * 5.0,
+            ry: 2.0 + rng.f64() * 2.0,
+            kind: CarveKind::LavaLake { level },
+        };
+        if bounds.intersects(&shape) {
+            shapes.push(shape);
+        }
+    }
+}
+
+//Shapes of every carver that starts in a column
+type ColumnShapes = Arc<Vec<CarveShape>>;
+
+//Keeps the carvers that start in each column so that they do not have to be
+//generated again for every column around it, the least recently used columns
+//are removed once there are more than CARVER_CACHE_SIZE columns
+//This should only be used with one world generator
+#[derive(Default)]
+pub struct CarverCache {
+    //(x, z) -> (last time the column was used, shapes)
+    columns: Mutex<HashMap<(i32, i32), (u64, ColumnShapes)>>,
+    time: AtomicU64,
+}
+
+impl CarverCache {
+    //Returns the shapes of every carver that starts in the column (x, z)
+    fn get(&self, x: i32, z: i32, world_generator: &WorldGenerator) -> ColumnShapes {
+        let time = self.time.fetch_add(1, Ordering::Relaxed);
+        if let Some((last_used, shapes)) = self
+            .columns
+            .lock()
+            .expect("carver cache lock poisoned")
+            .get_mut(&(x, z))
+        {
+            *last_used = time;
+            return shapes.clone();
+        }
+
+        //Generate the shapes without holding the lock so that other columns
+        //can be generated at the same time
+        let bounds = Bounds {
+            minx: f64::MIN,
+            minz: f64::MIN,
+            maxx: f64::MAX,
+            maxz: f64::MAX,
+        };
+        let mut shapes = vec![];
+        gen_column_carvers(x, z, world_generator, &bounds, &mut shapes);
+        let shapes = Arc::new(shapes);
+
+        let mut columns = self.columns.lock().expect("carver cache lock poisoned");
+        if columns.len() >= CARVER_CACHE_SIZE {
+            //Remove the least recently used half of the cache
+            let mut times: Vec<u64> = columns.values().map(|(t, _)| *t).collect();
+            let (_, median, _) = times.select_nth_unstable(CARVER_CACHE_SIZE / 2);
+            let median = *median;
+            columns.retain(|_, (t, _)| *t > median);
+        }
+        columns.insert((x, z), (time, shapes.clone()));
+        shapes
+    }
+}
+
+pub struct Carvers {
+    shapes: Vec<CarveShape>,
+}
+
+impl Carvers {
+    //Returns every carver that reaches the chunk column (x, z)
+    pub fn for_column(
+        x: i32,
+        z: i32,
+        world_generator: &WorldGenerator,
+        cache: &CarverCache,
+    ) -> Self {
+        let bounds = Bounds {
+            minx: (x * CHUNK_SIZE_I32) as f64 - COLUMN_MARGIN,
+            minz: (z * CHUNK_SIZE_I32) as f64 - COLUMN_MARGIN,
+            maxx: ((x + 1) * CHUNK_SIZE_I32) as f64 + COLUMN_MARGIN,
+            maxz: ((z + 1) * CHUNK_SIZE_I32) as f64 + COLUMN_MARGIN,
+        };
+
+        let mut shapes = vec![];
+        for cx in (x - CARVER_RANGE)..=(x + CARVER_RANGE) {
+            for cz in (z - CARVER_RANGE)..=(z + CARVER_RANGE) {
+                let column = cache.get(cx, cz, world_generator);
+                shapes.extend(column.iter().filter(|shape| bounds.intersects(shape)));
+            }
+        }
+        Self { shapes }
+    }
+
+    //Returns if a block at (x, y, z) would be carved (this does not check
+    //if the block is in a column that is underwater)
+    pub fn is_carved(&self, x: i32, y: i32, z: i32) -> bool {
+        self.shapes.iter().any(|shape| shape.contains(x, y, z))
+    }
+
+    //Carves the terrain in the chunk, `heights` is the height of each column
+    //in the chunk, this should be called before ore is generated so that ore
+    //does not generate in the carved out space
+    pub fn carve_chunk(
+        &self,
+        chunk: &mut Chunk,
+        heights: &[i32],
+        world_generator: &WorldGenerator,
+    ) {
+        let settings = &world_generator.settings;
+        let chunkpos = chunk.get_chunk_pos();
+        let posx = chunkpos.x * CHUNK_SIZE_I32;
+        let posy = chunkpos.y * CHUNK_SIZE_I32;
+        let posz = chunkpos.z * CHUNK_SIZE_I32;
+
+        for shape in &self.shapes {
+            let miny = ((shape.y - shape.ry).floor() as i32).max(posy);
+            let maxy = ((shape.y + shape.ry).ceil() as i32).min(posy + CHUNK_SIZE_I32 - 1);
+            let minx = ((shape.x - shape.rxz).floor() as i32).max(posx);
+            let maxx = ((shape.x + shape.rxz).ceil() as i32).min(posx + CHUNK_SIZE_I32 - 1);
+            let minz = ((shape.z - shape.rxz).floor() as i32).max(posz);
+            let maxz = ((shape.z + shape.rxz).ceil() as i32).min(posz + CHUNK_SIZE_I32 - 1);
+
+            for x in minx..=maxx {
+                for z in minz..=maxz {
+                    let index = ((z - posz) * CHUNK_SIZE_I32 + (x - posx)) as usize;
+                    let height = heights[index];
+                    for y in miny..=maxy {
+                        if !shape.contains(x, y, z) {
+                            continue;
+                        }
+
+                        //Do not break through the floor of the ocean, rivers
+                        //and lakes
+                        if height < settings.sea_level && y >= height - 1 {
+                            continue;
+                        }
+
+                        let block = chunk.get_block(x, y, z);
+                        if block.id == INDESTRUCTIBLE || block.is_fluid() {
+                            continue;
+                        }
+
+                        let carved = match shape.kind {
+                            CarveKind::Tunnel if block.id == EMPTY_BLOCK => continue,
+                            CarveKind::Tunnel if y <= settings.lava_level => Block::new_fluid(13),
+                            CarveKind::Tunnel => Block::new(),
+                            CarveKind::LavaLake { level } if y <= level => Block::new_fluid(13),
+                            CarveKind::LavaLake { .. } => Block::new(),
+                        };
+                        chunk.set_block(x, y, z, carved);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::world::terrain_settings::TerrainSettings;
+
+    #[test]
+    fn test_carver_range() {
+        let settings = TerrainSettings {
+            tunnel_chance: 1.0,
+            ravine_chance: 1.0,
+            ..TerrainSettings::default()
+        };
+        let world_generator = WorldGenerator::new(0, settings);
+        let bounds = Bounds {
+            minx: f64::MIN,
+            minz: f64::MIN,
+            maxx: f64::MAX,
+            maxz: f64::MAX,
+        };
+        //Furthest chunk (including the margin) that a shape reaches from
+        //the chunk that its carver started in
+        let reach = |pos: f64, r: f64, start: i32| {
+            let min = ((pos - r - COLUMN_MARGIN) / CHUNK_SIZE_I32 as f64).floor() as i32;
+            let max = ((pos + r + COLUMN_MARGIN) / CHUNK_SIZE_I32 as f64).floor() as i32;
+            (start - min).max(max - start)
+        };
+        let mut max_reach = 0;
+        for x in -50..50 {
+            for z in -50..50 {
+                let mut shapes = vec![];
+                gen_column_carvers(x, z, &world_generator, &bounds, &mut shapes);
+                for shape in shapes {
+                    max_reach = max_reach
+                        .max(reach(shape.x, shape.rxz, x))
+                        .max(reach(shape.z, shape.rxz, z));
+                }
+            }
+        }
+        //Carvers regularly reach further than 8 chunks
+        assert!(max_reach > 8);
+        assert!(max_reach <= CARVER_RANGE);
+    }
+}
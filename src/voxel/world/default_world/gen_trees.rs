@@ -1,5 +1,7 @@
 use super::terrain::{get_height, get_height_mountain, is_noise_cave};
-use super::{is_beach, is_mountain, WorldGenerator, SEA_LEVEL};
+use super::{carvers::Carvers, column_rng, get_biome_at, is_mountain, WorldGenerator};
+use crate::voxel::biome_registry::{BiomeRegistry, TreeKind};
+use crate::voxel::world::trees::TreeTemplate;
 use crate::voxel::{Block, Chunk, CHUNK_SIZE_I32, EMPTY_BLOCK};
 use noise::{NoiseFn, Perlin};
 use std::collections::HashSet;
@@ -9,7 +11,6 @@ fn gen_tree_positions(
     chunkz: i32,
     tree_noise: &Perlin,
     positions: &mut Vec<(i32, i32)>,
-    heights: &mut Vec<i32>,
     world_seed: u32,
 ) {
     let xz = [chunkx as f64 / 9.0 + 0.5, 0.0, chunkz as f64 / 9.0 + 0.5];
@@ -31,50 +32,21 @@ fn gen_tree_positions(
         }
         let x = treex + chunkx * CHUNK_SIZE_I32;
         let z = treez + chunkz * CHUNK_SIZE_I32;
-        let h = tree_generator.i32(4..=6);
         positions.push((x, z));
-        heights.push(h);
         generated.insert((treex, treez));
     }
 }
 
-fn place_leaves(chunk: &mut Chunk, x: i32, y: i32, z: i32, id: u8) {
+fn place_leaves(chunk: &mut Chunk, x: i32, y: i32, z: i32, block: Block) {
     let replace = chunk.get_block(x, y, z);
     if replace.id != EMPTY_BLOCK && replace.shape() == 0 {
         return;
     }
-    chunk.set_block(x, y, z, Block::new_id(id));
+    chunk.set_block(x, y, z, block);
 }
 
-fn generate_leaves(chunk: &mut Chunk, starty: i32, x: i32, y: i32, z: i32, height: i32, id: u8) {
-    if y == starty + height {
-        place_leaves(chunk, x, y, z, id);
-        place_leaves(chunk, x - 1, y, z, id);
-        place_leaves(chunk, x + 1, y, z, id);
-        place_leaves(chunk, x, y, z - 1, id);
-        place_leaves(chunk, x, y, z + 1, id);
-    } else if y == starty + height - 1 {
-        for ix in (x - 1)..=(x + 1) {
-            for iz in (z - 1)..=(z + 1) {
-                place_leaves(chunk, ix, y, iz, id);
-            }
-        }
-    } else if y >= starty + height - 3 {
-        for ix in (x - 2)..=(x + 2) {
-            for iz in (z - 2)..=(z + 2) {
-                place_leaves(chunk, ix, y, iz, id);
-            }
-        }
-    }
-}
-
-pub fn get_tree_gen_info(
-    x: i32,
-    z: i32,
-    world_generator: &WorldGenerator,
-) -> (Vec<(i32, i32)>, Vec<i32>) {
+pub fn get_tree_gen_info(x: i32, z: i32, world_generator: &WorldGenerator) -> Vec<(i32, i32)> {
     let mut tree_positions = vec![];
-    let mut tree_heights = vec![];
     for dx in -1..=1 {
         for dz in -1..=1 {
             gen_tree_positions(
@@ -82,81 +54,105 @@ pub fn get_tree_gen_info(
                 z + dz,
                 &world_generator.tree_generator,
                 &mut tree_positions,
-                &mut tree_heights,
                 world_generator.world_seed,
             );
         }
     }
 
-    (tree_positions, tree_heights)
+    tree_positions
 }
 
-fn gen_cactus(chunk: &mut Chunk, x: i32, z: i32, height: i32, world_generator: &WorldGenerator) {
+//Returns the height of the ground that the tree grows on, None if the tree
+//can not be placed
+fn get_tree_ground(
+    x: i32,
+    z: i32,
+    template: &TreeTemplate,
+    carvers: &Carvers,
+    world_generator: &WorldGenerator,
+) -> Option<i32> {
     let h = get_height_mountain(x, z, world_generator);
-    let terrain = get_height(x, z, world_generator);
-    if is_mountain(h, terrain) {
-        return;
-    }
-
     //Below sea level
-    if h <= SEA_LEVEL {
-        return;
+    if h < world_generator.settings.sea_level {
+        return None;
     }
 
-    //Check to make sure we are not in a cave (an empty block)
-    if is_noise_cave(x, h, z, &world_generator.noise_cave_generator) {
-        return;
-    }
+    for ix in x..(x + template.width) {
+        for iz in z..(z + template.width) {
+            let ground = get_height_mountain(ix, iz, world_generator);
+            let terrain = get_height(ix, iz, world_generator);
+            if is_mountain(ground, terrain) {
+                return None;
+            }
 
-    //Generate cactus
-    let cactus_height = (height - 3).clamp(1, 3);
-    for y in (h + 1)..(h + 1 + cactus_height) {
-        chunk.set_block(x, y, z, Block::new_id(88));
-    }
-}
+            //Giant trees need flat ground
+            if (ground - h).abs() > 1 {
+                return None;
+            }
 
-fn gen_tree(chunk: &mut Chunk, x: i32, z: i32, height: i32, world_generator: &WorldGenerator) {
-    let h = get_height_mountain(x, z, world_generator);
-    let terrain = get_height(x, z, world_generator);
-    if is_mountain(h, terrain) {
-        return;
+            //Check to make sure we are not in a cave (an empty block)
+            if is_noise_cave(ix, ground, iz, world_generator) || carvers.is_carved(ix, ground, iz) {
+                return None;
+            }
+        }
     }
 
-    //Below sea level
-    if h < SEA_LEVEL {
-        return;
-    }
+    Some(h)
+}
 
-    //Check to make sure we are not in a cave (an empty block)
-    if is_noise_cave(x, h, z, &world_generator.noise_cave_generator) {
+fn gen_tree(
+    chunk: &mut Chunk,
+    x: i32,
+    z: i32,
+    template: &TreeTemplate,
+    carvers: &Carvers,
+    world_generator: &WorldGenerator,
+) {
+    let Some(h) = get_tree_ground(x, z, template, carvers, world_generator) else {
         return;
-    }
-
-    let temperature = (world_generator.get_temperature(x, z) * 100.0).ceil() as i32;
-    let leaf_id = if temperature < 25 {
-        //Snowy leaf in cold biomes
-        91
-    } else {
-        //Normal leaf
-        7
     };
 
-    for y in (h + 1)..(h + 1 + height) {
-        //Generate trunk
-        chunk.set_block(x, y, z, Block::new_id(8));
+    //Every chunk that the tree is in needs to generate the same tree
+    let mut rng = column_rng(x, z, world_generator.world_seed);
+    for ((ix, iy, iz), block) in template.gen_blocks(x, h + 1, z, &mut rng) {
+        if block.id == template.leaves {
+            place_leaves(chunk, ix, iy, iz, block);
+        } else {
+            chunk.set_block(ix, iy, iz, block);
+        }
+    }
 
-        //Generate leaves
-        generate_leaves(chunk, h + 1, x, y, z, height, leaf_id);
+    //Fill in the gap under the trunk of giant trees on uneven ground
+    for ix in x..(x + template.width) {
+        for iz in z..(z + template.width) {
+            let ground = get_height_mountain(ix, iz, world_generator);
+            for iy in (ground + 1)..=h {
+                chunk.set_block(ix, iy, iz, Block::new_id(template.trunk));
+            }
+        }
     }
-    generate_leaves(chunk, h + 1, x, h + 1 + height, z, height, leaf_id);
+}
+
+//The kind of tree only depends on its position so that it is the same in
+//every chunk that the tree overlaps
+fn get_tree_kind(
+    x: i32,
+    z: i32,
+    world_generator: &WorldGenerator,
+    biomes: &BiomeRegistry,
+) -> Option<TreeKind> {
+    let biome = get_biome_at(x, z, world_generator, biomes);
+    let rand_val = column_rng(x, z, world_generator.world_seed).u32(..) as usize;
+    biome.get_tree(rand_val)
 }
 
 //Also generates cacti as well
 pub fn generate_trees(
     chunk: &mut Chunk,
     tree_positions: &[(i32, i32)],
-    tree_heights: &[i32],
+    carvers: &Carvers,
     world_generator: &WorldGenerator,
+    biomes: &BiomeRegistry,
 ) {
     let chunkpos = chunk.get_chunk_pos();
     let lower_x = chunkpos.x * CHUNK_SIZE_I32;
@@ -164,28 +160,99 @@ pub fn generate_trees(
     let lower_z = chunkpos.z * CHUNK_SIZE_I32;
     let upper_z = chunkpos.z * CHUNK_SIZE_I32 + CHUNK_SIZE_I32 - 1;
 
-    for (i, (x, z)) in tree_positions.iter().copied().enumerate() {
-        //Do not generate trees in deserts
-        let temperature = (world_generator.get_temperature(x, z) * 100.0).ceil() as i32;
+    for (x, z) in tree_positions.iter().copied() {
+        let Some(kind) = get_tree_kind(x, z, world_generator, biomes) else {
+            continue;
+        };
+        let template = kind.template();
 
-        let height = get_height_mountain(x, z, world_generator);
-        if is_beach(temperature, height) {
+        let r = template.radius();
+        let w = template.width - 1;
+        if x - lower_x < -r - w || x - upper_x > r || z - lower_z < -r - w || z - upper_z > r {
             continue;
         }
 
-        if temperature > 75 {
-            //Generate cacti instead
-            if i % 3 == 0 {
-                //Only a third of the time
-                gen_cactus(chunk, x, z, tree_heights[i], world_generator);
+        gen_tree(chunk, x, z, template, carvers, world_generator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::world::default_world::carvers::CarverCache;
+    use crate::{
+        game::GameMode,
+        voxel::{biome_registry::get_biome_registry, init_test_registries, World},
+    };
+
+    #[test]
+    fn test_trees_across_chunks() {
+        init_test_registries();
+
+        let mut world = World::new(0, 1, "default2", "default", GameMode::Creative).unwrap();
+        let world_generator = &world.world_generator;
+        let seed = world_generator.world_seed;
+        let biomes = get_biome_registry().unwrap();
+        let cache = CarverCache::default();
+        //Find trees that extend into the chunk east of them
+        let in_neighbor = |chunkx: i32, chunkz: i32, (x, _, z): (i32, i32, i32)| {
+            x.div_euclid(CHUNK_SIZE_I32) == chunkx + 1 && z.div_euclid(CHUNK_SIZE_I32) == chunkz
+        };
+        let trees: Vec<_> = (-64..64)
+            .flat_map(|cz| (-64..64).map(move |cx| (cx, cz)))
+            .flat_map(|(cx, cz)| {
+                let mut positions = vec![];
+                let tree_noise = &world_generator.tree_generator;
+                gen_tree_positions(cx, cz, tree_noise, &mut positions, seed);
+                positions
+            })
+            .filter_map(|(x, z)| {
+                let kind = get_tree_kind(x, z, world_generator, biomes)?;
+                let template = kind.template();
+                let chunkx = x.div_euclid(CHUNK_SIZE_I32);
+                let chunkz = z.div_euclid(CHUNK_SIZE_I32);
+                let carvers = Carvers::for_column(chunkx, chunkz, world_generator, &cache);
+                let h = get_tree_ground(x, z, template, &carvers, world_generator)?;
+                let mut rng = column_rng(x, z, seed);
+                let blocks = template.gen_blocks(x, h + 1, z, &mut rng);
+                blocks
+                    .iter()
+                    .any(|(pos, _)| in_neighbor(chunkx, chunkz, *pos))
+                    .then_some((x, z, template, blocks))
+            })
+            .take(8)
+            .collect();
+        assert_eq!(trees.len(), 8);
+
+        let mut positions = HashSet::new();
+        for (_, _, _, blocks) in &trees {
+            for ((x, y, z), _) in blocks {
+                let chunkx = x.div_euclid(CHUNK_SIZE_I32);
+                let chunky = y.div_euclid(CHUNK_SIZE_I32);
+                let chunkz = z.div_euclid(CHUNK_SIZE_I32);
+                for cy in (chunky - 1)..=chunky {
+                    positions.insert((chunkx, cy, chunkz));
+                }
             }
-            continue;
         }
+        world.generate_chunks(&positions);
 
-        if x - lower_x < -2 || x - upper_x > 2 || z - lower_z < -2 || z - upper_z > 2 {
-            continue;
+        //Every chunk that a tree is in should generate the same kind of tree
+        for (x, z, template, blocks) in trees {
+            let chunkx = x.div_euclid(CHUNK_SIZE_I32);
+            let chunkz = z.div_euclid(CHUNK_SIZE_I32);
+            let mut in_both = [false, false];
+            for ((ix, iy, iz), block) in blocks {
+                let id = world.get_block(ix, iy, iz).id;
+                if block.id == template.trunk {
+                    assert_eq!(id, template.trunk);
+                } else {
+                    //Leaves do not replace the terrain
+                    assert_ne!(id, EMPTY_BLOCK);
+                }
+                in_both[in_neighbor(chunkx, chunkz, (ix, iy, iz)) as usize] = true;
+            }
+            assert_eq!(in_both, [true, true]);
         }
-
-        gen_tree(chunk, x, z, tree_heights[i], world_generator);
     }
 }
@@ -1,215 +1,385 @@
-use crate::voxel::{Block, Chunk, CHUNK_SIZE_I32};
+/*
+ * Ore and other underground features (magma stone, clay and aqua ore) are
+ * defined in ORE_GEN_PATH so that they can be balanced without recompiling,
+ * the file is loaded when the default world generator is created.
+ * */
+
+use crate::impfile::{self, Entry};
+use crate::voxel::{
+    biome_registry::{get_biome_registry, BiomeRegistry},
+    block_registry::{get_block_registry, BlockRegistry},
+    world::terrain_settings::TerrainSettings,
+    Block, Chunk,
+};
 use fastrand::Rng;
 
-use super::{BOTTOM_OF_WORLD, LAVA_LEVEL, SEA_LEVEL};
+pub const ORE_GEN_PATH: &str = "assets/ore_gen.impfile";
 
-fn gen_vein(
-    chunk: &mut Chunk,
-    xyz: (i32, i32, i32),
-    size1: i32,
-    size2: i32,
-    ore: u8,
-    probability: f64,
-    rng: &mut Rng,
-) {
-    let (x, y, z) = xyz;
-    let ore_block = Block::new_id(ore);
-    chunk.set_block(x, y, z, ore_block);
-    for ix in (x - size2)..=(x + size1) {
-        for iy in (y - size2)..=(y + size1) {
-            for iz in (z - size2)..=(z + size1) {
-                let block = chunk.get_block(ix, iy, iz);
-                //Do not generate any ore in blocks that are not stone
-                if block.id != 2 && block.id != 15 {
-                    continue;
-                }
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum VeinShape {
+    Cube,
+    Sphere,
+}
 
-                if rng.f64() < probability {
-                    chunk.set_block(ix, iy, iz, ore_block);
-                }
-            }
-        }
-    }
+//What the y range of an ore is relative to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum HeightReference {
+    Absolute,
+    SeaLevel,
+    LavaLevel,
 }
 
-//starty > y > endy
-fn get_probability(y: i32, starty: i32, endy: i32, min_prob: f64, max_prob: f64) -> f64 {
-    if y > starty {
-        return 0.0;
-    }
-    let frac = 1.0 + (y - endy) as f64 / (endy - starty) as f64;
-    frac * (max_prob - min_prob) + min_prob
+#[derive(Clone, Debug)]
+struct OreDef {
+    name: String,
+    block: Block,
+    //Ids of the blocks that the ore can replace
+    replace: Vec<u16>,
+    min_y: i32,
+    max_y: i32,
+    relative_to: HeightReference,
+    //Chance that a block starts a vein at max_y and at min_y
+    frequency: f64,
+    frequency_bottom: f64,
+    //Width of the vein
+    vein_size: i32,
+    //Chance that a block in the vein is replaced
+    density: f64,
+    shape: VeinShape,
+    //Ids of the biomes that the ore generates in, empty = every biome
+    biomes: Vec<u8>,
 }
 
-pub fn generate_ore(chunk: &mut Chunk, x: i32, y: i32, z: i32, rng: &mut Rng) {
-    //If it is not stone, ignore it
-    let block = chunk.get_block(x, y, z);
-    if block.id != 2 && block.id != 15 {
-        return;
-    }
+fn parse_value<T: std::str::FromStr>(var: &str, val: &str) -> Result<T, String> {
+    val.parse::<T>()
+        .map_err(|_| format!("invalid value '{val}' for {var}"))
+}
 
-    //Generate coal ore
-    //Generates where ever stone generates
-    if y < -16 && rng.i32(0..2_000) == 0 {
-        gen_vein(chunk, (x, y, z), 2, 2, 18, 0.05, rng);
-    } else if y >= 0 && rng.i32(0..1_000) == 0 {
-        gen_vein(chunk, (x, y, z), 1, 1, 18, 0.2, rng);
+fn parse_chance(var: &str, val: &str) -> Result<f64, String> {
+    let chance = parse_value::<f64>(var, val)?;
+    if !(0.0..=1.0).contains(&chance) {
+        return Err(format!("{var} must be between 0 and 1"));
     }
+    Ok(chance)
+}
 
-    //Generate crimson crystal ore
-    //Generates below y = -32
-    if rng.f64() < get_probability(y, -32, BOTTOM_OF_WORLD, 1.0 / 8_000.0, 1.0 / 2_000.0) {
-        gen_vein(chunk, (x, y, z), 1, 1, 23, 0.3, rng);
-    }
+fn parse_block(blocks: &BlockRegistry, name: &str) -> Result<u16, String> {
+    blocks.get_id(name).ok_or(format!("unknown block '{name}'"))
+}
 
-    //Generate uranium ore
-    //Generate below y = -40
-    if rng.f64() < get_probability(y, -40, BOTTOM_OF_WORLD, 1.0 / 5_500.0, 1.0 / 4_000.0) {
-        gen_vein(chunk, (x, y, z), 1, 1, 94, 0.4, rng);
-    }
+fn parse_biome(biomes: &BiomeRegistry, name: &str) -> Result<u8, String> {
+    biomes
+        .iter()
+        .find(|biome| biome.name == name)
+        .map(|biome| biome.id)
+        .ok_or(format!("unknown biome '{name}'"))
+}
 
-    //Generate iron ore
-    //Generates below y = 0
-    if rng.f64() < get_probability(y, 0, BOTTOM_OF_WORLD, 1.0 / 4_000.0, 1.0 / 2_000.0) {
-        gen_vein(chunk, (x, y, z), 0, 1, 19, 0.5, rng);
-    }
+//Splits a comma separated list
+fn split_list(val: &str) -> impl Iterator<Item = &str> {
+    val.split(',').map(|s| s.trim()).filter(|s| !s.is_empty())
+}
 
-    //Generate gold ore
-    //Generates below y = -32
-    if rng.f64() < get_probability(y, -32, BOTTOM_OF_WORLD, 1.0 / 4_500.0, 1.0 / 3_000.0) {
-        gen_vein(chunk, (x, y, z), 0, 1, 20, 0.33, rng);
-    }
+impl OreDef {
+    fn from_entry(
+        entry: &Entry,
+        blocks: &BlockRegistry,
+        biomes: &BiomeRegistry,
+    ) -> Result<Self, String> {
+        for var in ["block", "min_y", "max_y", "frequency"] {
+            if entry.get_var(var).is_empty() {
+                return Err(format!("missing {var}"));
+            }
+        }
 
-    //Generate diamond ore
-    //Generates below y = -40
-    if rng.f64() < get_probability(y, -40, BOTTOM_OF_WORLD, 1.0 / 5_500.0, 1.0 / 4_500.0) {
-        gen_vein(chunk, (x, y, z), 0, 1, 21, 0.25, rng);
-    }
+        let mut def = Self {
+            name: entry.get_name(),
+            block: Block::new(),
+            replace: vec![parse_block(blocks, "stone")?],
+            min_y: 0,
+            max_y: 0,
+            relative_to: HeightReference::Absolute,
+            frequency: 0.0,
+            frequency_bottom: -1.0,
+            vein_size: 1,
+            density: 1.0,
+            shape: VeinShape::Cube,
+            biomes: vec![],
+        };
 
-    //Generate rainbow ore
-    //Generates below y = -50
-    if y < -50 && rng.i32(0..5_000) == 0 {
-        chunk.set_block(x, y, z, Block::new_id(22));
+        for (var, val) in entry.get_all_vars() {
+            match var.as_str() {
+                "block" => def.block = Block::new_id(parse_block(blocks, &val)?),
+                "replace" => {
+                    def.replace = split_list(&val)
+                        .map(|name| parse_block(blocks, name))
+                        .collect::<Result<Vec<u16>, String>>()?;
+                }
+                "min_y" => def.min_y = parse_value(&var, &val)?,
+                "max_y" => def.max_y = parse_value(&var, &val)?,
+                "relative_to" => {
+                    def.relative_to = match val.as_str() {
+                        "absolute" => HeightReference::Absolute,
+                        "sea_level" => HeightReference::SeaLevel,
+                        "lava_level" => HeightReference::LavaLevel,
+                        _ => return Err(format!("invalid value '{val}' for {var}")),
+                    };
+                }
+                "frequency" => def.frequency = parse_chance(&var, &val)?,
+                "frequency_bottom" => def.frequency_bottom = parse_chance(&var, &val)?,
+                "vein_size" => {
+                    def.vein_size = parse_value(&var, &val)?;
+                    if def.vein_size < 1 {
+                        return Err("vein_size must be at least 1".to_string());
+                    }
+                }
+                "density" => def.density = parse_chance(&var, &val)?,
+                "shape" => {
+                    def.shape = match val.as_str() {
+                        "cube" => VeinShape::Cube,
+                        "sphere" => VeinShape::Sphere,
+                        _ => return Err(format!("invalid value '{val}' for {var}")),
+                    };
+                }
+                "biomes" => {
+                    def.biomes = split_list(&val)
+                        .map(|name| parse_biome(biomes, name))
+                        .collect::<Result<Vec<u8>, String>>()?;
+                }
+                _ => return Err(format!("unknown variable '{var}'")),
+            }
+        }
+
+        if def.min_y > def.max_y {
+            return Err("min_y can not be greater than max_y".to_string());
+        }
+
+        if def.frequency_bottom < 0.0 {
+            def.frequency_bottom = def.frequency;
+        }
+
+        Ok(def)
     }
-}
 
-pub fn generate_magma_blocks(chunk: &mut Chunk, x: i32, y: i32, z: i32, rng: &mut Rng) {
-    //If it is not stone, ignore it
-    if chunk.get_block(x, y, z).id != 2 {
-        return;
+    //Returns the chance that a block at y starts a vein
+    fn get_chance(&self, y: i32, settings: &TerrainSettings) -> f64 {
+        let offset = match self.relative_to {
+            HeightReference::Absolute => 0,
+            HeightReference::SeaLevel => settings.sea_level,
+            HeightReference::LavaLevel => settings.lava_level,
+        };
+        let (min_y, max_y) = (self.min_y + offset, self.max_y + offset);
+        if y < min_y || y > max_y {
+            return 0.0;
+        }
+
+        if min_y == max_y {
+            return self.frequency;
+        }
+        let frac = (max_y - y) as f64 / (max_y - min_y) as f64;
+        self.frequency + frac * (self.frequency_bottom - self.frequency)
     }
 
-    if rng.f64()
-        < get_probability(
-            y,
-            LAVA_LEVEL + 4,
-            BOTTOM_OF_WORLD,
-            1.0 / 3_000.0,
-            1.0 / 1_000.0,
-        )
-    {
-        gen_vein(chunk, (x, y, z), 3, 3, 15, 0.66, rng);
+    fn gen_vein(&self, chunk: &mut Chunk, (x, y, z): (i32, i32, i32), rng: &mut Rng) {
+        chunk.set_block(x, y, z, self.block);
+        //The vein is centered on (x, y, z), even sizes extend further
+        //in the negative direction
+        let lower = self.vein_size / 2;
+        let upper = self.vein_size - 1 - lower;
+        let radius2 = lower * lower;
+        for ix in (x - lower)..=(x + upper) {
+            for iy in (y - lower)..=(y + upper) {
+                for iz in (z - lower)..=(z + upper) {
+                    let dist2 = (ix - x).pow(2) + (iy - y).pow(2) + (iz - z).pow(2);
+                    if self.shape == VeinShape::Sphere && dist2 > radius2 {
+                        continue;
+                    }
+
+                    let block = chunk.get_block(ix, iy, iz);
+                    if !self.replace.contains(&block.id) {
+                        continue;
+                    }
+
+                    if rng.f64() < self.density {
+                        chunk.set_block(ix, iy, iz, self.block);
+                    }
+                }
+            }
+        }
     }
 }
 
-const CLAY_RADIUS: i32 = 2;
+#[derive(Clone, Debug)]
+pub struct OreTable {
+    ores: Vec<OreDef>,
+}
 
-pub fn generate_clay(chunk: &mut Chunk, x: i32, y: i32, z: i32, rng: &mut Rng) {
-    //If it is not sand, ignore it
-    if chunk.get_block(x, y, z).id != 11 {
-        return;
-    }
+impl OreTable {
+    pub fn from_entries(
+        entries: &[Entry],
+        blocks: &BlockRegistry,
+        biomes: &BiomeRegistry,
+    ) -> Result<Self, String> {
+        let mut ores: Vec<OreDef> = vec![];
+        let mut errors = vec![];
+        for entry in entries {
+            let name = entry.get_name();
+            match OreDef::from_entry(entry, blocks, biomes) {
+                Ok(_) if ores.iter().any(|ore| ore.name == name) => {
+                    errors.push(format!("ore '{name}' is defined more than once"));
+                }
+                Ok(def) => ores.push(def),
+                Err(msg) => errors.push(format!("ore '{name}': {msg}")),
+            }
+        }
 
-    let pos = chunk.get_chunk_pos();
-    let minx = pos.x * CHUNK_SIZE_I32 + CLAY_RADIUS;
-    let miny = pos.y * CHUNK_SIZE_I32 + CLAY_RADIUS;
-    let minz = pos.z * CHUNK_SIZE_I32 + CLAY_RADIUS;
-    if x < minx || y < miny || z < minz {
-        return;
-    }
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
 
-    let maxx = pos.x * CHUNK_SIZE_I32 + CHUNK_SIZE_I32 - 1 - CLAY_RADIUS;
-    let maxy = pos.y * CHUNK_SIZE_I32 + CHUNK_SIZE_I32 - 1 - CLAY_RADIUS;
-    let maxz = pos.z * CHUNK_SIZE_I32 + CHUNK_SIZE_I32 - 1 - CLAY_RADIUS;
-    if x > maxx || y > maxy || z > maxz {
-        return;
+        Ok(Self { ores })
     }
 
-    if y >= SEA_LEVEL - 2 {
-        return;
+    pub fn load(
+        path: &str,
+        blocks: &BlockRegistry,
+        biomes: &BiomeRegistry,
+    ) -> Result<Self, String> {
+        let entries = impfile::parse_file(path);
+        Self::from_entries(&entries, blocks, biomes)
+            .map_err(|msg| format!("invalid ores in {path}:\n{msg}"))
     }
+}
 
-    if rng.f64() > 1.0 / 640.0 {
-        return;
-    }
+//Loads the ore file once so that mistakes in it are reported when the game
+//starts instead of when the first world is generated
+//(requires the block and biome registries)
+pub fn check_ore_gen(path: &str) -> Result<(), String> {
+    let blocks = get_block_registry().ok_or("blocks have not been loaded")?;
+    let biomes = get_biome_registry().ok_or("biomes have not been loaded")?;
+    let table = OreTable::load(path, blocks, biomes)?;
+    eprintln!("Loaded {} ores", table.ores.len());
+    Ok(())
+}
 
-    for ix in (x - CLAY_RADIUS)..=(x + CLAY_RADIUS) {
-        for iy in (y - CLAY_RADIUS)..=(y + CLAY_RADIUS) {
-            for iz in (z - CLAY_RADIUS)..=(z + CLAY_RADIUS) {
-                let dist = (ix - x).pow(2) + (iy - y).pow(2) + (iz - z).pow(2);
-                if dist > CLAY_RADIUS * CLAY_RADIUS {
-                    continue;
-                }
+//Every ore that can replace the block at (x, y, z) has a chance of starting
+//a vein at that position
+pub fn generate_ores(
+    chunk: &mut Chunk,
+    (x, y, z): (i32, i32, i32),
+    rng: &mut Rng,
+    ores: &OreTable,
+    settings: &TerrainSettings,
+) {
+    for ore in &ores.ores {
+        let block = chunk.get_block(x, y, z);
+        if !ore.replace.contains(&block.id) {
+            continue;
+        }
 
-                let block = chunk.get_block(ix, iy, iz);
-                if block.id != 11 {
-                    continue;
-                }
-                if y >= SEA_LEVEL - 1 {
-                    continue;
-                }
-                chunk.set_block(ix, iy, iz, Block::new_id(93));
+        let chance = ore.get_chance(y, settings);
+        if chance <= 0.0 || rng.f64() >= chance {
+            continue;
+        }
+
+        if !ore.biomes.is_empty() {
+            let biome = chunk.get_biome(x, z);
+            if !biome.is_some_and(|biome| ore.biomes.contains(&biome)) {
+                continue;
             }
         }
+
+        ore.gen_vein(chunk, (x, y, z), rng);
     }
 }
 
-const AQUA_VEIN_SIZE: i32 = 1;
-
-pub fn generate_aqua_ore(chunk: &mut Chunk, x: i32, y: i32, z: i32, rng: &mut Rng) {
-    //If it is not sand, ignore it
-    if chunk.get_block(x, y, z).id != 11 {
-        return;
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::init_test_registries;
 
-    let pos = chunk.get_chunk_pos();
-    let minx = pos.x * CHUNK_SIZE_I32 + AQUA_VEIN_SIZE;
-    let miny = pos.y * CHUNK_SIZE_I32 + AQUA_VEIN_SIZE;
-    let minz = pos.z * CHUNK_SIZE_I32 + AQUA_VEIN_SIZE;
-    if x < minx || y < miny || z < minz {
-        return;
+    fn ore_entry(name: &str, vars: &[(&str, &str)]) -> Entry {
+        let vars = vars
+            .iter()
+            .map(|(var, val)| (var.to_string(), val.to_string()))
+            .collect();
+        Entry::from_vec(name, vars)
     }
 
-    let maxx = pos.x * CHUNK_SIZE_I32 + CHUNK_SIZE_I32 - 1 - AQUA_VEIN_SIZE;
-    let maxy = pos.y * CHUNK_SIZE_I32 + CHUNK_SIZE_I32 - 1 - AQUA_VEIN_SIZE;
-    let maxz = pos.z * CHUNK_SIZE_I32 + CHUNK_SIZE_I32 - 1 - AQUA_VEIN_SIZE;
-    if x > maxx || y > maxy || z > maxz {
-        return;
-    }
+    #[test]
+    fn test_ore_table() {
+        init_test_registries();
+        let blocks = get_block_registry().unwrap();
+        let biomes = get_biome_registry().unwrap();
+        let settings = TerrainSettings::default();
 
-    if y >= SEA_LEVEL - 4 {
-        return;
-    }
+        let table = OreTable::load(ORE_GEN_PATH, blocks, biomes).unwrap();
+        let iron = table.ores.iter().find(|ore| ore.name == "iron").unwrap();
+        assert_eq!(iron.block.id, 19);
+        assert_eq!(iron.get_chance(1, &settings), 0.0);
+        assert_eq!(iron.get_chance(0, &settings), 0.00025);
+        assert_eq!(iron.get_chance(-64, &settings), 0.0005);
+        let magma = table
+            .ores
+            .iter()
+            .find(|ore| ore.name == "magma_stone")
+            .unwrap();
+        assert!(magma.get_chance(settings.lava_level + 4, &settings) > 0.0);
+        assert_eq!(magma.get_chance(settings.lava_level + 5, &settings), 0.0);
 
-    if rng.f64() > 1.0 / 1600.0 {
-        return;
-    }
+        let entries = [
+            ore_entry("a", &[("block", "not_a_block")]),
+            ore_entry(
+                "b",
+                &[
+                    ("block", "gold_ore"),
+                    ("min_y", "0"),
+                    ("max_y", "-10"),
+                    ("frequency", "0.1"),
+                ],
+            ),
+            ore_entry(
+                "c",
+                &[
+                    ("block", "gold_ore"),
+                    ("min_y", "-10"),
+                    ("max_y", "0"),
+                    ("frequency", "0.1"),
+                    ("biomes", "not_a_biome"),
+                ],
+            ),
+        ];
+        let msg = OreTable::from_entries(&entries, blocks, biomes)
+            .err()
+            .unwrap();
+        assert!(msg.contains("ore 'a': missing min_y"));
+        assert!(msg.contains("ore 'b': min_y can not be greater than max_y"));
+        assert!(msg.contains("ore 'c': unknown biome 'not_a_biome'"));
 
-    for ix in (x - AQUA_VEIN_SIZE)..=(x + AQUA_VEIN_SIZE) {
-        for iy in (y - AQUA_VEIN_SIZE)..=(y + AQUA_VEIN_SIZE) {
-            for iz in (z - AQUA_VEIN_SIZE)..=(z + AQUA_VEIN_SIZE) {
-                let block = chunk.get_block(ix, iy, iz);
-                if block.id != 11 {
-                    continue;
-                }
-                if y >= SEA_LEVEL - 1 {
-                    continue;
-                }
-                if rng.f64() > 0.66 {
-                    continue;
-                }
-                chunk.set_block(ix, iy, iz, Block::new_id(96));
-            }
-        }
+        //Veins only replace the host blocks and only in the listed biomes
+        let entries = [ore_entry(
+            "sand_ore",
+            &[
+                ("block", "gold_ore"),
+                ("replace", "sand"),
+                ("min_y", "0"),
+                ("max_y", "15"),
+                ("frequency", "1.0"),
+                ("vein_size", "3"),
+                ("biomes", "desert"),
+            ],
+        )];
+        let table = OreTable::from_entries(&entries, blocks, biomes).unwrap();
+        let desert = parse_biome(biomes, "desert").unwrap();
+        let mut chunk = Chunk::new(0, 0, 0);
+        chunk.set_block(1, 1, 1, Block::new_id(11));
+        chunk.set_block(2, 1, 1, Block::new_id(2));
+        let mut rng = Rng::with_seed(0);
+        generate_ores(&mut chunk, (1, 1, 1), &mut rng, &table, &settings);
+        assert_eq!(chunk.get_block(1, 1, 1).id, 11);
+        chunk.set_biomes(vec![desert; 256]);
+        generate_ores(&mut chunk, (1, 1, 1), &mut rng, &table, &settings);
+        assert_eq!(chunk.get_block(1, 1, 1).id, 20);
+        assert_eq!(chunk.get_block(2, 1, 1).id, 2);
     }
 }
@@ -1,8 +1,7 @@
 use super::{
-    is_beach, is_mountain, is_noise_cave,
-    terrain::{get_height, get_height_mountain},
-    WorldGenerator, SEA_LEVEL,
+    carvers::Carvers, get_biome_at, is_noise_cave, terrain::get_height_mountain, WorldGenerator,
 };
+use crate::voxel::biome_registry::{BiomeRegistry, PLANT_ROLLS};
 use crate::voxel::{Block, Chunk, CHUNK_SIZE_I32, EMPTY_BLOCK};
 use fastrand::Rng;
 
@@ -30,21 +29,18 @@ pub fn generate_plants(
     chunk: &mut Chunk,
     plant_positions: &[(i32, i32)],
     rng: &mut Rng,
+    carvers: &Carvers,
     world_generator: &WorldGenerator,
+    biomes: &BiomeRegistry,
 ) {
     for (x, z) in plant_positions {
-        let rand_val = rng.i32(0..80);
-        let h = get_height_mountain(*x, *z, world_generator);
-        let terrain = get_height(*x, *z, world_generator);
-        if is_mountain(h, terrain) {
-            continue;
-        }
-
-        let temperature = (world_generator.get_temperature(*x, *z) * 100.0).ceil() as i32;
-        if is_beach(temperature, h) {
+        let rand_val = rng.i32(0..PLANT_ROLLS);
+        let biome = get_biome_at(*x, *z, world_generator, biomes);
+        let Some(plant) = biome.get_plant(rand_val) else {
             continue;
-        }
+        };
 
+        let h = get_height_mountain(*x, *z, world_generator);
         let replace = chunk.get_block(*x, h + 1, *z);
         //Replace any non solid block or empty block
         if !(replace == Block::new() || replace.shape() != 0) {
@@ -52,63 +48,21 @@ pub fn generate_plants(
         }
 
         //Below sea level
-        if h < SEA_LEVEL {
+        if h < world_generator.settings.sea_level {
             continue;
         }
 
         //Check to make sure we are not in a cave (an empty block)
-        if is_noise_cave(*x, h, *z, &world_generator.noise_cave_generator) {
-            continue;
-        }
-
-        //Desert biome
-        if temperature > 75 {
-            match rand_val {
-                //Dead bush
-                0..20 => chunk.set_block(*x, h + 1, *z, Block::new_id(90)),
-                //Mushroom (very rare)
-                20 => chunk.set_block(*x, h + 1, *z, Block::new_id(48)),
-                _ => {}
-            }
-            continue;
-        }
-
-        //Cold biome
-        if temperature < 25 {
-            if (0..10).contains(&rand_val) {
-                //Mushroom
-                chunk.set_block(*x, h + 1, *z, Block::new_id(48));
-            }
+        if is_noise_cave(*x, h, *z, world_generator) || carvers.is_carved(*x, h, *z) {
             continue;
         }
 
-        match rand_val {
-            //Tall grass
-            0..50 => chunk.set_block(*x, h + 1, *z, Block::new_id(49)),
-            //Red flower
-            50..58 => chunk.set_block(*x, h + 1, *z, Block::new_id(54)),
-            //Yellow flower
-            58..66 => chunk.set_block(*x, h + 1, *z, Block::new_id(55)),
-            //Blue flower
-            66..70 => chunk.set_block(*x, h + 1, *z, Block::new_id(56)),
-            //Mushroom
-            70..72 => chunk.set_block(*x, h + 1, *z, Block::new_id(48)),
-            //Cotton
-            72 => {
-                //Cotton should generate in warmer areas
-                if temperature < 60 {
-                    continue;
-                }
-                chunk.set_block(*x, h + 1, *z, Block::new_id(102));
-            }
-            //Nothing
-            _ => {}
-        }
+        chunk.set_block(*x, h + 1, *z, Block::new_id(plant));
     }
 }
 
-fn is_adjacent_to_water(x: i32, z: i32, h: i32, heights: &[i32]) -> bool {
-    if h != SEA_LEVEL {
+fn is_adjacent_to_water(x: i32, z: i32, h: i32, heights: &[i32], sea_level: i32) -> bool {
+    if h != sea_level {
         return false;
     }
 
@@ -118,7 +72,7 @@ fn is_adjacent_to_water(x: i32, z: i32, h: i32, heights: &[i32]) -> bool {
             continue;
         }
         let index = ((z + dz) * CHUNK_SIZE_I32 + x + dx) as usize;
-        if heights[index] < SEA_LEVEL {
+        if heights[index] < sea_level {
             return true;
         }
     }
@@ -126,7 +80,12 @@ fn is_adjacent_to_water(x: i32, z: i32, h: i32, heights: &[i32]) -> bool {
     false
 }
 
-pub fn get_water_adjacent(chunkx: i32, chunkz: i32, heights: &[i32]) -> Vec<(i32, i32)> {
+pub fn get_water_adjacent(
+    chunkx: i32,
+    chunkz: i32,
+    heights: &[i32],
+    sea_level: i32,
+) -> Vec<(i32, i32)> {
     let mut adjacent = vec![];
     let posx = chunkx * CHUNK_SIZE_I32;
     let posz = chunkz * CHUNK_SIZE_I32;
@@ -134,7 +93,7 @@ pub fn get_water_adjacent(chunkx: i32, chunkz: i32, heights: &[i32]) -> Vec<(i32
         for z in 0..CHUNK_SIZE_I32 {
             let index = (z * CHUNK_SIZE_I32 + x) as usize;
             let h = heights[index];
-            if is_adjacent_to_water(x, z, h, heights) {
+            if is_adjacent_to_water(x, z, h, heights, sea_level) {
                 adjacent.push((posx + x, posz + z));
             }
         }
@@ -142,15 +101,26 @@ pub fn get_water_adjacent(chunkx: i32, chunkz: i32, heights: &[i32]) -> Vec<(i32
     adjacent
 }
 
-pub fn generate_sugarcane(chunk: &mut Chunk, water_adjacent: &[(i32, i32)], rng: &mut Rng) {
+pub fn generate_sugarcane(
+    chunk: &mut Chunk,
+    water_adjacent: &[(i32, i32)],
+    rng: &mut Rng,
+    carvers: &Carvers,
+    sea_level: i32,
+) {
     for (x, z) in water_adjacent {
         if rng.i32(0..60) != 0 {
             continue;
         }
 
         let height = rng.i32(1..=3);
+        //The ground was carved out
+        if carvers.is_carved(*x, sea_level, *z) {
+            continue;
+        }
+
         for i in 0..height {
-            let y = SEA_LEVEL + 1 + i;
+            let y = sea_level + 1 + i;
             if chunk.get_block(*x, y, *z).id != EMPTY_BLOCK {
                 break;
             }
@@ -0,0 +1,323 @@
+/*
+ * Structures (villages, dungeons, ruined towers and desert wells) are placed
+ * in regions, each kind of structure has its own grid of regions and every
+ * region has at most one start of that kind which only depends on the seed
+ * and the position of the region.
+ * When a chunk column is generated, every structure that could reach the
+ * column is built from its start and is split up into pieces (one for each
+ * chunk that it is in), only the pieces in that column are kept. This means
+ * that structures line up across chunks no matter what order the chunks are
+ * generated in.
+ * */
+
+mod desert_well;
+mod dungeon;
+mod loot;
+mod ruined_tower;
+mod village;
+
+use super::{column_rng, get_biome_at, terrain::get_height_mountain, WorldGenerator};
+use crate::voxel::{
+    biome_registry::{BiomeRegistry, StructureKind},
+    tile_data::TileData,
+    world_to_chunk_position, Block, Chunk, CHUNK_SIZE_I32,
+};
+use fastrand::Rng;
+use std::collections::HashMap;
+
+struct StructureType {
+    kind: StructureKind,
+    //Width of a region in chunks
+    spacing: i32,
+    //Chance that a region has a start
+    chance: f64,
+    //How far (in blocks) the structure can extend from its start
+    radius: i32,
+}
+
+const STRUCTURE_TYPES: [StructureType; 4] = [
+    StructureType {
+        kind: StructureKind::Village,
+        spacing: 16,
+        chance: 0.6,
+        radius: village::RADIUS,
+    },
+    StructureType {
+        kind: StructureKind::Dungeon,
+        spacing: 4,
+        chance: 0.35,
+        radius: dungeon::RADIUS,
+    },
+    StructureType {
+        kind: StructureKind::RuinedTower,
+        spacing: 12,
+        chance: 0.4,
+        radius: ruined_tower::RADIUS,
+    },
+    StructureType {
+        kind: StructureKind::DesertWell,
+        spacing: 6,
+        chance: 0.5,
+        radius: desert_well::RADIUS,
+    },
+];
+
+//Part of a structure that is in a single chunk
+#[derive(Default)]
+pub struct StructurePiece {
+    blocks: Vec<((i32, i32, i32), Block)>,
+    tile_data: Vec<((i32, i32, i32), TileData)>,
+}
+
+impl StructurePiece {
+    fn generate(&self, chunk: &mut Chunk) {
+        for ((x, y, z), block) in &self.blocks {
+            chunk.set_block(*x, *y, *z, *block);
+        }
+
+        for ((x, y, z), tile_data) in &self.tile_data {
+            chunk.set_tile_data(*x, *y, *z, Some(tile_data.clone()));
+        }
+    }
+}
+
+//Blocks are placed in the order that they are set in
+#[derive(Default)]
+pub struct Structure {
+    pieces: HashMap<(i32, i32, i32), StructurePiece>,
+}
+
+impl Structure {
+    fn get_piece(&mut self, x: i32, y: i32, z: i32) -> &mut StructurePiece {
+        self.pieces
+            .entry(world_to_chunk_position(x, y, z))
+            .or_default()
+    }
+
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: Block) {
+        self.get_piece(x, y, z).blocks.push(((x, y, z), block));
+    }
+
+    pub fn set_tile_data(&mut self, x: i32, y: i32, z: i32, tile_data: TileData) {
+        self.get_piece(x, y, z)
+            .tile_data
+            .push(((x, y, z), tile_data));
+    }
+
+    //Fills the box between the two corners (inclusive)
+    pub fn fill(
+        &mut self,
+        (x1, y1, z1): (i32, i32, i32),
+        (x2, y2, z2): (i32, i32, i32),
+        block: Block,
+    ) {
+        for x in x1.min(x2)..=x1.max(x2) {
+            for y in y1.min(y2)..=y1.max(y2) {
+                for z in z1.min(z2)..=z1.max(z2) {
+                    self.set_block(x, y, z, block);
+                }
+            }
+        }
+    }
+
+    //Fills in the space between the terrain and (x, y, z) so that the
+    //structure is not floating, at most `depth` blocks are filled in
+    pub fn fill_foundation(
+        &mut self,
+        (x, y, z): (i32, i32, i32),
+        depth: i32,
+        block: Block,
+        world_generator: &WorldGenerator,
+    ) {
+        let h = get_height_mountain(x, z, world_generator);
+        for iy in h.max(y - depth)..y {
+            self.set_block(x, iy, z, block);
+        }
+    }
+
+    //Returns the position of every block that was set in the structure
+    #[cfg(test)]
+    fn block_positions(&self) -> impl Iterator<Item = (i32, i32, i32)> + '_ {
+        self.pieces
+            .values()
+            .flat_map(|piece| piece.blocks.iter().map(|(pos, _)| *pos))
+    }
+}
+
+//Returns the position of the start in the region (x, z) and the random number
+//generator that is used to build the structure
+fn get_start(
+    index: usize,
+    x: i32,
+    z: i32,
+    world_generator: &WorldGenerator,
+) -> Option<(i32, i32, Rng)> {
+    let structure_type = &STRUCTURE_TYPES[index];
+    let seed = world_generator
+        .world_seed
+        .wrapping_add(world_generator.settings.structure_seed)
+        .wrapping_add((index as u32).wrapping_mul(0x9e37_79b9));
+    let mut rng = column_rng(x, z, seed);
+    if rng.f64() >= structure_type.chance {
+        return None;
+    }
+
+    let size = structure_type.spacing * CHUNK_SIZE_I32;
+    let startx = x * size + rng.i32(0..size);
+    let startz = z * size + rng.i32(0..size);
+    Some((startx, startz, rng))
+}
+
+//Returns None if the structure can not generate at its start
+fn build_structure(
+    kind: StructureKind,
+    (x, z): (i32, i32),
+    rng: &mut Rng,
+    world_generator: &WorldGenerator,
+    biomes: &BiomeRegistry,
+) -> Option<Structure> {
+    if kind != StructureKind::Dungeon {
+        let biome = get_biome_at(x, z, world_generator, biomes);
+        if !biome.structures.contains(&kind) {
+            return None;
+        }
+    }
+
+    let mut structure = Structure::default();
+    let built = match kind {
+        StructureKind::Village => village::build(&mut structure, x, z, rng, world_generator),
+        StructureKind::Dungeon => dungeon::build(&mut structure, x, z, rng, world_generator),
+        StructureKind::RuinedTower => {
+            ruined_tower::build(&mut structure, x, z, rng, world_generator)
+        }
+        StructureKind::DesertWell => desert_well::build(&mut structure, x, z, rng, world_generator),
+    };
+    built.then_some(structure)
+}
+
+//Pieces of every structure in a chunk column
+pub struct ColumnStructures {
+    //Indexed by the y position of the chunk
+    pieces: HashMap<i32, Vec<StructurePiece>>,
+}
+
+impl ColumnStructures {
+    pub fn for_column(
+        x: i32,
+        z: i32,
+        world_generator: &WorldGenerator,
+        biomes: &BiomeRegistry,
+    ) -> Self {
+        let mut pieces: HashMap<i32, Vec<StructurePiece>> = HashMap::new();
+        if !world_generator.settings.structures {
+            return Self { pieces };
+        }
+
+        let minx = x * CHUNK_SIZE_I32;
+        let minz = z * CHUNK_SIZE_I32;
+        let maxx = minx + CHUNK_SIZE_I32 - 1;
+        let maxz = minz + CHUNK_SIZE_I32 - 1;
+        for (i, structure_type) in STRUCTURE_TYPES.iter().enumerate() {
+            let size = structure_type.spacing * CHUNK_SIZE_I32;
+            let radius = structure_type.radius;
+            for rx in (minx - radius).div_euclid(size)..=(maxx + radius).div_euclid(size) {
+                for rz in (minz - radius).div_euclid(size)..=(maxz + radius).div_euclid(size) {
+                    let Some((startx, startz, mut rng)) = get_start(i, rx, rz, world_generator)
+                    else {
+                        continue;
+                    };
+
+                    //Too far away to reach this column
+                    if startx + radius < minx
+                        || startx - radius > maxx
+                        || startz + radius < minz
+                        || startz - radius > maxz
+                    {
+                        continue;
+                    }
+
+                    let start = (startx, startz);
+                    let kind = structure_type.kind;
+                    let Some(structure) =
+                        build_structure(kind, start, &mut rng, world_generator, biomes)
+                    else {
+                        continue;
+                    };
+
+                    for ((chunkx, chunky, chunkz), piece) in structure.pieces {
+                        if chunkx == x && chunkz == z {
+                            pieces.entry(chunky).or_default().push(piece);
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { pieces }
+    }
+
+    pub fn generate(&self, chunk: &mut Chunk) {
+        let chunky = chunk.get_chunk_pos().y;
+        for piece in self.pieces.get(&chunky).into_iter().flatten() {
+            piece.generate(chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::{inventory::Item, GameMode},
+        voxel::{biome_registry::get_biome_registry, init_test_registries, World},
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_structures_across_chunks() {
+        init_test_registries();
+        let biomes = get_biome_registry().unwrap();
+
+        let mut world = World::new(0, 1, "default2", "default", GameMode::Creative).unwrap();
+        let world_generator = &world.world_generator;
+        let structure = (0..32)
+            .flat_map(|x| (0..32).map(move |z| (x, z)))
+            .find_map(|(x, z)| {
+                let (startx, startz, mut rng) = get_start(0, x, z, world_generator)?;
+                let start = (startx, startz);
+                let kind = StructureKind::Village;
+                build_structure(kind, start, &mut rng, world_generator, biomes)
+            })
+            .expect("there should be a village");
+
+        //Villages should span multiple chunks and have a chest with loot
+        let positions: HashSet<(i32, i32, i32)> = structure.pieces.keys().copied().collect();
+        assert!(positions.len() > 1);
+        let (chest_x, chest_y, chest_z) = structure
+            .pieces
+            .values()
+            .flat_map(|piece| piece.tile_data.iter())
+            .map(|(pos, _)| *pos)
+            .next()
+            .expect("there should be a chest");
+
+        world.generate_chunks(&positions);
+        assert_eq!(world.get_block(chest_x, chest_y, chest_z).id, 37);
+        let chest = world.get_tile_data(chest_x, chest_y, chest_z).unwrap();
+        assert!(chest
+            .get_items()
+            .iter()
+            .any(|item| !matches!(item, Item::Empty)));
+
+        //Generating the chunks in a different order should give the same result
+        let mut other = World::new(0, 1, "default2", "default", GameMode::Creative).unwrap();
+        let mut sorted: Vec<(i32, i32, i32)> = positions.iter().copied().collect();
+        sorted.sort();
+        for pos in sorted.into_iter().rev() {
+            other.generate_chunks(&HashSet::from([pos]));
+        }
+        for (x, y, z) in structure.block_positions() {
+            assert_eq!(world.get_block(x, y, z), other.get_block(x, y, z));
+        }
+    }
+}
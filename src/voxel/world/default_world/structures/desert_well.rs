@@ -0,0 +1,55 @@
+use super::{
+    get_height_mountain,
+    loot::{gen_chest_loot, LootTable},
+    Structure, WorldGenerator,
+};
+use crate::voxel::{Block, SLAB};
+use fastrand::Rng;
+
+pub const RADIUS: i32 = 3;
+
+pub fn build(
+    structure: &mut Structure,
+    x: i32,
+    z: i32,
+    rng: &mut Rng,
+    world_generator: &WorldGenerator,
+) -> bool {
+    let h = get_height_mountain(x, z, world_generator);
+    if h <= world_generator.settings.sea_level {
+        return false;
+    }
+
+    let sand_bricks = Block::new_id(32);
+    let mut roof = Block::new_id(36);
+    roof.set_shape(SLAB);
+
+    //Base
+    for ix in (x - 2)..=(x + 2) {
+        for iz in (z - 2)..=(z + 2) {
+            structure.fill_foundation((ix, h, iz), 4, sand_bricks, world_generator);
+        }
+    }
+    structure.fill((x - 2, h, z - 2), (x + 2, h, z + 2), sand_bricks);
+    structure.fill((x - 2, h + 1, z - 2), (x + 2, h + 4, z + 2), Block::new());
+
+    //Well
+    structure.fill((x - 1, h + 1, z - 1), (x + 1, h + 1, z + 1), sand_bricks);
+    structure.fill((x, h - 2, z), (x, h + 1, z), Block::new_fluid(12));
+    for (dx, dz) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+        structure.fill(
+            (x + dx, h + 2, z + dz),
+            (x + dx, h + 3, z + dz),
+            sand_bricks,
+        );
+    }
+    structure.fill((x - 1, h + 4, z - 1), (x + 1, h + 4, z + 1), roof);
+
+    //Buried chest under one of the corners of the base
+    let (dx, dz) = [(-2, -2), (-2, 2), (2, -2), (2, 2)][rng.usize(0..4)];
+    structure.set_block(x + dx, h - 1, z + dz, Block::new_id(37));
+    let chest = gen_chest_loot(LootTable::DesertWell, 4, rng);
+    structure.set_tile_data(x + dx, h - 1, z + dz, chest);
+
+    true
+}
@@ -0,0 +1,74 @@
+use super::{
+    get_height_mountain,
+    loot::{gen_chest_loot, LootTable},
+    Structure, WorldGenerator,
+};
+use crate::voxel::Block;
+use fastrand::Rng;
+
+pub const RADIUS: i32 = 6;
+//Height of the inside of the room
+const ROOM_HEIGHT: i32 = 4;
+
+//Cobblestone with some moss
+fn wall_block(rng: &mut Rng) -> Block {
+    if rng.i32(0..4) == 0 {
+        Block::new_id(17)
+    } else {
+        Block::new_id(16)
+    }
+}
+
+pub fn build(
+    structure: &mut Structure,
+    x: i32,
+    z: i32,
+    rng: &mut Rng,
+    world_generator: &WorldGenerator,
+) -> bool {
+    let settings = &world_generator.settings;
+    //Dungeons are always at least 12 blocks underground
+    let h = get_height_mountain(x, z, world_generator);
+    let miny = settings.lava_level + 2;
+    let maxy = (h - 12 - ROOM_HEIGHT).min(settings.cave_top);
+    if miny > maxy {
+        return false;
+    }
+
+    let y = rng.i32(miny..=maxy);
+    let sx = rng.i32(2..=4);
+    let sz = rng.i32(2..=4);
+    for ix in (x - sx - 1)..=(x + sx + 1) {
+        for iz in (z - sz - 1)..=(z + sz + 1) {
+            for iy in (y - 1)..=(y + ROOM_HEIGHT) {
+                let wall = ix.abs_diff(x) as i32 == sx + 1
+                    || iz.abs_diff(z) as i32 == sz + 1
+                    || iy == y - 1
+                    || iy == y + ROOM_HEIGHT;
+                let block = if wall { wall_block(rng) } else { Block::new() };
+                structure.set_block(ix, iy, iz, block);
+            }
+        }
+    }
+
+    //Chests against the walls
+    let chest_count = rng.i32(1..=2);
+    for _ in 0..chest_count {
+        let (cx, cz, orientation) = match rng.i32(0..4) {
+            0 => (x - sx, z + rng.i32(-sz..=sz), 1),
+            1 => (x + sx, z + rng.i32(-sz..=sz), 4),
+            2 => (x + rng.i32(-sx..=sx), z - sz, 2),
+            _ => (x + rng.i32(-sx..=sx), z + sz, 5),
+        };
+        let mut chest = Block::new_id(37);
+        chest.set_orientation(orientation);
+        structure.set_block(cx, y, cz, chest);
+        let loot = gen_chest_loot(LootTable::Dungeon, 8, rng);
+        structure.set_tile_data(cx, y, cz, loot);
+    }
+
+    //Torch in the middle of the room
+    structure.set_block(x, y, z, Block::new_id(71));
+
+    true
+}
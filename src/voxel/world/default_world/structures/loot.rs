@@ -0,0 +1,135 @@
+use crate::game::inventory::{
+    food::FoodInfo,
+    tools::{ToolInfo, ToolMaterial, ToolType},
+    Item,
+};
+use crate::voxel::{tile_data::TileData, Block};
+use fastrand::Rng;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LootTable {
+    Dungeon,
+    Village,
+    RuinedTower,
+    DesertWell,
+}
+
+//(item, maximum amount, weight), items that do not stack (tools and food)
+//always have an amount of 1
+type LootEntry = (Item, u8, u32);
+
+fn tool(id: u16, tool: ToolType, material: ToolMaterial) -> Item {
+    Item::Tool(id, ToolInfo::new_tool(tool, material))
+}
+
+fn food(id: u16, health: i32, stamina: u8) -> Item {
+    Item::Food(id, FoodInfo::new(health, stamina))
+}
+
+fn block(id: u16) -> Item {
+    Item::Block(Block::new_id(id), 1)
+}
+
+//The ids are the same as the ones in assets/item_aliases.impfile
+fn get_entries(table: LootTable) -> Vec<LootEntry> {
+    match table {
+        LootTable::Dungeon => vec![
+            //Coal, iron ingot, gold ingot, diamond
+            (Item::Sprite(2, 1), 8, 10),
+            (Item::Sprite(7, 1), 5, 8),
+            (Item::Sprite(8, 1), 4, 4),
+            (Item::Sprite(9, 1), 2, 1),
+            //Red dust
+            (Item::Sprite(11, 1), 6, 4),
+            //Bread and apples
+            (food(67, 5, 20), 1, 6),
+            (food(64, 2, 10), 1, 6),
+            (tool(42, ToolType::Pickaxe, ToolMaterial::Iron), 1, 2),
+            (tool(46, ToolType::Sword, ToolMaterial::Iron), 1, 2),
+            (Item::Bucket(13), 1, 1),
+        ],
+        LootTable::Village => vec![
+            //Bread, apples and wheat seeds
+            (food(67, 5, 20), 1, 10),
+            (food(64, 2, 10), 1, 10),
+            (block(77), 6, 8),
+            //Stick, coal and iron ingot
+            (Item::Sprite(1, 1), 8, 6),
+            (Item::Sprite(2, 1), 6, 6),
+            (Item::Sprite(7, 1), 3, 3),
+            //Torches and planks
+            (block(71), 8, 5),
+            (block(6), 12, 5),
+            (tool(37, ToolType::Pickaxe, ToolMaterial::Stone), 1, 2),
+            (tool(39, ToolType::Axe, ToolMaterial::Stone), 1, 2),
+            (Item::Bucket(12), 1, 1),
+        ],
+        LootTable::RuinedTower => vec![
+            //Coal, iron ingot and gold ingot
+            (Item::Sprite(2, 1), 6, 8),
+            (Item::Sprite(7, 1), 4, 6),
+            (Item::Sprite(8, 1), 3, 4),
+            //Stone bricks and torches
+            (block(10), 16, 4),
+            (block(71), 8, 4),
+            (food(64, 2, 10), 1, 4),
+            (tool(52, ToolType::Sword, ToolMaterial::Gold), 1, 1),
+            (tool(48, ToolType::Pickaxe, ToolMaterial::Gold), 1, 1),
+        ],
+        LootTable::DesertWell => vec![
+            //Sand, cactus and sugar cane
+            (block(11), 16, 6),
+            (block(88), 4, 4),
+            (block(69), 4, 4),
+            //Desert soup and gold ingot
+            (food(69, 1, 5), 1, 4),
+            (Item::Sprite(8, 1), 3, 2),
+            (Item::Bucket(12), 1, 2),
+        ],
+    }
+}
+
+fn pick_entry(entries: &[LootEntry], rng: &mut Rng) -> Option<LootEntry> {
+    let total: u32 = entries.iter().map(|(_, _, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut val = rng.u32(0..total);
+    for entry in entries {
+        if val < entry.2 {
+            return Some(*entry);
+        }
+        val -= entry.2;
+    }
+    None
+}
+
+//Creates the tile data for a chest with `rolls` random items from the loot
+//table, the items are put in random slots in the chest
+pub fn gen_chest_loot(table: LootTable, rolls: usize, rng: &mut Rng) -> TileData {
+    let mut chest = TileData::new_chest();
+    let entries = get_entries(table);
+    let w = chest.inventory.w();
+    let h = chest.inventory.h();
+    for _ in 0..rolls {
+        let Some((item, max_amount, _)) = pick_entry(&entries, rng) else {
+            break;
+        };
+        let amount = rng.u8(1..=max_amount.max(1));
+        let item = match item {
+            Item::Block(block, _) => Item::Block(block, amount),
+            Item::Sprite(id, _) => Item::Sprite(id, amount),
+            _ => item,
+        };
+
+        //Try a few random slots, if they are all full then the item is lost
+        for _ in 0..4 {
+            let (x, y) = (rng.usize(0..w), rng.usize(0..h));
+            if matches!(chest.inventory.get_item(x, y), Item::Empty) {
+                chest.inventory.set_item(x, y, item);
+                break;
+            }
+        }
+    }
+    chest
+}
@@ -0,0 +1,101 @@
+use super::{
+    get_height_mountain,
+    loot::{gen_chest_loot, LootTable},
+    Structure, WorldGenerator,
+};
+use crate::voxel::Block;
+use fastrand::Rng;
+
+pub const RADIUS: i32 = 6;
+//Radius of the walls of the tower
+const TOWER_RADIUS: i32 = 3;
+
+//Stone bricks that are starting to crumble
+fn wall_block(rng: &mut Rng) -> Block {
+    match rng.i32(0..10) {
+        0 | 1 => Block::new_id(16),
+        2 => Block::new_id(17),
+        _ => Block::new_id(10),
+    }
+}
+
+pub fn build(
+    structure: &mut Structure,
+    x: i32,
+    z: i32,
+    rng: &mut Rng,
+    world_generator: &WorldGenerator,
+) -> bool {
+    let h = get_height_mountain(x, z, world_generator);
+    if h <= world_generator.settings.sea_level {
+        return false;
+    }
+
+    let height = rng.i32(8..=14);
+    //The top few layers of the tower have fallen apart
+    let ruined = rng.i32(3..=5);
+    //Side of the tower that has the entrance
+    let (doorx, doorz) = [(-1, 0), (1, 0), (0, -1), (0, 1)][rng.usize(0..4)];
+    for ix in (x - TOWER_RADIUS)..=(x + TOWER_RADIUS) {
+        for iz in (z - TOWER_RADIUS)..=(z + TOWER_RADIUS) {
+            let (dx, dz) = (ix - x, iz - z);
+            let dist2 = dx * dx + dz * dz;
+            if dist2 > TOWER_RADIUS * TOWER_RADIUS + 1 {
+                continue;
+            }
+
+            structure.fill_foundation((ix, h, iz), 6, Block::new_id(16), world_generator);
+            structure.set_block(ix, h, iz, Block::new_id(16));
+            let wall = dist2 > (TOWER_RADIUS - 1) * (TOWER_RADIUS - 1);
+            let door = (dx * doorx > 0 && dz == 0) || (dz * doorz > 0 && dx == 0);
+            for iy in (h + 1)..=(h + height) {
+                let layer = iy - h;
+                if !wall || (door && layer <= 2) {
+                    structure.set_block(ix, iy, iz, Block::new());
+                    continue;
+                }
+
+                //Less of the wall is left the higher up it is
+                let top = height - ruined;
+                if layer > top && rng.i32(0..ruined + 1) < layer - top {
+                    structure.set_block(ix, iy, iz, Block::new());
+                    continue;
+                }
+
+                structure.set_block(ix, iy, iz, wall_block(rng));
+            }
+        }
+    }
+
+    //Rubble that has fallen off of the tower
+    for _ in 0..rng.i32(3..=8) {
+        let rx = x + rng.i32(-RADIUS..=RADIUS);
+        let rz = z + rng.i32(-RADIUS..=RADIUS);
+        let (dx, dz) = (rx - x, rz - z);
+        if dx * dx + dz * dz <= TOWER_RADIUS * TOWER_RADIUS + 1 {
+            continue;
+        }
+        let ground = get_height_mountain(rx, rz, world_generator);
+        if ground <= world_generator.settings.sea_level {
+            continue;
+        }
+        structure.set_block(rx, ground + 1, rz, wall_block(rng));
+    }
+
+    //Chest on the opposite side of the entrance
+    let (cx, cz) = (x - doorx * 2, z - doorz * 2);
+    let mut chest = Block::new_id(37);
+    //Face the entrance
+    let orientation = match (doorx, doorz) {
+        (1, _) => 1,
+        (-1, _) => 4,
+        (_, 1) => 2,
+        _ => 5,
+    };
+    chest.set_orientation(orientation);
+    structure.set_block(cx, h + 1, cz, chest);
+    let loot = gen_chest_loot(LootTable::RuinedTower, 6, rng);
+    structure.set_tile_data(cx, h + 1, cz, loot);
+
+    true
+}
@@ -0,0 +1,285 @@
+use super::{
+    get_height_mountain,
+    loot::{gen_chest_loot, LootTable},
+    Structure, WorldGenerator,
+};
+use crate::voxel::{Block, SLAB};
+use fastrand::Rng;
+use std::f64::consts::TAU;
+
+pub const RADIUS: i32 = 36;
+//Lots (houses and farms) are placed in a ring around the center of the village
+const MIN_LOT_DIST: f64 = 14.0;
+const MAX_LOT_DIST: f64 = 26.0;
+//Lots are skipped if the ground is too steep
+const MAX_SLOPE: i32 = 3;
+const WALL_HEIGHT: i32 = 3;
+
+fn cobblestone() -> Block {
+    Block::new_id(16)
+}
+
+//Flat area with a well in the center of the village
+fn build_center(
+    structure: &mut Structure,
+    x: i32,
+    h: i32,
+    z: i32,
+    world_generator: &WorldGenerator,
+) {
+    for ix in (x - 3)..=(x + 3) {
+        for iz in (z - 3)..=(z + 3) {
+            structure.fill_foundation((ix, h, iz), 4, Block::new_id(4), world_generator);
+        }
+    }
+    structure.fill((x - 3, h, z - 3), (x + 3, h, z + 3), cobblestone());
+    structure.fill((x - 3, h + 1, z - 3), (x + 3, h + 5, z + 3), Block::new());
+
+    structure.fill((x - 1, h - 3, z - 1), (x + 1, h + 1, z + 1), cobblestone());
+    structure.fill((x, h - 2, z), (x, h, z), Block::new_fluid(12));
+    structure.set_block(x, h + 1, z, Block::new());
+    for (dx, dz) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+        structure.fill(
+            (x + dx, h + 2, z + dz),
+            (x + dx, h + 3, z + dz),
+            Block::new_id(76),
+        );
+    }
+    let mut roof = Block::new_id(6);
+    roof.set_shape(SLAB);
+    structure.fill((x - 1, h + 4, z - 1), (x + 1, h + 4, z + 1), roof);
+}
+
+//Cobblestone path from (x1, z1) to (x2, z2), planks are used over water
+fn build_path(
+    structure: &mut Structure,
+    (x1, z1): (i32, i32),
+    (x2, z2): (i32, i32),
+    world_generator: &WorldGenerator,
+) {
+    let sea_level = world_generator.settings.sea_level;
+    let (dx, dz) = ((x2 - x1) as f64, (z2 - z1) as f64);
+    let steps = (dx.abs().max(dz.abs()) * 2.0).ceil() as i32;
+    for i in 0..=steps {
+        let t = i as f64 / steps.max(1) as f64;
+        let px = (x1 as f64 + dx * t).round() as i32;
+        let pz = (z1 as f64 + dz * t).round() as i32;
+        for (ix, iz) in [(px, pz), (px + 1, pz), (px, pz + 1)] {
+            let h = get_height_mountain(ix, iz, world_generator);
+            if h < sea_level {
+                structure.set_block(ix, sea_level, iz, Block::new_id(6));
+                continue;
+            }
+            structure.set_block(ix, h, iz, cobblestone());
+            structure.set_block(ix, h + 1, iz, Block::new());
+        }
+    }
+}
+
+//Returns the lowest and highest point of the terrain in the area
+fn get_height_range(
+    x: i32,
+    z: i32,
+    sx: i32,
+    sz: i32,
+    world_generator: &WorldGenerator,
+) -> (i32, i32) {
+    let mut range = (i32::MAX, i32::MIN);
+    for ix in (x - sx)..=(x + sx) {
+        for iz in (z - sz)..=(z + sz) {
+            let h = get_height_mountain(ix, iz, world_generator);
+            range = (range.0.min(h), range.1.max(h));
+        }
+    }
+    range
+}
+
+//(dx, dz) is the direction that the door faces
+fn build_house(
+    structure: &mut Structure,
+    (x, h, z): (i32, i32, i32),
+    (sx, sz): (i32, i32),
+    (dx, dz): (i32, i32),
+    index: usize,
+    rng: &mut Rng,
+    world_generator: &WorldGenerator,
+) {
+    let top = h + WALL_HEIGHT;
+    for ix in (x - sx)..=(x + sx) {
+        for iz in (z - sz)..=(z + sz) {
+            structure.fill_foundation((ix, h, iz), 6, cobblestone(), world_generator);
+            structure.set_block(ix, h, iz, cobblestone());
+            let edgex = ix.abs_diff(x) as i32 == sx;
+            let edgez = iz.abs_diff(z) as i32 == sz;
+            for iy in (h + 1)..=top {
+                let block = if edgex && edgez {
+                    Block::new_id(8)
+                } else if edgex || edgez {
+                    Block::new_id(6)
+                } else {
+                    Block::new()
+                };
+                structure.set_block(ix, iy, iz, block);
+            }
+        }
+    }
+
+    //Windows in the middle of each wall
+    structure.set_block(x - sx, h + 2, z, Block::new_id(9));
+    structure.set_block(x + sx, h + 2, z, Block::new_id(9));
+    structure.set_block(x, h + 2, z - sz, Block::new_id(9));
+    structure.set_block(x, h + 2, z + sz, Block::new_id(9));
+
+    //Roof
+    for layer in 0..=sx.min(sz) {
+        let (rx, rz) = (sx + 1 - layer, sz + 1 - layer);
+        let y = top + 1 + layer;
+        structure.fill((x - rx, y, z - rz), (x + rx, y, z + rz), Block::new_id(6));
+        structure.fill(
+            (x - rx, y + 1, z - rz),
+            (x + rx, y + 2, z + rz),
+            Block::new(),
+        );
+    }
+
+    //Door
+    let (doorx, doorz) = (x + dx * sx, z + dz * sz);
+    let orientation = match (dx, dz) {
+        (1, _) => 1,
+        (-1, _) => 4,
+        (_, 1) => 2,
+        _ => 5,
+    };
+    let mut door = Block::new_id(79);
+    door.set_orientation(orientation);
+    let mut door_top = door;
+    door_top.id = 81;
+    structure.set_block(doorx, h + 1, doorz, door);
+    structure.set_block(doorx, h + 2, doorz, door_top);
+    //Clear the area in front of the door
+    structure.fill(
+        (doorx + dx, h + 1, doorz + dz),
+        (doorx + dx, h + 2, doorz + dz),
+        Block::new(),
+    );
+
+    //Inside of the house, the first house always has a chest
+    let (backx, backz) = (x - dx * (sx - 1), z - dz * (sz - 1));
+    match index {
+        0 => {
+            let mut chest = Block::new_id(37);
+            chest.set_orientation(orientation);
+            structure.set_block(backx, h + 1, backz, chest);
+            let loot = gen_chest_loot(LootTable::Village, 6, rng);
+            structure.set_tile_data(backx, h + 1, backz, loot);
+        }
+        1 => {
+            let mut furnace = Block::new_id(40);
+            furnace.set_orientation(orientation);
+            structure.set_block(backx, h + 1, backz, furnace);
+        }
+        _ => {
+            if rng.i32(0..2) == 0 {
+                structure.set_block(backx, h + 1, backz, Block::new_id(82));
+            }
+        }
+    }
+    structure.set_block(
+        x + dz * (sx - 1),
+        h + 1,
+        z + dx * (sz - 1),
+        Block::new_id(71),
+    );
+}
+
+//Field of wheat with a row of water in the middle
+fn build_farm(
+    structure: &mut Structure,
+    (x, h, z): (i32, i32, i32),
+    (sx, sz): (i32, i32),
+    rng: &mut Rng,
+    world_generator: &WorldGenerator,
+) {
+    for ix in (x - sx)..=(x + sx) {
+        for iz in (z - sz)..=(z + sz) {
+            structure.fill_foundation((ix, h, iz), 6, Block::new_id(4), world_generator);
+            structure.fill((ix, h + 1, iz), (ix, h + 3, iz), Block::new());
+            let edge = ix.abs_diff(x) as i32 == sx || iz.abs_diff(z) as i32 == sz;
+            if edge {
+                structure.set_block(ix, h, iz, Block::new_id(8));
+            } else if ix == x {
+                structure.set_block(ix, h, iz, Block::new_fluid(12));
+            } else {
+                structure.set_block(ix, h, iz, Block::new_id(43));
+                let wheat = Block::new_id(rng.u16(50..=53));
+                structure.set_block(ix, h + 1, iz, wheat);
+            }
+        }
+    }
+}
+
+pub fn build(
+    structure: &mut Structure,
+    x: i32,
+    z: i32,
+    rng: &mut Rng,
+    world_generator: &WorldGenerator,
+) -> bool {
+    let sea_level = world_generator.settings.sea_level;
+    let h = get_height_mountain(x, z, world_generator);
+    if h <= sea_level {
+        return false;
+    }
+
+    //(position, size, direction of the door, is a farm)
+    let mut lots = vec![];
+    let lot_count = rng.usize(4..=7);
+    let angle_offset = rng.f64() * TAU;
+    for i in 0..lot_count {
+        let angle = angle_offset + i as f64 * TAU / lot_count as f64 + (rng.f64() - 0.5) * 0.3;
+        let dist = MIN_LOT_DIST + rng.f64() * (MAX_LOT_DIST - MIN_LOT_DIST);
+        let lotx = x + (angle.cos() * dist).round() as i32;
+        let lotz = z + (angle.sin() * dist).round() as i32;
+        let size = (rng.i32(2..=3), rng.i32(2..=3));
+        let farm = i > 0 && rng.i32(0..4) == 0;
+
+        let (min_h, max_h) = get_height_range(lotx, lotz, size.0, size.1, world_generator);
+        if min_h <= sea_level || max_h - min_h > MAX_SLOPE {
+            continue;
+        }
+        let lot_h = get_height_mountain(lotx, lotz, world_generator);
+
+        //The door faces the center of the village
+        let (offx, offz) = (x - lotx, z - lotz);
+        let dir = if offx.abs() > offz.abs() {
+            (offx.signum(), 0)
+        } else {
+            (0, offz.signum())
+        };
+        lots.push(((lotx, lot_h, lotz), size, dir, farm));
+    }
+
+    //A village needs at least one house
+    if lots.iter().all(|(_, _, _, farm)| *farm) {
+        return false;
+    }
+
+    for ((lotx, _, lotz), size, dir, _) in &lots {
+        let door = (lotx + dir.0 * (size.0 + 1), lotz + dir.1 * (size.1 + 1));
+        build_path(structure, (x, z), door, world_generator);
+    }
+
+    build_center(structure, x, h, z, world_generator);
+
+    let mut houses = 0;
+    for (pos, size, dir, farm) in lots {
+        if farm {
+            build_farm(structure, pos, size, rng, world_generator);
+        } else {
+            build_house(structure, pos, size, dir, houses, rng, world_generator);
+            houses += 1;
+        }
+    }
+
+    true
+}
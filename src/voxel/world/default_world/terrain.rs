@@ -1,5 +1,5 @@
 use crate::voxel::{world::WorldGenerator, CHUNK_SIZE_I32};
-use noise::{NoiseFn, Perlin};
+use noise::NoiseFn;
 use std::collections::HashMap;
 
 pub type HeightMap = HashMap<(i32, i32), Vec<i32>>;
@@ -19,9 +19,130 @@ fn cave_perc(y: i32, cave_y1: i32, cave_y2: i32, cave_y3: i32) -> f64 {
     }
 }
 
-pub fn is_noise_cave(x: i32, y: i32, z: i32, cave_noise: &Perlin) -> bool {
+pub fn is_noise_cave(x: i32, y: i32, z: i32, world_generator: &WorldGenerator) -> bool {
+    let settings = &world_generator.settings;
     let xyz = [x as f64 / 8.0, y as f64 / 8.0, z as f64 / 8.0];
-    cave_noise.get(xyz) < cave_perc(y, -64, -51, 64)
+    let perc = cave_perc(
+        y,
+        settings.cave_bottom,
+        settings.cave_middle,
+        settings.cave_top,
+    );
+    world_generator.noise_cave_generator.get(xyz) < perc
+}
+
+//Rivers lower the terrain around them up to VALLEY_WIDTH * river_width
+//away from the center of the river
+const VALLEY_WIDTH: f64 = 4.0;
+//How far below lake_threshold the terrain starts being lowered
+const LAKE_SHORE: f64 = 0.08;
+//How far above lake_threshold the lake reaches its full depth
+const LAKE_SLOPE: f64 = 0.15;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaterBody {
+    River,
+    Lake,
+}
+
+//How rivers and lakes lower the terrain in a column
+#[derive(Clone, Copy)]
+struct WaterCarve {
+    kind: WaterBody,
+    //Height of the river or lake bed
+    bed: i32,
+    //How much the terrain is lowered to the bed (0.0 to 1.0)
+    amount: f64,
+    //If the column is in the river or lake and not just on its banks
+    in_water: bool,
+}
+
+fn smoothstep(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn get_river_carve(x: i32, z: i32, world_generator: &WorldGenerator) -> Option<WaterCarve> {
+    let settings = &world_generator.settings;
+    let width = settings.river_width;
+    let dist = world_generator.get_river(x, z).abs();
+    if width <= 0.0 || dist >= width * VALLEY_WIDTH {
+        return None;
+    }
+
+    let in_water = dist < width;
+    //The banks of the river are lowered to sea level
+    let bed = if in_water {
+        let depth = (1.0 - dist / width) * settings.river_depth as f64;
+        settings.sea_level - 1 - depth.round() as i32
+    } else {
+        settings.sea_level
+    };
+    Some(WaterCarve {
+        kind: WaterBody::River,
+        bed,
+        amount: smoothstep((width * VALLEY_WIDTH - dist) / (width * (VALLEY_WIDTH - 1.0))),
+        in_water,
+    })
+}
+
+fn get_lake_carve(x: i32, z: i32, world_generator: &WorldGenerator) -> Option<WaterCarve> {
+    let settings = &world_generator.settings;
+    let threshold = settings.lake_threshold;
+    let val = world_generator.get_lake(x, z);
+    if threshold >= 1.0 || val <= threshold - LAKE_SHORE {
+        return None;
+    }
+
+    let in_water = val > threshold;
+    //The shore of the lake is lowered to sea level
+    let bed = if in_water {
+        let depth = ((val - threshold) / LAKE_SLOPE).min(1.0) * settings.lake_depth as f64;
+        settings.sea_level - 1 - depth.round() as i32
+    } else {
+        settings.sea_level
+    };
+    Some(WaterCarve {
+        kind: WaterBody::Lake,
+        bed,
+        amount: smoothstep((val - threshold + LAKE_SHORE) / LAKE_SHORE),
+        in_water,
+    })
+}
+
+//Terrain is never raised by rivers and lakes
+fn carve(h: i32, carve: Option<WaterCarve>) -> i32 {
+    match carve {
+        Some(carve) if h > carve.bed => {
+            let lowered = h as f64 + (carve.bed - h) as f64 * carve.amount;
+            (lowered.round() as i32).min(h)
+        }
+        _ => h,
+    }
+}
+
+fn carve_water(h: i32, x: i32, z: i32, world_generator: &WorldGenerator) -> i32 {
+    let river_h = carve(h, get_river_carve(x, z, world_generator));
+    let lake_h = carve(h, get_lake_carve(x, z, world_generator));
+    river_h.min(lake_h)
+}
+
+//Returns the river or lake that the column is in, `height` is the height of
+//the terrain in that column
+pub fn get_water_body(
+    x: i32,
+    z: i32,
+    height: i32,
+    world_generator: &WorldGenerator,
+) -> Option<WaterBody> {
+    let river = get_river_carve(x, z, world_generator);
+    let lake = get_lake_carve(x, z, world_generator);
+    //Columns that are deeper than the bed are in the ocean
+    [river, lake]
+        .into_iter()
+        .flatten()
+        .find(|carve| carve.in_water && height >= carve.bed)
+        .map(|carve| carve.kind)
 }
 
 pub fn get_height(x: i32, z: i32, world_generator: &WorldGenerator) -> i32 {
@@ -29,8 +150,10 @@ pub fn get_height(x: i32, z: i32, world_generator: &WorldGenerator) -> i32 {
     let steepness = world_generator.get_steepness(x, z);
     let elevation = world_generator.get_elevation(x, z);
 
+    let settings = &world_generator.settings;
     let transformed_noise = base + steepness * elevation;
-    (transformed_noise * 64.0) as i32
+    let h = (transformed_noise * settings.height_scale) as i32 + settings.height_offset;
+    carve_water(h, x, z, world_generator)
 }
 
 pub fn get_temperature(x: i32, z: i32, world_generator: &WorldGenerator) -> i32 {
@@ -38,41 +161,16 @@ pub fn get_temperature(x: i32, z: i32, world_generator: &WorldGenerator) -> i32
 }
 
 pub fn get_mountain(x: i32, z: i32, world_generator: &WorldGenerator) -> i32 {
-    (world_generator.get_mountain(x, z) * 80.0) as i32
+    let settings = &world_generator.settings;
+    let h = (world_generator.get_mountain(x, z) * settings.mountain_scale) as i32;
+    carve_water(h, x, z, world_generator)
 }
 
 pub fn get_height_mountain(x: i32, z: i32, world_generator: &WorldGenerator) -> i32 {
-    let mountain_h = (world_generator.get_mountain(x, z) * 80.0) as i32;
+    let mountain_h = get_mountain(x, z, world_generator);
     get_height(x, z, world_generator).max(mountain_h)
 }
 
-pub fn generate_heightmap(
-    positions: &Vec<(i32, i32, i32)>,
-    world_generator: &WorldGenerator,
-    height_func: fn(i32, i32, &WorldGenerator) -> i32,
-) -> HeightMap {
-    let mut heightmap = HeightMap::new();
-
-    for (chunkx, _, chunkz) in positions {
-        if heightmap.contains_key(&(*chunkx, *chunkz)) {
-            continue;
-        }
-        let posx = chunkx * CHUNK_SIZE_I32;
-        let posz = chunkz * CHUNK_SIZE_I32;
-        let mut heights = vec![0; (CHUNK_SIZE_I32 * CHUNK_SIZE_I32) as usize];
-        for x in posx..(posx + CHUNK_SIZE_I32) {
-            for z in posz..(posz + CHUNK_SIZE_I32) {
-                let index = ((z - posz) * CHUNK_SIZE_I32 + (x - posx)) as usize;
-                let h = height_func(x, z, world_generator);
-                heights[index] = h;
-            }
-        }
-        heightmap.insert((*chunkx, *chunkz), heights);
-    }
-
-    heightmap
-}
-
 pub fn add_to_heightmap(
     chunkx: i32,
     chunkz: i32,
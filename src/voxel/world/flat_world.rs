@@ -1,117 +1,135 @@
-use std::collections::HashSet;
+use super::{
+    chunk_generator::{gen_chunks_parallel, ChunkGenerator},
+    WorldGenerator,
+};
+use crate::voxel::{block_registry::get_block_registry, Block, Chunk, CHUNK_SIZE_I32};
 
-use super::World;
-use crate::voxel::{Block, Chunk, CHUNK_SIZE_I32, INDESTRUCTIBLE};
-use crossbeam::{queue::ArrayQueue, thread};
+//Layers are listed from the bottom to the top and the top layer is at y = -1
+pub const DEFAULT_FLAT_PRESET: &str = "bedrock,58*stone,3*dirt,grass";
+const MAX_LAYERS: u32 = 512;
 
-fn gen_flat_chunk(chunk: &mut Chunk) {
-    let chunkpos = chunk.get_chunk_pos();
-    let posx = chunkpos.x * CHUNK_SIZE_I32;
-    let posy = chunkpos.y * CHUNK_SIZE_I32;
-    let posz = chunkpos.z * CHUNK_SIZE_I32;
+//Parses a comma separated list of layers, each layer is either a block name
+//or `count*block_name`, `air` can be used for empty layers
+fn parse_layers(preset: &str) -> Result<Vec<Block>, String> {
+    let registry = get_block_registry().ok_or("blocks have not been loaded")?;
 
-    if chunkpos.y > 1 || chunkpos.y < -4 {
-        return;
-    }
+    let mut layers = vec![];
+    for layer in preset.split(',') {
+        let layer = layer.trim();
+        let (count, name) = match layer.split_once('*') {
+            Some((count, name)) => {
+                let count = count
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid layer count in '{layer}'"))?;
+                (count, name.trim())
+            }
+            None => (1, layer),
+        };
 
-    for x in posx..(posx + CHUNK_SIZE_I32) {
-        for y in posy..(posy + CHUNK_SIZE_I32) {
-            for z in posz..(posz + CHUNK_SIZE_I32) {
-                if y == -1 {
-                    //Grass
-                    chunk.set_block(x, y, z, Block::new_id(1));
-                } else if (-4..-1).contains(&y) {
-                    chunk.set_block(x, y, z, Block::new_id(4));
-                } else if (-62..-4).contains(&y) {
-                    //Stone
-                    chunk.set_block(x, y, z, Block::new_id(2));
-                } else if y == -63 {
-                    //Bottom of the world
-                    chunk.set_block(x, y, z, Block::new_id(INDESTRUCTIBLE));
-                }
+        if count == 0 {
+            return Err(format!("layer '{layer}' must have at least 1 block"));
+        }
+
+        if layers.len() as u32 + count > MAX_LAYERS {
+            return Err(format!("can not have more than {MAX_LAYERS} layers"));
+        }
+
+        let block = if name == "air" {
+            Block::new()
+        } else {
+            let id = registry
+                .get_id(name)
+                .ok_or(format!("unknown block '{name}'"))?;
+            let block = Block::new_id(id);
+            if block.is_fluid() {
+                Block::new_fluid(id)
+            } else {
+                block
             }
+        };
+
+        for _ in 0..count {
+            layers.push(block);
         }
     }
+
+    Ok(layers)
 }
 
-impl World {
-    //Generates a flat world
-    pub fn gen_flat(&mut self) {
-        for chunk in &mut self.chunks.values_mut() {
-            gen_flat_chunk(chunk);
-        }
+pub struct FlatGenerator {
+    //Bottom layer first
+    layers: Vec<Block>,
+}
+
+impl FlatGenerator {
+    pub fn from_preset(preset: &str) -> Result<Self, String> {
+        Ok(Self {
+            layers: parse_layers(preset)?,
+        })
     }
 
-    //Generates missing flat world chunks on load
-    pub fn gen_flat_on_load(&mut self) {
-        let mut to_generate = HashSet::new();
-        for y in (self.centery - self.range)..=(self.centery + self.range) {
-            for z in (self.centerz - self.range)..=(self.centerz + self.range) {
-                for x in (self.centerx - self.range)..=(self.centerx + self.range) {
-                    if self.chunks.contains_key(&(x, y, z)) {
-                        continue;
-                    }
-                    to_generate.insert((x, y, z));
-                }
-            }
+    fn gen_flat_chunk(&self, chunk: &mut Chunk) {
+        let chunkpos = chunk.get_chunk_pos();
+        let posx = chunkpos.x * CHUNK_SIZE_I32;
+        let posy = chunkpos.y * CHUNK_SIZE_I32;
+        let posz = chunkpos.z * CHUNK_SIZE_I32;
+
+        let bottom = -(self.layers.len() as i32);
+        if posy + CHUNK_SIZE_I32 <= bottom || posy >= 0 {
+            return;
         }
 
-        //Generate new chunks
-        for (chunkx, chunky, chunkz) in &to_generate {
-            let pos = (*chunkx, *chunky, *chunkz);
-            if self.chunks.contains_key(&pos) {
+        for y in posy.max(bottom)..(posy + CHUNK_SIZE_I32).min(0) {
+            let block = self.layers[(y - bottom) as usize];
+            if block.id == 0 {
                 continue;
             }
-            let mut new_chunk = Chunk::new(*chunkx, *chunky, *chunkz);
-            gen_flat_chunk(&mut new_chunk);
-            self.chunks.insert(pos, new_chunk);
+
+            for x in posx..(posx + CHUNK_SIZE_I32) {
+                for z in posz..(posz + CHUNK_SIZE_I32) {
+                    chunk.set_block(x, y, z, block);
+                }
+            }
         }
     }
+}
 
-    pub fn generate_column_flat(&mut self, x: i32, z: i32, yvals: &HashSet<i32>) {
-        //Generate new chunks
-        let start = std::time::Instant::now();
-        let generated = ArrayQueue::new(yvals.len());
-        let mut generated_count = 0;
-        thread::scope(|s| {
-            for y in yvals {
-                if !self.in_range(x, *y, z) {
-                    continue;
-                }
+impl ChunkGenerator for FlatGenerator {
+    fn generate_column(&self, _: i32, _: i32, chunks: &mut [Chunk], _: &WorldGenerator) {
+        gen_chunks_parallel(chunks, |chunk| self.gen_flat_chunk(chunk));
+    }
 
-                if self.chunks.contains_key(&(x, *y, z)) {
-                    continue;
-                }
+    fn bottom(&self) -> i32 {
+        (-128).min(-(self.layers.len() as i32) - 64)
+    }
+}
 
-                generated_count += 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::{init_test_registries, INDESTRUCTIBLE};
 
-                s.spawn(|_| {
-                    let mut new_chunk = Chunk::new(x, *y, z);
-                    gen_flat_chunk(&mut new_chunk);
-                    //This should never fail
-                    generated
-                        .push(new_chunk)
-                        .expect("Error: Failed to push onto ArrayQueue");
-                });
-            }
-        })
-        .expect("Failed to generate new chunks!");
+    #[test]
+    fn test_parse_layers() {
+        init_test_registries();
 
-        for chunk in generated {
-            let chunkpos = chunk.get_chunk_pos();
-            let pos = (chunkpos.x, chunkpos.y, chunkpos.z);
-            self.chunks.insert(pos, chunk);
-        }
-        let time = start.elapsed().as_millis();
-        if time > 15 {
-            //Only report time taken if it exceeds 15 ms
-            eprintln!("Took {time} ms to generate {generated_count} new chunks");
-        }
-    }
+        let layers = parse_layers(DEFAULT_FLAT_PRESET).unwrap();
+        assert_eq!(layers.len(), 63);
+        assert_eq!(layers[0].id, INDESTRUCTIBLE);
+        assert!(layers[1..59].iter().all(|block| block.id == 2));
+        assert!(layers[59..62].iter().all(|block| block.id == 4));
+        assert_eq!(layers[62].id, 1);
+
+        let layers = parse_layers(" bedrock , 2 * air, water ").unwrap();
+        assert_eq!(layers.len(), 4);
+        assert_eq!(layers[1].id, 0);
+        assert_eq!(layers[3], Block::new_fluid(12));
 
-    pub fn add_flat_chunk(&mut self, chunkx: i32, chunky: i32, chunkz: i32) {
-        let mut chunk = Chunk::new(chunkx, chunky, chunkz);
-        gen_flat_chunk(&mut chunk);
-        self.chunks.insert((chunkx, chunky, chunkz), chunk);
+        assert!(parse_layers("bedrock,not_a_block").is_err());
+        assert!(parse_layers("0*stone").is_err());
+        assert!(parse_layers("x*stone").is_err());
+        assert!(parse_layers("").is_err());
+        assert!(parse_layers("1000*stone").is_err());
     }
 }
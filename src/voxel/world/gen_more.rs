@@ -1,12 +1,9 @@
 use cgmath::Vector3;
 
-use super::{World, WorldGenType};
+use super::World;
 use crate::{
     gfx::{ChunkTables, ChunkVaoTable},
-    voxel::{
-        region::{chunkpos_to_regionpos, Region},
-        Chunk, CHUNK_SIZE_F32,
-    },
+    voxel::{region::chunkpos_to_regionpos, Chunk, CHUNK_SIZE_F32},
 };
 use std::collections::{HashMap, HashSet, VecDeque};
 
@@ -272,17 +269,23 @@ impl World {
             }
             loaded.insert((rx, ry, rz));
 
-            if let Some(region) = Region::load_region(&self.path, rx, ry, rz) {
+            if let Some(region) = self.load_region(rx, ry, rz) {
                 self.add_region_col(region, x, z);
             }
         }
 
         //Generate the new chunks
-        match self.gen_type {
-            WorldGenType::Flat => self.generate_column_flat(x, z, &yvals),
-            WorldGenType::OldGen => self.generate_column_old(x, z, &yvals),
-            WorldGenType::DefaultGen => self.generate_column_default(x, z, &yvals),
-            WorldGenType::Skyblock => self.generate_column_skyblock(x, z, &yvals),
+        let start = std::time::Instant::now();
+        let to_generate = yvals
+            .iter()
+            .map(|y| (x, *y, z))
+            .filter(|(x, y, z)| self.in_range(*x, *y, *z))
+            .collect();
+        let generated_count = self.generate_chunks(&to_generate);
+        let time = start.elapsed().as_millis();
+        if time > 15 {
+            //Only report time taken if it exceeds 15 ms
+            eprintln!("Took {time} ms to generate {generated_count} new chunks");
         }
 
         for y in yvals.iter().copied() {
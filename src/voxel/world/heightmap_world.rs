@@ -0,0 +1,367 @@
+/*
+ * Generates terrain from a grayscale PNG, black pixels are at min_height and
+ * white pixels are at max_height. The options are a comma separated list of
+ * `name=value` pairs:
+ *   image - path to the PNG (required)
+ *   scale - number of blocks per pixel (defaults to 1)
+ *   origin_x, origin_z - position of the top left corner of the image
+ *   (defaults to centering the image on 0, 0)
+ *   min_height, max_height - height of black and white pixels
+ *   fallback - what generates outside of the image: ocean (default) or
+ *   default (the default world generator)
+ * The image is not copied into the world, so a heightmap world can not be
+ * loaded if its image is moved or deleted (instead of being loaded with a
+ * different image and saving over the original options).
+ * */
+
+use super::{
+    chunk_generator::{gen_chunks_parallel, ChunkGenerator},
+    default_world::DefaultGenerator,
+    terrain_settings::{TerrainSettings, DEFAULT_TERRAIN_PRESET},
+    WorldGenerator,
+};
+use crate::assets::texture::load_grayscale_pixels;
+use crate::voxel::biome_registry::{get_biome_registry, BiomeDef, BiomeRegistry};
+use crate::voxel::{Block, Chunk, CHUNK_SIZE, CHUNK_SIZE_I32, INDESTRUCTIBLE};
+
+pub const DEFAULT_HEIGHTMAP_PRESET: &str =
+    "image=assets/heightmaps/island.png,scale=2,min_height=-24,max_height=80";
+const BOTTOM_OF_WORLD: i32 = -64;
+//Depth of the ocean outside of the image
+const OCEAN_DEPTH: i32 = 16;
+//Number of blocks beneath the surface that use the `under` block of the biome
+const UNDER_DEPTH: i32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Fallback {
+    Ocean,
+    Default,
+}
+
+pub struct HeightmapGenerator {
+    //Values from 0.0 to 1.0, row by row
+    pixels: Vec<f32>,
+    width: u32,
+    height: u32,
+    scale: f64,
+    origin: (i32, i32),
+    min_height: i32,
+    max_height: i32,
+    fallback: Fallback,
+    //Only used if the fallback is the default generator
+    default_generator: Option<DefaultGenerator>,
+    //The surface of the image uses the blocks of the default biome
+    biomes: &'static BiomeRegistry,
+}
+
+fn parse_value<T: std::str::FromStr>(var: &str, val: &str) -> Result<T, String> {
+    val.parse::<T>()
+        .map_err(|_| format!("invalid value '{val}' for {var}"))
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+impl HeightmapGenerator {
+    pub fn from_preset(preset: &str) -> Result<Self, String> {
+        let mut image = None;
+        let mut scale = 1.0;
+        let (mut origin_x, mut origin_z) = (None, None);
+        let (mut min_height, mut max_height) = (-24, 80);
+        let mut fallback = Fallback::Ocean;
+        for option in preset.split(',').filter(|s| !s.trim().is_empty()) {
+            let (var, val) = option
+                .split_once('=')
+                .ok_or(format!("expected name=value, got '{}'", option.trim()))?;
+            let (var, val) = (var.trim(), val.trim());
+            match var {
+                "image" => image = Some(val.to_string()),
+                "scale" => scale = parse_value(var, val)?,
+                "origin_x" => origin_x = Some(parse_value(var, val)?),
+                "origin_z" => origin_z = Some(parse_value(var, val)?),
+                "min_height" => min_height = parse_value(var, val)?,
+                "max_height" => max_height = parse_value(var, val)?,
+                "fallback" => {
+                    fallback = match val {
+                        "ocean" => Fallback::Ocean,
+                        "default" => Fallback::Default,
+                        _ => return Err(format!("invalid value '{val}' for {var}")),
+                    };
+                }
+                _ => return Err(format!("unknown option '{var}'")),
+            }
+        }
+
+        let image = image.ok_or("missing image")?;
+        if scale <= 0.0 {
+            return Err("scale must be greater than 0".to_string());
+        }
+        if min_height >= max_height {
+            return Err("min_height must be less than max_height".to_string());
+        }
+        if min_height <= BOTTOM_OF_WORLD {
+            return Err(format!("min_height must be above {BOTTOM_OF_WORLD}"));
+        }
+
+        if !std::path::Path::new(&image).is_file() {
+            return Err(format!("image '{image}' does not exist"));
+        }
+        let (pixels, width, height) = load_grayscale_pixels(&image)
+            .map_err(|msg| format!("failed to load image '{image}': {msg}"))?;
+        if pixels.is_empty() {
+            return Err(format!("image '{image}' is empty"));
+        }
+
+        //Center the image on (0, 0) by default
+        let origin = (
+            origin_x.unwrap_or(-(width as f64 * scale / 2.0) as i32),
+            origin_z.unwrap_or(-(height as f64 * scale / 2.0) as i32),
+        );
+
+        let biomes = get_biome_registry().ok_or("biomes have not been loaded")?;
+        let default_generator = match fallback {
+            Fallback::Ocean => None,
+            Fallback::Default => Some(DefaultGenerator::from_preset(DEFAULT_TERRAIN_PRESET)?),
+        };
+
+        Ok(Self {
+            pixels,
+            width,
+            height,
+            scale,
+            origin,
+            min_height,
+            max_height,
+            fallback,
+            default_generator,
+            biomes,
+        })
+    }
+
+    fn get_pixel(&self, x: usize, z: usize) -> f64 {
+        self.pixels[z * self.width as usize + x] as f64
+    }
+
+    //Returns None if (x, z) is outside of the image, the pixels are
+    //interpolated if the scale is greater than 1
+    fn get_height(&self, x: i32, z: i32) -> Option<i32> {
+        let px = (x - self.origin.0) as f64 / self.scale;
+        let pz = (z - self.origin.1) as f64 / self.scale;
+        let (w, h) = ((self.width - 1) as f64, (self.height - 1) as f64);
+        if px < 0.0 || pz < 0.0 || px > w || pz > h {
+            return None;
+        }
+
+        let (x0, z0) = (px.floor() as usize, pz.floor() as usize);
+        let x1 = (x0 + 1).min(self.width as usize - 1);
+        let z1 = (z0 + 1).min(self.height as usize - 1);
+        let (tx, tz) = (px.fract(), pz.fract());
+        let top = lerp(self.get_pixel(x0, z0), self.get_pixel(x1, z0), tx);
+        let bot = lerp(self.get_pixel(x0, z1), self.get_pixel(x1, z1), tx);
+        let val = lerp(top, bot, tz);
+        let range = (self.max_height - self.min_height) as f64;
+        Some(self.min_height + (val * range).round() as i32)
+    }
+
+    fn gen_column(
+        chunk: &mut Chunk,
+        (x, z): (i32, i32),
+        height: i32,
+        sea_level: i32,
+        biome: &BiomeDef,
+    ) {
+        let posy = chunk.get_chunk_pos().y * CHUNK_SIZE_I32;
+        for y in posy.max(BOTTOM_OF_WORLD)..(posy + CHUNK_SIZE_I32) {
+            let block = if y == BOTTOM_OF_WORLD {
+                Block::new_id(INDESTRUCTIBLE)
+            } else if y < height - UNDER_DEPTH {
+                //Stone
+                Block::new_id(2)
+            } else if y < height || (y == height && height < sea_level) {
+                biome.under
+            } else if y == height {
+                biome.surface
+            } else if y <= sea_level {
+                Block::new_fluid(12)
+            } else {
+                break;
+            };
+            chunk.set_block(x, y, z, block);
+        }
+    }
+
+    fn clear_column(chunk: &mut Chunk, x: i32, z: i32) {
+        let posy = chunk.get_chunk_pos().y * CHUNK_SIZE_I32;
+        for y in posy..(posy + CHUNK_SIZE_I32) {
+            chunk.set_block(x, y, z, Block::new());
+            chunk.set_tile_data(x, y, z, None);
+        }
+    }
+}
+
+impl ChunkGenerator for HeightmapGenerator {
+    fn generate_column(
+        &self,
+        x: i32,
+        z: i32,
+        chunks: &mut [Chunk],
+        world_generator: &WorldGenerator,
+    ) {
+        let posx = x * CHUNK_SIZE_I32;
+        let posz = z * CHUNK_SIZE_I32;
+        let heights: Vec<Option<i32>> = (0..(CHUNK_SIZE * CHUNK_SIZE) as i32)
+            .map(|i| self.get_height(posx + i % CHUNK_SIZE_I32, posz + i / CHUNK_SIZE_I32))
+            .collect();
+
+        //The default generator fills in the columns outside of the image and
+        //then the columns inside of the image are replaced
+        let default_generator = self
+            .default_generator
+            .as_ref()
+            .filter(|_| heights.iter().any(|h| h.is_none()));
+        if let Some(generator) = default_generator {
+            generator.generate_column(x, z, chunks, world_generator);
+            if heights.iter().all(|h| h.is_none()) {
+                return;
+            }
+        }
+
+        let sea_level = world_generator.settings.sea_level;
+        let top = self.max_height.max(sea_level);
+        let biome = self.biomes.default_biome();
+        gen_chunks_parallel(chunks, |chunk| {
+            let posy = chunk.get_chunk_pos().y * CHUNK_SIZE_I32;
+            let in_range = posy <= top && posy + CHUNK_SIZE_I32 > BOTTOM_OF_WORLD;
+            for (i, h) in heights.iter().enumerate() {
+                let ix = posx + i as i32 % CHUNK_SIZE_I32;
+                let iz = posz + i as i32 / CHUNK_SIZE_I32;
+                let h = match (h, self.fallback) {
+                    (Some(h), _) => *h,
+                    (None, Fallback::Ocean) => sea_level - OCEAN_DEPTH,
+                    (None, Fallback::Default) => continue,
+                };
+
+                if default_generator.is_some() {
+                    Self::clear_column(chunk, ix, iz);
+                }
+
+                if in_range {
+                    Self::gen_column(chunk, (ix, iz), h, sea_level, biome);
+                }
+            }
+        });
+    }
+
+    fn terrain_settings(&self) -> TerrainSettings {
+        match &self.default_generator {
+            Some(generator) => generator.terrain_settings(),
+            None => TerrainSettings::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::GameMode,
+        impfile::Entry,
+        voxel::{init_test_registries, World},
+    };
+    use std::collections::HashSet;
+    use std::fs::File;
+
+    //Writes a 4x2 grayscale image
+    fn write_test_image(path: &str) {
+        let file = File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(file, 4, 2);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[0, 51, 102, 255, 255, 255, 255, 255])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_heightmap_generator() {
+        init_test_registries();
+        assert!(HeightmapGenerator::from_preset(DEFAULT_HEIGHTMAP_PRESET).is_ok());
+
+        let path = std::env::temp_dir().join(format!(
+            "voxelworld_test_heightmap_{}.png",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        write_test_image(path);
+
+        let preset =
+            format!("image={path},scale=2,origin_x=0,origin_z=0,min_height=-20,max_height=30");
+        let generator = HeightmapGenerator::from_preset(&preset).unwrap();
+        assert_eq!(generator.get_height(0, 0), Some(-20));
+        assert_eq!(generator.get_height(2, 0), Some(-10));
+        //Interpolated between two pixels
+        assert_eq!(generator.get_height(3, 0), Some(-5));
+        assert_eq!(generator.get_height(6, 2), Some(30));
+        assert_eq!(generator.get_height(7, 0), None);
+        assert_eq!(generator.get_height(0, 3), None);
+        assert_eq!(generator.get_height(-1, 0), None);
+
+        let errors = [
+            "scale=2",
+            &format!("image={path},scale=0"),
+            &format!("image={path},min_height=10,max_height=10"),
+            &format!("image={path},fallback=lava"),
+            &format!("image={path},size=4"),
+            "image=not_a_file.png",
+        ];
+        for preset in errors {
+            assert!(HeightmapGenerator::from_preset(preset).is_err(), "{preset}");
+        }
+
+        let mut world = World::new(0, 1, "heightmap", &preset, GameMode::Creative).unwrap();
+        assert_eq!(world.gen_type, "heightmap");
+        //Remove the empty chunks that are created with the world
+        world.chunks.clear();
+        let chunks: HashSet<(i32, i32, i32)> = (-4..=2).map(|y| (0, y, 0)).collect();
+        world.generate_chunks(&chunks);
+        let biome = get_biome_registry().unwrap().default_biome();
+        //Under the sea
+        assert_eq!(world.get_block(0, -20, 0), biome.under);
+        assert_eq!(world.get_block(0, -19, 0), Block::new_fluid(12));
+        //Above the sea
+        assert_eq!(world.get_block(6, 30, 0), biome.surface);
+        assert_eq!(world.get_block(6, 29, 0), biome.under);
+        assert_eq!(world.get_block(6, 26, 0).id, 2);
+        assert_eq!(world.get_block(6, 31, 0).id, 0);
+        assert_eq!(world.get_block(6, BOTTOM_OF_WORLD, 0).id, INDESTRUCTIBLE);
+        //Outside of the image
+        assert_eq!(world.get_block(10, -OCEAN_DEPTH, 10), biome.under);
+        assert_eq!(world.get_block(10, 0, 10), Block::new_fluid(12));
+        assert_eq!(world.get_block(10, 1, 10).id, 0);
+
+        //A saved world whose image is missing fails to load
+        let world_path = std::env::temp_dir()
+            .join(format!(
+                "voxelworld_test_heightmap_world_{}/",
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .to_string();
+        std::fs::create_dir_all(&world_path).unwrap();
+        let mut entry = Entry::new("world");
+        entry.add_string("gen_type", "heightmap");
+        entry.add_string("gen_options", &preset);
+        std::fs::write(
+            world_path.clone() + "world.impfile",
+            entry.to_impfile_string(),
+        )
+        .unwrap();
+        assert!(World::load_world_metadata(&world_path, 1).is_ok());
+        std::fs::remove_file(path).unwrap();
+        let msg = World::load_world_metadata(&world_path, 1).err().unwrap();
+        assert!(msg.contains("does not exist"));
+        std::fs::remove_dir_all(&world_path).unwrap();
+    }
+}
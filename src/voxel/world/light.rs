@@ -652,16 +652,49 @@ pub fn get_neighbor_sky_srcs(
 }
 
 impl World {
-    //Called when the world is first loaded
-    pub fn init_block_light(&mut self) {
+    //Called when the world is first loaded, light is only calculated in
+    //chunks that did not have their light loaded from the save file
+    pub fn init_light(&mut self) {
+        let unlit: HashSet<(i32, i32, i32)> = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| !chunk.light_initialized())
+            .map(|(pos, _)| *pos)
+            .collect();
+        eprintln!(
+            "Loaded light for {} chunks",
+            self.chunks.len() - unlit.len()
+        );
+        self.init_block_light(&unlit);
+        self.init_sky_light(&unlit);
+    }
+
+    fn init_block_light(&mut self, unlit: &HashSet<(i32, i32, i32)>) {
         let start = std::time::Instant::now();
 
         let mut srcs = vec![];
-        for chunk in self.chunks.values() {
-            chunk.get_light_srcs(&mut srcs);
+        for pos in unlit {
+            if let Some(chunk) = self.chunks.get(pos) {
+                chunk.get_light_srcs(&mut srcs);
+            }
         }
         propagate_fast(self, &srcs);
 
+        //Light coming from chunks that already have their light
+        let mut neighbor_srcs = HashMap::new();
+        for pos in unlit {
+            if let Some(chunk) = self.chunks.get(pos) {
+                get_neighbor_srcs(chunk, &self.get_adjacent(chunk), &mut neighbor_srcs, unlit);
+            }
+        }
+        let neighbor_srcs: Vec<((i32, i32, i32), LightSrc)> = neighbor_srcs
+            .iter()
+            .map(|((x, y, z), light)| {
+                ((*x, *y, *z), LightSrc::new(light.r(), light.g(), light.b()))
+            })
+            .collect();
+        propagate_fast(self, &neighbor_srcs);
+
         let time = start.elapsed().as_millis();
         eprintln!("Took {time} ms to init light");
     }
@@ -682,8 +715,7 @@ impl World {
         }
     }
 
-    //Called when the world is first loaded
-    pub fn init_sky_light(&mut self) {
+    fn init_sky_light(&mut self, unlit: &HashSet<(i32, i32, i32)>) {
         let start = std::time::Instant::now();
 
         for (x, _, z) in self.chunks.keys().copied() {
@@ -699,10 +731,11 @@ impl World {
             }
         }
 
-        for chunk in self.chunks.values_mut() {
-            let chunkpos = chunk.get_chunk_pos();
-            let x = chunkpos.x;
-            let z = chunkpos.z;
+        for (pos, chunk) in self.chunks.iter_mut() {
+            if !unlit.contains(pos) {
+                continue;
+            }
+            let (x, _, z) = *pos;
             if let Some(map) = self.skylightmap.get(&(x, z)) {
                 chunk.init_sky_light(map);
             }
@@ -710,8 +743,15 @@ impl World {
 
         let heights = self.get_skylightmap_heights(None);
         let mut srcs = vec![];
-        for chunk in self.chunks.values() {
-            chunk.get_sky_light_srcs(self, &heights, &mut srcs);
+        let mut sky_srcs = HashMap::new();
+        for pos in unlit {
+            if let Some(chunk) = self.chunks.get(pos) {
+                chunk.get_sky_light_srcs(self, &heights, &mut srcs);
+                get_neighbor_sky_srcs(chunk, &self.get_adjacent(chunk), &mut sky_srcs, unlit);
+            }
+        }
+        for ((x, y, z), sky_src) in sky_srcs {
+            srcs.push(((x, y, z), LightSrc::new(sky_src, sky_src, sky_src)));
         }
         propagate_sky_fast(self, &srcs);
 
@@ -839,6 +879,9 @@ impl World {
         updated.extend(propagate_updates(self, &block_updates));
 
         updated.extend(self.update_sky_light(positions));
+        //Light is saved with the chunk so any chunk that had its light changed
+        //needs to be saved again
+        self.to_save.extend(updated.iter().copied());
 
         updated
     }
@@ -875,11 +918,15 @@ impl World {
     }
 
     //Get any chunks that need to be updated and how many chunks are under a chunk
+    //Chunks in `lit` already have their sky light and only need to be updated
+    //if a chunk without light was loaded above them
     fn get_to_update(
         &self,
         chunks: &HashSet<(i32, i32, i32)>,
+        lit: &HashSet<(i32, i32, i32)>,
     ) -> (ChunkList, HashMap<(i32, i32, i32), i32>) {
-        let mut to_update = ChunkList::from_iter(chunks.iter().copied());
+        let mut to_update =
+            ChunkList::from_iter(chunks.iter().copied().filter(|pos| !lit.contains(pos)));
         let mut columns = HashMap::<(i32, i32), Vec<i32>>::new();
         let mut under_chunk = HashMap::<(i32, i32, i32), i32>::new();
 
@@ -912,7 +959,13 @@ impl World {
                 }
             }
 
-            to_update.extend(under);
+            //Chunks that were saved together already have the correct light
+            let saved = lit.contains(&(x, y, z));
+            to_update.extend(
+                under
+                    .into_iter()
+                    .filter(|pos| !(saved && lit.contains(pos))),
+            );
         }
 
         (to_update, under_chunk)
@@ -928,7 +981,14 @@ impl World {
     //the moment, there could be room for optimizations and there could be some
     //cases where this function causes some slight issues with performance but
     //those cases likely won't happen to most users (I hope).
-    fn init_sky_light_new_chunks(&mut self, chunks: &HashSet<(i32, i32, i32)>) -> ChunkList {
+    //Chunks in `lit` already have their light from the save file or the chunk
+    //cache so their sky light is only recalculated if the chunks loaded above
+    //them change it.
+    fn init_sky_light_new_chunks(
+        &mut self,
+        chunks: &HashSet<(i32, i32, i32)>,
+        lit: &HashSet<(i32, i32, i32)>,
+    ) -> ChunkList {
         let start = std::time::Instant::now();
 
         //Clean out sky light map
@@ -949,7 +1009,7 @@ impl World {
         }
 
         //Get any chunks that need to be updated
-        let (to_update, under_chunk) = self.get_to_update(chunks);
+        let (to_update, under_chunk) = self.get_to_update(chunks, lit);
 
         //Begin initialization of sky light
         let mut skip = ChunkList::new();
@@ -1126,6 +1186,31 @@ impl World {
         }
 
         //Initialize sky light in new chunks and return any chunks that have been updated
-        self.init_sky_light_new_chunks(chunks)
+        let lit = chunks.difference(&initialized).copied().collect();
+        self.init_sky_light_new_chunks(chunks, &lit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::GameMode,
+        voxel::{init_test_registries, Block, Chunk, World},
+    };
+
+    #[test]
+    fn test_light_update_saves_chunks() {
+        init_test_registries();
+        let mut world = World::new(0, 0, "flat", "", GameMode::Creative).unwrap();
+        world.chunks.insert((0, 0, 0), Chunk::new(0, 0, 0));
+        world.chunks.insert((1, 0, 0), Chunk::new(1, 0, 0));
+        world.to_save.clear();
+
+        //A torch on the edge of a chunk lights up the chunk next to it
+        world.set_block(15, 8, 8, Block::new_id(71));
+        let updated = world.update_block_light(&[(15, 8, 8)]);
+        assert!(updated.contains(&(1, 0, 0)));
+        assert!(world.to_save.contains(&(0, 0, 0)));
+        assert!(world.to_save.contains(&(1, 0, 0)));
     }
 }
@@ -8,7 +8,7 @@ fn gen_vein(
     xyz: (i32, i32, i32),
     size1: i32,
     size2: i32,
-    ore: u8,
+    ore: u16,
     probability: f64,
     rng: &mut Rng,
 ) {
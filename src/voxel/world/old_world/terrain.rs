@@ -38,33 +38,6 @@ pub fn get_height(x: i32, z: i32, terrain_generator: &Fbm<Perlin>, steepness: &P
     (transformed_noise * 64.0) as i32 + 12
 }
 
-pub fn generate_heightmap(
-    positions: &Vec<(i32, i32, i32)>,
-    terrain_generator: &Fbm<Perlin>,
-    steepness: &Perlin,
-) -> HeightMap {
-    let mut heightmap = HeightMap::new();
-
-    for (chunkx, _, chunkz) in positions {
-        if heightmap.contains_key(&(*chunkx, *chunkz)) {
-            continue;
-        }
-        let posx = chunkx * CHUNK_SIZE_I32;
-        let posz = chunkz * CHUNK_SIZE_I32;
-        let mut heights = vec![0; (CHUNK_SIZE_I32 * CHUNK_SIZE_I32) as usize];
-        for x in posx..(posx + CHUNK_SIZE_I32) {
-            for z in posz..(posz + CHUNK_SIZE_I32) {
-                let index = ((z - posz) * CHUNK_SIZE_I32 + (x - posx)) as usize;
-                let h = get_height(x, z, terrain_generator, steepness);
-                heights[index] = h;
-            }
-        }
-        heightmap.insert((*chunkx, *chunkz), heights);
-    }
-
-    heightmap
-}
-
 pub fn add_to_heightmap(
     chunkx: i32,
     chunkz: i32,
@@ -1,10 +1,20 @@
-use super::{LoadChunkQueue, World, WorldGenType, WorldGenerator};
+use super::{
+    block_update::{NeighborChanges, TickQueue},
+    chunk_generator::create_chunk_generator,
+    terrain_settings::TerrainSettings,
+    LoadChunkQueue, World, WorldGenerator,
+};
 use crate::{
-    game::GameMode,
+    bin_data::Compression,
+    game::{
+        migrate::WORLD_FORMAT_VERSION,
+        save_thread::{RegionKind, SaveThread},
+        GameMode,
+    },
     impfile::{self, Entry},
     voxel::{
         coordinates::f32coord_to_int,
-        region::{chunkpos_to_regionpos, get_region_chunks, save::serialize_region, Region},
+        region::{chunkpos_to_regionpos, get_region_chunks, Region},
         world_to_chunk_position,
     },
 };
@@ -30,50 +40,27 @@ fn string_to_game_mode(s: &str) -> GameMode {
     }
 }
 
-fn gen_type_to_string(world_gen_type: WorldGenType) -> String {
-    match world_gen_type {
-        WorldGenType::Flat => "flat".to_string(),
-        WorldGenType::OldGen => "old".to_string(),
-        WorldGenType::DefaultGen => "default2".to_string(),
-        WorldGenType::Skyblock => "skyblock".to_string(),
-    }
-}
-
-fn string_to_gen_type(s: &str) -> WorldGenType {
-    if s == "flat" {
-        WorldGenType::Flat
-    } else if s == "old" || s == "default" {
-        WorldGenType::OldGen
-    } else if s == "skyblock" {
-        WorldGenType::Skyblock
-    } else {
-        WorldGenType::DefaultGen
-    }
-}
-
-pub fn save_region(region: &Region, world_path: &str) {
-    if let Err(path) = serialize_region(world_path, region) {
-        if let Err(msg) = std::fs::remove_file(&path) {
-            eprintln!("Failed to remove {path}");
-            eprintln!("{msg}");
-        }
-    }
-}
-
 impl World {
     fn save_world_metadata(&self) {
         let mut entry = Entry::new("world");
+        entry.add_integer("format_version", WORLD_FORMAT_VERSION as i64);
         entry.add_integer("centerx", self.centerx as i64);
         entry.add_integer("centery", self.centery as i64);
         entry.add_integer("centerz", self.centerz as i64);
         entry.add_integer("seed", self.world_seed as i64);
         entry.add_integer("days_passed", self.days_passed as i64);
+        entry.add_integer("ticks", self.ticks as i64);
         entry.add_float("time", self.time);
-        entry.add_string("gen_type", &gen_type_to_string(self.gen_type));
+        entry.add_string("gen_type", &self.gen_type);
+        if !self.gen_options.is_empty() {
+            entry.add_string("gen_options", &self.gen_options);
+        }
         entry.add_string("game_mode", &game_mode_to_string(self.game_mode));
 
         let world_save_path = self.path.clone() + "world.impfile";
-        let world_entry_str = entry.to_impfile_string();
+        let terrain_entry = self.world_generator.settings.to_entry("terrain");
+        let world_entry_str =
+            entry.to_impfile_string() + "\n\n" + &terrain_entry.to_impfile_string();
         let res = match File::create(world_save_path) {
             Ok(mut player_file) => {
                 impfile::write_comment(&mut player_file, "World metadata");
@@ -87,20 +74,26 @@ impl World {
         }
     }
 
+    //Only saves the chunks that were modified
     pub fn save(&mut self) {
         self.save_world_metadata();
-        let mut regions_to_save = HashSet::new();
-        for (x, y, z) in &self.to_save {
-            let regionpos = chunkpos_to_regionpos(*x, *y, *z);
-            regions_to_save.insert(regionpos);
+        let mut regions_to_save = HashMap::new();
+        for (x, y, z) in self.to_save.drain() {
+            let chunk = self
+                .chunks
+                .get(&(x, y, z))
+                .or_else(|| self.chunk_cache.get(&(x, y, z)));
+            if let Some(chunk) = chunk {
+                let (rx, ry, rz) = chunkpos_to_regionpos(x, y, z);
+                regions_to_save
+                    .entry((rx, ry, rz))
+                    .or_insert(Region::new(rx, ry, rz))
+                    .set_chunk(x, y, z, Some(chunk.clone()));
+            }
         }
-        self.to_save.clear();
         let savedcount = regions_to_save.len();
-        for (rx, ry, rz) in regions_to_save {
-            let mut region = Region::new(rx, ry, rz);
-            get_region_chunks(&mut region, &self.chunks);
-            get_region_chunks(&mut region, &self.chunk_cache);
-            save_region(&region, &self.path);
+        for region in regions_to_save.into_values() {
+            self.saver.save_region(&self.path, region, self.compression);
         }
         eprintln!("Saved {savedcount} regions.");
     }
@@ -130,16 +123,17 @@ impl World {
         for (x, y, z) in regions {
             let mut region = Region::new(x, y, z);
             get_region_chunks(&mut region, &chunks_to_save);
-            save_region(&region, &self.path);
+            self.saver.save_region(&self.path, region, self.compression);
         }
         eprintln!("Saved {count} regions.");
     }
 
-    pub fn load_world_metadata(world_dir_path: &str, range: i32) -> Self {
+    //Returns an error if the world generator can not be created
+    pub fn load_world_metadata(world_dir_path: &str, range: i32) -> Result<Self, String> {
         let path = world_dir_path.to_string() + "world.impfile";
         let world_metadata_entries = impfile::parse_file(&path);
         if world_metadata_entries.is_empty() {
-            return Self::empty();
+            return Ok(Self::empty());
         }
 
         let rand_seed = fastrand::u32(..);
@@ -149,8 +143,24 @@ impl World {
             .unwrap_or(rand_seed);
 
         let mode = world_metadata_entries[0].get_var("game_mode");
+        let (gen_type, gen_options, generator) = create_chunk_generator(
+            &world_metadata_entries[0].get_var("gen_type"),
+            &world_metadata_entries[0].get_var("gen_options"),
+        )?;
+        //Worlds saved before the terrain settings were stored use the defaults
+        let terrain_entry = world_metadata_entries
+            .iter()
+            .find(|entry| entry.get_name() == "terrain");
+        let mut terrain_settings = terrain_entry
+            .map(TerrainSettings::from_entry)
+            .unwrap_or(Ok(TerrainSettings::default()))
+            .unwrap_or_else(|msg| {
+                eprintln!("E: failed to load terrain settings: {msg}");
+                TerrainSettings::default()
+            });
+        terrain_settings.disable_missing_features(terrain_entry);
 
-        Self {
+        Ok(Self {
             chunks: HashMap::new(),
             skylightmap: HashMap::new(),
             range,
@@ -167,15 +177,22 @@ impl World {
                 .parse::<i32>()
                 .unwrap_or(0),
             chunk_cache: HashMap::new(),
-            world_generator: WorldGenerator::new(seed),
+            world_generator: WorldGenerator::new(seed, terrain_settings),
             world_seed: seed,
-            gen_type: string_to_gen_type(&world_metadata_entries[0].get_var("gen_type")),
+            gen_type,
+            gen_options,
+            generator,
             path: world_dir_path.to_string(),
             block_update_timer: 0.0,
             random_update_timer: 0.0,
             updating: HashSet::new(),
             in_update_range: HashSet::new(),
-            ticks: 0,
+            tick_queue: TickQueue::new(),
+            neighbor_changes: NeighborChanges::default(),
+            ticks: world_metadata_entries[0]
+                .get_var("ticks")
+                .parse::<u64>()
+                .unwrap_or(0),
             time: world_metadata_entries[0]
                 .get_var("time")
                 .parse::<f32>()
@@ -185,11 +202,19 @@ impl World {
                 .parse::<u64>()
                 .unwrap_or(0),
             to_save: HashSet::new(),
-            removed_from_cache: vec![],
+            saver: SaveThread::new(),
             to_load: LoadChunkQueue::new(),
             chunktable_update_list: HashSet::new(),
             game_mode: string_to_game_mode(&mode),
-        }
+            compression: Compression::default(),
+        })
+    }
+
+    //Loads a region from the disk, waits for the region to be written first
+    //if it is still being saved
+    pub fn load_region(&self, rx: i32, ry: i32, rz: i32) -> Option<Region> {
+        self.saver.wait_for(RegionKind::Chunks, rx, ry, rz);
+        Region::load_region(&self.path, rx, ry, rz)
     }
 
     //Adds the chunks in a region to the world
@@ -264,19 +289,36 @@ impl World {
                     }
                     loaded.insert((rx, ry, rz));
 
-                    if let Some(region) = Region::load_region(&self.path, rx, ry, rz) {
+                    if let Some(region) = self.load_region(rx, ry, rz) {
                         self.add_region(region);
                     }
                 }
             }
         }
 
-        match self.gen_type {
-            WorldGenType::Flat => self.gen_flat_on_load(),
-            WorldGenType::OldGen => self.gen_old_on_load(),
-            WorldGenType::DefaultGen => self.gen_default_on_load(),
-            WorldGenType::Skyblock => self.gen_skyblock_on_load(),
+        //Generate any missing chunks
+        let mut to_generate = HashSet::new();
+        for y in (self.centery - self.range)..=(self.centery + self.range) {
+            for z in (self.centerz - self.range)..=(self.centerz + self.range) {
+                for x in (self.centerx - self.range)..=(self.centerx + self.range) {
+                    if self.chunks.contains_key(&(x, y, z)) {
+                        continue;
+                    }
+                    to_generate.insert((x, y, z));
+                }
+            }
+        }
+
+        if to_generate.is_empty() {
+            return;
         }
+
+        let start = std::time::Instant::now();
+        let generated_count = self.generate_chunks(&to_generate);
+        eprintln!(
+            "Took {} ms to generate {generated_count} new chunks",
+            start.elapsed().as_millis()
+        );
     }
 
     //I don't really have a good name for this function,
@@ -302,22 +344,13 @@ impl World {
         self.chunk_cache.clear();
         for dy in -1..=1 {
             let pos = (chunkx, chunky + dy, chunkz);
-            if self.chunks.contains_key(&pos) {
-                continue;
-            }
-            let (rx, ry, rz) = chunkpos_to_regionpos(chunkx, chunky + dy, chunkz);
-            if let Some(region) = Region::load_region(&self.path, rx, ry, rz) {
-                self.add_region(region);
+            if let Some(chunk) = Region::load_chunk(&self.path, pos.0, pos.1, pos.2) {
+                self.chunks.insert(pos, chunk);
             }
 
             if !self.chunks.contains_key(&pos) {
                 //Generate new chunk
-                match self.gen_type {
-                    WorldGenType::Flat => self.add_flat_chunk(chunkx, chunky, chunkz),
-                    WorldGenType::OldGen => self.add_old_chunk(chunkx, chunky, chunkz),
-                    WorldGenType::DefaultGen => self.add_default_chunk(chunkx, chunky, chunkz),
-                    WorldGenType::Skyblock => self.add_skyblock_chunk(chunkx, chunky, chunkz),
-                }
+                self.generate_chunks(&HashSet::from([pos]));
             }
         }
     }
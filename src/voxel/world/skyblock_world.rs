@@ -1,18 +1,11 @@
-use std::collections::HashSet;
-
-use super::World;
+use super::{
+    chunk_generator::{gen_chunks_parallel, ChunkGenerator},
+    WorldGenerator,
+};
 use crate::{
     game::inventory::Item,
     voxel::{tile_data::TileData, Block, Chunk, CHUNK_SIZE_I32, EMPTY_BLOCK, INDESTRUCTIBLE},
 };
-use crossbeam::{queue::ArrayQueue, thread};
-
-/*
- * NOTE: This code is effectively just copied and pasted from `flat_world.rs`
- * so there is a lot of repetition. I can probably improve the world generation
- * code to be better but I don't feel like doing that right now and it works fine
- * so copy-paste it is.
- */
 
 fn place_leaves(chunk: &mut Chunk, x: usize, y: usize, z: usize) {
     let replace = chunk.get_block_relative(x, y, z);
@@ -179,84 +172,14 @@ fn gen_skyblock_chunk(chunk: &mut Chunk) {
     }
 }
 
-impl World {
-    //Generates a skyblock world
-    pub fn gen_skyblock(&mut self) {
-        for chunk in &mut self.chunks.values_mut() {
-            gen_skyblock_chunk(chunk);
-        }
-    }
-
-    //Generates missing flat world chunks on load
-    pub fn gen_skyblock_on_load(&mut self) {
-        let mut to_generate = HashSet::new();
-        for y in (self.centery - self.range)..=(self.centery + self.range) {
-            for z in (self.centerz - self.range)..=(self.centerz + self.range) {
-                for x in (self.centerx - self.range)..=(self.centerx + self.range) {
-                    if self.chunks.contains_key(&(x, y, z)) {
-                        continue;
-                    }
-                    to_generate.insert((x, y, z));
-                }
-            }
-        }
-
-        //Generate new chunks
-        for (chunkx, chunky, chunkz) in &to_generate {
-            let pos = (*chunkx, *chunky, *chunkz);
-            if self.chunks.contains_key(&pos) {
-                continue;
-            }
-            let mut new_chunk = Chunk::new(*chunkx, *chunky, *chunkz);
-            gen_skyblock_chunk(&mut new_chunk);
-            self.chunks.insert(pos, new_chunk);
-        }
-    }
-
-    pub fn generate_column_skyblock(&mut self, x: i32, z: i32, yvals: &HashSet<i32>) {
-        //Generate new chunks
-        let start = std::time::Instant::now();
-        let generated = ArrayQueue::new(yvals.len());
-        let mut generated_count = 0;
-        thread::scope(|s| {
-            for y in yvals {
-                if !self.in_range(x, *y, z) {
-                    continue;
-                }
-
-                if self.chunks.contains_key(&(x, *y, z)) {
-                    continue;
-                }
+pub struct SkyblockGenerator;
 
-                generated_count += 1;
-
-                s.spawn(|_| {
-                    let mut new_chunk = Chunk::new(x, *y, z);
-                    gen_skyblock_chunk(&mut new_chunk);
-                    //This should never fail
-                    generated
-                        .push(new_chunk)
-                        .expect("Error: Failed to push onto ArrayQueue");
-                });
-            }
-        })
-        .expect("Failed to generate new chunks!");
-
-        for chunk in generated {
-            let chunkpos = chunk.get_chunk_pos();
-            let pos = (chunkpos.x, chunkpos.y, chunkpos.z);
-            self.chunks.insert(pos, chunk);
-        }
-        let time = start.elapsed().as_millis();
-        if time > 15 {
-            //Only report time taken if it exceeds 15 ms
-            eprintln!("Took {time} ms to generate {generated_count} new chunks");
-        }
+impl ChunkGenerator for SkyblockGenerator {
+    fn generate_column(&self, _: i32, _: i32, chunks: &mut [Chunk], _: &WorldGenerator) {
+        gen_chunks_parallel(chunks, gen_skyblock_chunk);
     }
 
-    pub fn add_skyblock_chunk(&mut self, chunkx: i32, chunky: i32, chunkz: i32) {
-        let mut chunk = Chunk::new(chunkx, chunky, chunkz);
-        gen_skyblock_chunk(&mut chunk);
-        self.chunks.insert((chunkx, chunky, chunkz), chunk);
+    fn bottom(&self) -> i32 {
+        -32
     }
 }
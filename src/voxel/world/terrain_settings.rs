@@ -0,0 +1,335 @@
+/*
+ * Settings for the noise used to generate terrain, presets are defined in
+ * assets/terrain_presets.impfile and the settings that a world was created
+ * with are saved in its metadata so that it always generates the same way
+ * even if the presets are changed.
+ * */
+
+use crate::impfile::{self, Entry};
+use noise::{Fbm, Perlin};
+use std::str::FromStr;
+
+pub const TERRAIN_PRESETS_PATH: &str = "assets/terrain_presets.impfile";
+//Uses the built in settings
+pub const DEFAULT_TERRAIN_PRESET: &str = "default";
+const MAX_OCTAVES: usize = Fbm::<Perlin>::DEFAULT_OCTAVE_COUNT;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainSettings {
+    //Noise octaves/persistence
+    pub terrain_octaves: usize,
+    pub terrain_persistence: f64,
+    pub elevation_octaves: usize,
+    pub elevation_persistence: f64,
+    pub mountain_octaves: usize,
+    pub mountain_persistence: f64,
+    //Added to the world seed for each noise generator
+    pub terrain_seed: u32,
+    pub cave_seed: u32,
+    pub tree_seed: u32,
+    pub steepness_seed: u32,
+    pub temperature_seed: u32,
+    pub elevation_seed: u32,
+    pub mountain_seed: u32,
+    pub river_seed: u32,
+    pub lake_seed: u32,
+    pub carver_seed: u32,
+    pub structure_seed: u32,
+    //Heights
+    pub height_scale: f64,
+    pub height_offset: i32,
+    pub mountain_scale: f64,
+    //The highest y coordinate that terrain can generate at
+    pub max_height: i32,
+    //Noise caves are largest at cave_middle and do not generate outside
+    //of cave_bottom and cave_top
+    pub cave_bottom: i32,
+    pub cave_middle: i32,
+    pub cave_top: i32,
+    pub sea_level: i32,
+    pub lava_level: i32,
+    //Rivers are where the river noise is between -river_width and
+    //river_width, there are no rivers if this is 0
+    pub river_width: f64,
+    pub river_depth: i32,
+    //Lakes are where the lake noise is above lake_threshold, there are no
+    //lakes if this is 1 or more
+    pub lake_threshold: f64,
+    pub lake_depth: i32,
+    //Chance (0 to 1) that a chunk column starts a tunnel, ravine or lava lake
+    pub tunnel_chance: f64,
+    pub ravine_chance: f64,
+    pub lava_lake_chance: f64,
+    //Villages, dungeons and other structures
+    pub structures: bool,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            terrain_octaves: 5,
+            terrain_persistence: 0.5,
+            elevation_octaves: 3,
+            elevation_persistence: 0.25,
+            mountain_octaves: 4,
+            mountain_persistence: 0.6,
+            terrain_seed: 0,
+            cave_seed: 1,
+            tree_seed: 2,
+            steepness_seed: 3,
+            temperature_seed: 4,
+            elevation_seed: 5,
+            mountain_seed: 6,
+            river_seed: 7,
+            lake_seed: 8,
+            carver_seed: 9,
+            structure_seed: 10,
+            height_scale: 64.0,
+            height_offset: 0,
+            mountain_scale: 80.0,
+            max_height: 95,
+            cave_bottom: -64,
+            cave_middle: -51,
+            cave_top: 64,
+            sea_level: 0,
+            lava_level: -50,
+            river_width: 0.025,
+            river_depth: 4,
+            lake_threshold: 0.65,
+            lake_depth: 6,
+            tunnel_chance: 0.14,
+            ravine_chance: 0.02,
+            lava_lake_chance: 0.04,
+            structures: true,
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(var: &str, val: &str) -> Result<T, String> {
+    val.parse::<T>()
+        .map_err(|_| format!("invalid value '{val}' for {var}"))
+}
+
+impl TerrainSettings {
+    //Any variable that is not in the entry is set to the default value
+    pub fn from_entry(entry: &Entry) -> Result<Self, String> {
+        let mut settings = Self::default();
+        for (var, val) in entry.get_all_vars() {
+            let val = val.as_str();
+            match var.as_str() {
+                "terrain_octaves" => settings.terrain_octaves = parse_value(&var, val)?,
+                "terrain_persistence" => settings.terrain_persistence = parse_value(&var, val)?,
+                "elevation_octaves" => settings.elevation_octaves = parse_value(&var, val)?,
+                "elevation_persistence" => settings.elevation_persistence = parse_value(&var, val)?,
+                "mountain_octaves" => settings.mountain_octaves = parse_value(&var, val)?,
+                "mountain_persistence" => settings.mountain_persistence = parse_value(&var, val)?,
+                "terrain_seed" => settings.terrain_seed = parse_value(&var, val)?,
+                "cave_seed" => settings.cave_seed = parse_value(&var, val)?,
+                "tree_seed" => settings.tree_seed = parse_value(&var, val)?,
+                "steepness_seed" => settings.steepness_seed = parse_value(&var, val)?,
+                "temperature_seed" => settings.temperature_seed = parse_value(&var, val)?,
+                "elevation_seed" => settings.elevation_seed = parse_value(&var, val)?,
+                "mountain_seed" => settings.mountain_seed = parse_value(&var, val)?,
+                "river_seed" => settings.river_seed = parse_value(&var, val)?,
+                "lake_seed" => settings.lake_seed = parse_value(&var, val)?,
+                "carver_seed" => settings.carver_seed = parse_value(&var, val)?,
+                "structure_seed" => settings.structure_seed = parse_value(&var, val)?,
+                "height_scale" => settings.height_scale = parse_value(&var, val)?,
+                "height_offset" => settings.height_offset = parse_value(&var, val)?,
+                "mountain_scale" => settings.mountain_scale = parse_value(&var, val)?,
+                "max_height" => settings.max_height = parse_value(&var, val)?,
+                "cave_bottom" => settings.cave_bottom = parse_value(&var, val)?,
+                "cave_middle" => settings.cave_middle = parse_value(&var, val)?,
+                "cave_top" => settings.cave_top = parse_value(&var, val)?,
+                "sea_level" => settings.sea_level = parse_value(&var, val)?,
+                "lava_level" => settings.lava_level = parse_value(&var, val)?,
+                "river_width" => settings.river_width = parse_value(&var, val)?,
+                "river_depth" => settings.river_depth = parse_value(&var, val)?,
+                "lake_threshold" => settings.lake_threshold = parse_value(&var, val)?,
+                "lake_depth" => settings.lake_depth = parse_value(&var, val)?,
+                "tunnel_chance" => settings.tunnel_chance = parse_value(&var, val)?,
+                "ravine_chance" => settings.ravine_chance = parse_value(&var, val)?,
+                "lava_lake_chance" => settings.lava_lake_chance = parse_value(&var, val)?,
+                "structures" => settings.structures = parse_value(&var, val)?,
+                _ => return Err(format!("unknown terrain setting '{var}'")),
+            }
+        }
+
+        if settings.cave_bottom >= settings.cave_middle || settings.cave_middle >= settings.cave_top
+        {
+            return Err("cave_bottom < cave_middle < cave_top must hold".to_string());
+        }
+
+        if settings.river_width < 0.0 || settings.river_depth < 0 || settings.lake_depth < 0 {
+            return Err("river_width, river_depth and lake_depth can not be negative".to_string());
+        }
+
+        let chances = [
+            settings.tunnel_chance,
+            settings.ravine_chance,
+            settings.lava_lake_chance,
+        ];
+        if chances.iter().any(|chance| !(0.0..=1.0).contains(chance)) {
+            return Err(
+                "tunnel_chance, ravine_chance and lava_lake_chance must be between 0 and 1"
+                    .to_string(),
+            );
+        }
+
+        //The octaves are set directly on the noise generators (so that the
+        //default settings match worlds created before these settings existed)
+        //which only have sources for up to MAX_OCTAVES octaves
+        let octaves = [
+            settings.terrain_octaves,
+            settings.elevation_octaves,
+            settings.mountain_octaves,
+        ];
+        if octaves
+            .iter()
+            .any(|octaves| *octaves == 0 || *octaves > MAX_OCTAVES)
+        {
+            return Err(format!("octaves must be between 1 and {MAX_OCTAVES}"));
+        }
+
+        Ok(settings)
+    }
+
+    pub fn to_entry(&self, name: &str) -> Entry {
+        let mut entry = Entry::new(name);
+        let values = [
+            ("terrain_octaves", self.terrain_octaves.to_string()),
+            ("terrain_persistence", self.terrain_persistence.to_string()),
+            ("elevation_octaves", self.elevation_octaves.to_string()),
+            (
+                "elevation_persistence",
+                self.elevation_persistence.to_string(),
+            ),
+            ("mountain_octaves", self.mountain_octaves.to_string()),
+            (
+                "mountain_persistence",
+                self.mountain_persistence.to_string(),
+            ),
+            ("terrain_seed", self.terrain_seed.to_string()),
+            ("cave_seed", self.cave_seed.to_string()),
+            ("tree_seed", self.tree_seed.to_string()),
+            ("steepness_seed", self.steepness_seed.to_string()),
+            ("temperature_seed", self.temperature_seed.to_string()),
+            ("elevation_seed", self.elevation_seed.to_string()),
+            ("mountain_seed", self.mountain_seed.to_string()),
+            ("river_seed", self.river_seed.to_string()),
+            ("lake_seed", self.lake_seed.to_string()),
+            ("carver_seed", self.carver_seed.to_string()),
+            ("structure_seed", self.structure_seed.to_string()),
+            ("height_scale", self.height_scale.to_string()),
+            ("height_offset", self.height_offset.to_string()),
+            ("mountain_scale", self.mountain_scale.to_string()),
+            ("max_height", self.max_height.to_string()),
+            ("cave_bottom", self.cave_bottom.to_string()),
+            ("cave_middle", self.cave_middle.to_string()),
+            ("cave_top", self.cave_top.to_string()),
+            ("sea_level", self.sea_level.to_string()),
+            ("lava_level", self.lava_level.to_string()),
+            ("river_width", self.river_width.to_string()),
+            ("river_depth", self.river_depth.to_string()),
+            ("lake_threshold", self.lake_threshold.to_string()),
+            ("lake_depth", self.lake_depth.to_string()),
+            ("tunnel_chance", self.tunnel_chance.to_string()),
+            ("ravine_chance", self.ravine_chance.to_string()),
+            ("lava_lake_chance", self.lava_lake_chance.to_string()),
+            ("structures", self.structures.to_string()),
+        ];
+        for (var, val) in values {
+            entry.add_string(var, &val);
+        }
+        entry
+    }
+
+    //Features that were added after a world was created are disabled so that
+    //newly generated chunks still line up with the chunks that were already
+    //generated, `entry` is the terrain entry saved with the world
+    pub fn disable_missing_features(&mut self, entry: Option<&Entry>) {
+        let missing = |var: &str| entry.is_none_or(|entry| entry.get_var(var).is_empty());
+        if missing("river_width") {
+            self.river_width = 0.0;
+        }
+        if missing("lake_threshold") {
+            self.lake_threshold = 1.0;
+        }
+        if missing("tunnel_chance") {
+            self.tunnel_chance = 0.0;
+            self.ravine_chance = 0.0;
+            self.lava_lake_chance = 0.0;
+        }
+        if missing("structures") {
+            self.structures = false;
+        }
+    }
+
+    //Loads a preset from TERRAIN_PRESETS_PATH
+    pub fn load_preset(name: &str) -> Result<Self, String> {
+        if name == DEFAULT_TERRAIN_PRESET {
+            return Ok(Self::default());
+        }
+
+        let entries = impfile::parse_file(TERRAIN_PRESETS_PATH);
+        match entries.iter().find(|entry| entry.get_name() == name) {
+            Some(entry) => Self::from_entry(entry).map_err(|msg| format!("{name}: {msg}")),
+            None => {
+                let mut names = vec![DEFAULT_TERRAIN_PRESET.to_string()];
+                names.extend(entries.iter().map(|entry| entry.get_name()));
+                Err(format!(
+                    "unknown terrain preset '{name}' (presets: {})",
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terrain_settings() {
+        let settings = TerrainSettings {
+            height_scale: 1.0 / 3.0,
+            sea_level: 12,
+            ..Default::default()
+        };
+        let entry = settings.to_entry("terrain");
+        assert_eq!(TerrainSettings::from_entry(&entry).unwrap(), settings);
+
+        let entry = Entry::from_vec("terrain", vec![("sea_level".into(), "4".into())]);
+        let loaded = TerrainSettings::from_entry(&entry).unwrap();
+        assert_eq!(loaded.sea_level, 4);
+        assert_eq!(loaded.lava_level, TerrainSettings::default().lava_level);
+
+        let mut old = TerrainSettings::default();
+        old.disable_missing_features(Some(&entry));
+        assert_eq!(old.river_width, 0.0);
+        assert_eq!(old.lake_threshold, 1.0);
+        assert_eq!(old.tunnel_chance, 0.0);
+        assert_eq!(old.lava_lake_chance, 0.0);
+        assert!(!old.structures);
+        let mut new = TerrainSettings::default();
+        new.disable_missing_features(Some(&settings.to_entry("terrain")));
+        assert_eq!(new, TerrainSettings::default());
+
+        let entry = Entry::from_vec("terrain", vec![("not_a_setting".into(), "4".into())]);
+        assert!(TerrainSettings::from_entry(&entry).is_err());
+        let entry = Entry::from_vec("terrain", vec![("cave_top".into(), "-60".into())]);
+        assert!(TerrainSettings::from_entry(&entry).is_err());
+    }
+
+    #[test]
+    fn test_load_terrain_presets() {
+        let default = TerrainSettings::load_preset(DEFAULT_TERRAIN_PRESET).unwrap();
+        assert_eq!(default, TerrainSettings::default());
+        for name in ["amplified", "islands", "flat-ish"] {
+            let settings = TerrainSettings::load_preset(name).unwrap();
+            assert_ne!(settings, default);
+        }
+        assert!(TerrainSettings::load_preset("not_a_preset").is_err());
+    }
+}
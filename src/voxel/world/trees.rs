@@ -0,0 +1,344 @@
+/*
+ * Tree templates, these are shared by the default world generator and by
+ * saplings growing during random block updates so that both produce the
+ * same trees.
+ * */
+
+use crate::voxel::{biome_registry::TreeKind, Axis, Block, EMPTY_BLOCK};
+use fastrand::Rng;
+
+//Leaves decay if there is no log within this many blocks so canopies and
+//the leaves around branches should never extend further than this
+pub const MAX_LEAF_DIST: i32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Canopy {
+    None,
+    //Layers of leaves that get smaller towards the top (the original oak)
+    Blob,
+    //Sphere of leaves centered right below the top of the trunk
+    Sphere(i32),
+    //Cone of leaves that gets wider towards the bottom
+    Cone(i32),
+}
+
+pub struct TreeTemplate {
+    pub trunk: u16,
+    //EMPTY_BLOCK if the tree has no leaves
+    pub leaves: u16,
+    //Range of the trunk height (inclusive)
+    pub height: (i32, i32),
+    //Trunks are either 1x1 or 2x2
+    pub width: i32,
+    pub canopy: Canopy,
+    //Range of the number of branches and their length (inclusive)
+    pub branches: (i32, i32),
+    pub branch_length: (i32, i32),
+}
+
+const OAK: TreeTemplate = TreeTemplate {
+    trunk: 8,
+    leaves: 7,
+    height: (4, 6),
+    width: 1,
+    canopy: Canopy::Blob,
+    branches: (0, 0),
+    branch_length: (0, 0),
+};
+
+const SNOWY_OAK: TreeTemplate = TreeTemplate { leaves: 91, ..OAK };
+
+const BIG_OAK: TreeTemplate = TreeTemplate {
+    trunk: 8,
+    leaves: 7,
+    height: (7, 10),
+    width: 1,
+    canopy: Canopy::Sphere(3),
+    branches: (2, 4),
+    branch_length: (2, 3),
+};
+
+const GIANT_OAK: TreeTemplate = TreeTemplate {
+    height: (10, 14),
+    width: 2,
+    branches: (3, 5),
+    branch_length: (2, 4),
+    ..BIG_OAK
+};
+
+const SPRUCE: TreeTemplate = TreeTemplate {
+    trunk: 8,
+    leaves: 91,
+    height: (6, 9),
+    width: 1,
+    canopy: Canopy::Cone(2),
+    branches: (0, 0),
+    branch_length: (0, 0),
+};
+
+const GIANT_SPRUCE: TreeTemplate = TreeTemplate {
+    height: (13, 18),
+    width: 2,
+    canopy: Canopy::Cone(3),
+    ..SPRUCE
+};
+
+const CACTUS: TreeTemplate = TreeTemplate {
+    trunk: 88,
+    leaves: EMPTY_BLOCK,
+    height: (1, 3),
+    width: 1,
+    canopy: Canopy::None,
+    branches: (0, 0),
+    branch_length: (0, 0),
+};
+
+impl TreeKind {
+    pub fn template(self) -> &'static TreeTemplate {
+        match self {
+            TreeKind::Oak => &OAK,
+            TreeKind::SnowyOak => &SNOWY_OAK,
+            TreeKind::BigOak => &BIG_OAK,
+            TreeKind::GiantOak => &GIANT_OAK,
+            TreeKind::Spruce => &SPRUCE,
+            TreeKind::GiantSpruce => &GIANT_SPRUCE,
+            TreeKind::Cactus => &CACTUS,
+        }
+    }
+
+    //Returns the tree that grows from a sapling, giant is true if the
+    //sapling is part of a 2x2 square of saplings
+    pub fn from_sapling(id: u16, giant: bool, rng: &mut Rng) -> Option<Self> {
+        match (id, giant) {
+            (47, true) => Some(TreeKind::GiantOak),
+            (47, false) if rng.i32(0..8) == 0 => Some(TreeKind::BigOak),
+            (47, false) => Some(TreeKind::Oak),
+            (92, true) => Some(TreeKind::GiantSpruce),
+            (92, false) if rng.i32(0..2) == 0 => Some(TreeKind::Spruce),
+            (92, false) => Some(TreeKind::SnowyOak),
+            _ => None,
+        }
+    }
+}
+
+//Rotates a log so that it runs along the axis
+fn log(id: u16, axis: Axis) -> Block {
+    match axis {
+        Axis::X => Block::new_id_orientation(id, 1),
+        Axis::Y => Block::new_id(id),
+        Axis::Z => Block::new_id_orientation(id, 2),
+    }
+}
+
+//Distance from v to the range start..start + width
+fn dist_to_trunk(v: i32, start: i32, width: i32) -> i32 {
+    if v < start {
+        start - v
+    } else {
+        (v - (start + width - 1)).max(0)
+    }
+}
+
+impl TreeTemplate {
+    //Returns how far the tree can extend horizontally from its trunk
+    pub fn radius(&self) -> i32 {
+        let canopy = match self.canopy {
+            Canopy::None => 0,
+            Canopy::Blob => 2,
+            Canopy::Sphere(r) | Canopy::Cone(r) => r,
+        };
+        let branches = if self.branches.1 > 0 {
+            self.branch_length.1 + 2
+        } else {
+            0
+        };
+        canopy.max(branches)
+    }
+
+    fn add_leaves(&self, blocks: &mut Vec<((i32, i32, i32), Block)>, x: i32, y: i32, z: i32) {
+        blocks.push(((x, y, z), Block::new_id(self.leaves)));
+    }
+
+    //Adds leaves in a layer around the trunk, the layer extends r blocks
+    //from the trunk and the corners are cut off if round is true
+    fn add_layer(
+        &self,
+        blocks: &mut Vec<((i32, i32, i32), Block)>,
+        (x, y, z): (i32, i32, i32),
+        r: i32,
+        round: bool,
+    ) {
+        for ix in (x - r)..(x + r + self.width) {
+            for iz in (z - r)..(z + r + self.width) {
+                let dx = dist_to_trunk(ix, x, self.width);
+                let dz = dist_to_trunk(iz, z, self.width);
+                if round && dx * dx + dz * dz > r * r + 1 {
+                    continue;
+                }
+                self.add_leaves(blocks, ix, y, iz);
+            }
+        }
+    }
+
+    fn gen_canopy(&self, blocks: &mut Vec<((i32, i32, i32), Block)>, x: i32, top: i32, z: i32) {
+        match self.canopy {
+            Canopy::None => {}
+            Canopy::Blob => {
+                self.add_layer(blocks, (x, top, z), 0, false);
+                for i in 0..self.width {
+                    self.add_leaves(blocks, x - 1, top, z + i);
+                    self.add_leaves(blocks, x + self.width, top, z + i);
+                    self.add_leaves(blocks, x + i, top, z - 1);
+                    self.add_leaves(blocks, x + i, top, z + self.width);
+                }
+                self.add_layer(blocks, (x, top - 1, z), 1, false);
+                self.add_layer(blocks, (x, top - 2, z), 2, false);
+                self.add_layer(blocks, (x, top - 3, z), 2, false);
+            }
+            Canopy::Sphere(r) => {
+                for dy in -r..=r {
+                    //Flatten the top and bottom of the sphere
+                    let layer_r = ((r * r - dy * dy) as f64).sqrt().round() as i32;
+                    self.add_layer(blocks, (x, top - 1 + dy, z), layer_r, true);
+                }
+            }
+            Canopy::Cone(r) => {
+                for i in 0..(r * 2 + 2) {
+                    //Every other layer is slightly smaller
+                    let layer_r = ((i + 1) / 2).min(r) - (i % 2) * (i / 3).min(1);
+                    self.add_layer(blocks, (x, top - i, z), layer_r, true);
+                }
+            }
+        }
+    }
+
+    //Branches grow out from the upper part of the trunk and slowly rise,
+    //they end in a small cluster of leaves
+    fn gen_branch(
+        &self,
+        blocks: &mut Vec<((i32, i32, i32), Block)>,
+        (x, y, z): (i32, i32, i32),
+        height: i32,
+        rng: &mut Rng,
+    ) {
+        let (dx, dz) = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng.usize(0..4)];
+        let axis = if dx != 0 { Axis::X } else { Axis::Z };
+        let length = rng.i32(self.branch_length.0..=self.branch_length.1);
+        //Start on the side of the trunk that the branch grows towards
+        let startx = if dx > 0 { x + self.width - 1 } else { x };
+        let startz = if dz > 0 { z + self.width - 1 } else { z };
+        let starty = y + rng.i32((height / 2)..(height - 1).max(height / 2 + 1));
+
+        let mut end = (startx, starty, startz);
+        for i in 1..=length {
+            end = (startx + dx * i, starty + i / 2, startz + dz * i);
+            blocks.push((end, log(self.trunk, axis)));
+        }
+
+        let (ex, ey, ez) = end;
+        for ix in (ex - 2)..=(ex + 2) {
+            for iy in ey..=(ey + 1) {
+                for iz in (ez - 2)..=(ez + 2) {
+                    let dist2 = (ix - ex).pow(2) + (iy - ey).pow(2) * 2 + (iz - ez).pow(2);
+                    if dist2 <= 4 {
+                        self.add_leaves(blocks, ix, iy, iz);
+                    }
+                }
+            }
+        }
+    }
+
+    //Returns the blocks of a tree with its trunk starting at (x, y, z), the
+    //trunk of 2x2 trees extends in the positive x and z direction, logs are
+    //placed after the leaves so that they should overwrite them
+    pub fn gen_blocks(
+        &self,
+        x: i32,
+        y: i32,
+        z: i32,
+        rng: &mut Rng,
+    ) -> Vec<((i32, i32, i32), Block)> {
+        let mut leaves = vec![];
+        let height = rng.i32(self.height.0..=self.height.1);
+        let top = y + height;
+        if self.leaves != EMPTY_BLOCK {
+            self.gen_canopy(&mut leaves, x, top, z);
+        }
+
+        let mut logs = vec![];
+        let branch_count = rng.i32(self.branches.0..=self.branches.1);
+        for _ in 0..branch_count {
+            self.gen_branch(&mut logs, (x, y, z), height, rng);
+        }
+        //Separate the leaves at the end of the branches from the logs
+        let (branch_leaves, mut logs): (Vec<_>, Vec<_>) = logs
+            .into_iter()
+            .partition(|(_, block)| block.id == self.leaves);
+        leaves.extend(branch_leaves);
+
+        for iy in y..top {
+            for ix in x..(x + self.width) {
+                for iz in z..(z + self.width) {
+                    logs.push(((ix, iy, iz), log(self.trunk, Axis::Y)));
+                }
+            }
+        }
+
+        leaves.extend(logs);
+        leaves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_templates() {
+        let kinds = [
+            TreeKind::Oak,
+            TreeKind::SnowyOak,
+            TreeKind::BigOak,
+            TreeKind::GiantOak,
+            TreeKind::Spruce,
+            TreeKind::GiantSpruce,
+            TreeKind::Cactus,
+        ];
+        for kind in kinds {
+            let template = kind.template();
+            for seed in 0..32 {
+                let blocks = template.gen_blocks(0, 0, 0, &mut Rng::with_seed(seed));
+                //Trees are the same for the same seed
+                let again = template.gen_blocks(0, 0, 0, &mut Rng::with_seed(seed));
+                assert_eq!(blocks, again);
+
+                let logs: Vec<_> = blocks
+                    .iter()
+                    .filter(|(_, block)| block.id == template.trunk)
+                    .collect();
+                for ix in 0..template.width {
+                    for iz in 0..template.width {
+                        assert!(logs.iter().any(|(pos, _)| *pos == (ix, 0, iz)));
+                    }
+                }
+
+                for ((x, y, z), block) in &blocks {
+                    assert!(x.abs().max(z.abs()) <= template.radius() + template.width);
+                    if block.id == template.trunk {
+                        //Branches are rotated along the direction they grow
+                        let horizontal =
+                            *x < 0 || *z < 0 || *x >= template.width || *z >= template.width;
+                        assert_eq!(block.orientation() != 0, horizontal);
+                        continue;
+                    }
+
+                    //Leaves should not decay
+                    let near_log = logs.iter().any(|((lx, ly, lz), _)| {
+                        (lx - x).abs().max((ly - y).abs()).max((lz - z).abs()) <= MAX_LEAF_DIST
+                    });
+                    assert!(near_log, "{kind:?} {seed} {x} {y} {z}");
+                }
+            }
+        }
+    }
+}